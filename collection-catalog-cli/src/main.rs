@@ -1,12 +1,39 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
 use collection_catalog_core::{
-    Item, ItemAction, ItemCategory, ItemFilter, add_item, export_to_csv, get_filtered_items,
-    init_db, soft_delete_item, update_item_fields,
+    Assignment, Beneficiary, CSV_COLUMNS, CodePosition, ConditionReport, Creator, CreatorRole,
+    CsvOptions, Currency, DEFAULT_MIN_SCORE, DEFAULT_PHOTO_DISTANCE, DateExpr, DueReminder,
+    ExportProfile, FilterExpr, FuzzyDate, Identifier, Item, ItemAction, ItemCategory, ItemFilter,
+    ItemStream, ItemTemplate, LabelOptions, LabelTemplate, MergeField, Money, OfflineProvider,
+    OptionalField, ProvenanceEvent, ProvenanceRole, Query, Rate, RelationKind, Reminder,
+    ReminderKind, ReportGrouping, ReportOptions, SavedSearch, SpreadsheetFormat,
+    SpreadsheetOptions, ThumbnailSize, acquire_wishlist_item, add_attachment, add_beneficiary,
+    add_condition_report, add_creator, add_identifier, add_item, add_relation, add_reminder,
+    append_provenance_event, assign_item, assignment_conflicts, audit_report, beneficiary_list,
+    beneficiary_lists, bulk_soft_delete, bulk_update, clone_item, complete_reminder, create_set,
+    delete_audit, delete_beneficiary, delete_export_profile, delete_provenance_event,
+    delete_reminder, delete_saved_search, delete_template, due_reminders,
+    duplicate_creator_candidates, export_reminders_ics, export_to_spreadsheet, find_beneficiary,
+    find_duplicates, find_items_by_identifier, find_similar_photos, finish_audit, get_attachments,
+    get_audit, get_audits, get_beneficiary, get_condition_history, get_condition_scale,
+    get_creator, get_creator_stats, get_creators, get_exchange_rates, get_export_profile,
+    get_export_profiles, get_filtered_items, get_home_currency, get_identifiers,
+    get_item_beneficiaries, get_money_format, get_provenance, get_provenance_event,
+    get_related_items, get_reminders, get_saved_searches, get_set_members, get_set_stats,
+    get_stats, get_template, get_templates, get_wishlist, hash_photos, init_db, link_creator,
+    lookup_metadata, mark_found, merge_creators, merge_items, metadata::DEFAULT_DUMP_FILE,
+    move_provenance_event, parse_query, query_items, remove_identifier, remove_relation,
+    run_saved_search, save_export_profile, save_search, save_template,
+    saved_search::RELATIVE_DATE_FIELDS, scan_barcode_file, scan_item_attachments,
+    set_condition_scale, set_exchange_rate, set_home_currency, set_money_format, soft_delete_item,
+    start_audit, stream_filtered_items, stream_query_items, thumbnail, unassign_item,
+    unlink_creator, unmark_found, update_beneficiary, update_creator, update_item_fields,
+    update_provenance_event, write_beneficiary_report, write_csv, write_json, write_labels,
+    write_report,
 };
 use rusqlite::Connection;
 
@@ -18,10 +45,189 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("Collection Catalog CLI");
         println!("Usage:");
         println!("  list field=value [field=value...]               - List all items");
-        println!("  export <path> field=value [field=value...]      - Export all items to CSV");
+        println!("  list --saved <name>                             - Run a saved search");
+        println!("  list --query <query>                            - List items matching a query");
+        println!(
+            "  export <path> field=value [field=value...]      - Export all items to CSV, or JSON for a .json path"
+        );
+        println!(
+            "  export <path> --query <query>                   - Export items matching a query"
+        );
+        println!(
+            "  export <path.xlsx|path.ods> [--by-category]   - Export to a spreadsheet with totals"
+        );
+        println!(
+            "  export <path> --profile <name> ...              - Export to CSV using a saved export profile"
+        );
         println!("  add <name> <description> <category> <action>    - Add a new item");
+        println!(
+            "  add --template <template> <name> <description> [field=value...] - Add an item from a template"
+        );
+        println!("  clone <item_id> [--attachments]                 - Copy an item as a new one");
         println!("  delete <id>                                     - Export all items to CSV");
         println!("  update <item_id> field=value [field=value...]   - Update an existing item");
+        println!(
+            "  bulk-update [--dry-run] filter=value... set field=value... - Update all matching items"
+        );
+        println!("  bulk-delete [--dry-run] filter=value...         - Delete all matching items");
+        println!("  stats field=value [field=value...]              - Show item count and totals");
+        println!("  rates                                           - List exchange rates");
+        println!(
+            "  rate <currency> <rate>                          - Set exchange rate to home currency"
+        );
+        println!("  home-currency <currency>                        - Set the home currency");
+        println!(
+            "  money-format [decimal=c] [group=c] [code=pos] - Show or set how amounts are displayed"
+        );
+        println!(
+            "  report <path.html|path.pdf> [group=location] field=value... - Insurance report"
+        );
+        println!("  attach <item_id> <path> [caption]                - Attach a photo or file");
+        println!("  attachments <item_id>                           - List an item's attachments");
+        println!(
+            "  thumbnails <item_id> [small|medium|large] [dir] - Make thumbnails of an item's photos"
+        );
+        println!(
+            "  condition <item_id> [grade] [notes]             - Show or record an item's condition"
+        );
+        println!(
+            "  scale <category> [grade,grade,...]              - Show or set a condition scale"
+        );
+        println!("  link <item_id> <kind> <other_id>                - Relate two items");
+        println!("      kinds: part-of, set-member, related, replaced-by");
+        println!("  unlink <relation_id>                            - Remove a relation");
+        println!("  related <item_id>                               - List an item's relations");
+        println!("  set <set_id> [member_id...]                     - Add members, show set value");
+        println!(
+            "  provenance <item_id>                            - Show an item's ownership chain"
+        );
+        println!("  provenance <item_id> add <role> <name> [field=value...] - Append an event");
+        println!("      roles: owner, dealer, auction; fields: from, to, location, source");
+        println!("  provenance edit <event_id> field=value...       - Edit an event");
+        println!(
+            "  provenance move <event_id> <position>           - Reorder an event (1 = oldest)"
+        );
+        println!("  provenance delete <event_id>                    - Remove an event");
+        println!("  creators                                        - List creators");
+        println!("  creators add <name> [field=value...]            - Add a creator");
+        println!("      fields: aliases (comma separated), born, died, role");
+        println!("  creators edit <creator_id> field=value...       - Edit a creator");
+        println!("  creators link <item_id> <creator_id> [role]     - Link an item to a creator");
+        println!("  creators unlink <item_id> <creator_id>          - Unlink an item and creator");
+        println!("  creators duplicates                             - Suggest creators to merge");
+        println!("  creators merge <keep_id> <merge_id>             - Merge two creators");
+        println!(
+            "  creators stats field=value...                   - Item count and value per creator"
+        );
+        println!("  searches                                        - List saved searches");
+        println!(
+            "  searches save <name> field=value...             - Save a search, replacing one with the same name"
+        );
+        println!(
+            "      date filters also take e.g. \"last 30 days\", \"3 months ago\", \"this year\""
+        );
+        println!("  searches delete <name>                          - Delete a saved search");
+        println!("  export-profiles                                 - List CSV export profiles");
+        println!(
+            "  export-profiles columns                         - List the columns a CSV export can include"
+        );
+        println!(
+            "  export-profiles save <name> option=value...     - Save a profile: columns, headers, delimiter, quoting, date_format, bom"
+        );
+        println!("  export-profiles delete <name>                   - Delete an export profile");
+        println!("  templates                                       - List item templates");
+        println!(
+            "  templates save <name> field=value...            - Save a template, replacing one with the same name"
+        );
+        println!("  templates delete <name>                         - Delete a template");
+        println!(
+            "  dedupe [min_score]                              - Report likely duplicate items"
+        );
+        println!(
+            "  dedupe --photos [max_distance]                  - Report near-identical photos on different items"
+        );
+        println!(
+            "  merge <keep_id> <merge_id> [field...]           - Merge a duplicate into an item"
+        );
+        println!("      fields listed are taken from the merged item, e.g. description location");
+        println!(
+            "  identifiers <item_id>                           - List an item's ISBN/UPC/EAN codes"
+        );
+        println!(
+            "  identifiers add <item_id> <code>                - Add an ISBN/UPC/EAN to an item"
+        );
+        println!("  identifiers remove <identifier_id>              - Remove a code from an item");
+        println!("  identifiers find <code>                         - Find items with a code");
+        println!(
+            "  scan <image_path>|--item <item_id>              - Read barcodes from an image or item photos"
+        );
+        println!(
+            "  lookup <code|image_path> [--dump <path>] [--add] - Look up a code, --add creates the item"
+        );
+        println!(
+            "  labels <path.pdf|path.svg> [--template <name|file.json>] [--skip N] field=value... - QR code labels"
+        );
+        println!("  audits                                          - List inventory audits");
+        println!(
+            "  audit start <name> field=value...               - Start an audit of the matching items"
+        );
+        println!(
+            "  audit scan <audit_id>                           - Mark items found, one id or label per line"
+        );
+        println!("  audit found <audit_id> <id|label>...            - Mark items found");
+        println!("  audit unfound <audit_id> <item_id>              - Undo marking an item found");
+        println!(
+            "  audit report <audit_id>                         - List missing and unexpected items"
+        );
+        println!("  audit finish <audit_id>                         - Close an audit");
+        println!("  audit delete <audit_id>                         - Delete an audit");
+        println!(
+            "  reminders [days]                                - Reminders due within days (30), and overdue"
+        );
+        println!("  reminders item <item_id>                        - List an item's reminders");
+        println!(
+            "  reminders add <item_id> <kind> <due> [every=6 months] [note=text] - Add a reminder"
+        );
+        println!("      kinds: maintenance, warranty, appraisal, other");
+        println!(
+            "  reminders done <reminder_id> [date]             - Mark done, recurring ones come round again"
+        );
+        println!("  reminders delete <reminder_id>                  - Delete a reminder");
+        println!(
+            "  reminders export <path.ics>                     - Export open reminders to a calendar file"
+        );
+        println!(
+            "  beneficiaries                                   - List beneficiaries with item count and value"
+        );
+        println!("  beneficiaries add <name> [field=value...]       - Add a beneficiary");
+        println!("      fields: relationship, contact, notes");
+        println!("  beneficiaries edit <id> field=value...          - Edit a beneficiary");
+        println!(
+            "  beneficiaries delete <id>                       - Delete a beneficiary and their assignments"
+        );
+        println!(
+            "  beneficiaries show <id|name>                    - List the items meant for a beneficiary"
+        );
+        println!(
+            "  beneficiaries assign <item_id> <id|name> [priority=N] [note=text] - Assign an item"
+        );
+        println!("  beneficiaries unassign <item_id> <id|name>      - Remove an assignment");
+        println!(
+            "  beneficiaries conflicts                         - List items assigned to more than one person"
+        );
+        println!(
+            "  beneficiaries report <path.html|pdf|csv> [id|name] - Per-person lists, everyone by default"
+        );
+        println!(
+            "  wishlist                                        - List wishlist items, most wanted first"
+        );
+        println!(
+            "  wishlist add <name> <description> <category> [field=value...] - Add a wishlist item"
+        );
+        println!("      fields: target_price, priority, notes");
+        println!(
+            "  wishlist acquire <item_id> [price] [date]       - Move a wishlist item into the collection"
+        );
         println!("  help                                            - Show help message");
         return Ok(());
     }
@@ -38,64 +244,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args[0].as_str() {
         "list" => {
-            let mut filter = ItemFilter::default();
-
-            // Parse optional filters: field=value
-            for arg in args.iter().skip(1) {
-                if let Some((field, value)) = arg.split_once('=') {
-                    match field {
-                        // Partial string match filters
-                        "name" => filter.name_contains = Some(value.to_string()),
-                        "description" => filter.description_contains = Some(value.to_string()),
-                        "creator" => filter.creator_contains = Some(value.to_string()),
-                        "provenance" => filter.provenance_contains = Some(value.to_string()),
-                        // Enums / Exact match filters
-                        "category" => filter.category = ItemCategory::from_str(value).ok(),
-                        "action" => filter.action = ItemAction::from_str(value).ok(),
-                        "working" => filter.working = value.parse::<bool>().ok(),
-                        "deleted" => filter.deleted = value.parse::<bool>().ok(),
-                        // Date filters
-                        "date_added_min" => {
-                            filter.date_added_min = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "date_added_max" => {
-                            filter.date_added_max = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "last_updated_min" => {
-                            filter.last_updated_min = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "last_updated_max" => {
-                            filter.last_updated_max = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "date_acquired_min" => {
-                            filter.date_acquired_min = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "date_acquired_max" => {
-                            filter.date_acquired_max = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        // Number filters
-                        "age_years_min" => filter.age_years_min = value.parse::<u32>().ok(),
-                        "age_years_max" => filter.age_years_max = value.parse::<u32>().ok(),
-                        "purchase_price_min" => {
-                            filter.purchase_price_min = value.parse::<f64>().ok()
-                        }
-                        "purchase_price_max" => {
-                            filter.purchase_price_max = value.parse::<f64>().ok()
-                        }
-                        "estimated_value_min" => {
-                            filter.estimated_value_min = value.parse::<f64>().ok()
-                        }
-                        "estimated_value_max" => {
-                            filter.estimated_value_max = value.parse::<f64>().ok()
-                        }
-                        // Catchall
-                        _ => eprintln!("Warning: unknown filter field'{}'", field),
+            // Call core function to list items
+            let items = if args.get(1).is_some_and(|a| a == "--saved") {
+                let Some(name) = args.get(2) else {
+                    eprintln!("Usage: list --saved <name>");
+                    return Ok(());
+                };
+                run_saved_search(&conn, name)?
+            } else if args.get(1).is_some_and(|a| a == "--query") {
+                let Some(query) = args.get(2).and_then(|q| parse_query_arg(q)) else {
+                    return Ok(());
+                };
+                query_items(&conn, &query)?
+            } else {
+                match parse_filter_expr(&args[1..]) {
+                    Ok(filter) => get_filtered_items(&conn, filter)?,
+                    Err(e) => {
+                        eprintln!("Invalid filter: {e}");
+                        return Ok(());
                     }
                 }
-            }
-
-            // Call core function to list items
-            let items = get_filtered_items(&conn, filter)?;
+            };
             println!("Listing {} items...", items.len());
             for item in items {
                 println!("{:?}", item);
@@ -107,66 +276,108 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 return Ok(());
             }
             let path = &args[1];
-            let mut filter = ItemFilter::default();
-
-            // Parse optional filters after path
-            for arg in args.iter().skip(2) {
-                if let Some((field, value)) = arg.split_once('=') {
-                    match field {
-                        // Partial string match filters
-                        "name" => filter.name_contains = Some(value.to_string()),
-                        "description" => filter.description_contains = Some(value.to_string()),
-                        "creator" => filter.creator_contains = Some(value.to_string()),
-                        "provenance" => filter.provenance_contains = Some(value.to_string()),
-                        // Enums / Exact match filters
-                        "category" => filter.category = ItemCategory::from_str(value).ok(),
-                        "action" => filter.action = ItemAction::from_str(value).ok(),
-                        "working" => filter.working = value.parse::<bool>().ok(),
-                        "deleted" => filter.deleted = value.parse::<bool>().ok(),
-                        // Date filters
-                        "date_added_min" => {
-                            filter.date_added_min = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "date_added_max" => {
-                            filter.date_added_max = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "last_updated_min" => {
-                            filter.last_updated_min = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "last_updated_max" => {
-                            filter.last_updated_max = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "date_acquired_min" => {
-                            filter.date_acquired_min = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        "date_acquired_max" => {
-                            filter.date_acquired_max = chrono::NaiveDate::from_str(value).ok()
-                        }
-                        // Number filters
-                        "age_years_min" => filter.age_years_min = value.parse::<u32>().ok(),
-                        "age_years_max" => filter.age_years_max = value.parse::<u32>().ok(),
-                        "purchase_price_min" => {
-                            filter.purchase_price_min = value.parse::<f64>().ok()
-                        }
-                        "purchase_price_max" => {
-                            filter.purchase_price_max = value.parse::<f64>().ok()
-                        }
-                        "estimated_value_min" => {
-                            filter.estimated_value_min = value.parse::<f64>().ok()
-                        }
-                        "estimated_value_max" => {
-                            filter.estimated_value_max = value.parse::<f64>().ok()
-                        }
-                        // Catchall
-                        _ => eprintln!("Warning: unknown filter field'{}'", field),
-                    }
+            let mut options = SpreadsheetOptions::default();
+            let mut profile = None;
+            let mut rest = vec![];
+            let mut flags = args[2..].iter();
+            while let Some(arg) = flags.next() {
+                match arg.as_str() {
+                    "--by-category" => options.sheet_per_category = true,
+                    "--profile" => profile = flags.next(),
+                    _ => rest.push(arg.clone()),
                 }
             }
-            let items = get_filtered_items(&conn, filter)?;
-            export_to_csv(&items, path)?;
-            println!("Exported {} items to {}", items.len(), path);
+            let csv_options = match profile {
+                Some(name) => match get_export_profile(&conn, name)? {
+                    Some(profile) => profile.options,
+                    None => {
+                        eprintln!("No export profile named '{}'", name);
+                        return Ok(());
+                    }
+                },
+                None => CsvOptions::default(),
+            };
+            let query = if rest.first().is_some_and(|a| a == "--query") {
+                let Some(query) = rest.get(1).and_then(|q| parse_query_arg(q)) else {
+                    return Ok(());
+                };
+                Some(query)
+            } else {
+                None
+            };
+            let filter = match &query {
+                Some(_) => FilterExpr::default(),
+                None => match parse_filter_expr(&rest) {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        eprintln!("Invalid filter: {e}");
+                        return Ok(());
+                    }
+                },
+            };
+            let count = if SpreadsheetFormat::from_path(path).is_some() {
+                // The summary sheet needs every item up front
+                let items = match &query {
+                    Some(query) => query_items(&conn, query)?,
+                    None => get_filtered_items(&conn, filter)?,
+                };
+                export_to_spreadsheet(&conn, &items, path, &options)?;
+                items.len()
+            } else {
+                // CSV and JSON are written as the items are read
+                let out = fs::File::create(path)?;
+                let json = path.to_ascii_lowercase().ends_with(".json");
+                let write = |items: &mut ItemStream| {
+                    if json {
+                        write_json(items, out)
+                    } else {
+                        write_csv(&conn, items, out, &csv_options)
+                    }
+                };
+                match &query {
+                    Some(query) => stream_query_items(&conn, query, write)?,
+                    None => stream_filtered_items(&conn, filter, write)?,
+                }
+            };
+            println!("Exported {} items to {}", count, path);
         }
 
+        "add" if args.get(1).map(String::as_str) == Some("--template") => {
+            if args.len() < 5 {
+                eprintln!("Usage: add --template <template> <name> <description> [field=value...]");
+                return Ok(());
+            }
+            let Some(mut template) = get_template(&conn, &args[2])? else {
+                eprintln!("No template named '{}'", args[2]);
+                return Ok(());
+            };
+
+            // Fields given here override the template's defaults
+            for update in &args[5..] {
+                let Some((field, value)) = update.split_once('=') else {
+                    eprintln!("Invalid field format: {update}. Use field=value");
+                    return Ok(());
+                };
+                template
+                    .defaults
+                    .insert(field.trim().to_string(), value.trim().to_string());
+            }
+            let mut item = template.draft_item()?;
+            item.name = args[3].clone();
+            item.description = args[4].clone();
+
+            let id = add_item(&conn, &item)?;
+            println!("Added item {}: {}", id, item.name);
+        }
+        "clone" => {
+            let Some(item_id) = args.get(1).and_then(|id| id.parse().ok()) else {
+                eprintln!("Usage: clone <item_id> [--attachments]");
+                return Ok(());
+            };
+            let with_attachments = args.iter().any(|a| a == "--attachments");
+            let id = clone_item(&conn, item_id, with_attachments)?;
+            println!("Copied item {} as item {}", item_id, id);
+        }
         "add" => {
             if args.len() < 5 {
                 eprintln!("Error: add requires <name> <description> <category> <action>");
@@ -196,10 +407,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             let item = Item {
                 id: 0, // DB will auto-assing
-                name: name,
-                description: description,
-                category: category,
-                action: action,
+                name,
+                description,
+                category,
+                action,
                 date_added: today,
                 last_updated: today,
                 deleted: false,
@@ -262,35 +473,1772 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Err(e) => eprintln!("Failed to update item {item_id}: {e}"),
             }
         }
-        "help" => {
-            println!("Collection Catalog CLI");
-            println!("Usage:");
-            println!("  list field=value [field=value...]               - List all items");
-            println!("  export <path> field=value [field=value...]      - Export all items to CSV");
-            println!("  add <name> <description> <category> <action>    - Add a new item");
-            println!("  delete <id>                                     - Export all items to CSV");
-            println!("  update <item_id> field=value [field=value...]   - Update an existing item");
-            println!("  help                                            - Show this help message");
+        "bulk-update" => {
+            let dry_run = args.get(1).is_some_and(|a| a == "--dry-run");
+            let args = &args[1 + dry_run as usize..];
+            let Some(set) = args.iter().position(|a| a == "set") else {
+                eprintln!("Usage: bulk-update [--dry-run] filter=value... set field=value...");
+                return Ok(());
+            };
+            if set == 0 || set + 1 == args.len() {
+                eprintln!("Give at least one filter and one field to update");
+                return Ok(());
+            }
 
-            println!("\nFilterable fields for list/export:");
-            println!("  name, description, creator, provenance (partial match)");
+            let mut updates = HashMap::new();
+            for update in &args[set + 1..] {
+                if let Some((field, value)) = update.split_once('=') {
+                    updates.insert(field.trim(), value.trim().to_string());
+                } else {
+                    eprintln!("Invalid update format: {update}. Use field=value");
+                    return Ok(());
+                }
+            }
+
+            let filter = match parse_filter_strict(&args[..set]) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return Ok(());
+                }
+            };
+            match bulk_update(&conn, filter, updates, dry_run) {
+                Ok(ids) if dry_run => println!("Would update {} items: {:?}", ids.len(), ids),
+                Ok(ids) => println!("Updated {} items: {:?}", ids.len(), ids),
+                Err(e) => eprintln!("Bulk update failed, nothing was changed: {e}"),
+            }
+        }
+        "bulk-delete" => {
+            let dry_run = args.get(1).is_some_and(|a| a == "--dry-run");
+            let args = &args[1 + dry_run as usize..];
+            if args.is_empty() {
+                eprintln!("Usage: bulk-delete [--dry-run] filter=value...");
+                return Ok(());
+            }
+
+            let filter = match parse_filter_strict(args) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return Ok(());
+                }
+            };
+            match bulk_soft_delete(&conn, filter, dry_run) {
+                Ok(ids) if dry_run => println!("Would delete {} items: {:?}", ids.len(), ids),
+                Ok(ids) => println!("Marked {} items as deleted: {:?}", ids.len(), ids),
+                Err(e) => eprintln!("Bulk delete failed, nothing was changed: {e}"),
+            }
+        }
+        "stats" => {
+            let filter = parse_filter(&args[1..]);
+            let stats = get_stats(&conn, filter)?;
+            let fmt = get_money_format(&conn)?;
+
+            println!("Items: {}", stats.item_count);
+            for (label, totals, home_total) in [
+                (
+                    "Purchase price",
+                    &stats.purchase_totals,
+                    stats.purchase_total_home,
+                ),
+                (
+                    "Estimated value",
+                    &stats.estimated_totals,
+                    stats.estimated_total_home,
+                ),
+            ] {
+                println!("{label}:");
+                for total in totals {
+                    println!("  {}", total.format(&fmt));
+                }
+                match home_total {
+                    Some(total) => println!("  Total: {}", total.format(&fmt)),
+                    None => println!("  Total: unavailable"),
+                }
+            }
+            println!("Pieces: {}", stats.total_quantity);
+            if let Some(weight) = stats.total_weight {
+                println!("Total weight: {}", weight);
+            }
+            if stats.total_volume_m3 > 0.0 {
+                println!("Total volume: {:.3} m³", stats.total_volume_m3);
+            }
+            if !stats.missing_rates.is_empty() {
+                let codes: Vec<String> =
+                    stats.missing_rates.iter().map(|c| c.to_string()).collect();
+                println!(
+                    "Missing exchange rates to {}: {}",
+                    stats.home_currency,
+                    codes.join(", ")
+                );
+            }
+        }
+        "rates" => {
+            println!("Home currency: {}", get_home_currency(&conn)?);
+            for rate in get_exchange_rates(&conn)? {
+                println!(
+                    "  {} {} (updated {})",
+                    rate.currency, rate.rate, rate.updated
+                );
+            }
+        }
+        "rate" => {
+            if args.len() < 3 {
+                eprintln!("Usage: rate <currency> <rate>");
+                return Ok(());
+            }
+            let currency = Currency::from_str(&args[1])?;
+            let rate = Rate::from_str(&args[2])?;
+            set_exchange_rate(&conn, currency, rate)?;
+            println!("1 {} = {} {}", currency, rate, get_home_currency(&conn)?);
+        }
+        "home-currency" => {
+            if args.len() < 2 {
+                eprintln!("Usage: home-currency <currency>");
+                return Ok(());
+            }
+            let currency = Currency::from_str(&args[1])?;
+            set_home_currency(&conn, currency)?;
+            println!("Home currency set to {}", currency);
+        }
+        "money-format" => {
+            let mut fmt = get_money_format(&conn)?;
+            for arg in &args[1..] {
+                let single = |sep: &str| sep.chars().count() == 1;
+                match arg.split_once('=') {
+                    Some(("decimal", sep)) if single(sep) => fmt.decimal_separator = sep.parse()?,
+                    Some(("group", "none")) => fmt.group_separator = None,
+                    Some(("group", sep)) if single(sep) => fmt.group_separator = Some(sep.parse()?),
+                    Some(("code", "before")) => fmt.code_position = CodePosition::Before,
+                    Some(("code", "after")) => fmt.code_position = CodePosition::After,
+                    Some(("code", "hidden")) => fmt.code_position = CodePosition::Hidden,
+                    _ => {
+                        eprintln!(
+                            "Usage: money-format [decimal=<char>] [group=<char>|none] [code=before|after|hidden]"
+                        );
+                        return Ok(());
+                    }
+                }
+            }
+            if args.len() > 1 {
+                set_money_format(&conn, &fmt)?;
+            }
             println!(
-                "  category (Exact: Book, Artwork, Collectible, Document, Electronic, Furniture, Jewelry, Other)"
+                "Amounts are shown like {}",
+                Money::from_minor(123456789, get_home_currency(&conn)?).format(&fmt)
             );
-            println!("  action (Exact: Keep, Sell)");
-            println!("  working (true/false)");
-            println!("  deleted (true/false)");
-            println!("  date_added_min, date_added_max (YYYY-MM-DD)");
-            println!("  last_updated_min, last_updated_max (YYYY-MM-DD)");
-            println!("  date_acquired_min, date_acquired_max (YYYY-MM-DD)");
-            println!("  age_years_min, age_years_max (integer)");
-            println!("  purchase_price_min, purchase_price_max (float)");
-            println!("  estimated_value_min, estimated_value_max (float)");
         }
-        _ => {
-            eprintln!("unknown command: {}", args[0]);
+        "report" => {
+            if args.len() < 2 {
+                eprintln!(
+                    "Usage: report <path.html|path.pdf> [group=category|location] field=value..."
+                );
+                return Ok(());
+            }
+            let path = &args[1];
+            let mut options = ReportOptions {
+                money_format: get_money_format(&conn)?,
+                ..Default::default()
+            };
+            let mut filter_args = vec![];
+            for arg in &args[2..] {
+                match arg.split_once('=') {
+                    Some(("group", "location")) => options.grouping = ReportGrouping::Location,
+                    Some(("group", "category")) => options.grouping = ReportGrouping::Category,
+                    Some(("title", title)) => options.title = title.to_string(),
+                    _ => filter_args.push(arg.clone()),
+                }
+            }
+            let filter = parse_filter(&filter_args);
+            let count = write_report(&conn, filter, &options, path)?;
+            println!("Wrote report of {} items to {}", count, path);
         }
-    }
-
+        "attach" => {
+            if args.len() < 3 {
+                eprintln!("Usage: attach <item_id> <path> [caption]");
+                return Ok(());
+            }
+            let item_id: i32 = match args[1].parse() {
+                Ok(id) => id,
+                Err(_) => {
+                    eprintln!("Error: item_id must be an integer, got '{}'", args[1]);
+                    return Ok(());
+                }
+            };
+            let path = fs::canonicalize(&args[2])?;
+            let id = add_attachment(
+                &conn,
+                item_id,
+                &path.to_string_lossy(),
+                args.get(3).map(String::as_str),
+            )?;
+            println!(
+                "Attached {} to item {} (attachment {})",
+                path.display(),
+                item_id,
+                id
+            );
+        }
+        "attachments" => {
+            if args.len() < 2 {
+                eprintln!("Usage: attachments <item_id>");
+                return Ok(());
+            }
+            let item_id: i32 = match args[1].parse() {
+                Ok(id) => id,
+                Err(_) => {
+                    eprintln!("Error: item_id must be an integer, got '{}'", args[1]);
+                    return Ok(());
+                }
+            };
+            for attachment in get_attachments(&conn, item_id)? {
+                println!(
+                    "  [{}] {} {}",
+                    attachment.id,
+                    attachment.path,
+                    attachment.caption.unwrap_or_default()
+                );
+                if attachment.captured.is_some() || attachment.camera.is_some() {
+                    println!(
+                        "      taken {} {}",
+                        attachment
+                            .captured
+                            .map(|d| d.to_string())
+                            .unwrap_or_default(),
+                        attachment
+                            .camera
+                            .map(|c| format!("with {}", c))
+                            .unwrap_or_default()
+                    );
+                }
+            }
+        }
+        "thumbnails" => {
+            let Some(item_id) = args.get(1).and_then(|id| id.parse::<i32>().ok()) else {
+                eprintln!("Usage: thumbnails <item_id> [small|medium|large] [dir]");
+                return Ok(());
+            };
+            let size = match args.get(2) {
+                Some(size) => ThumbnailSize::from_str(size)?,
+                None => ThumbnailSize::default(),
+            };
+            let dir = Path::new(args.get(3).map_or("thumbnails", String::as_str));
+            for attachment in get_attachments(&conn, item_id)? {
+                match thumbnail(&attachment, size, dir) {
+                    Ok(path) => println!("  [{}] {}", attachment.id, path.display()),
+                    Err(e) => eprintln!("  [{}] skipped: {}", attachment.id, e),
+                }
+            }
+        }
+        "condition" => {
+            if args.len() < 2 {
+                eprintln!("Usage: condition <item_id> [grade] [notes]");
+                return Ok(());
+            }
+            let item_id: i32 = match args[1].parse() {
+                Ok(id) => id,
+                Err(_) => {
+                    eprintln!("Error: item_id must be an integer, got '{}'", args[1]);
+                    return Ok(());
+                }
+            };
+            if let Some(grade) = args.get(2) {
+                let report = ConditionReport {
+                    item_id,
+                    date: chrono::Local::now().date_naive(),
+                    grade: grade.to_string(),
+                    notes: args.get(3).cloned(),
+                    ..Default::default()
+                };
+                let id = add_condition_report(&conn, &report)?;
+                println!("Recorded condition report {} for item {}", id, item_id);
+            } else {
+                for report in get_condition_history(&conn, item_id)? {
+                    println!(
+                        "  {} {} {}",
+                        report.date,
+                        report.grade,
+                        report.notes.unwrap_or_default()
+                    );
+                    for photo in report.photos {
+                        println!("    photo: {}", photo.path);
+                    }
+                }
+            }
+        }
+        "scale" => {
+            if args.len() < 2 {
+                eprintln!("Usage: scale <category> [grade,grade,...]");
+                return Ok(());
+            }
+            let category = match ItemCategory::from_str(&args[1]) {
+                Ok(c) => c,
+                Err(_) => {
+                    eprintln!("Invalid category: {}", args[1]);
+                    return Ok(());
+                }
+            };
+            if let Some(grades) = args.get(2) {
+                let grades: Vec<String> = grades.split(',').map(String::from).collect();
+                set_condition_scale(&conn, &category, &grades)?;
+            }
+            println!(
+                "{}: {}",
+                category,
+                get_condition_scale(&conn, &category)?.join(", ")
+            );
+        }
+        "link" => {
+            if args.len() < 4 {
+                eprintln!(
+                    "Usage: link <item_id> <part-of|set-member|related|replaced-by> <other_id>"
+                );
+                return Ok(());
+            }
+            let (Ok(item_id), Ok(other_id)) = (args[1].parse::<i32>(), args[3].parse::<i32>())
+            else {
+                eprintln!("Error: item ids must be integers");
+                return Ok(());
+            };
+            let kind = RelationKind::from_str(&args[2])?;
+            let id = add_relation(&conn, item_id, kind, other_id)?;
+            println!(
+                "Linked item {} {} item {} (relation {})",
+                item_id, kind, other_id, id
+            );
+        }
+        "unlink" => {
+            let Some(Ok(relation_id)) = args.get(1).map(|a| a.parse::<i32>()) else {
+                eprintln!("Usage: unlink <relation_id>");
+                return Ok(());
+            };
+            remove_relation(&conn, relation_id)?;
+            println!("Removed relation {}", relation_id);
+        }
+        "related" => {
+            let Some(Ok(item_id)) = args.get(1).map(|a| a.parse::<i32>()) else {
+                eprintln!("Usage: related <item_id>");
+                return Ok(());
+            };
+            for related in get_related_items(&conn, item_id)? {
+                println!(
+                    "  [{}] {} {} ({})",
+                    related.relation_id, related.label, related.item.name, related.item.id
+                );
+            }
+        }
+        "set" => {
+            let Some(Ok(set_id)) = args.get(1).map(|a| a.parse::<i32>()) else {
+                eprintln!("Usage: set <set_id> [member_id...]");
+                return Ok(());
+            };
+            let member_ids = args[2..]
+                .iter()
+                .map(|a| a.parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()?;
+            if !member_ids.is_empty() {
+                create_set(&conn, set_id, &member_ids)?;
+            }
+            for member in get_set_members(&conn, set_id)? {
+                println!("  {} ({})", member.name, member.id);
+            }
+            let stats = get_set_stats(&conn, set_id)?;
+            let fmt = get_money_format(&conn)?;
+            println!("Items in set: {}", stats.item_count);
+            match stats.estimated_total_home {
+                Some(total) => println!("Estimated value: {}", total.format(&fmt)),
+                None => println!("Estimated value: unavailable"),
+            }
+        }
+        "provenance" => {
+            let id = args.get(2).and_then(|a| a.parse::<i32>().ok());
+            match (args.get(1).map(String::as_str), id) {
+                (Some("edit"), Some(event_id)) => {
+                    let Some(mut event) = get_provenance_event(&conn, event_id)? else {
+                        eprintln!("Provenance event {} not found", event_id);
+                        return Ok(());
+                    };
+                    apply_provenance_fields(&mut event, &args[3..])?;
+                    update_provenance_event(&conn, &event)?;
+                    println!("Updated: {}", event);
+                }
+                (Some("move"), Some(event_id)) => {
+                    let Some(Ok(position)) = args.get(3).map(|a| a.parse::<usize>()) else {
+                        eprintln!("Usage: provenance move <event_id> <position>");
+                        return Ok(());
+                    };
+                    move_provenance_event(&conn, event_id, position)?;
+                    println!(
+                        "Moved provenance event {} to position {}",
+                        event_id, position
+                    );
+                }
+                (Some("delete"), Some(event_id)) => {
+                    delete_provenance_event(&conn, event_id)?;
+                    println!("Deleted provenance event {}", event_id);
+                }
+                (Some(item), _) if item.parse::<i32>().is_ok() => {
+                    let item_id = item.parse::<i32>()?;
+                    if args.get(2).map(String::as_str) == Some("add") {
+                        if args.len() < 5 {
+                            eprintln!(
+                                "Usage: provenance <item_id> add <role> <name> [field=value...]"
+                            );
+                            return Ok(());
+                        }
+                        let mut event = ProvenanceEvent {
+                            item_id,
+                            role: ProvenanceRole::from_str(&args[3])?,
+                            name: args[4].clone(),
+                            ..Default::default()
+                        };
+                        apply_provenance_fields(&mut event, &args[5..])?;
+                        let id = append_provenance_event(&conn, &event)?;
+                        println!("Added provenance event {}: {}", id, event);
+                    } else {
+                        for event in get_provenance(&conn, item_id)? {
+                            println!("  {}. [{}] {}", event.position, event.id, event);
+                        }
+                    }
+                }
+                _ => eprintln!(
+                    "Usage: provenance <item_id> | <item_id> add ... | edit|move|delete <event_id> ..."
+                ),
+            }
+        }
+        "creators" => match args.get(1).map(String::as_str) {
+            None => {
+                for creator in get_creators(&conn)? {
+                    let role = creator
+                        .role
+                        .map(|r| format!(" [{}]", r))
+                        .unwrap_or_default();
+                    println!("  ({}) {}{}", creator.id, creator.display_name(), role);
+                    if !creator.aliases.is_empty() {
+                        println!("      aka {}", creator.aliases.join(", "));
+                    }
+                }
+            }
+            Some("add") if args.len() > 2 => {
+                let mut creator = Creator {
+                    name: args[2].clone(),
+                    ..Default::default()
+                };
+                apply_creator_fields(&mut creator, &args[3..])?;
+                let id = add_creator(&conn, &creator)?;
+                println!("Added creator {}: {}", id, creator.display_name());
+            }
+            Some("edit") if args.len() > 2 => {
+                let Some(mut creator) = get_creator(&conn, args[2].parse()?)? else {
+                    eprintln!("Creator {} not found", args[2]);
+                    return Ok(());
+                };
+                apply_creator_fields(&mut creator, &args[3..])?;
+                update_creator(&conn, &creator)?;
+                println!("Updated creator {}: {}", creator.id, creator.display_name());
+            }
+            Some("link") if args.len() > 3 => {
+                let item_id: i32 = args[2].parse()?;
+                let creator_id: i32 = args[3].parse()?;
+                let role = match args.get(4) {
+                    Some(role) => CreatorRole::from_str(role)?,
+                    None => CreatorRole::default(),
+                };
+                link_creator(&conn, item_id, creator_id, role)?;
+                println!(
+                    "Linked item {} to creator {} as {}",
+                    item_id, creator_id, role
+                );
+            }
+            Some("unlink") if args.len() > 3 => {
+                unlink_creator(&conn, args[2].parse()?, args[3].parse()?)?;
+                println!("Unlinked item {} from creator {}", args[2], args[3]);
+            }
+            Some("duplicates") => {
+                for (a, b) in duplicate_creator_candidates(&conn)? {
+                    println!("  ({}) {}  <->  ({}) {}", a.id, a.name, b.id, b.name);
+                }
+            }
+            Some("merge") if args.len() > 3 => {
+                let kept = merge_creators(&conn, args[2].parse()?, args[3].parse()?)?;
+                println!(
+                    "Merged into ({}) {}, aka {}",
+                    kept.id,
+                    kept.name,
+                    kept.aliases.join(", ")
+                );
+            }
+            Some("stats") => {
+                let filter = parse_filter(&args[2..]);
+                let fmt = get_money_format(&conn)?;
+                for entry in get_creator_stats(&conn, filter)? {
+                    let value = entry
+                        .stats
+                        .estimated_total_home
+                        .map(|m| m.format(&fmt))
+                        .unwrap_or_else(|| "unavailable".to_string());
+                    println!(
+                        "  {}: {} items, estimated value {}",
+                        entry.creator.name, entry.stats.item_count, value
+                    );
+                }
+            }
+            _ => eprintln!("Usage: creators [add|edit|link|unlink|duplicates|merge|stats] ..."),
+        },
+        "searches" => match args.get(1).map(String::as_str) {
+            None => {
+                for search in get_saved_searches(&conn)? {
+                    println!("  {}", search.name);
+                    for (field, expr) in &search.relative_dates {
+                        println!("      {} = {}", field, expr);
+                    }
+                }
+            }
+            Some("save") if args.len() > 3 => {
+                // Date filters that aren't plain dates are kept as expressions
+                let mut relative_dates = BTreeMap::new();
+                let mut filters = vec![];
+                for arg in &args[3..] {
+                    match arg.split_once('=') {
+                        Some((field, value))
+                            if RELATIVE_DATE_FIELDS.contains(&field)
+                                && chrono::NaiveDate::from_str(value).is_err() =>
+                        {
+                            relative_dates.insert(field.to_string(), DateExpr::from_str(value)?);
+                        }
+                        _ => filters.push(arg.clone()),
+                    }
+                }
+                let search = SavedSearch {
+                    name: args[2].clone(),
+                    filter: parse_filter(&filters),
+                    relative_dates,
+                    ..Default::default()
+                };
+                save_search(&conn, &search)?;
+                println!("Saved search '{}'", search.name);
+            }
+            Some("delete") if args.len() > 2 => {
+                if delete_saved_search(&conn, &args[2])? {
+                    println!("Deleted saved search '{}'", args[2]);
+                } else {
+                    eprintln!("No saved search named '{}'", args[2]);
+                }
+            }
+            _ => eprintln!("Usage: searches [save <name> field=value...|delete <name>]"),
+        },
+        "export-profiles" => match args.get(1).map(String::as_str) {
+            None => {
+                for profile in get_export_profiles(&conn)? {
+                    println!("  {}", profile.name);
+                    let options = &profile.options;
+                    if !options.columns.is_empty() {
+                        println!("      columns = {}", options.columns.join(","));
+                    }
+                    println!("      headers = {:?}", options.headers);
+                    println!("      delimiter = {:?}", options.delimiter);
+                    println!("      quoting = {:?}", options.quoting);
+                    println!("      date_format = {}", options.date_format);
+                    println!("      bom = {}", options.bom);
+                }
+            }
+            Some("columns") => {
+                for (key, header) in CSV_COLUMNS {
+                    println!("  {:<20} {}", key, header);
+                }
+            }
+            Some("save") if args.len() > 3 => {
+                let mut profile = ExportProfile {
+                    name: args[2].clone(),
+                    ..Default::default()
+                };
+                for arg in &args[3..] {
+                    let Some((option, value)) = arg.split_once('=') else {
+                        eprintln!("Invalid option format: {arg}. Use option=value");
+                        return Ok(());
+                    };
+                    if let Err(e) = profile.options.set(option.trim(), value) {
+                        eprintln!("{e}");
+                        return Ok(());
+                    }
+                }
+                save_export_profile(&conn, &profile)?;
+                println!("Saved export profile '{}'", profile.name);
+            }
+            Some("delete") if args.len() > 2 => {
+                if delete_export_profile(&conn, &args[2])? {
+                    println!("Deleted export profile '{}'", args[2]);
+                } else {
+                    eprintln!("No export profile named '{}'", args[2]);
+                }
+            }
+            _ => eprintln!(
+                "Usage: export-profiles [columns|save <name> option=value...|delete <name>]"
+            ),
+        },
+        "templates" => match args.get(1).map(String::as_str) {
+            None => {
+                for template in get_templates(&conn)? {
+                    println!("  {}", template.name);
+                    for (field, value) in &template.defaults {
+                        println!("      {} = {}", field, value);
+                    }
+                }
+            }
+            Some("save") if args.len() > 3 => {
+                let mut defaults = BTreeMap::new();
+                for arg in &args[3..] {
+                    let Some((field, value)) = arg.split_once('=') else {
+                        eprintln!("Invalid field format: {arg}. Use field=value");
+                        return Ok(());
+                    };
+                    defaults.insert(field.trim().to_string(), value.trim().to_string());
+                }
+                let template = ItemTemplate {
+                    name: args[2].clone(),
+                    defaults,
+                    ..Default::default()
+                };
+                save_template(&conn, &template)?;
+                println!("Saved template '{}'", template.name);
+            }
+            Some("delete") if args.len() > 2 => {
+                if delete_template(&conn, &args[2])? {
+                    println!("Deleted template '{}'", args[2]);
+                } else {
+                    eprintln!("No template named '{}'", args[2]);
+                }
+            }
+            _ => eprintln!("Usage: templates [save <name> field=value...|delete <name>]"),
+        },
+        "dedupe" if args.get(1).map(String::as_str) == Some("--photos") => {
+            let max_distance = match args.get(2) {
+                Some(distance) => distance.parse()?,
+                None => DEFAULT_PHOTO_DISTANCE,
+            };
+            hash_photos(&conn)?;
+            let matches = find_similar_photos(&conn, max_distance)?;
+            println!("Found {} pairs of similar photos", matches.len());
+            for photo in matches {
+                println!(
+                    "  {} bits apart  item {} attachment {}  <->  item {} attachment {}",
+                    photo.distance,
+                    photo.item_id,
+                    photo.attachment_id,
+                    photo.other_item_id,
+                    photo.other_attachment_id
+                );
+            }
+        }
+        "dedupe" => {
+            let min_score = match args.get(1) {
+                Some(score) => score.parse()?,
+                None => DEFAULT_MIN_SCORE,
+            };
+            // Photos attached before hashes were kept are hashed first
+            hash_photos(&conn)?;
+            let candidates = find_duplicates(&conn, min_score)?;
+            println!("Found {} possible duplicates", candidates.len());
+            for candidate in candidates {
+                println!(
+                    "  {:.0}%  ({}) {}  <->  ({}) {}",
+                    candidate.score * 100.0,
+                    candidate.item.id,
+                    candidate.item.name,
+                    candidate.other.id,
+                    candidate.other.name
+                );
+                println!("        {}", candidate.reasons.join(", "));
+                if let Some(photo) = candidate.photo_match {
+                    println!(
+                        "        compare attachments {} and {}",
+                        photo.attachment_id, photo.other_attachment_id
+                    );
+                }
+            }
+        }
+        "merge" => {
+            if args.len() < 3 {
+                eprintln!("Usage: merge <keep_id> <merge_id> [field...]");
+                return Ok(());
+            }
+            let take = args[3..]
+                .iter()
+                .map(|field| MergeField::from_str(field))
+                .collect::<Result<Vec<_>, _>>()?;
+            let item = merge_items(&conn, args[1].parse()?, args[2].parse()?, &take)?;
+            println!(
+                "Merged item {} into item {}: {}",
+                args[2], item.id, item.name
+            );
+        }
+        "identifiers" => match args.get(1).map(String::as_str) {
+            Some("add") if args.len() > 3 => {
+                let item_id: i32 = args[2].parse()?;
+                let identifier = Identifier::from_str(&args[3])?;
+                add_identifier(&conn, item_id, &identifier)?;
+                println!("Added {} to item {}", identifier, item_id);
+            }
+            Some("remove") if args.len() > 2 => {
+                remove_identifier(&conn, args[2].parse()?)?;
+                println!("Removed identifier {}", args[2]);
+            }
+            Some("find") if args.len() > 2 => {
+                let identifier = Identifier::from_str(&args[2])?;
+                for item in find_items_by_identifier(&conn, &identifier)? {
+                    println!("  [{}] {}", item.id, item.name);
+                }
+            }
+            Some(item_id) if item_id.parse::<i32>().is_ok() => {
+                for stored in get_identifiers(&conn, item_id.parse()?)? {
+                    println!("  [{}] {}", stored.id, stored.identifier);
+                }
+            }
+            _ => eprintln!(
+                "Usage: identifiers <item_id>|add <item_id> <code>|remove <identifier_id>|find <code>"
+            ),
+        },
+        "scan" => {
+            let codes = match (args.get(1).map(String::as_str), args.get(2)) {
+                (Some("--item"), Some(item_id)) => scan_item_attachments(&conn, item_id.parse()?)?,
+                (Some(path), _) if path != "--item" => scan_barcode_file(Path::new(path))?,
+                _ => {
+                    eprintln!("Usage: scan <image_path>|--item <item_id>");
+                    return Ok(());
+                }
+            };
+            if codes.is_empty() {
+                println!("No barcodes found");
+            }
+            for code in codes {
+                println!("  {}", code);
+            }
+        }
+        "lookup" => {
+            let Some(input) = args.get(1) else {
+                eprintln!("Usage: lookup <code|image_path> [--dump <path>] [--add]");
+                return Ok(());
+            };
+            let dump = match args.iter().position(|a| a == "--dump") {
+                Some(i) => Path::new(args.get(i + 1).ok_or("--dump needs a path")?).to_path_buf(),
+                None => path.join(DEFAULT_DUMP_FILE),
+            };
+            let add = args.iter().any(|a| a == "--add");
+
+            // An image is scanned for its barcode first
+            let image = Path::new(input).is_file().then(|| Path::new(input));
+            let identifier = match image {
+                Some(image) => scan_barcode_file(image)?
+                    .into_iter()
+                    .next()
+                    .ok_or("No barcode found in the image")?,
+                None => Identifier::from_str(input)?,
+            };
+            let provider = OfflineProvider::open(&dump)?;
+            let Some(metadata) = lookup_metadata(&[&provider], &identifier)? else {
+                println!("{} not found in {}", identifier, dump.display());
+                return Ok(());
+            };
+            let item = metadata.draft_item(&identifier);
+            println!("{} ({})", identifier, metadata.source);
+            println!("  name: {}", item.name);
+            println!("  description: {}", item.description);
+            println!("  category: {}", item.category);
+            if let Some(creator) = &item.creator {
+                println!("  creator: {}", creator);
+            }
+            if let Some(date_made) = &item.date_made {
+                println!("  date_made: {}", date_made);
+            }
+            if add {
+                let id = add_item(&conn, &item)?;
+                add_identifier(&conn, id, &identifier)?;
+                if let Some(image) = image {
+                    let image = fs::canonicalize(image)?;
+                    add_attachment(&conn, id, &image.to_string_lossy(), Some("Barcode"))?;
+                }
+                println!("Added item {}", id);
+            }
+        }
+        "labels" => {
+            if args.len() < 2 {
+                eprintln!(
+                    "Usage: labels <path.pdf|path.svg> [--template <name|file.json>] [--skip N] field=value..."
+                );
+                return Ok(());
+            }
+            let path = &args[1];
+            let mut options = LabelOptions::default();
+            let mut filter_args = vec![];
+            let mut rest = args[2..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--template" => {
+                        let name = rest.next().ok_or("--template needs a name or file")?;
+                        options.template = LabelTemplate::find(name)?;
+                    }
+                    "--skip" => {
+                        let skip = rest.next().ok_or("--skip needs a number")?;
+                        options.skip = skip
+                            .parse()
+                            .map_err(|_| format!("--skip must be a number, got '{}'", skip))?;
+                    }
+                    _ => filter_args.push(arg.clone()),
+                }
+            }
+            let filter = parse_filter(&filter_args);
+            let count = write_labels(&conn, filter, &options, path)?;
+            println!(
+                "Wrote {} labels on {} to {}",
+                count, options.template.name, path
+            );
+        }
+        "audits" => {
+            for audit in get_audits(&conn)? {
+                let status = match audit.finished {
+                    Some(date) => format!("finished {}", date),
+                    None => "open".to_string(),
+                };
+                println!(
+                    "  {}  {} (started {}, {})  {}/{} found, {} unexpected",
+                    audit.id,
+                    audit.name,
+                    audit.started,
+                    status,
+                    audit.found,
+                    audit.expected,
+                    audit.unexpected
+                );
+            }
+        }
+        "audit" => {
+            let usage = "Usage: audit start <name> field=value...|scan <audit_id>|found <audit_id> <id|label>...|unfound <audit_id> <item_id>|report <audit_id>|finish <audit_id>|delete <audit_id>";
+            let (Some(action), Some(target)) = (args.get(1), args.get(2)) else {
+                eprintln!("{}", usage);
+                return Ok(());
+            };
+            if action == "start" {
+                let filter = parse_filter(&args[3..]);
+                let id = start_audit(&conn, target, filter)?;
+                let audit = get_audit(&conn, id)?.ok_or("Audit not saved")?;
+                println!("Started audit {} of {} items", id, audit.expected);
+                return Ok(());
+            }
+            let audit_id: i32 = match target.parse() {
+                Ok(id) => id,
+                Err(_) => {
+                    eprintln!("Error: audit_id must be an integer, got '{}'", target);
+                    return Ok(());
+                }
+            };
+            let print_mark = |scan: &str| match mark_found(&conn, audit_id, scan) {
+                Ok(mark) => {
+                    let note = match (mark.expected, mark.already_found) {
+                        (_, true) => " (already found)",
+                        (false, _) => " (UNEXPECTED)",
+                        _ => "",
+                    };
+                    println!("  found ({}) {}{}", mark.item.id, mark.item.name, note);
+                }
+                Err(e) => eprintln!("  {}: {}", scan, e),
+            };
+            match action.as_str() {
+                "found" => args[3..].iter().for_each(|scan| print_mark(scan)),
+                // A barcode scanner types each label followed by Enter
+                "scan" => {
+                    println!("Scan labels or type item ids, a blank line to stop");
+                    for line in std::io::stdin().lines() {
+                        let line = line?;
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        print_mark(&line);
+                    }
+                }
+                "unfound" => {
+                    let item_id: i32 = args.get(3).ok_or(usage)?.parse()?;
+                    unmark_found(&conn, audit_id, item_id)?;
+                    println!("Item {} is no longer marked found", item_id);
+                }
+                "report" => {
+                    let report = audit_report(&conn, audit_id)?;
+                    let audit = &report.audit;
+                    println!(
+                        "Audit {}: {} (started {})",
+                        audit.id, audit.name, audit.started
+                    );
+                    println!(
+                        "{} of {} found, {} missing, {} unexpected",
+                        audit.found,
+                        audit.expected,
+                        report.missing.len(),
+                        report.unexpected.len()
+                    );
+                    for (heading, items) in [
+                        ("Missing", &report.missing),
+                        ("Unexpected", &report.unexpected),
+                    ] {
+                        if !items.is_empty() {
+                            println!("\n{}:", heading);
+                        }
+                        for item in items {
+                            println!(
+                                "  ({}) {}  [{}]",
+                                item.id,
+                                item.name,
+                                item.location.as_deref().unwrap_or("no location")
+                            );
+                        }
+                    }
+                }
+                "finish" => {
+                    finish_audit(&conn, audit_id)?;
+                    println!("Finished audit {}", audit_id);
+                }
+                "delete" => {
+                    if delete_audit(&conn, audit_id)? {
+                        println!("Deleted audit {}", audit_id);
+                    } else {
+                        eprintln!("No audit {}", audit_id);
+                    }
+                }
+                _ => eprintln!("{}", usage),
+            }
+        }
+        "reminders" => {
+            let today = chrono::Local::now().date_naive();
+            let print_due = |due: &DueReminder| {
+                let reminder = &due.reminder;
+                println!(
+                    "  {}  {}{}  {} ({}) {}{}{}",
+                    reminder.id,
+                    reminder.due,
+                    if due.is_overdue(today) {
+                        " OVERDUE"
+                    } else {
+                        ""
+                    },
+                    reminder.kind,
+                    reminder.item_id,
+                    due.item_name,
+                    reminder
+                        .recurrence
+                        .map(|r| format!(", {}", r))
+                        .unwrap_or_default(),
+                    reminder
+                        .note
+                        .as_ref()
+                        .map(|n| format!(" - {}", n))
+                        .unwrap_or_default()
+                );
+            };
+            match args.get(1).map(String::as_str) {
+                None => due_reminders(&conn, today + chrono::Days::new(30))?
+                    .iter()
+                    .for_each(print_due),
+                Some(days) if days.parse::<u64>().is_ok() => {
+                    due_reminders(&conn, today + chrono::Days::new(days.parse()?))?
+                        .iter()
+                        .for_each(print_due)
+                }
+                Some("item") if args.len() > 2 => {
+                    for reminder in get_reminders(&conn, args[2].parse()?)? {
+                        let status = match (reminder.is_open(), reminder.last_done) {
+                            (false, _) => "done".to_string(),
+                            (true, Some(date)) => format!("last done {}", date),
+                            (true, None) => "open".to_string(),
+                        };
+                        println!(
+                            "  {}  {}  {} ({}){}",
+                            reminder.id,
+                            reminder.due,
+                            reminder.kind,
+                            status,
+                            reminder
+                                .recurrence
+                                .map(|r| format!(", {}", r))
+                                .unwrap_or_default()
+                        );
+                    }
+                }
+                Some("add") if args.len() > 4 => {
+                    let mut reminder = Reminder {
+                        item_id: args[2].parse()?,
+                        kind: ReminderKind::from_str(&args[3])?,
+                        due: chrono::NaiveDate::from_str(&args[4])?,
+                        ..Default::default()
+                    };
+                    for arg in &args[5..] {
+                        match arg.split_once('=') {
+                            Some(("every", every)) => reminder.recurrence = Some(every.parse()?),
+                            Some(("note", note)) => reminder.note = Some(note.to_string()),
+                            _ => {
+                                eprintln!("Unknown reminder option '{}'", arg);
+                                return Ok(());
+                            }
+                        }
+                    }
+                    let id = add_reminder(&conn, &reminder)?;
+                    println!("Added reminder {}", id);
+                }
+                Some("done") if args.len() > 2 => {
+                    let date = match args.get(3) {
+                        Some(date) => chrono::NaiveDate::from_str(date)?,
+                        None => today,
+                    };
+                    let reminder = complete_reminder(&conn, args[2].parse()?, date)?;
+                    match reminder.recurrence {
+                        Some(_) => println!("Done, next due {}", reminder.due),
+                        None => println!("Done"),
+                    }
+                }
+                Some("delete") if args.len() > 2 => {
+                    if delete_reminder(&conn, args[2].parse()?)? {
+                        println!("Deleted reminder {}", args[2]);
+                    } else {
+                        eprintln!("No reminder {}", args[2]);
+                    }
+                }
+                Some("export") if args.len() > 2 => {
+                    let count = export_reminders_ics(&conn, &args[2])?;
+                    println!("Exported {} reminders to {}", count, args[2]);
+                }
+                _ => eprintln!(
+                    "Usage: reminders [days]|item <item_id>|add <item_id> <kind> <due> [every=...] [note=...]|done <reminder_id> [date]|delete <reminder_id>|export <path.ics>"
+                ),
+            }
+        }
+        "beneficiaries" => match args.get(1).map(String::as_str) {
+            None => {
+                let fmt = get_money_format(&conn)?;
+                for list in beneficiary_lists(&conn)? {
+                    println!(
+                        "  ({}) {}: {} items, {}",
+                        list.beneficiary.id,
+                        list.beneficiary.display_name(),
+                        list.stats.item_count,
+                        list.stats
+                            .estimated_total_home
+                            .map(|m| m.format(&fmt))
+                            .unwrap_or_else(|| "value unavailable".to_string())
+                    );
+                }
+            }
+            Some("add") if args.len() > 2 => {
+                let mut beneficiary = Beneficiary {
+                    name: args[2].clone(),
+                    ..Default::default()
+                };
+                apply_beneficiary_fields(&mut beneficiary, &args[3..])?;
+                let id = add_beneficiary(&conn, &beneficiary)?;
+                println!("Added beneficiary {}: {}", id, beneficiary.display_name());
+            }
+            Some("edit") if args.len() > 2 => {
+                let Some(mut beneficiary) = get_beneficiary(&conn, args[2].parse()?)? else {
+                    eprintln!("Beneficiary {} not found", args[2]);
+                    return Ok(());
+                };
+                apply_beneficiary_fields(&mut beneficiary, &args[3..])?;
+                update_beneficiary(&conn, &beneficiary)?;
+                println!(
+                    "Updated beneficiary {}: {}",
+                    beneficiary.id,
+                    beneficiary.display_name()
+                );
+            }
+            Some("delete") if args.len() > 2 => {
+                if delete_beneficiary(&conn, args[2].parse()?)? {
+                    println!("Deleted beneficiary {}", args[2]);
+                } else {
+                    eprintln!("No beneficiary {}", args[2]);
+                }
+            }
+            Some("show") if args.len() > 2 => {
+                let list = beneficiary_list(&conn, find_beneficiary_arg(&conn, &args[2])?)?;
+                let fmt = get_money_format(&conn)?;
+                println!("{}", list.beneficiary.display_name());
+                for assigned in &list.items {
+                    println!(
+                        "  {}({}) {}{}{}{}",
+                        assigned
+                            .priority
+                            .map(|p| format!("{}. ", p))
+                            .unwrap_or_default(),
+                        assigned.item.id,
+                        assigned.item.name,
+                        assigned
+                            .item
+                            .estimated_value
+                            .map(|m| format!(", {}", m.format(&fmt)))
+                            .unwrap_or_default(),
+                        assigned
+                            .note
+                            .as_ref()
+                            .map(|n| format!(" - {}", n))
+                            .unwrap_or_default(),
+                        if assigned.also_wanted_by.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" (also wanted by {})", assigned.also_wanted_by.join(", "))
+                        }
+                    );
+                }
+                for total in &list.stats.estimated_totals {
+                    println!("Total: {}", total.format(&fmt));
+                }
+            }
+            Some("assign") if args.len() > 3 => {
+                let mut assignment = Assignment {
+                    item_id: args[2].parse()?,
+                    beneficiary_id: find_beneficiary_arg(&conn, &args[3])?,
+                    ..Default::default()
+                };
+                for arg in &args[4..] {
+                    match arg.split_once('=') {
+                        Some(("priority", priority)) => {
+                            assignment.priority = Some(priority.parse()?)
+                        }
+                        Some(("note", note)) => assignment.note = Some(note.to_string()),
+                        _ => {
+                            eprintln!("Unknown assignment option '{}'", arg);
+                            return Ok(());
+                        }
+                    }
+                }
+                assign_item(&conn, &assignment)?;
+                let others: Vec<String> = get_item_beneficiaries(&conn, assignment.item_id)?
+                    .into_iter()
+                    .filter(|c| c.beneficiary.id != assignment.beneficiary_id)
+                    .map(|c| c.beneficiary.name)
+                    .collect();
+                println!("Assigned item {}", assignment.item_id);
+                if !others.is_empty() {
+                    println!("Warning: also assigned to {}", others.join(", "));
+                }
+            }
+            Some("unassign") if args.len() > 3 => {
+                let beneficiary_id = find_beneficiary_arg(&conn, &args[3])?;
+                if unassign_item(&conn, args[2].parse()?, beneficiary_id)? {
+                    println!("Unassigned item {}", args[2]);
+                } else {
+                    eprintln!("Item {} is not assigned to {}", args[2], args[3]);
+                }
+            }
+            Some("conflicts") => {
+                let conflicts = assignment_conflicts(&conn)?;
+                if conflicts.is_empty() {
+                    println!("No conflicts");
+                }
+                for conflict in conflicts {
+                    println!("  ({}) {}", conflict.item.id, conflict.item.name);
+                    for claim in conflict.claims {
+                        println!(
+                            "      {}{}{}",
+                            claim.beneficiary.display_name(),
+                            claim
+                                .priority
+                                .map(|p| format!(", priority {}", p))
+                                .unwrap_or_default(),
+                            claim.note.map(|n| format!(" - {}", n)).unwrap_or_default()
+                        );
+                    }
+                }
+            }
+            Some("report") if args.len() > 2 => {
+                let beneficiary_id = args
+                    .get(3)
+                    .map(|who| find_beneficiary_arg(&conn, who))
+                    .transpose()?;
+                let count = write_beneficiary_report(&conn, beneficiary_id, &args[2])?;
+                println!("Wrote {} assignments to {}", count, args[2]);
+            }
+            _ => eprintln!(
+                "Usage: beneficiaries [add <name> field=value...|edit <id> field=value...|delete <id>|show <id|name>|assign <item_id> <id|name> [priority=N] [note=text]|unassign <item_id> <id|name>|conflicts|report <path> [id|name]]"
+            ),
+        },
+        "wishlist" => match args.get(1).map(String::as_str) {
+            None => {
+                let fmt = get_money_format(&conn)?;
+                for item in get_wishlist(&conn)? {
+                    println!(
+                        "  {}({}) {}{}{}",
+                        item.wishlist_priority
+                            .map(|p| format!("{}. ", p))
+                            .unwrap_or_default(),
+                        item.id,
+                        item.name,
+                        item.target_price
+                            .map(|m| format!(", up to {}", m.format(&fmt)))
+                            .unwrap_or_default(),
+                        item.wishlist_notes
+                            .map(|n| format!(" - {}", n))
+                            .unwrap_or_default()
+                    );
+                }
+            }
+            Some("add") if args.len() > 4 => {
+                let Ok(category) = ItemCategory::from_str(&args[4]) else {
+                    eprintln!("Invalid category: {}", args[4]);
+                    return Ok(());
+                };
+                let mut item = Item {
+                    name: args[2].clone(),
+                    description: args[3].clone(),
+                    category,
+                    wishlist: true,
+                    ..Default::default()
+                };
+                for arg in &args[5..] {
+                    match arg.split_once('=') {
+                        Some(("target_price", price)) => {
+                            let home = get_home_currency(&conn)?;
+                            item.target_price = Some(Money::parse_with_default(price, home)?)
+                        }
+                        Some(("priority", priority)) => {
+                            item.wishlist_priority = Some(priority.parse()?)
+                        }
+                        Some(("notes", notes)) => item.wishlist_notes = Some(notes.to_string()),
+                        _ => {
+                            eprintln!("Unknown wishlist field '{}'", arg);
+                            return Ok(());
+                        }
+                    }
+                }
+                let id = add_item(&conn, &item)?;
+                println!("Added {} to the wishlist as item {}", item.name, id);
+            }
+            Some("acquire") if args.len() > 2 => {
+                let price = match args.get(3) {
+                    Some(price) => {
+                        let home = get_home_currency(&conn)?;
+                        Some(Money::parse_with_default(price, home)?)
+                    }
+                    None => None,
+                };
+                let date = match args.get(4) {
+                    Some(date) => chrono::NaiveDate::from_str(date)?,
+                    None => chrono::Local::now().date_naive(),
+                };
+                let item = acquire_wishlist_item(&conn, args[2].parse()?, date, price)?;
+                println!("Acquired {} on {}", item.name, date);
+            }
+            _ => eprintln!(
+                "Usage: wishlist [add <name> <description> <category> [field=value...]|acquire <item_id> [price] [date]]"
+            ),
+        },
+        "help" => {
+            println!("Collection Catalog CLI");
+            println!("Usage:");
+            println!("  list field=value [field=value...]               - List all items");
+            println!("  list --saved <name>                             - Run a saved search");
+            println!(
+                "  list --query <query>                            - List items matching a query"
+            );
+            println!(
+                "  export <path> field=value [field=value...]      - Export all items to CSV, or JSON for a .json path"
+            );
+            println!(
+                "  export <path> --query <query>                   - Export items matching a query"
+            );
+            println!(
+                "  export <path.xlsx|path.ods> [--by-category]   - Export to a spreadsheet with totals"
+            );
+            println!(
+                "  export <path> --profile <name> ...              - Export to CSV using a saved export profile"
+            );
+            println!("  add <name> <description> <category> <action>    - Add a new item");
+            println!(
+                "  add --template <template> <name> <description> [field=value...] - Add an item from a template"
+            );
+            println!(
+                "  clone <item_id> [--attachments]                 - Copy an item as a new one"
+            );
+            println!("  delete <id>                                     - Export all items to CSV");
+            println!("  update <item_id> field=value [field=value...]   - Update an existing item");
+            println!(
+                "  bulk-update [--dry-run] filter=value... set field=value... - Update all matching items"
+            );
+            println!(
+                "  bulk-delete [--dry-run] filter=value...         - Delete all matching items"
+            );
+            println!(
+                "  stats field=value [field=value...]              - Show item count and totals"
+            );
+            println!("  rates                                           - List exchange rates");
+            println!(
+                "  rate <currency> <rate>                          - Set exchange rate to home currency"
+            );
+            println!("  home-currency <currency>                        - Set the home currency");
+            println!(
+                "  money-format [decimal=c] [group=c] [code=pos] - Show or set how amounts are displayed"
+            );
+            println!(
+                "  report <path.html|path.pdf> [group=location] field=value... - Insurance report"
+            );
+            println!("  attach <item_id> <path> [caption]                - Attach a photo or file");
+            println!(
+                "  attachments <item_id>                           - List an item's attachments"
+            );
+            println!(
+                "  thumbnails <item_id> [small|medium|large] [dir] - Make thumbnails of an item's photos"
+            );
+            println!(
+                "  condition <item_id> [grade] [notes]             - Show or record an item's condition"
+            );
+            println!(
+                "  scale <category> [grade,grade,...]              - Show or set a condition scale"
+            );
+            println!("  link <item_id> <kind> <other_id>                - Relate two items");
+            println!("      kinds: part-of, set-member, related, replaced-by");
+            println!("  unlink <relation_id>                            - Remove a relation");
+            println!(
+                "  related <item_id>                               - List an item's relations"
+            );
+            println!(
+                "  set <set_id> [member_id...]                     - Add members, show set value"
+            );
+            println!(
+                "  provenance <item_id>                            - Show an item's ownership chain"
+            );
+            println!("  provenance <item_id> add <role> <name> [field=value...] - Append an event");
+            println!("      roles: owner, dealer, auction; fields: from, to, location, source");
+            println!("  provenance edit <event_id> field=value...       - Edit an event");
+            println!(
+                "  provenance move <event_id> <position>           - Reorder an event (1 = oldest)"
+            );
+            println!("  provenance delete <event_id>                    - Remove an event");
+            println!("  creators                                        - List creators");
+            println!("  creators add <name> [field=value...]            - Add a creator");
+            println!("      fields: aliases (comma separated), born, died, role");
+            println!("  creators edit <creator_id> field=value...       - Edit a creator");
+            println!(
+                "  creators link <item_id> <creator_id> [role]     - Link an item to a creator"
+            );
+            println!(
+                "  creators unlink <item_id> <creator_id>          - Unlink an item and creator"
+            );
+            println!(
+                "  creators duplicates                             - Suggest creators to merge"
+            );
+            println!("  creators merge <keep_id> <merge_id>             - Merge two creators");
+            println!(
+                "  creators stats field=value...                   - Item count and value per creator"
+            );
+            println!("  searches                                        - List saved searches");
+            println!(
+                "  searches save <name> field=value...             - Save a search, replacing one with the same name"
+            );
+            println!(
+                "      date filters also take e.g. \"last 30 days\", \"3 months ago\", \"this year\""
+            );
+            println!("  searches delete <name>                          - Delete a saved search");
+            println!(
+                "  export-profiles                                 - List CSV export profiles"
+            );
+            println!(
+                "  export-profiles columns                         - List the columns a CSV export can include"
+            );
+            println!(
+                "  export-profiles save <name> option=value...     - Save a profile: columns, headers, delimiter, quoting, date_format, bom"
+            );
+            println!(
+                "  export-profiles delete <name>                   - Delete an export profile"
+            );
+            println!("  templates                                       - List item templates");
+            println!(
+                "  templates save <name> field=value...            - Save a template, replacing one with the same name"
+            );
+            println!("  templates delete <name>                         - Delete a template");
+            println!(
+                "  dedupe [min_score]                              - Report likely duplicate items"
+            );
+            println!(
+                "  dedupe --photos [max_distance]                  - Report near-identical photos on different items"
+            );
+            println!(
+                "  merge <keep_id> <merge_id> [field...]           - Merge a duplicate into an item"
+            );
+            println!(
+                "      fields listed are taken from the merged item, e.g. description location"
+            );
+            println!(
+                "  identifiers <item_id>                           - List an item's ISBN/UPC/EAN codes"
+            );
+            println!(
+                "  identifiers add <item_id> <code>                - Add an ISBN/UPC/EAN to an item"
+            );
+            println!(
+                "  identifiers remove <identifier_id>              - Remove a code from an item"
+            );
+            println!("  identifiers find <code>                         - Find items with a code");
+            println!(
+                "  scan <image_path>|--item <item_id>              - Read barcodes from an image or item photos"
+            );
+            println!(
+                "  lookup <code|image_path> [--dump <path>] [--add] - Look up a code, --add creates the item"
+            );
+            println!(
+                "  labels <path.pdf|path.svg> [--template <name|file.json>] [--skip N] field=value... - QR code labels"
+            );
+            println!("      templates: {}", LabelTemplate::BUILTIN.join(", "));
+            println!("  audits                                          - List inventory audits");
+            println!(
+                "  audit start <name> field=value...               - Start an audit of the matching items"
+            );
+            println!(
+                "  audit scan <audit_id>                           - Mark items found, one id or label per line"
+            );
+            println!("  audit found <audit_id> <id|label>...            - Mark items found");
+            println!(
+                "  audit unfound <audit_id> <item_id>              - Undo marking an item found"
+            );
+            println!(
+                "  audit report <audit_id>                         - List missing and unexpected items"
+            );
+            println!("  audit finish <audit_id>                         - Close an audit");
+            println!("  audit delete <audit_id>                         - Delete an audit");
+            println!(
+                "  reminders [days]                                - Reminders due within days (30), and overdue"
+            );
+            println!(
+                "  reminders item <item_id>                        - List an item's reminders"
+            );
+            println!(
+                "  reminders add <item_id> <kind> <due> [every=6 months] [note=text] - Add a reminder"
+            );
+            println!("      kinds: maintenance, warranty, appraisal, other");
+            println!(
+                "  reminders done <reminder_id> [date]             - Mark done, recurring ones come round again"
+            );
+            println!("  reminders delete <reminder_id>                  - Delete a reminder");
+            println!(
+                "  reminders export <path.ics>                     - Export open reminders to a calendar file"
+            );
+            println!(
+                "  beneficiaries                                   - List beneficiaries with item count and value"
+            );
+            println!("  beneficiaries add <name> [field=value...]       - Add a beneficiary");
+            println!("      fields: relationship, contact, notes");
+            println!("  beneficiaries edit <id> field=value...          - Edit a beneficiary");
+            println!(
+                "  beneficiaries delete <id>                       - Delete a beneficiary and their assignments"
+            );
+            println!(
+                "  beneficiaries show <id|name>                    - List the items meant for a beneficiary"
+            );
+            println!(
+                "  beneficiaries assign <item_id> <id|name> [priority=N] [note=text] - Assign an item"
+            );
+            println!("  beneficiaries unassign <item_id> <id|name>      - Remove an assignment");
+            println!(
+                "  beneficiaries conflicts                         - List items assigned to more than one person"
+            );
+            println!(
+                "  beneficiaries report <path.html|pdf|csv> [id|name] - Per-person lists, everyone by default"
+            );
+            println!(
+                "  wishlist                                        - List wishlist items, most wanted first"
+            );
+            println!(
+                "  wishlist add <name> <description> <category> [field=value...] - Add a wishlist item"
+            );
+            println!("      fields: target_price, priority, notes");
+            println!(
+                "  wishlist acquire <item_id> [price] [date]       - Move a wishlist item into the collection"
+            );
+            println!("  help                                            - Show this help message");
+
+            println!("\nFilterable fields for list/export:");
+            println!("  name, description, creator, provenance, location (partial match)");
+            println!(
+                "  provenance_event (partial match on owner, dealer, auction, place or source)"
+            );
+            println!(
+                "  category (Exact: Book, Artwork, Collectible, Document, Electronic, Furniture, Jewelry, Other)"
+            );
+            println!("  action (Exact: Keep, Sell)");
+            println!("  working (true/false)");
+            println!("  creator_id (Exact: id from the creators command)");
+            println!("  condition (Exact, case-insensitive: e.g. Good, Needs Repair)");
+            println!("  deleted (true/false)");
+            println!("  wishlist (true/false, wishlist items are left out unless this is given)");
+            println!("  date_added_min, date_added_max (YYYY-MM-DD)");
+            println!("  last_updated_min, last_updated_max (YYYY-MM-DD)");
+            println!("  date_acquired_min, date_acquired_max (YYYY-MM-DD)");
+            println!(
+                "  date_made_min, date_made_max (1890-05-03, 1890-05, 1890, 1970s, circa 1890)"
+            );
+            println!("  date_made_strict (true: whole uncertain range must be inside min/max)");
+            println!("  age_years_min, age_years_max (integer, derived from date made)");
+            println!("  purchase_price_min, purchase_price_max (amount, e.g. 100 or 100.50 EUR)");
+            println!("  estimated_value_min, estimated_value_max (amount, e.g. 100 or 100.50 EUR)");
+            println!("  target_price_min, target_price_max (amount, wishlist items only)");
+            println!("  length_min/max, width_min/max, height_min/max (e.g. 75 cm, 30 in, 1.2 m)");
+            println!("  weight_min, weight_max (e.g. 12 kg, 40 lb)");
+            println!("  quantity_min, quantity_max (integer, unset counts as 1)");
+            println!("  missing (field with no value, e.g. location, estimated_value, date_made)");
+            println!("\nCombining list/export filters:");
+            println!("  category, action and condition take lists: category=Book,Antique");
+            println!("  not before a filter excludes it: not category=Book");
+            println!(
+                "  or between groups of filters matches either: category=Book or missing=location"
+            );
+
+            println!("\nQueries for list/export --query:");
+            println!("  field:value (contains for text), field=value, field>value, >=, <, <=");
+            println!("  field:low..high ranges on numbers, amounts, dates and measurements");
+            println!(
+                "  AND is implied, OR, NOT or -term, (grouping), bare words search name/description"
+            );
+            println!(
+                "  e.g. \"category:Book value>100 (creator:tolkien OR creator:lewis) -deleted\""
+            );
+        }
+        _ => {
+            eprintln!("unknown command: {}", args[0]);
+        }
+    }
+
+    Ok(())
+}
+
+// Apply provenance event fields given as field=value
+fn apply_provenance_fields(
+    event: &mut ProvenanceEvent,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for arg in args {
+        let Some((field, value)) = arg.split_once('=') else {
+            return Err(format!("Invalid field format: {arg}. Use field=value").into());
+        };
+        let text = Some(value.to_string()).filter(|v| !v.is_empty());
+        match field {
+            "role" => event.role = ProvenanceRole::from_str(value)?,
+            "name" => event.name = value.to_string(),
+            "from" => event.date_from = text.map(|v| FuzzyDate::from_str(&v)).transpose()?,
+            "to" => event.date_to = text.map(|v| FuzzyDate::from_str(&v)).transpose()?,
+            "location" => event.location = text,
+            "source" => event.source = text,
+            _ => return Err(format!("Unknown provenance field: {field}").into()),
+        }
+    }
+    Ok(())
+}
+
+// Apply creator fields given as field=value
+fn apply_creator_fields(
+    creator: &mut Creator,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for arg in args {
+        let Some((field, value)) = arg.split_once('=') else {
+            return Err(format!("Invalid field format: {arg}. Use field=value").into());
+        };
+        let text = Some(value.to_string()).filter(|v| !v.is_empty());
+        match field {
+            "name" => creator.name = value.to_string(),
+            "aliases" => creator.aliases = value.split(',').map(|a| a.trim().to_string()).collect(),
+            "born" => creator.born = text.map(|v| FuzzyDate::from_str(&v)).transpose()?,
+            "died" => creator.died = text.map(|v| FuzzyDate::from_str(&v)).transpose()?,
+            "role" => creator.role = text.map(|v| CreatorRole::from_str(&v)).transpose()?,
+            _ => return Err(format!("Unknown creator field: {field}").into()),
+        }
+    }
+    Ok(())
+}
+
+// Apply beneficiary fields given as field=value
+fn apply_beneficiary_fields(
+    beneficiary: &mut Beneficiary,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for arg in args {
+        let Some((field, value)) = arg.split_once('=') else {
+            return Err(format!("Invalid field format: {arg}. Use field=value").into());
+        };
+        let text = Some(value.to_string()).filter(|v| !v.is_empty());
+        match field {
+            "name" => beneficiary.name = value.to_string(),
+            "relationship" => beneficiary.relationship = text,
+            "contact" => beneficiary.contact = text,
+            "notes" => beneficiary.notes = text,
+            _ => return Err(format!("Unknown beneficiary field: {field}").into()),
+        }
+    }
+    Ok(())
+}
+
+// A beneficiary given by id or by name
+fn find_beneficiary_arg(conn: &Connection, who: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    if let Ok(id) = who.parse() {
+        return Ok(id);
+    }
+    match find_beneficiary(conn, who)? {
+        Some(beneficiary) => Ok(beneficiary.id),
+        None => Err(format!("No beneficiary named '{who}'").into()),
+    }
+}
+
+// Parse a --query argument, pointing at the problem if it doesn't parse
+fn parse_query_arg(text: &str) -> Option<Query> {
+    match parse_query(text) {
+        Ok(query) => Some(query),
+        Err(e) => {
+            eprintln!("Invalid query: {}", e);
+            eprintln!("  {}", text);
+            eprintln!("  {}^", " ".repeat(e.position));
+            None
+        }
+    }
+}
+
+// Parse list/export filters: field=value terms that must all match, `or` between groups of
+// terms, `not` before a term, comma separated lists for category, action and condition, and
+// missing=field for fields with no value. Anything that doesn't parse is an error.
+fn parse_filter_expr(args: &[String]) -> Result<FilterExpr, String> {
+    let groups = args.split(|a| a.eq_ignore_ascii_case("or")).map(|group| {
+        let mut parts = vec![];
+        let mut fields = ItemFilter::default();
+        let mut negate = false;
+        for arg in group {
+            if arg.eq_ignore_ascii_case("not") {
+                negate = true;
+                continue;
+            }
+            fn list<T: FromStr>(field: &str, value: &str) -> Result<Vec<T>, String> {
+                value
+                    .split(',')
+                    .map(|v| {
+                        v.trim()
+                            .parse()
+                            .map_err(|_| format!("Invalid {} in list: '{}'", field, v.trim()))
+                    })
+                    .collect()
+            }
+            let (field, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid filter '{}'. Use field=value", arg))?;
+            let term = match field {
+                "category" if value.contains(',') => FilterExpr::CategoryIn(list(field, value)?),
+                "action" if value.contains(',') => FilterExpr::ActionIn(list(field, value)?),
+                "condition" if value.contains(',') => FilterExpr::ConditionIn(list(field, value)?),
+                "missing" => FilterExpr::IsNull(OptionalField::from_str(value)?),
+                _ if negate => {
+                    let mut filter = ItemFilter::default();
+                    set_filter_field(&mut filter, field, value)?;
+                    filter.into()
+                }
+                // Plain terms are gathered into one filter
+                _ => {
+                    set_filter_field(&mut fields, field, value)?;
+                    continue;
+                }
+            };
+            parts.push(if std::mem::take(&mut negate) {
+                !term
+            } else {
+                term
+            });
+        }
+        if negate {
+            return Err("'not' must come before a filter".to_string());
+        }
+        parts.insert(0, fields.into());
+        Ok(FilterExpr::All(parts))
+    });
+    let mut groups = groups.collect::<Result<Vec<FilterExpr>, String>>()?;
+    Ok(if groups.len() == 1 {
+        groups.pop().unwrap()
+    } else {
+        FilterExpr::Any(groups)
+    })
+}
+
+// Parse filters given as field=value, warning about and skipping any that don't parse
+fn parse_filter(args: &[String]) -> ItemFilter {
+    let mut filter = ItemFilter::default();
+    for arg in args {
+        if let Some((field, value)) = arg.split_once('=')
+            && let Err(e) = set_filter_field(&mut filter, field, value)
+        {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    filter
+}
+
+// Like `parse_filter`, but any filter that doesn't parse is an error, as is giving none.
+// Used where a mistyped filter would change more items than meant.
+fn parse_filter_strict(args: &[String]) -> Result<ItemFilter, String> {
+    let mut filter = ItemFilter::default();
+    for arg in args {
+        let (field, value) = arg
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid filter '{}'. Use field=value", arg))?;
+        set_filter_field(&mut filter, field, value)?;
+    }
+    if filter.is_empty() {
+        return Err("Give at least one filter".to_string());
+    }
+    Ok(filter)
+}
+
+fn set_filter_field(filter: &mut ItemFilter, field: &str, value: &str) -> Result<(), String> {
+    fn parse<T: FromStr>(field: &str, value: &str) -> Result<Option<T>, String> {
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid value for filter '{}': '{}'", field, value))
+    }
+    let text = Some(value.to_string());
+    match field {
+        // Partial string match filters
+        "name" => filter.name_contains = text,
+        "description" => filter.description_contains = text,
+        "creator" => filter.creator_contains = text,
+        "provenance" => filter.provenance_contains = text,
+        "location" => filter.location_contains = text,
+        "provenance_event" => filter.provenance_event_contains = text,
+        // Enums / Exact match filters
+        "category" => filter.category = parse(field, value)?,
+        "action" => filter.action = parse(field, value)?,
+        "working" => filter.working = parse(field, value)?,
+        "creator_id" => filter.creator_id = parse(field, value)?,
+        "condition" => filter.condition = text,
+        "deleted" => filter.deleted = parse(field, value)?,
+        "wishlist" => filter.wishlist = parse(field, value)?,
+        // Date filters
+        "date_added_min" => filter.date_added_min = parse(field, value)?,
+        "date_added_max" => filter.date_added_max = parse(field, value)?,
+        "last_updated_min" => filter.last_updated_min = parse(field, value)?,
+        "last_updated_max" => filter.last_updated_max = parse(field, value)?,
+        "date_acquired_min" => filter.date_acquired_min = parse(field, value)?,
+        "date_acquired_max" => filter.date_acquired_max = parse(field, value)?,
+        // Number filters
+        "date_made_min" => filter.date_made_min = parse(field, value)?,
+        "date_made_max" => filter.date_made_max = parse(field, value)?,
+        "date_made_strict" => filter.date_made_strict = parse(field, value)?,
+        "age_years_min" => filter.age_years_min = parse(field, value)?,
+        "age_years_max" => filter.age_years_max = parse(field, value)?,
+        // Money filters
+        "purchase_price_min" => filter.purchase_price_min = parse(field, value)?,
+        "purchase_price_max" => filter.purchase_price_max = parse(field, value)?,
+        "estimated_value_min" => filter.estimated_value_min = parse(field, value)?,
+        "estimated_value_max" => filter.estimated_value_max = parse(field, value)?,
+        "target_price_min" => filter.target_price_min = parse(field, value)?,
+        "target_price_max" => filter.target_price_max = parse(field, value)?,
+        // Measurement filters, any unit
+        "length_min" => filter.length_min = parse(field, value)?,
+        "length_max" => filter.length_max = parse(field, value)?,
+        "width_min" => filter.width_min = parse(field, value)?,
+        "width_max" => filter.width_max = parse(field, value)?,
+        "height_min" => filter.height_min = parse(field, value)?,
+        "height_max" => filter.height_max = parse(field, value)?,
+        "weight_min" => filter.weight_min = parse(field, value)?,
+        "weight_max" => filter.weight_max = parse(field, value)?,
+        "quantity_min" => filter.quantity_min = parse(field, value)?,
+        "quantity_max" => filter.quantity_max = parse(field, value)?,
+        _ => return Err(format!("Unknown filter field '{}'", field)),
+    }
     Ok(())
 }
@@ -0,0 +1,130 @@
+//! Peak heap use while exporting a large catalog, streamed versus collected first.
+//!
+//! Run with `cargo bench -p collection-catalog-core --bench export_memory`. The catalog grows
+//! to 500,000 items, or `CATALOG_ITEMS` if set. Streamed exports should stay flat as it grows,
+//! while collecting the items first grows with the catalog. Only Rust allocations are counted;
+//! SQLite's page cache has its own fixed limit.
+
+use collection_catalog_core::{
+    CsvOptions, Item, ItemCategory, ItemFilter, Money, add_item, get_filtered_items, init_db,
+    stream_filtered_items, write_csv, write_json,
+};
+use rusqlite::Connection;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn track(grown: usize) {
+    let now = CURRENT.fetch_add(grown, Ordering::Relaxed) + grown;
+    PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            track(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            track(new_size);
+        }
+        new
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+// Heap grown by `f` above what was in use before it ran, in KiB
+fn peak_kib(f: impl FnOnce() -> anyhow::Result<usize>) -> (usize, usize) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let count = f().unwrap();
+    (count, (PEAK.load(Ordering::Relaxed) - base) / 1024)
+}
+
+fn add_items(conn: &Connection, from: usize, to: usize) {
+    let tx = conn.unchecked_transaction().unwrap();
+    let value = Money::from_str("125.00 USD").unwrap();
+    for n in from..to {
+        let item = Item {
+            name: format!("Item {}", n),
+            description: "A reasonably long description of the item, like a real catalog entry"
+                .to_string(),
+            category: if n % 2 == 0 {
+                ItemCategory::Book
+            } else {
+                ItemCategory::Antique
+            },
+            estimated_value: Some(value),
+            location: Some(format!("Shelf {}", n % 40)),
+            ..Default::default()
+        };
+        add_item(&tx, &item).unwrap();
+    }
+    tx.commit().unwrap();
+}
+
+fn main() {
+    let total: usize = std::env::var("CATALOG_ITEMS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(500_000);
+    let path = std::env::temp_dir().join(format!("catalog-bench-{}.db", std::process::id()));
+    let conn = Connection::open(&path).unwrap();
+    init_db(&conn).unwrap();
+
+    println!(
+        "{:>9}  {:>16}  {:>17}  {:>17}  {:>8}",
+        "items", "streamed CSV KiB", "streamed JSON KiB", "collected CSV KiB", "CSV secs"
+    );
+    let mut added = 0;
+    for size in [total / 100, total / 10, total / 2, total] {
+        add_items(&conn, added, size);
+        added = size;
+
+        let options = CsvOptions::default();
+        let started = Instant::now();
+        let (count, streamed_csv) = peak_kib(|| {
+            stream_filtered_items(&conn, ItemFilter::default(), |items| {
+                write_csv(&conn, items, io::sink(), &options)
+            })
+        });
+        let secs = started.elapsed().as_secs_f64();
+        assert_eq!(count, size);
+        let (_, streamed_json) = peak_kib(|| {
+            stream_filtered_items(&conn, ItemFilter::default(), |items| {
+                write_json(items, io::sink())
+            })
+        });
+        let (_, collected) = peak_kib(|| {
+            let items = get_filtered_items(&conn, ItemFilter::default())?;
+            write_csv(&conn, items.iter().map(Ok), io::sink(), &options)
+        });
+        println!(
+            "{:>9}  {:>16}  {:>17}  {:>17}  {:>8.2}",
+            size, streamed_csv, streamed_json, collected, secs
+        );
+    }
+
+    drop(conn);
+    std::fs::remove_file(&path).unwrap();
+}
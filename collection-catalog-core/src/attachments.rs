@@ -0,0 +1,122 @@
+use crate::images::{is_supported_image, read_image_metadata, record_photo_hash};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A file (usually a photo) stored on disk and linked to an item.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i32,
+    pub item_id: i32,
+    pub path: String,
+    pub caption: Option<String>,
+    pub date_added: NaiveDate,
+    /// Set when the photo documents a condition report
+    pub condition_report_id: Option<i32>,
+    /// When a photo was taken, from its EXIF data
+    pub captured: Option<NaiveDateTime>,
+    /// The camera that took a photo, from its EXIF data
+    pub camera: Option<String>,
+}
+
+impl Attachment {
+    pub fn mime_type(&self) -> Option<&'static str> {
+        let ext = Path::new(&self.path)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        match ext.as_str() {
+            "jpg" | "jpeg" => Some("image/jpeg"),
+            "png" => Some("image/png"),
+            "webp" => Some("image/webp"),
+            "gif" => Some("image/gif"),
+            "pdf" => Some("application/pdf"),
+            _ => None,
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type().is_some_and(|m| m.starts_with("image/"))
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Attachment {
+            id: row.get("id")?,
+            item_id: row.get("item_id")?,
+            path: row.get("path")?,
+            caption: row.get("caption")?,
+            date_added: NaiveDate::parse_from_str(&row.get::<_, String>("date_added")?, "%Y-%m-%d")
+                .unwrap_or(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+            condition_report_id: row.get("condition_report_id")?,
+            captured: row
+                .get::<_, Option<String>>("captured")?
+                .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").ok()),
+            camera: row.get("camera")?,
+        })
+    }
+}
+
+pub fn add_attachment(
+    conn: &Connection,
+    item_id: i32,
+    path: &str,
+    caption: Option<&str>,
+) -> Result<i32> {
+    let today = Local::now().date_naive();
+    conn.execute(
+        "INSERT INTO attachments (item_id, path, caption, date_added) VALUES (?1, ?2, ?3, ?4)",
+        params![item_id, path, caption, today.to_string()],
+    )?;
+    let id = conn.last_insert_rowid() as i32;
+    record_photo_details(conn, id, path)?;
+    Ok(id)
+}
+
+/// Stores the capture date, camera and perceptual hash of a photo attachment. Files that
+/// aren't photos, or can't be read, are left without them.
+pub(crate) fn record_photo_details(conn: &Connection, id: i32, path: &str) -> Result<()> {
+    let path = Path::new(path);
+    record_photo_hash(conn, id, path)?;
+    if !is_supported_image(path) {
+        return Ok(());
+    }
+    record_image_metadata(conn, id, path)
+}
+
+/// Stores the capture date and camera read from a photo's EXIF data, if it can be read.
+pub(crate) fn record_image_metadata(conn: &Connection, id: i32, path: &Path) -> Result<()> {
+    if let Ok(metadata) = read_image_metadata(path) {
+        conn.execute(
+            "UPDATE attachments SET captured = ?2, camera = ?3 WHERE id = ?1",
+            params![
+                id,
+                metadata
+                    .captured
+                    .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string()),
+                metadata.camera(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn get_attachments(conn: &Connection, item_id: i32) -> Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare("SELECT * FROM attachments WHERE item_id = ?1 ORDER BY id")?;
+    let rows = stmt.query_map(params![item_id], Attachment::from_row)?;
+    rows.collect()
+}
+
+pub fn get_attachment(conn: &Connection, id: i32) -> Result<Option<Attachment>> {
+    conn.query_row(
+        "SELECT * FROM attachments WHERE id = ?1",
+        params![id],
+        Attachment::from_row,
+    )
+    .optional()
+}
+
+pub fn delete_attachment(conn: &Connection, id: i32) -> Result<()> {
+    conn.execute("DELETE FROM attachments WHERE id = ?1", params![id])?;
+    Ok(())
+}
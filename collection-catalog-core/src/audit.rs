@@ -0,0 +1,335 @@
+//! Physical inventory audits: walking the collection and checking off what is still there.
+//!
+//! Starting an audit records every item its filter matches as expected. Items are then marked
+//! found by id or by scanning their label, and anything found that wasn't expected is kept as
+//! unexpected, such as an item turning up in the wrong room.
+
+use crate::db::{get_filtered_items, get_item_by_id};
+use crate::labels::{LabelCode, get_catalog_id};
+use crate::models::{Item, ItemFilter};
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::{Local, NaiveDate};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Audit {
+    pub id: i32,
+    pub name: String,
+    /// The items expected to be found
+    pub scope: ItemFilter,
+    pub started: NaiveDate,
+    pub finished: Option<NaiveDate>,
+    /// Counts worked out when the audit is loaded
+    #[serde(skip_deserializing)]
+    pub expected: u32,
+    #[serde(skip_deserializing)]
+    pub found: u32,
+    #[serde(skip_deserializing)]
+    pub unexpected: u32,
+}
+
+impl Audit {
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let date = |s: String| {
+            NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+            })
+        };
+        Ok(Audit {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            scope: serde_json::from_str(&row.get::<_, String>("scope")?).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+            })?,
+            started: date(row.get("started")?)?,
+            finished: row
+                .get::<_, Option<String>>("finished")?
+                .map(date)
+                .transpose()?,
+            expected: row.get("expected")?,
+            found: row.get("found")?,
+            unexpected: row.get("unexpected")?,
+        })
+    }
+}
+
+const AUDIT_SELECT: &str = "SELECT audits.*,
+    (SELECT COUNT(*) FROM audit_items a WHERE a.audit_id = audits.id AND a.expected)
+        AS expected,
+    (SELECT COUNT(*) FROM audit_items a
+        WHERE a.audit_id = audits.id AND a.expected AND a.found_on IS NOT NULL) AS found,
+    (SELECT COUNT(*) FROM audit_items a WHERE a.audit_id = audits.id AND NOT a.expected)
+        AS unexpected
+    FROM audits";
+
+/// Starts an audit of the items matching `scope`, such as everything in one location.
+/// Deleted items are left out unless the filter asks for them. Returns the audit id.
+pub fn start_audit(conn: &Connection, name: &str, mut scope: ItemFilter) -> AnyResult<i32> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("Audit name cannot be empty."));
+    }
+    if scope.deleted.is_none() {
+        scope.deleted = Some(false);
+    }
+    let items = get_filtered_items(conn, scope.clone())?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO audits (name, scope, started) VALUES (?1, ?2, ?3)",
+        params![
+            name.trim(),
+            serde_json::to_string(&scope)?,
+            Local::now().date_naive().to_string()
+        ],
+    )?;
+    let id = tx.last_insert_rowid() as i32;
+    for item in &items {
+        tx.execute(
+            "INSERT INTO audit_items (audit_id, item_id, expected) VALUES (?1, ?2, 1)",
+            params![id, item.id],
+        )?;
+    }
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Newest first.
+pub fn get_audits(conn: &Connection) -> Result<Vec<Audit>> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY id DESC", AUDIT_SELECT))?;
+    let rows = stmt.query_map([], Audit::from_row)?;
+    rows.collect()
+}
+
+pub fn get_audit(conn: &Connection, audit_id: i32) -> Result<Option<Audit>> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", AUDIT_SELECT),
+        params![audit_id],
+        Audit::from_row,
+    )
+    .optional()
+}
+
+fn open_audit(conn: &Connection, audit_id: i32) -> AnyResult<Audit> {
+    let audit =
+        get_audit(conn, audit_id)?.ok_or_else(|| anyhow!("Audit {} not found", audit_id))?;
+    if audit.is_finished() {
+        return Err(anyhow!("Audit {} is already finished", audit_id));
+    }
+    Ok(audit)
+}
+
+/// The item id in `scan`, which is an item id such as `42` or `#42`, or the text of one of
+/// this catalog's labels.
+pub fn resolve_scan(conn: &Connection, scan: &str) -> AnyResult<i32> {
+    let scan = scan.trim();
+    if let Ok(id) = scan.trim_start_matches('#').parse() {
+        return Ok(id);
+    }
+    let code: LabelCode = scan.parse().map_err(|e: String| anyhow!(e))?;
+    if code.catalog_id != get_catalog_id(conn)? {
+        return Err(anyhow!(
+            "Label for item {} is from a different catalog",
+            code.item_id
+        ));
+    }
+    Ok(code.item_id)
+}
+
+/// What marking an item found turned up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditMark {
+    pub item: Item,
+    /// Whether the item was in the audit's scope
+    pub expected: bool,
+    /// Whether it had already been marked found in this audit
+    pub already_found: bool,
+}
+
+/// Marks the item scanned or typed in `scan` as found, see `resolve_scan`, and records
+/// today as the date it was last audited. An item outside the audit's scope is kept as
+/// unexpected.
+pub fn mark_found(conn: &Connection, audit_id: i32, scan: &str) -> AnyResult<AuditMark> {
+    open_audit(conn, audit_id)?;
+    let item_id = resolve_scan(conn, scan)?;
+    let mut item =
+        get_item_by_id(conn, item_id)?.ok_or_else(|| anyhow!("Item {} not found", item_id))?;
+
+    let previous: Option<(bool, Option<String>)> = conn
+        .query_row(
+            "SELECT expected, found_on FROM audit_items WHERE audit_id = ?1 AND item_id = ?2",
+            params![audit_id, item_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let today = Local::now().date_naive();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO audit_items (audit_id, item_id, expected, found_on) VALUES (?1, ?2, 0, ?3)
+        ON CONFLICT(audit_id, item_id) DO UPDATE SET
+            found_on = COALESCE(found_on, excluded.found_on)",
+        params![audit_id, item_id, today.to_string()],
+    )?;
+    tx.execute(
+        "UPDATE items SET last_audited = ?1 WHERE id = ?2",
+        params![today.to_string(), item_id],
+    )?;
+    tx.commit()?;
+    item.last_audited = Some(today);
+
+    Ok(AuditMark {
+        item,
+        expected: previous.as_ref().is_some_and(|(expected, _)| *expected),
+        already_found: previous.is_some_and(|(_, found)| found.is_some()),
+    })
+}
+
+/// Undoes marking an item found, for a mistaken scan. An unexpected item is dropped from the
+/// audit. The item's last audited date is left as it is.
+pub fn unmark_found(conn: &Connection, audit_id: i32, item_id: i32) -> AnyResult<()> {
+    open_audit(conn, audit_id)?;
+    conn.execute(
+        "DELETE FROM audit_items WHERE audit_id = ?1 AND item_id = ?2 AND NOT expected",
+        params![audit_id, item_id],
+    )?;
+    conn.execute(
+        "UPDATE audit_items SET found_on = NULL WHERE audit_id = ?1 AND item_id = ?2",
+        params![audit_id, item_id],
+    )?;
+    Ok(())
+}
+
+/// Closes the audit so no more items can be marked. Its report stays available.
+pub fn finish_audit(conn: &Connection, audit_id: i32) -> AnyResult<()> {
+    open_audit(conn, audit_id)?;
+    conn.execute(
+        "UPDATE audits SET finished = ?1 WHERE id = ?2",
+        params![Local::now().date_naive().to_string(), audit_id],
+    )?;
+    Ok(())
+}
+
+/// Deletes an audit and its marks. Items keep their last audited dates.
+pub fn delete_audit(conn: &Connection, audit_id: i32) -> Result<bool> {
+    conn.execute(
+        "DELETE FROM audit_items WHERE audit_id = ?1",
+        params![audit_id],
+    )?;
+    let deleted = conn.execute("DELETE FROM audits WHERE id = ?1", params![audit_id])?;
+    Ok(deleted > 0)
+}
+
+/// Where an audit stands, with items sorted by location so missing ones can be looked for
+/// room by room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub audit: Audit,
+    pub found: Vec<Item>,
+    /// Expected but not yet found
+    pub missing: Vec<Item>,
+    /// Found but outside the audit's scope
+    pub unexpected: Vec<Item>,
+}
+
+impl AuditReport {
+    /// Whether everything expected was found and nothing else was.
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+pub fn audit_report(conn: &Connection, audit_id: i32) -> AnyResult<AuditReport> {
+    let audit =
+        get_audit(conn, audit_id)?.ok_or_else(|| anyhow!("Audit {} not found", audit_id))?;
+    let items = |condition: &str| -> Result<Vec<Item>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT items.* FROM audit_items JOIN items ON items.id = audit_items.item_id
+            WHERE audit_items.audit_id = ?1 AND {}
+            ORDER BY items.location IS NULL, items.location COLLATE NOCASE,
+                items.name COLLATE NOCASE",
+            condition
+        ))?;
+        let rows = stmt.query_map(params![audit_id], Item::from_row)?;
+        rows.collect()
+    };
+    Ok(AuditReport {
+        found: items("expected AND found_on IS NOT NULL")?,
+        missing: items("expected AND found_on IS NULL")?,
+        unexpected: items("NOT expected")?,
+        audit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db};
+
+    #[test]
+    fn test_audit_discrepancies() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, location) in [("Lamp", "Attic"), ("Trunk", "Attic"), ("Sofa", "Lounge")] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                location: Some(location.to_string()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+
+        let scope = ItemFilter {
+            location_contains: Some("attic".to_string()),
+            ..Default::default()
+        };
+        let id = start_audit(&conn, "Attic 2025", scope).unwrap();
+        assert_eq!(get_audit(&conn, id).unwrap().unwrap().expected, 2);
+
+        // Found by id, then again by its label
+        let mark = mark_found(&conn, id, "#1").unwrap();
+        assert!(mark.expected && !mark.already_found);
+        assert_eq!(mark.item.last_audited, Some(Local::now().date_naive()));
+        let label = LabelCode {
+            catalog_id: get_catalog_id(&conn).unwrap(),
+            item_id: 1,
+        };
+        assert!(
+            mark_found(&conn, id, &label.to_string())
+                .unwrap()
+                .already_found
+        );
+        let foreign = LabelCode {
+            catalog_id: "0000".to_string(),
+            item_id: 1,
+        };
+        assert!(mark_found(&conn, id, &foreign.to_string()).is_err());
+        assert!(mark_found(&conn, id, "99").is_err());
+
+        // The sofa turned up in the attic
+        assert!(!mark_found(&conn, id, "3").unwrap().expected);
+
+        let report = audit_report(&conn, id).unwrap();
+        let names = |items: &[Item]| items.iter().map(|i| i.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&report.found), vec!["Lamp"]);
+        assert_eq!(names(&report.missing), vec!["Trunk"]);
+        assert_eq!(names(&report.unexpected), vec!["Sofa"]);
+        assert!(!report.is_clean());
+
+        unmark_found(&conn, id, 3).unwrap();
+        let audit = get_audit(&conn, id).unwrap().unwrap();
+        assert_eq!((audit.found, audit.unexpected), (1, 0));
+
+        finish_audit(&conn, id).unwrap();
+        assert!(mark_found(&conn, id, "2").is_err());
+        assert_eq!(get_audits(&conn).unwrap().len(), 1);
+        assert!(delete_audit(&conn, id).unwrap());
+        // The date stays with the item
+        let lamp = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert!(lamp.last_audited.is_some());
+    }
+}
@@ -0,0 +1,278 @@
+//! Reads EAN-13, UPC-A and EAN-8 barcodes from photos and scans, without any network access.
+//!
+//! Each scanned line across the image is turned into runs of dark and light, which are
+//! matched against the bar widths of the barcode's guards and digits. A code is only
+//! returned when its check digit is correct.
+
+use crate::attachments::get_attachments;
+use crate::identifiers::{Identifier, IdentifierKind};
+use anyhow::Result as AnyResult;
+use image::{DynamicImage, GrayImage};
+use rusqlite::Connection;
+use std::path::Path;
+
+// Widths in modules of the space, bar, space, bar of each left-hand odd parity (L) digit.
+// Even parity (G) digits are the same widths reversed, and right-hand digits swap bars
+// for spaces, which leaves them with the L widths.
+const DIGIT_WIDTHS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+
+// The parity of an EAN-13's six left-hand digits gives its first digit
+const FIRST_DIGIT_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL",
+    "LGGLGL",
+];
+
+// How far in modules a digit's widths may be from the nearest pattern in total
+const MAX_DIGIT_ERROR: f64 = 1.5;
+
+// A line across the image as alternating (dark, width) runs
+fn runs(line: &[u8]) -> Vec<(bool, usize)> {
+    let (min, max) = line
+        .iter()
+        .fold((u8::MAX, u8::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    // Too little contrast to hold a barcode
+    if max.saturating_sub(min) < 40 {
+        return vec![];
+    }
+    let threshold = (min as u16 + max as u16) / 2;
+    let mut runs: Vec<(bool, usize)> = vec![];
+    for &v in line {
+        let dark = (v as u16) < threshold;
+        match runs.last_mut() {
+            Some((d, width)) if *d == dark => *width += 1,
+            _ => runs.push((dark, 1)),
+        }
+    }
+    runs
+}
+
+// The digit and whether it has even parity, for four runs starting with a space on the left
+// half or a bar on the right
+fn decode_digit(widths: &[(bool, usize)], allow_even: bool) -> Option<(u8, bool)> {
+    let total: usize = widths.iter().map(|(_, w)| w).sum();
+    let module = total as f64 / 7.0;
+    let measured: Vec<f64> = widths.iter().map(|(_, w)| *w as f64 / module).collect();
+    let error = |pattern: &mut dyn Iterator<Item = &u8>| -> f64 {
+        measured
+            .iter()
+            .zip(pattern)
+            .map(|(m, p)| (m - *p as f64).abs())
+            .sum()
+    };
+
+    let mut best: Option<(u8, bool, f64)> = None;
+    for (digit, pattern) in DIGIT_WIDTHS.iter().enumerate() {
+        let mut candidates = vec![(false, error(&mut pattern.iter()))];
+        if allow_even {
+            candidates.push((true, error(&mut pattern.iter().rev())));
+        }
+        for (even, err) in candidates {
+            if best.is_none_or(|(_, _, e)| err < e) {
+                best = Some((digit as u8, even, err));
+            }
+        }
+    }
+    best.filter(|(_, _, err)| *err <= MAX_DIGIT_ERROR)
+        .map(|(digit, even, _)| (digit, even))
+}
+
+// Whether each run is about one module wide, as guard bars are
+fn is_guard(runs: &[(bool, usize)], module: f64) -> bool {
+    runs.iter()
+        .all(|(_, w)| (*w as f64 / module - 1.0).abs() < 0.75)
+}
+
+// `left` and `right` digits after a three run start guard, with a five run middle guard
+// and three run end guard. Returns the digits and the parity of the left ones.
+fn decode_symbol(
+    runs: &[(bool, usize)],
+    digits: usize,
+    modules: usize,
+) -> Option<(String, String)> {
+    let half = digits / 2;
+    let total: usize = runs.iter().map(|(_, w)| w).sum();
+    let module = total as f64 / modules as f64;
+    let middle = 3 + 4 * half;
+    let end = middle + 5 + 4 * half;
+    if !is_guard(&runs[..3], module)
+        || !is_guard(&runs[middle..middle + 5], module)
+        || !is_guard(&runs[end..end + 3], module)
+    {
+        return None;
+    }
+
+    let mut text = String::new();
+    let mut parity = String::new();
+    for i in 0..half {
+        let (digit, even) = decode_digit(&runs[3 + 4 * i..7 + 4 * i], digits == 12)?;
+        text.push((b'0' + digit) as char);
+        parity.push(if even { 'G' } else { 'L' });
+    }
+    for i in 0..half {
+        let start = middle + 5 + 4 * i;
+        let (digit, _) = decode_digit(&runs[start..start + 4], false)?;
+        text.push((b'0' + digit) as char);
+    }
+    Some((text, parity))
+}
+
+// EAN-13 has 59 runs over 95 modules, EAN-8 43 runs over 67
+fn decode_runs(runs: &[(bool, usize)]) -> Option<Identifier> {
+    for start in (0..runs.len()).filter(|&i| runs[i].0) {
+        if let Some(symbol) = runs.get(start..start + 59)
+            && let Some((digits, parity)) = decode_symbol(symbol, 12, 95)
+            && let Some(first) = FIRST_DIGIT_PARITY.iter().position(|p| *p == parity)
+        {
+            let code = format!("{}{}", first, digits);
+            // A UPC-A is an EAN-13 with a leading zero
+            let identifier = match code.strip_prefix('0') {
+                Some(upc) => Identifier::new(IdentifierKind::Upc, upc),
+                None => code.parse(),
+            };
+            if let Ok(identifier) = identifier {
+                return Some(identifier);
+            }
+        }
+        if let Some(symbol) = runs.get(start..start + 43)
+            && let Some((digits, _)) = decode_symbol(symbol, 8, 67)
+            && let Ok(identifier) = Identifier::new(IdentifierKind::Ean, &digits)
+        {
+            return Some(identifier);
+        }
+    }
+    None
+}
+
+fn scan_lines(image: &GrayImage, found: &mut Vec<Identifier>) {
+    let (width, height) = image.dimensions();
+    // Lines spread across the middle of the image, where a barcode is most likely to be
+    for step in 1..20 {
+        let y = height * step / 20;
+        let line: Vec<u8> = (0..width).map(|x| image.get_pixel(x, y).0[0]).collect();
+        let mut line_runs = runs(&line);
+        let mut decoded = decode_runs(&line_runs);
+        if decoded.is_none() {
+            // Upside down
+            line_runs.reverse();
+            decoded = decode_runs(&line_runs);
+        }
+        if let Some(identifier) = decoded
+            && !found.contains(&identifier)
+        {
+            found.push(identifier);
+        }
+    }
+}
+
+/// Every barcode found in the image, read across and then down.
+pub fn decode_barcodes(image: &DynamicImage) -> Vec<Identifier> {
+    let gray = image.to_luma8();
+    let mut found = vec![];
+    scan_lines(&gray, &mut found);
+    scan_lines(&image::imageops::rotate90(&gray), &mut found);
+    found
+}
+
+pub fn scan_barcode_file(path: &Path) -> AnyResult<Vec<Identifier>> {
+    let image = image::open(path)?;
+    Ok(decode_barcodes(&image))
+}
+
+/// Barcodes found in any of an item's photos. Attachments that can't be read are skipped.
+pub fn scan_item_attachments(conn: &Connection, item_id: i32) -> AnyResult<Vec<Identifier>> {
+    let mut found = vec![];
+    for attachment in get_attachments(conn, item_id)?
+        .into_iter()
+        .filter(|a| a.is_image())
+    {
+        if let Ok(codes) = scan_barcode_file(Path::new(&attachment.path)) {
+            for code in codes {
+                if !found.contains(&code) {
+                    found.push(code);
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, imageops};
+
+    // Bars (true) and spaces for each module of an EAN-13 or EAN-8
+    fn modules(code: &str) -> Vec<bool> {
+        let digits: Vec<usize> = code.bytes().map(|b| (b - b'0') as usize).collect();
+        let (parity, left, right) = match digits.len() {
+            13 => (FIRST_DIGIT_PARITY[digits[0]], &digits[1..7], &digits[7..]),
+            _ => ("LLLL", &digits[..4], &digits[4..]),
+        };
+        let mut bits = vec![true, false, true];
+        let push = |digit: usize, even: bool, first_bar: bool, bits: &mut Vec<bool>| {
+            let mut widths = DIGIT_WIDTHS[digit].to_vec();
+            if even {
+                widths.reverse();
+            }
+            for (i, w) in widths.into_iter().enumerate() {
+                bits.extend(std::iter::repeat_n(first_bar == (i % 2 == 0), w as usize));
+            }
+        };
+        for (digit, p) in left.iter().zip(parity.chars()) {
+            push(*digit, p == 'G', false, &mut bits);
+        }
+        bits.extend([false, true, false, true, false]);
+        for digit in right {
+            push(*digit, false, true, &mut bits);
+        }
+        bits.extend([true, false, true]);
+        bits
+    }
+
+    fn barcode_image(code: &str) -> GrayImage {
+        let bits = modules(code);
+        let scale = 3;
+        let quiet = 10 * scale;
+        let width = bits.len() as u32 * scale + 2 * quiet;
+        GrayImage::from_fn(width, 60, |x, _| {
+            let module = x.checked_sub(quiet).map(|x| (x / scale) as usize);
+            match module.and_then(|m| bits.get(m)) {
+                Some(true) => Luma([20]),
+                _ => Luma([235]),
+            }
+        })
+    }
+
+    #[test]
+    fn test_decode_barcodes() {
+        let decode = |image: GrayImage| decode_barcodes(&DynamicImage::ImageLuma8(image));
+
+        let isbn = barcode_image("9780306406157");
+        assert_eq!(decode(isbn.clone())[0].to_string(), "ISBN 9780306406157");
+        assert_eq!(decode(imageops::rotate180(&isbn))[0].value, "9780306406157");
+        assert_eq!(decode(imageops::rotate90(&isbn))[0].value, "9780306406157");
+
+        assert_eq!(
+            decode(barcode_image("0036000291452"))[0].to_string(),
+            "UPC 036000291452"
+        );
+        assert_eq!(
+            decode(barcode_image("96385074"))[0].to_string(),
+            "EAN 96385074"
+        );
+
+        // A wrong check digit is never returned
+        assert!(decode(barcode_image("9780306406158")).is_empty());
+    }
+}
@@ -0,0 +1,722 @@
+//! Estate planning: who in the family is meant to receive which items.
+//!
+//! An item can be assigned to more than one beneficiary while wishes are being gathered.
+//! Those items are reported as conflicts until all but one assignment has been removed.
+
+use crate::db::get_item_by_id;
+use crate::models::Item;
+use crate::money::{MoneyFormat, get_home_currency, get_money_format, get_rate_table};
+use crate::pdf::Font;
+use crate::report::{PdfWriter, REPORT_CSS, ReportFormat, escape_html, total_lines};
+use crate::stats::{CatalogStats, compute_stats};
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::Local;
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// A person items may be left to.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beneficiary {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    /// e.g. "daughter" or "nephew"
+    pub relationship: Option<String>,
+    pub contact: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl Beneficiary {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.name.trim().is_empty() {
+            errors.push("Beneficiary name cannot be empty.".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Beneficiary {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            relationship: row.get("relationship")?,
+            contact: row.get("contact")?,
+            notes: row.get("notes")?,
+        })
+    }
+
+    /// Name with the relationship, e.g. "Anna (daughter)".
+    pub fn display_name(&self) -> String {
+        match &self.relationship {
+            Some(relationship) => format!("{} ({})", self.name, relationship),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// An item meant for a beneficiary.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub item_id: i32,
+    pub beneficiary_id: i32,
+    /// 1 for the beneficiary's first choice. Unset when they haven't ranked it.
+    pub priority: Option<u32>,
+    pub note: Option<String>,
+}
+
+/// A beneficiary as assigned to a particular item.
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemBeneficiary {
+    pub beneficiary: Beneficiary,
+    pub priority: Option<u32>,
+    pub note: Option<String>,
+}
+
+/// An item wanted by more than one beneficiary, with their claims in priority order.
+#[derive(Debug, Clone, Serialize)]
+pub struct AssignmentConflict {
+    pub item: Item,
+    pub claims: Vec<ItemBeneficiary>,
+}
+
+/// An item on a beneficiary's list.
+#[derive(Debug, Clone, Serialize)]
+pub struct AssignedItem {
+    pub item: Item,
+    pub priority: Option<u32>,
+    pub note: Option<String>,
+    /// Names of anyone else the item is also assigned to
+    pub also_wanted_by: Vec<String>,
+}
+
+/// Everything assigned to one beneficiary, first choices first.
+#[derive(Debug, Clone, Serialize)]
+pub struct BeneficiaryList {
+    pub beneficiary: Beneficiary,
+    pub items: Vec<AssignedItem>,
+    pub stats: CatalogStats,
+}
+
+pub fn add_beneficiary(conn: &Connection, beneficiary: &Beneficiary) -> AnyResult<i32> {
+    beneficiary
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+    conn.execute(
+        "INSERT INTO beneficiaries (name, relationship, contact, notes) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            beneficiary.name.trim(),
+            beneficiary.relationship,
+            beneficiary.contact,
+            beneficiary.notes
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+pub fn update_beneficiary(conn: &Connection, beneficiary: &Beneficiary) -> AnyResult<()> {
+    beneficiary
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+    let updated = conn.execute(
+        "UPDATE beneficiaries SET name = ?1, relationship = ?2, contact = ?3, notes = ?4
+        WHERE id = ?5",
+        params![
+            beneficiary.name.trim(),
+            beneficiary.relationship,
+            beneficiary.contact,
+            beneficiary.notes,
+            beneficiary.id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(anyhow!("Beneficiary {} not found", beneficiary.id));
+    }
+    Ok(())
+}
+
+/// Removes the beneficiary along with their assignments. Returns false if there was no
+/// such beneficiary.
+pub fn delete_beneficiary(conn: &Connection, id: i32) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM assignments WHERE beneficiary_id = ?1",
+        params![id],
+    )?;
+    let deleted = tx.execute("DELETE FROM beneficiaries WHERE id = ?1", params![id])?;
+    tx.commit()?;
+    Ok(deleted > 0)
+}
+
+pub fn get_beneficiary(conn: &Connection, id: i32) -> Result<Option<Beneficiary>> {
+    conn.query_row(
+        "SELECT * FROM beneficiaries WHERE id = ?1",
+        params![id],
+        Beneficiary::from_row,
+    )
+    .optional()
+}
+
+/// Sorted by name.
+pub fn get_beneficiaries(conn: &Connection) -> Result<Vec<Beneficiary>> {
+    let mut stmt = conn.prepare("SELECT * FROM beneficiaries ORDER BY name COLLATE NOCASE, id")?;
+    let rows = stmt.query_map([], Beneficiary::from_row)?;
+    rows.collect()
+}
+
+/// Case-insensitive lookup by name.
+pub fn find_beneficiary(conn: &Connection, name: &str) -> Result<Option<Beneficiary>> {
+    conn.query_row(
+        "SELECT * FROM beneficiaries WHERE name = ?1 COLLATE NOCASE ORDER BY id LIMIT 1",
+        params![name.trim()],
+        Beneficiary::from_row,
+    )
+    .optional()
+}
+
+/// Assigns an item to a beneficiary, replacing the priority and note if it was already
+/// assigned to them.
+pub fn assign_item(conn: &Connection, assignment: &Assignment) -> AnyResult<()> {
+    match get_item_by_id(conn, assignment.item_id)? {
+        Some(item) if item.wishlist => {
+            return Err(anyhow!(
+                "Item {} is on the wishlist, not owned",
+                assignment.item_id
+            ));
+        }
+        Some(item) if !item.deleted => {}
+        _ => return Err(anyhow!("Item {} not found", assignment.item_id)),
+    }
+    if get_beneficiary(conn, assignment.beneficiary_id)?.is_none() {
+        return Err(anyhow!(
+            "Beneficiary {} not found",
+            assignment.beneficiary_id
+        ));
+    }
+    if assignment.priority == Some(0) {
+        return Err(anyhow!("Priority starts at 1 for a first choice"));
+    }
+    conn.execute(
+        "INSERT INTO assignments (item_id, beneficiary_id, priority, note)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (item_id, beneficiary_id) DO UPDATE SET
+            priority = excluded.priority, note = excluded.note",
+        params![
+            assignment.item_id,
+            assignment.beneficiary_id,
+            assignment.priority,
+            assignment.note
+        ],
+    )?;
+    Ok(())
+}
+
+/// Returns false if the item wasn't assigned to the beneficiary.
+pub fn unassign_item(conn: &Connection, item_id: i32, beneficiary_id: i32) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM assignments WHERE item_id = ?1 AND beneficiary_id = ?2",
+        params![item_id, beneficiary_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+// Ranked choices first, then unranked
+const CLAIM_ORDER: &str = "a.priority IS NULL, a.priority";
+
+pub fn get_item_beneficiaries(conn: &Connection, item_id: i32) -> Result<Vec<ItemBeneficiary>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.*, a.priority AS assignment_priority, a.note AS assignment_note
+        FROM assignments a JOIN beneficiaries b ON b.id = a.beneficiary_id
+        WHERE a.item_id = ?1
+        ORDER BY {}, b.name COLLATE NOCASE",
+        CLAIM_ORDER
+    ))?;
+    let rows = stmt.query_map(params![item_id], |row| {
+        Ok(ItemBeneficiary {
+            beneficiary: Beneficiary::from_row(row)?,
+            priority: row.get("assignment_priority")?,
+            note: row.get("assignment_note")?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Items assigned to more than one beneficiary, sorted by item name. Deleted items are
+/// left out.
+pub fn assignment_conflicts(conn: &Connection) -> Result<Vec<AssignmentConflict>> {
+    let mut stmt = conn.prepare(
+        "SELECT items.* FROM items
+        WHERE items.deleted = 0
+            AND (SELECT COUNT(*) FROM assignments a WHERE a.item_id = items.id) > 1
+        ORDER BY items.name COLLATE NOCASE, items.id",
+    )?;
+    let items = stmt
+        .query_map([], Item::from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    items
+        .into_iter()
+        .map(|item| {
+            Ok(AssignmentConflict {
+                claims: get_item_beneficiaries(conn, item.id)?,
+                item,
+            })
+        })
+        .collect()
+}
+
+/// The items assigned to a beneficiary, with totals. Deleted items are left out.
+pub fn beneficiary_list(conn: &Connection, beneficiary_id: i32) -> AnyResult<BeneficiaryList> {
+    let beneficiary = get_beneficiary(conn, beneficiary_id)?
+        .ok_or_else(|| anyhow!("Beneficiary {} not found", beneficiary_id))?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT items.*, a.priority AS assignment_priority, a.note AS assignment_note
+        FROM assignments a JOIN items ON items.id = a.item_id
+        WHERE a.beneficiary_id = ?1 AND items.deleted = 0
+        ORDER BY {}, items.name COLLATE NOCASE",
+        CLAIM_ORDER
+    ))?;
+    let rows = stmt
+        .query_map(params![beneficiary_id], |row| {
+            Ok((
+                Item::from_row(row)?,
+                row.get::<_, Option<u32>>("assignment_priority")?,
+                row.get::<_, Option<String>>("assignment_note")?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut items = vec![];
+    for (item, priority, note) in rows {
+        let also_wanted_by = get_item_beneficiaries(conn, item.id)?
+            .into_iter()
+            .filter(|c| c.beneficiary.id != beneficiary_id)
+            .map(|c| c.beneficiary.name)
+            .collect();
+        items.push(AssignedItem {
+            item,
+            priority,
+            note,
+            also_wanted_by,
+        });
+    }
+
+    let plain: Vec<Item> = items.iter().map(|a| a.item.clone()).collect();
+    let stats = compute_stats(&plain, get_home_currency(conn)?, &get_rate_table(conn)?);
+    Ok(BeneficiaryList {
+        beneficiary,
+        items,
+        stats,
+    })
+}
+
+/// A list for every beneficiary, including those with nothing assigned yet.
+pub fn beneficiary_lists(conn: &Connection) -> AnyResult<Vec<BeneficiaryList>> {
+    get_beneficiaries(conn)?
+        .into_iter()
+        .map(|b| beneficiary_list(conn, b.id))
+        .collect()
+}
+
+pub fn render_beneficiaries_html(
+    lists: &[BeneficiaryList],
+    conflicts: &[AssignmentConflict],
+    fmt: &MoneyFormat,
+) -> String {
+    let mut html = String::new();
+    html.push_str(&format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\" />\n\
+         <title>Beneficiaries</title>\n<style>\n{}{}</style>\n</head>\n<body>\n",
+        REPORT_CSS, BENEFICIARY_CSS
+    ));
+    html.push_str(&format!(
+        "<h1>Beneficiaries</h1>\n<p class=\"meta\">Generated {}</p>\n",
+        Local::now().date_naive()
+    ));
+
+    for list in lists {
+        html.push_str(&format!(
+            "<section class=\"person\">\n<h2>{}</h2>\n",
+            escape_html(&list.beneficiary.display_name())
+        ));
+        for detail in [&list.beneficiary.contact, &list.beneficiary.notes]
+            .into_iter()
+            .flatten()
+        {
+            html.push_str(&format!("<p class=\"meta\">{}</p>\n", escape_html(detail)));
+        }
+        if list.items.is_empty() {
+            html.push_str("<p>Nothing assigned.</p>\n</section>\n");
+            continue;
+        }
+        html.push_str(
+            "<table>\n<thead><tr><th>Priority</th><th>Item</th><th>Location</th>\
+             <th>Estimated Value</th></tr></thead>\n<tbody>\n",
+        );
+        for assigned in &list.items {
+            let item = &assigned.item;
+            let mut notes = vec![];
+            if let Some(note) = &assigned.note {
+                notes.push(escape_html(note));
+            }
+            if !assigned.also_wanted_by.is_empty() {
+                notes.push(format!(
+                    "<span class=\"conflict\">Also wanted by {}</span>",
+                    escape_html(&assigned.also_wanted_by.join(", "))
+                ));
+            }
+            html.push_str(&format!(
+                "<tr><td>{}</td><td><strong>{}</strong> <span class=\"meta\">#{}</span>{}</td>\
+                 <td>{}</td><td class=\"money\">{}</td></tr>\n",
+                assigned.priority.map(|p| p.to_string()).unwrap_or_default(),
+                escape_html(&item.name),
+                item.id,
+                notes
+                    .iter()
+                    .map(|n| format!("<br />{}", n))
+                    .collect::<String>(),
+                escape_html(item.location.as_deref().unwrap_or("")),
+                item.estimated_value
+                    .map(|m| m.format(fmt))
+                    .unwrap_or_default(),
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n<table class=\"totals\">\n");
+        for (label, line) in total_lines(&list.stats, fmt) {
+            html.push_str(&format!(
+                "<tr><th>{}</th><td class=\"money\">{}</td></tr>\n",
+                label,
+                escape_html(&line)
+            ));
+        }
+        html.push_str("</table>\n</section>\n");
+    }
+
+    if !conflicts.is_empty() {
+        html.push_str(
+            "<section class=\"person\">\n<h2>Conflicts</h2>\n<table>\n<thead><tr><th>Item</th>\
+             <th>Wanted by</th></tr></thead>\n<tbody>\n",
+        );
+        for conflict in conflicts {
+            html.push_str(&format!(
+                "<tr><td><strong>{}</strong> <span class=\"meta\">#{}</span></td><td>{}</td></tr>\n",
+                escape_html(&conflict.item.name),
+                conflict.item.id,
+                escape_html(&claims_text(&conflict.claims))
+            ));
+        }
+        html.push_str("</tbody>\n</table>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+pub fn render_beneficiaries_pdf(
+    lists: &[BeneficiaryList],
+    conflicts: &[AssignmentConflict],
+    fmt: &MoneyFormat,
+) -> Vec<u8> {
+    let mut out = PdfWriter::new();
+
+    // Each person starts a new page so their list can be printed and handed over on its own
+    for (n, list) in lists.iter().enumerate() {
+        if n > 0 {
+            out.new_page();
+        }
+        out.line_of_text(&list.beneficiary.display_name(), 18.0, Font::Bold, 0.0);
+        out.line_of_text(
+            &format!(
+                "Generated {}  -  {} items",
+                Local::now().date_naive(),
+                list.stats.item_count
+            ),
+            9.0,
+            Font::Regular,
+            0.0,
+        );
+        for detail in [&list.beneficiary.contact, &list.beneficiary.notes]
+            .into_iter()
+            .flatten()
+        {
+            out.paragraph(detail, 9.0, Font::Regular, 0.0);
+        }
+        out.rule();
+
+        for assigned in &list.items {
+            let item = &assigned.item;
+            out.gap(6.0);
+            out.ensure(30.0);
+            let rank = assigned
+                .priority
+                .map(|p| format!("{}. ", p))
+                .unwrap_or_default();
+            out.line_of_text(
+                &format!("{}{} (#{})", rank, item.name, item.id),
+                11.0,
+                Font::Bold,
+                0.0,
+            );
+
+            let mut facts = vec![];
+            if let Some(location) = &item.location {
+                facts.push(format!("Location {}", location));
+            }
+            if let Some(value) = item.estimated_value {
+                facts.push(format!("Estimated value {}", value.format(fmt)));
+            }
+            if !facts.is_empty() {
+                out.paragraph(&facts.join("   "), 9.0, Font::Regular, 12.0);
+            }
+            if let Some(note) = &assigned.note {
+                out.paragraph(note, 9.0, Font::Regular, 12.0);
+            }
+            if !assigned.also_wanted_by.is_empty() {
+                out.paragraph(
+                    &format!("Also wanted by {}", assigned.also_wanted_by.join(", ")),
+                    9.0,
+                    Font::Bold,
+                    12.0,
+                );
+            }
+        }
+
+        out.gap(12.0);
+        out.ensure(60.0);
+        out.rule();
+        for (label, line) in total_lines(&list.stats, fmt) {
+            out.paragraph(&format!("{}: {}", label, line), 10.0, Font::Regular, 0.0);
+        }
+    }
+
+    if !conflicts.is_empty() {
+        if !lists.is_empty() {
+            out.new_page();
+        }
+        out.line_of_text("Conflicts", 18.0, Font::Bold, 0.0);
+        out.rule();
+        for conflict in conflicts {
+            out.gap(6.0);
+            out.ensure(30.0);
+            out.line_of_text(
+                &format!("{} (#{})", conflict.item.name, conflict.item.id),
+                11.0,
+                Font::Bold,
+                0.0,
+            );
+            out.paragraph(
+                &format!("Wanted by {}", claims_text(&conflict.claims)),
+                9.0,
+                Font::Regular,
+                12.0,
+            );
+        }
+    }
+
+    out.doc.to_bytes()
+}
+
+/// One row per assignment, for spreadsheets.
+pub fn render_beneficiaries_csv(lists: &[BeneficiaryList]) -> AnyResult<Vec<u8>> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    wtr.write_record([
+        "beneficiary",
+        "relationship",
+        "priority",
+        "item_id",
+        "item",
+        "category",
+        "location",
+        "estimated_value",
+        "note",
+        "also_wanted_by",
+    ])?;
+    let canonical = MoneyFormat::canonical();
+    for list in lists {
+        for assigned in &list.items {
+            let item = &assigned.item;
+            wtr.write_record([
+                list.beneficiary.name.clone(),
+                list.beneficiary.relationship.clone().unwrap_or_default(),
+                assigned.priority.map(|p| p.to_string()).unwrap_or_default(),
+                item.id.to_string(),
+                item.name.clone(),
+                item.category.to_string(),
+                item.location.clone().unwrap_or_default(),
+                item.estimated_value
+                    .map(|m| m.format(&canonical))
+                    .unwrap_or_default(),
+                assigned.note.clone().unwrap_or_default(),
+                assigned.also_wanted_by.join("; "),
+            ])?;
+        }
+    }
+    Ok(wtr.into_inner()?)
+}
+
+/// Writes the list for one beneficiary, or for everyone followed by any conflicts, picking
+/// HTML, PDF or CSV from the file extension. Returns the number of assignments included.
+pub fn write_beneficiary_report(
+    conn: &Connection,
+    beneficiary_id: Option<i32>,
+    path: &str,
+) -> AnyResult<usize> {
+    let csv = path.to_ascii_lowercase().ends_with(".csv");
+    let format = ReportFormat::from_path(path);
+    if !csv && format.is_none() {
+        return Err(anyhow!(
+            "Report path must end in .html, .pdf or .csv: {}",
+            path
+        ));
+    }
+
+    let (lists, conflicts) = match beneficiary_id {
+        Some(id) => (vec![beneficiary_list(conn, id)?], vec![]),
+        None => (beneficiary_lists(conn)?, assignment_conflicts(conn)?),
+    };
+    let fmt = get_money_format(conn)?;
+    match format {
+        _ if csv => fs::write(path, render_beneficiaries_csv(&lists)?)?,
+        Some(ReportFormat::Html) => {
+            fs::write(path, render_beneficiaries_html(&lists, &conflicts, &fmt))?
+        }
+        Some(ReportFormat::Pdf) => {
+            fs::write(path, render_beneficiaries_pdf(&lists, &conflicts, &fmt))?
+        }
+        None => unreachable!(),
+    }
+    Ok(lists.iter().map(|l| l.items.len()).sum())
+}
+
+// e.g. "Anna (1), Ben (2), Carl"
+fn claims_text(claims: &[ItemBeneficiary]) -> String {
+    claims
+        .iter()
+        .map(|c| match c.priority {
+            Some(p) => format!("{} ({})", c.beneficiary.name, p),
+            None => c.beneficiary.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+const BENEFICIARY_CSS: &str = "section.person + section.person { page-break-before: always; }
+.conflict { color: #a00; font-size: 9pt; }
+table.totals { width: auto; margin-top: 1em; }
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db, soft_delete_item};
+    use crate::models::ItemCategory;
+    use crate::money::Money;
+    use std::str::FromStr;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, value) in [("Clock", "150.00"), ("Atlas", "40.25"), ("Chair", "99.75")] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                category: ItemCategory::Antique,
+                estimated_value: Some(Money::from_str(value).unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        for (name, relationship) in [("Anna", "daughter"), ("Ben", "son")] {
+            let beneficiary = Beneficiary {
+                name: name.to_string(),
+                relationship: Some(relationship.to_string()),
+                ..Default::default()
+            };
+            add_beneficiary(&conn, &beneficiary).unwrap();
+        }
+        conn
+    }
+
+    fn assign(conn: &Connection, item_id: i32, beneficiary_id: i32, priority: Option<u32>) {
+        let assignment = Assignment {
+            item_id,
+            beneficiary_id,
+            priority,
+            note: None,
+        };
+        assign_item(conn, &assignment).unwrap();
+    }
+
+    #[test]
+    fn test_lists_and_conflicts() {
+        let conn = setup();
+        assign(&conn, 1, 1, Some(2));
+        assign(&conn, 2, 1, None);
+        assign(&conn, 3, 1, Some(1));
+        assign(&conn, 1, 2, Some(1));
+        // Reassigning replaces the priority rather than adding a second claim
+        assign(&conn, 1, 2, Some(3));
+
+        let anna = beneficiary_list(&conn, 1).unwrap();
+        let names: Vec<&str> = anna.items.iter().map(|a| a.item.name.as_str()).collect();
+        assert_eq!(names, vec!["Chair", "Clock", "Atlas"]);
+        assert_eq!(anna.items[1].also_wanted_by, vec!["Ben"]);
+        assert!(anna.items[0].also_wanted_by.is_empty());
+        assert_eq!(
+            anna.stats.estimated_total_home,
+            Some(Money::from_str("290.00").unwrap())
+        );
+
+        let conflicts = assignment_conflicts(&conn).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].item.name, "Clock");
+        assert_eq!(claims_text(&conflicts[0].claims), "Anna (2), Ben (3)");
+
+        // Resolving the conflict and deleting an item both shorten the lists
+        assert!(unassign_item(&conn, 1, 2).unwrap());
+        assert!(assignment_conflicts(&conn).unwrap().is_empty());
+        soft_delete_item(&conn, 2).unwrap();
+        assert_eq!(beneficiary_list(&conn, 1).unwrap().items.len(), 2);
+
+        assert!(
+            assign_item(
+                &conn,
+                &Assignment {
+                    item_id: 2,
+                    beneficiary_id: 2,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+        assert!(delete_beneficiary(&conn, 1).unwrap());
+        assert!(get_item_beneficiaries(&conn, 3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_render_reports() {
+        let conn = setup();
+        assign(&conn, 1, 1, Some(1));
+        assign(&conn, 1, 2, None);
+        let lists = beneficiary_lists(&conn).unwrap();
+        let conflicts = assignment_conflicts(&conn).unwrap();
+        let fmt = MoneyFormat::default();
+
+        let html = render_beneficiaries_html(&lists, &conflicts, &fmt);
+        assert!(html.contains("<h2>Anna (daughter)</h2>"));
+        assert!(html.contains("Also wanted by Ben"));
+        assert!(html.contains("<h2>Conflicts</h2>"));
+        assert!(html.contains("USD 150.00"));
+
+        let pdf = render_beneficiaries_pdf(&lists, &conflicts, &fmt);
+        assert!(pdf.starts_with(b"%PDF"));
+
+        let csv = String::from_utf8(render_beneficiaries_csv(&lists).unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "Anna,daughter,1,1,Clock,Antique,,150.00 USD,,Ben");
+    }
+}
@@ -0,0 +1,258 @@
+use crate::attachments::{Attachment, get_attachments, record_photo_details};
+use crate::models::ItemCategory;
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::NaiveDate;
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+
+/// Grades used when a category has no scale of its own, best first.
+pub const DEFAULT_GRADES: &[&str] = &[
+    "Mint",
+    "Excellent",
+    "Good",
+    "Fair",
+    "Poor",
+    "Damaged",
+    "Needs Repair",
+];
+
+/// Conventional book grading, best first.
+pub const BOOK_GRADES: &[&str] = &["As New", "Fine", "Very Good", "Good", "Fair", "Poor"];
+
+/// A dated record of an item's condition. The latest report is mirrored on the item itself.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConditionReport {
+    pub id: i32,
+    pub item_id: i32,
+    pub date: NaiveDate,
+    pub grade: String,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub photos: Vec<Attachment>,
+}
+
+impl ConditionReport {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ConditionReport {
+            id: row.get("id")?,
+            item_id: row.get("item_id")?,
+            date: NaiveDate::parse_from_str(&row.get::<_, String>("date")?, "%Y-%m-%d")
+                .unwrap_or(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()),
+            grade: row.get("grade")?,
+            notes: row.get("notes")?,
+            photos: vec![],
+        })
+    }
+}
+
+/// Grading scale for `category`, best grade first.
+pub fn get_condition_scale(conn: &Connection, category: &ItemCategory) -> Result<Vec<String>> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT grades FROM condition_scales WHERE category = ?1",
+            params![category.to_string()],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(match stored {
+        Some(grades) => grades.split('|').map(String::from).collect(),
+        None => match category {
+            ItemCategory::Book => BOOK_GRADES,
+            _ => DEFAULT_GRADES,
+        }
+        .iter()
+        .map(|g| g.to_string())
+        .collect(),
+    })
+}
+
+pub fn set_condition_scale(
+    conn: &Connection,
+    category: &ItemCategory,
+    grades: &[String],
+) -> AnyResult<()> {
+    let grades: Vec<&str> = grades.iter().map(|g| g.trim()).collect();
+    if grades.is_empty() || grades.iter().any(|g| g.is_empty() || g.contains('|')) {
+        return Err(anyhow!("Grades must be non-empty and cannot contain '|'"));
+    }
+    conn.execute(
+        "INSERT INTO condition_scales (category, grades) VALUES (?1, ?2)
+        ON CONFLICT(category) DO UPDATE SET grades = excluded.grades",
+        params![category.to_string(), grades.join("|")],
+    )?;
+    Ok(())
+}
+
+/// Matches `grade` against the category's scale ignoring case, returning the scale's spelling.
+pub fn canonical_grade(
+    conn: &Connection,
+    category: &ItemCategory,
+    grade: &str,
+) -> AnyResult<String> {
+    let scale = get_condition_scale(conn, category)?;
+    scale
+        .iter()
+        .find(|g| g.eq_ignore_ascii_case(grade.trim()))
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown condition '{}' for {}. Expected one of: {}",
+                grade,
+                category,
+                scale.join(", ")
+            )
+        })
+}
+
+/// Records a condition report and, when it is the most recent one, makes it the item's
+/// current condition. Returns the new report id.
+pub fn add_condition_report(conn: &Connection, report: &ConditionReport) -> AnyResult<i32> {
+    let category: String = conn
+        .query_row(
+            "SELECT category FROM items WHERE id = ?1",
+            params![report.item_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("Item {} not found", report.item_id))?;
+    let category = category.parse().unwrap_or(ItemCategory::Other);
+    let grade = canonical_grade(conn, &category, &report.grade)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO condition_reports (item_id, date, grade, notes) VALUES (?1, ?2, ?3, ?4)",
+        params![report.item_id, report.date.to_string(), grade, report.notes],
+    )?;
+    let id = tx.last_insert_rowid() as i32;
+    tx.execute(
+        "UPDATE items SET condition = ?1, condition_notes = ?2
+        WHERE id = ?3
+        AND NOT EXISTS (
+            SELECT 1 FROM condition_reports WHERE item_id = ?3 AND date > ?4
+        )",
+        params![grade, report.notes, report.item_id, report.date.to_string()],
+    )?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Condition reports for an item, oldest first, with their photos.
+pub fn get_condition_history(conn: &Connection, item_id: i32) -> Result<Vec<ConditionReport>> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM condition_reports WHERE item_id = ?1 ORDER BY date, id")?;
+    let mut reports = stmt
+        .query_map(params![item_id], ConditionReport::from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    let attachments = get_attachments(conn, item_id)?;
+    for report in &mut reports {
+        report.photos = attachments
+            .iter()
+            .filter(|a| a.condition_report_id == Some(report.id))
+            .cloned()
+            .collect();
+    }
+    Ok(reports)
+}
+
+/// Attaches a photo to a condition report (and so to the report's item).
+pub fn add_condition_photo(
+    conn: &Connection,
+    report_id: i32,
+    path: &str,
+    caption: Option<&str>,
+) -> AnyResult<i32> {
+    let inserted = conn.execute(
+        "INSERT INTO attachments (item_id, path, caption, date_added, condition_report_id)
+        SELECT item_id, ?2, ?3, date, id FROM condition_reports WHERE id = ?1",
+        params![report_id, path, caption],
+    )?;
+    if inserted != 1 {
+        return Err(anyhow!("Condition report {} not found", report_id));
+    }
+    let id = conn.last_insert_rowid() as i32;
+    record_photo_details(conn, id, path)?;
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, get_filtered_items, get_item_by_id, init_db, update_item};
+    use crate::models::{Item, ItemFilter};
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let item = Item {
+            name: "Radio".to_string(),
+            description: "Valve radio".to_string(),
+            category: ItemCategory::ElectronicDevice,
+            ..Default::default()
+        };
+        add_item(&conn, &item).unwrap();
+        conn
+    }
+
+    fn report(date: &str, grade: &str) -> ConditionReport {
+        ConditionReport {
+            item_id: 1,
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            grade: grade.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_history_tracks_latest_condition() {
+        let conn = setup();
+        add_condition_report(&conn, &report("2024-03-01", "good")).unwrap();
+        add_condition_report(&conn, &report("2025-06-01", "Needs Repair")).unwrap();
+        // Backdated report doesn't replace the current condition
+        let old = add_condition_report(&conn, &report("2020-01-01", "Mint")).unwrap();
+        add_condition_photo(&conn, old, "/photos/radio.jpg", None).unwrap();
+        assert!(add_condition_photo(&conn, 99, "/photos/dial.jpg", None).is_err());
+
+        let item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(item.condition.as_deref(), Some("Needs Repair"));
+
+        let history = get_condition_history(&conn, 1).unwrap();
+        let grades: Vec<&str> = history.iter().map(|r| r.grade.as_str()).collect();
+        assert_eq!(grades, vec!["Mint", "Good", "Needs Repair"]);
+        assert_eq!(history[0].photos.len(), 1);
+        assert_eq!(get_attachments(&conn, 1).unwrap().len(), 1);
+
+        let filter = ItemFilter {
+            condition: Some("needs repair".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_items(&conn, filter).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_grade_must_be_on_scale() {
+        let conn = setup();
+        assert!(add_condition_report(&conn, &report("2024-03-01", "Shiny")).is_err());
+
+        let scale = vec!["Working".to_string(), "Broken".to_string()];
+        set_condition_scale(&conn, &ItemCategory::ElectronicDevice, &scale).unwrap();
+        assert!(add_condition_report(&conn, &report("2024-03-01", "Broken")).is_ok());
+        assert!(add_condition_report(&conn, &report("2024-03-01", "Mint")).is_err());
+
+        // A grade from before the scale changed is kept while it isn't edited
+        let scale = vec!["Fine".to_string(), "Poor".to_string()];
+        set_condition_scale(&conn, &ItemCategory::ElectronicDevice, &scale).unwrap();
+        let mut item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        item.name = "Valve Radio".to_string();
+        update_item(&conn, &item).unwrap();
+        let mut item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(item.condition.as_deref(), Some("Broken"));
+        item.condition = Some("Working".to_string());
+        assert!(update_item(&conn, &item).is_err());
+        item.condition = Some("poor".to_string());
+        update_item(&conn, &item).unwrap();
+        let item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(item.condition.as_deref(), Some("Poor"));
+    }
+}
@@ -0,0 +1,565 @@
+use crate::db::get_filtered_items;
+use crate::fuzzy_date::FuzzyDate;
+use crate::models::{Item, ItemCategory, ItemFilter};
+use crate::money::{get_home_currency, get_rate_table};
+use crate::stats::{CatalogStats, compute_stats};
+use anyhow::{Result as AnyResult, anyhow};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreatorRole {
+    Author,
+    Artist,
+    #[default]
+    Maker,
+    Manufacturer,
+    Publisher,
+    Other,
+}
+
+impl CreatorRole {
+    /// Role assumed when an item is linked by name alone.
+    pub fn default_for(category: &ItemCategory) -> Self {
+        match category {
+            ItemCategory::Book => CreatorRole::Author,
+            ItemCategory::ElectronicDevice => CreatorRole::Manufacturer,
+            _ => CreatorRole::Maker,
+        }
+    }
+}
+
+impl fmt::Display for CreatorRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CreatorRole::Author => "Author",
+                CreatorRole::Artist => "Artist",
+                CreatorRole::Maker => "Maker",
+                CreatorRole::Manufacturer => "Manufacturer",
+                CreatorRole::Publisher => "Publisher",
+                CreatorRole::Other => "Other",
+            }
+        )
+    }
+}
+
+impl FromStr for CreatorRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "author" => Ok(CreatorRole::Author),
+            "artist" => Ok(CreatorRole::Artist),
+            "maker" => Ok(CreatorRole::Maker),
+            "manufacturer" => Ok(CreatorRole::Manufacturer),
+            "publisher" => Ok(CreatorRole::Publisher),
+            "other" => Ok(CreatorRole::Other),
+            _ => Err(format!("Unknown creator role: '{}'", s)),
+        }
+    }
+}
+
+/// A person or company that made items. `Item::creator` holds the linked creators' names
+/// for display; the links themselves live in `item_creators`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Creator {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub born: Option<FuzzyDate>,
+    pub died: Option<FuzzyDate>,
+    pub role: Option<CreatorRole>,
+}
+
+impl Creator {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.name.trim().is_empty() {
+            errors.push("Creator name cannot be empty.".to_string());
+        }
+        if self.aliases.iter().any(|a| a.contains('|')) {
+            errors.push("Aliases cannot contain '|'.".to_string());
+        }
+        if let (Some(born), Some(died)) = (self.born, self.died)
+            && born.earliest() > died.latest()
+        {
+            errors.push("Creator cannot die before being born.".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let date = |column: &str| -> rusqlite::Result<Option<FuzzyDate>> {
+            Ok(row
+                .get::<_, Option<String>>(column)?
+                .and_then(|s| FuzzyDate::from_str(&s).ok()))
+        };
+        Ok(Creator {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            aliases: row
+                .get::<_, Option<String>>("aliases")?
+                .map(|a| a.split('|').map(String::from).collect())
+                .unwrap_or_default(),
+            born: date("born")?,
+            died: date("died")?,
+            role: row
+                .get::<_, Option<String>>("role")?
+                .and_then(|r| r.parse().ok()),
+        })
+    }
+
+    /// Name with life dates, e.g. "Thomas Chippendale (1718-1779)".
+    pub fn display_name(&self) -> String {
+        match (self.born, self.died) {
+            (None, None) => self.name.clone(),
+            (born, died) => format!(
+                "{} ({}-{})",
+                self.name,
+                born.map(|d| d.to_string()).unwrap_or_default(),
+                died.map(|d| d.to_string()).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// A creator as linked to a particular item.
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemCreator {
+    pub creator: Creator,
+    pub role: CreatorRole,
+}
+
+fn aliases_column(creator: &Creator) -> Option<String> {
+    let aliases: Vec<&str> = creator
+        .aliases
+        .iter()
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .collect();
+    Some(aliases.join("|")).filter(|a| !a.is_empty())
+}
+
+pub fn add_creator(conn: &Connection, creator: &Creator) -> AnyResult<i32> {
+    creator
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+    conn.execute(
+        "INSERT INTO creators (name, aliases, born, died, role) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            creator.name.trim(),
+            aliases_column(creator),
+            creator.born.map(|d| d.to_string()),
+            creator.died.map(|d| d.to_string()),
+            creator.role.map(|r| r.to_string()),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+/// Saves changes to a creator and refreshes the names shown on its items.
+pub fn update_creator(conn: &Connection, creator: &Creator) -> AnyResult<()> {
+    creator
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+    let tx = conn.unchecked_transaction()?;
+    save_creator(&tx, creator)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn save_creator(conn: &Connection, creator: &Creator) -> Result<()> {
+    conn.execute(
+        "UPDATE creators SET name = ?1, aliases = ?2, born = ?3, died = ?4, role = ?5
+        WHERE id = ?6",
+        params![
+            creator.name.trim(),
+            aliases_column(creator),
+            creator.born.map(|d| d.to_string()),
+            creator.died.map(|d| d.to_string()),
+            creator.role.map(|r| r.to_string()),
+            creator.id,
+        ],
+    )?;
+    refresh_creator_names(conn, creator.id)
+}
+
+pub fn get_creator(conn: &Connection, id: i32) -> Result<Option<Creator>> {
+    conn.query_row(
+        "SELECT * FROM creators WHERE id = ?1",
+        params![id],
+        Creator::from_row,
+    )
+    .optional()
+}
+
+pub fn get_creators(conn: &Connection) -> Result<Vec<Creator>> {
+    let mut stmt = conn.prepare("SELECT * FROM creators ORDER BY name COLLATE NOCASE")?;
+    let rows = stmt.query_map([], Creator::from_row)?;
+    rows.collect()
+}
+
+/// Looks a creator up by name or alias, ignoring case.
+pub fn find_creator(conn: &Connection, name: &str) -> Result<Option<Creator>> {
+    conn.query_row(
+        "SELECT * FROM creators
+        WHERE name = ?1 COLLATE NOCASE
+        OR instr('|' || lower(COALESCE(aliases, '')) || '|', '|' || lower(?1) || '|') > 0
+        ORDER BY name = ?1 COLLATE NOCASE DESC
+        LIMIT 1",
+        params![name.trim()],
+        Creator::from_row,
+    )
+    .optional()
+}
+
+// Rewrites `items.creator` from the item's links, in link order
+pub(crate) fn refresh_item_creator(conn: &Connection, item_id: i32) -> Result<()> {
+    conn.execute(
+        "UPDATE items SET creator = (
+            SELECT group_concat(name, '; ') FROM (
+                SELECT c.name FROM item_creators ic JOIN creators c ON c.id = ic.creator_id
+                WHERE ic.item_id = ?1 ORDER BY ic.position
+            )
+        ) WHERE id = ?1",
+        params![item_id],
+    )?;
+    Ok(())
+}
+
+fn refresh_creator_names(conn: &Connection, creator_id: i32) -> Result<()> {
+    let mut stmt =
+        conn.prepare("SELECT DISTINCT item_id FROM item_creators WHERE creator_id = ?1")?;
+    let item_ids = stmt
+        .query_map(params![creator_id], |row| row.get::<_, i32>(0))?
+        .collect::<Result<Vec<_>>>()?;
+    for item_id in item_ids {
+        refresh_item_creator(conn, item_id)?;
+    }
+    Ok(())
+}
+
+pub fn link_creator(
+    conn: &Connection,
+    item_id: i32,
+    creator_id: i32,
+    role: CreatorRole,
+) -> AnyResult<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO item_creators (item_id, creator_id, role, position)
+        SELECT ?1, ?2, ?3, COALESCE(MAX(position), 0) + 1 FROM item_creators WHERE item_id = ?1",
+        params![item_id, creator_id, role.to_string()],
+    )?;
+    refresh_item_creator(conn, item_id)?;
+    Ok(())
+}
+
+pub fn unlink_creator(conn: &Connection, item_id: i32, creator_id: i32) -> Result<()> {
+    conn.execute(
+        "DELETE FROM item_creators WHERE item_id = ?1 AND creator_id = ?2",
+        params![item_id, creator_id],
+    )?;
+    refresh_item_creator(conn, item_id)
+}
+
+/// Replaces an item's creator links with the creators named in `text`, separated by ';'.
+/// Names are matched against existing creators and aliases, and new creators are added for
+/// the rest. Creators that stay linked keep their role; the others get `role`.
+pub(crate) fn link_creators_by_name(
+    conn: &Connection,
+    item_id: i32,
+    text: Option<&str>,
+    role: CreatorRole,
+) -> AnyResult<()> {
+    let roles: HashMap<i32, CreatorRole> = get_item_creators(conn, item_id)?
+        .into_iter()
+        .map(|c| (c.creator.id, c.role))
+        .collect();
+    conn.execute(
+        "DELETE FROM item_creators WHERE item_id = ?1",
+        params![item_id],
+    )?;
+    for name in text.unwrap_or_default().split(';').map(str::trim) {
+        if name.is_empty() {
+            continue;
+        }
+        let creator_id = match find_creator(conn, name)? {
+            Some(creator) => creator.id,
+            None => add_creator(
+                conn,
+                &Creator {
+                    name: name.to_string(),
+                    ..Default::default()
+                },
+            )?,
+        };
+        let role = roles.get(&creator_id).copied().unwrap_or(role);
+        link_creator(conn, item_id, creator_id, role)?;
+    }
+    refresh_item_creator(conn, item_id)?;
+    Ok(())
+}
+
+pub fn get_item_creators(conn: &Connection, item_id: i32) -> Result<Vec<ItemCreator>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.*, ic.role AS link_role FROM item_creators ic
+        JOIN creators c ON c.id = ic.creator_id
+        WHERE ic.item_id = ?1 ORDER BY ic.position",
+    )?;
+    let rows = stmt.query_map(params![item_id], |row| {
+        Ok(ItemCreator {
+            creator: Creator::from_row(row)?,
+            role: row
+                .get::<_, String>("link_role")?
+                .parse()
+                .unwrap_or_default(),
+        })
+    })?;
+    rows.collect()
+}
+
+/// Folds `merge_id` into `keep_id`: its items are relinked, and its name and aliases become
+/// aliases of the kept creator so text lookups still find it.
+pub fn merge_creators(conn: &Connection, keep_id: i32, merge_id: i32) -> AnyResult<Creator> {
+    if keep_id == merge_id {
+        return Err(anyhow!("Cannot merge a creator into itself"));
+    }
+    let mut keep =
+        get_creator(conn, keep_id)?.ok_or_else(|| anyhow!("Creator {} not found", keep_id))?;
+    let merge =
+        get_creator(conn, merge_id)?.ok_or_else(|| anyhow!("Creator {} not found", merge_id))?;
+
+    for alias in std::iter::once(&merge.name).chain(&merge.aliases) {
+        if !alias.eq_ignore_ascii_case(&keep.name)
+            && !keep.aliases.iter().any(|a| a.eq_ignore_ascii_case(alias))
+        {
+            keep.aliases.push(alias.clone());
+        }
+    }
+    keep.born = keep.born.or(merge.born);
+    keep.died = keep.died.or(merge.died);
+    keep.role = keep.role.or(merge.role);
+
+    let tx = conn.unchecked_transaction()?;
+    // Items linked to both keep their existing link to the kept creator
+    tx.execute(
+        "UPDATE OR IGNORE item_creators SET creator_id = ?1 WHERE creator_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "DELETE FROM item_creators WHERE creator_id = ?1",
+        params![merge_id],
+    )?;
+    tx.execute("DELETE FROM creators WHERE id = ?1", params![merge_id])?;
+    save_creator(&tx, &keep)?;
+    tx.commit()?;
+    Ok(keep)
+}
+
+// "Smith, John" and "John Smith" both become ["john", "smith"]
+fn name_parts(name: &str) -> Vec<String> {
+    let name = match name.split_once(',') {
+        Some((last, first)) => format!("{} {}", first, last),
+        None => name.to_string(),
+    };
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_lowercase())
+        .collect()
+}
+
+// Same surname, and every given name either matches or is an initial of the other
+pub(crate) fn names_match(a: &str, b: &str) -> bool {
+    let (a, b) = (name_parts(a), name_parts(b));
+    let (Some((a_last, a_given)), Some((b_last, b_given))) = (a.split_last(), b.split_last())
+    else {
+        return false;
+    };
+    a_last == b_last
+        && a_given.len() == b_given.len()
+        && a_given.iter().zip(b_given).all(|(x, y)| {
+            x == y
+                || (x.len() == 1 && y.starts_with(x.as_str()))
+                || (y.len() == 1 && x.starts_with(y.as_str()))
+        })
+}
+
+/// Pairs of creators whose names or aliases look like the same person, e.g. "J. Smith",
+/// "John Smith" and "Smith, John". Candidates for `merge_creators`.
+pub fn duplicate_creator_candidates(conn: &Connection) -> Result<Vec<(Creator, Creator)>> {
+    let creators = get_creators(conn)?;
+    let mut pairs = vec![];
+    for (i, a) in creators.iter().enumerate() {
+        for b in &creators[i + 1..] {
+            let a_names = std::iter::once(&a.name).chain(&a.aliases);
+            let matched = a_names
+                .flat_map(|x| {
+                    std::iter::once(&b.name)
+                        .chain(&b.aliases)
+                        .map(move |y| (x, y))
+                })
+                .any(|(x, y)| names_match(x, y));
+            if matched {
+                pairs.push((a.clone(), b.clone()));
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+/// Totals per creator over the items matching `filter`. An item with several creators counts
+/// towards each of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatorStats {
+    pub creator: Creator,
+    pub stats: CatalogStats,
+}
+
+pub fn get_creator_stats(conn: &Connection, filter: ItemFilter) -> AnyResult<Vec<CreatorStats>> {
+    let home = get_home_currency(conn)?;
+    let rates = get_rate_table(conn)?;
+    let mut result = vec![];
+    for creator in get_creators(conn)? {
+        let items: Vec<Item> = get_filtered_items(
+            conn,
+            ItemFilter {
+                creator_id: Some(creator.id),
+                ..filter.clone()
+            },
+        )?;
+        if !items.is_empty() {
+            result.push(CreatorStats {
+                creator,
+                stats: compute_stats(&items, home, &rates),
+            });
+        }
+    }
+    result.sort_by_key(|c| std::cmp::Reverse(c.stats.item_count));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, get_item_by_id, init_db, update_item_fields};
+
+    fn add(conn: &Connection, name: &str, creator: &str) {
+        let item = Item {
+            name: name.to_string(),
+            description: "test".to_string(),
+            category: ItemCategory::Book,
+            creator: Some(creator.to_string()),
+            ..Default::default()
+        };
+        add_item(conn, &item).unwrap();
+    }
+
+    #[test]
+    fn test_merge_duplicate_creators() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        add(&conn, "Atlas", "J. Smith");
+        add(&conn, "Gazetteer", "Smith, John");
+        add(&conn, "Almanac", "John Smith; Mary Jones");
+
+        let pairs = duplicate_creator_candidates(&conn).unwrap();
+        assert_eq!(pairs.len(), 3);
+
+        let smith = find_creator(&conn, "John Smith").unwrap().unwrap();
+        for other in ["J. Smith", "Smith, John"] {
+            let other = find_creator(&conn, other).unwrap().unwrap();
+            merge_creators(&conn, smith.id, other.id).unwrap();
+        }
+        assert!(duplicate_creator_candidates(&conn).unwrap().is_empty());
+        assert_eq!(get_creators(&conn).unwrap().len(), 2);
+
+        // Merged names still find the creator and item text follows the kept name
+        assert_eq!(
+            find_creator(&conn, "smith, john").unwrap().unwrap().id,
+            smith.id
+        );
+        let atlas = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(atlas.creator.as_deref(), Some("John Smith"));
+        assert_eq!(
+            get_item_creators(&conn, 1).unwrap()[0].role,
+            CreatorRole::Author
+        );
+
+        let filter = ItemFilter {
+            creator_id: Some(smith.id),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_items(&conn, filter).unwrap().len(), 3);
+
+        let stats = get_creator_stats(&conn, ItemFilter::default()).unwrap();
+        assert_eq!(stats[0].creator.name, "John Smith");
+        assert_eq!(stats[0].stats.item_count, 3);
+    }
+
+    #[test]
+    fn test_editing_creator_text_relinks() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        add(&conn, "Atlas", "John Smith");
+        update_item_fields(
+            &conn,
+            1,
+            HashMap::from([("creator", "Mary Jones".to_string())]),
+        )
+        .unwrap();
+
+        let creators = get_item_creators(&conn, 1).unwrap();
+        assert_eq!(creators.len(), 1);
+        assert_eq!(creators[0].creator.name, "Mary Jones");
+
+        // A role set by hand survives edits that keep the creator
+        let jones = creators[0].creator.id;
+        unlink_creator(&conn, 1, jones).unwrap();
+        link_creator(&conn, 1, jones, CreatorRole::Artist).unwrap();
+        update_item_fields(
+            &conn,
+            1,
+            HashMap::from([("creator", "mary jones; A_B 100%".to_string())]),
+        )
+        .unwrap();
+        let creators = get_item_creators(&conn, 1).unwrap();
+        let roles: Vec<(&str, CreatorRole)> = creators
+            .iter()
+            .map(|c| (c.creator.name.as_str(), c.role))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                ("Mary Jones", CreatorRole::Artist),
+                ("A_B 100%", CreatorRole::Author)
+            ]
+        );
+
+        // LIKE wildcards in creator text match literally
+        let count = |text: &str| {
+            let filter = ItemFilter {
+                creator_contains: Some(text.to_string()),
+                ..Default::default()
+            };
+            get_filtered_items(&conn, filter).unwrap().len()
+        };
+        assert_eq!(count("100%"), 1);
+        assert_eq!(count("A_B"), 1);
+        assert_eq!(count("A%B"), 0);
+        assert_eq!(count("Mary_Jones"), 0);
+        assert!(find_creator(&conn, "A_B 100%").unwrap().is_some());
+        assert!(find_creator(&conn, "A%").unwrap().is_none());
+    }
+}
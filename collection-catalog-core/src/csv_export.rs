@@ -1,5 +1,417 @@
+//! CSV export, either every item field as stored or shaped by `CsvOptions`.
+//!
+//! Options pick the columns and their order, the header style, delimiter, quoting and date
+//! format, and can add related data such as linked creators. They can be saved by name as
+//! export profiles.
+
+use crate::creators::get_item_creators;
+use crate::identifiers::get_identifiers;
 use crate::models::Item;
+use crate::provenance::get_provenance;
+use crate::relations::get_related_items;
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::format::{Item as FormatItem, StrftimeItems};
+use chrono::{Local, NaiveDate};
+use csv::QuoteStyle;
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fs::File;
+use std::io::Write;
+
+/// Column keys with their readable headers. The last few pull in related records, joined
+/// with `; `.
+pub const CSV_COLUMNS: &[(&str, &str)] = &[
+    ("id", "ID"),
+    ("name", "Name"),
+    ("description", "Description"),
+    ("category", "Category"),
+    ("action", "Action"),
+    ("date_made", "Date Made"),
+    ("age_years", "Age (Years)"),
+    ("date_acquired", "Date Acquired"),
+    ("purchase_price", "Purchase Price"),
+    ("estimated_value", "Estimated Value"),
+    ("creator", "Creator"),
+    ("working", "Working"),
+    ("condition", "Condition"),
+    ("condition_notes", "Condition Notes"),
+    ("provenance", "Provenance"),
+    ("location", "Location"),
+    ("dimensions", "Dimensions"),
+    ("weight", "Weight"),
+    ("quantity", "Quantity"),
+    ("date_added", "Date Added"),
+    ("last_updated", "Last Updated"),
+    ("deleted", "Deleted"),
+    ("wishlist", "Wishlist"),
+    ("target_price", "Target Price"),
+    ("wishlist_priority", "Wishlist Priority"),
+    ("wishlist_notes", "Wishlist Notes"),
+    ("creators", "Creators"),
+    ("identifiers", "Identifiers"),
+    ("related_items", "Related Items"),
+    ("provenance_events", "Provenance Events"),
+];
+
+/// Columns exported when none are chosen, leaving out internal and wishlist fields.
+const DEFAULT_COLUMNS: &[&str] = &[
+    "name",
+    "description",
+    "category",
+    "action",
+    "date_made",
+    "date_acquired",
+    "purchase_price",
+    "estimated_value",
+    "creator",
+    "working",
+    "condition",
+    "provenance",
+    "location",
+    "dimensions",
+    "weight",
+    "quantity",
+    "date_added",
+    "last_updated",
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CsvHeaders {
+    /// e.g. `Date Acquired`
+    #[default]
+    Readable,
+    /// e.g. `date_acquired`
+    FieldNames,
+    None,
+}
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CsvQuoting {
+    /// Only fields containing the delimiter, quotes or line breaks
+    #[default]
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl From<CsvQuoting> for QuoteStyle {
+    fn from(quoting: CsvQuoting) -> Self {
+        match quoting {
+            CsvQuoting::Necessary => QuoteStyle::Necessary,
+            CsvQuoting::Always => QuoteStyle::Always,
+            CsvQuoting::NonNumeric => QuoteStyle::NonNumeric,
+            CsvQuoting::Never => QuoteStyle::Never,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvOptions {
+    /// Column keys from `CSV_COLUMNS` in output order, empty for the default set
+    pub columns: Vec<String>,
+    pub headers: CsvHeaders,
+    pub delimiter: char,
+    pub quoting: CsvQuoting,
+    /// strftime pattern for full dates, e.g. `%d/%m/%Y`. Partial dates such as `Date Made`
+    /// are written as entered.
+    pub date_format: String,
+    /// Start with a byte order mark so Excel reads the file as UTF-8
+    pub bom: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            columns: vec![],
+            headers: CsvHeaders::default(),
+            delimiter: ',',
+            quoting: CsvQuoting::default(),
+            date_format: "%Y-%m-%d".to_string(),
+            bom: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        for column in &self.columns {
+            if !CSV_COLUMNS.iter().any(|(key, _)| key == column) {
+                errors.push(format!("Unknown column: '{}'.", column));
+            }
+        }
+        if !self.delimiter.is_ascii() || matches!(self.delimiter, '"' | '\n' | '\r') {
+            errors.push(format!("Invalid delimiter: '{}'.", self.delimiter));
+        }
+        if self.date_format.is_empty()
+            || StrftimeItems::new(&self.date_format).any(|i| matches!(i, FormatItem::Error))
+        {
+            errors.push(format!("Invalid date format: '{}'.", self.date_format));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Sets an option from text, as typed on the command line: `columns` takes a comma
+    /// separated list and `delimiter` also accepts `tab`.
+    pub fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "columns" => {
+                self.columns = value
+                    .split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect()
+            }
+            "headers" => {
+                self.headers = match value.to_ascii_lowercase().as_str() {
+                    "readable" => CsvHeaders::Readable,
+                    "fields" | "field_names" => CsvHeaders::FieldNames,
+                    "none" => CsvHeaders::None,
+                    _ => return Err(format!("Unknown header style: '{}'", value)),
+                }
+            }
+            "delimiter" => {
+                self.delimiter = match value {
+                    "tab" | "\\t" => '\t',
+                    _ if value.chars().count() == 1 => value.chars().next().unwrap(),
+                    _ => return Err(format!("Delimiter must be one character: '{}'", value)),
+                }
+            }
+            "quoting" => {
+                self.quoting = match value.to_ascii_lowercase().as_str() {
+                    "necessary" => CsvQuoting::Necessary,
+                    "always" => CsvQuoting::Always,
+                    "nonnumeric" | "non_numeric" => CsvQuoting::NonNumeric,
+                    "never" => CsvQuoting::Never,
+                    _ => return Err(format!("Unknown quoting: '{}'", value)),
+                }
+            }
+            "date_format" => self.date_format = value.to_string(),
+            "bom" => {
+                self.bom = value
+                    .parse()
+                    .map_err(|_| format!("bom must be true or false: '{}'", value))?
+            }
+            _ => return Err(format!("Unknown export option: '{}'", option)),
+        }
+        Ok(())
+    }
+
+    fn column_keys(&self) -> Vec<&str> {
+        if self.columns.is_empty() {
+            DEFAULT_COLUMNS.to_vec()
+        } else {
+            self.columns.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+fn column_value(
+    conn: &Connection,
+    item: &Item,
+    column: &str,
+    options: &CsvOptions,
+) -> AnyResult<String> {
+    let text = |value: Option<String>| value.unwrap_or_default();
+    let date = |value: NaiveDate| value.format(&options.date_format).to_string();
+    let join = |values: Vec<String>| values.join("; ");
+    Ok(match column {
+        "id" => item.id.to_string(),
+        "name" => item.name.clone(),
+        "description" => item.description.clone(),
+        "category" => item.category.to_string(),
+        "action" => item.action.to_string(),
+        "date_made" => text(item.date_made.map(|d| d.to_string())),
+        "age_years" => text(item.age_years.map(|a| a.to_string())),
+        "date_acquired" => text(item.date_acquired.map(date)),
+        "purchase_price" => text(item.purchase_price.map(|m| m.to_string())),
+        "estimated_value" => text(item.estimated_value.map(|m| m.to_string())),
+        "creator" => text(item.creator.clone()),
+        "working" => text(item.working.map(|w| w.to_string())),
+        "condition" => text(item.condition.clone()),
+        "condition_notes" => text(item.condition_notes.clone()),
+        "provenance" => text(item.provenance.clone()),
+        "location" => text(item.location.clone()),
+        "dimensions" => text(item.dimensions.map(|d| d.to_string())),
+        "weight" => text(item.weight.map(|w| w.to_string())),
+        "quantity" => item.pieces().to_string(),
+        "date_added" => date(item.date_added),
+        "last_updated" => date(item.last_updated),
+        "deleted" => item.deleted.to_string(),
+        "wishlist" => item.wishlist.to_string(),
+        "target_price" => text(item.target_price.map(|m| m.to_string())),
+        "wishlist_priority" => text(item.wishlist_priority.map(|p| p.to_string())),
+        "wishlist_notes" => text(item.wishlist_notes.clone()),
+        "creators" => join(
+            get_item_creators(conn, item.id)?
+                .into_iter()
+                .map(|c| format!("{} ({})", c.creator.name, c.role))
+                .collect(),
+        ),
+        "identifiers" => join(
+            get_identifiers(conn, item.id)?
+                .into_iter()
+                .map(|i| i.identifier.to_string())
+                .collect(),
+        ),
+        "related_items" => join(
+            get_related_items(conn, item.id)?
+                .into_iter()
+                .map(|r| format!("{}: {} (#{})", r.label, r.item.name, r.item.id))
+                .collect(),
+        ),
+        "provenance_events" => join(
+            get_provenance(conn, item.id)?
+                .into_iter()
+                .map(|e| format!("{}: {}", e.role, e.name))
+                .collect(),
+        ),
+        _ => return Err(anyhow!("Unknown column: '{}'", column)),
+    })
+}
+
+/// Writes items to `out` as they arrive, e.g. from `stream_filtered_items`, shaped by
+/// `options`. Returns how many items were written.
+pub fn write_csv<W: Write>(
+    conn: &Connection,
+    items: impl IntoIterator<Item = Result<impl Borrow<Item>>>,
+    mut out: W,
+    options: &CsvOptions,
+) -> AnyResult<usize> {
+    options
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+    if options.bom {
+        out.write_all("\u{FEFF}".as_bytes())?;
+    }
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(options.delimiter as u8)
+        .quote_style(options.quoting.into())
+        .from_writer(out);
+
+    let columns = options.column_keys();
+    match options.headers {
+        CsvHeaders::Readable => wtr.write_record(columns.iter().map(|column| {
+            CSV_COLUMNS
+                .iter()
+                .find(|(key, _)| key == column)
+                .map_or(*column, |(_, header)| header)
+        }))?,
+        CsvHeaders::FieldNames => wtr.write_record(&columns)?,
+        CsvHeaders::None => {}
+    }
+    let mut count = 0;
+    let mut record = Vec::with_capacity(columns.len());
+    for item in items {
+        let item = item?;
+        record.clear();
+        for column in &columns {
+            record.push(column_value(conn, item.borrow(), column, options)?);
+        }
+        wtr.write_record(&record)?;
+        count += 1;
+    }
+    wtr.flush()?;
+    Ok(count)
+}
+
+/// Writes `items` to a CSV file shaped by `options`.
+pub fn export_to_csv_with_options(
+    conn: &Connection,
+    items: &[Item],
+    path: &str,
+    options: &CsvOptions,
+) -> AnyResult<()> {
+    write_csv(conn, items.iter().map(Ok), File::create(path)?, options)?;
+    Ok(())
+}
+
+/// CSV options saved under a name.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ExportProfile {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    #[serde(default)]
+    pub options: CsvOptions,
+}
+
+impl ExportProfile {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let options: String = row.get("options")?;
+        Ok(ExportProfile {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            options: serde_json::from_str(&options).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+            })?,
+        })
+    }
+}
+
+/// Saves `profile`, replacing any profile with the same name. Returns its id.
+pub fn save_export_profile(conn: &Connection, profile: &ExportProfile) -> AnyResult<i32> {
+    let mut errors = profile.options.validate().err().unwrap_or_default();
+    if profile.name.trim().is_empty() {
+        errors.insert(0, "Profile name cannot be empty.".to_string());
+    }
+    if !errors.is_empty() {
+        return Err(anyhow!("Validation failed: {}", errors.join("; ")));
+    }
+
+    let today = Local::now().date_naive();
+    conn.execute(
+        "INSERT INTO export_profiles (name, options, last_updated)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT(name) DO UPDATE SET
+            options = excluded.options,
+            last_updated = excluded.last_updated",
+        params![
+            profile.name.trim(),
+            serde_json::to_string(&profile.options)?,
+            today.to_string(),
+        ],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM export_profiles WHERE name = ?1",
+        params![profile.name.trim()],
+        |row| row.get(0),
+    )?)
+}
+
+pub fn get_export_profiles(conn: &Connection) -> Result<Vec<ExportProfile>> {
+    let mut stmt = conn.prepare("SELECT * FROM export_profiles ORDER BY name COLLATE NOCASE")?;
+    let rows = stmt.query_map([], ExportProfile::from_row)?;
+    rows.collect()
+}
+
+/// Looks a profile up by name, ignoring case.
+pub fn get_export_profile(conn: &Connection, name: &str) -> Result<Option<ExportProfile>> {
+    conn.query_row(
+        "SELECT * FROM export_profiles WHERE name = ?1",
+        params![name.trim()],
+        ExportProfile::from_row,
+    )
+    .optional()
+}
+
+/// Returns whether a profile with that name existed.
+pub fn delete_export_profile(conn: &Connection, name: &str) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM export_profiles WHERE name = ?1",
+        params![name.trim()],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Writes every `Item` field as stored, with the field names as headers.
 pub fn export_to_csv(items: &[Item], path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut wtr = csv::Writer::from_path(path)?;
     for item in items {
@@ -25,6 +437,8 @@ pub fn export_to_csv_string(items: &[Item]) -> Result<String, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::{add_item, init_db};
+    use crate::identifiers::add_identifier;
     use crate::models::{Item, ItemAction, ItemCategory};
     use chrono::NaiveDate;
     use std::fs;
@@ -68,4 +482,73 @@ mod tests {
         assert!(csv_output.contains("desc")); // Ensure item description is present
         assert!(csv_output.contains("Book")); // Ensure item category is present
     }
+
+    #[test]
+    fn test_export_with_options() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let mut item = sample_item();
+        item.id = add_item(&conn, &item).unwrap();
+        add_identifier(&conn, item.id, &"9780306406157".parse().unwrap()).unwrap();
+
+        let mut options = CsvOptions::default();
+        options
+            .set("columns", "name, date_added, identifiers")
+            .unwrap();
+        options.set("delimiter", ";").unwrap();
+        options.set("date_format", "%d/%m/%Y").unwrap();
+        options.set("quoting", "always").unwrap();
+        options.set("bom", "true").unwrap();
+        let mut out = vec![];
+        write_csv(&conn, [Ok(&item)], &mut out, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\u{FEFF}\"Name\";\"Date Added\";\"Identifiers\"\n\"Test\";\"01/01/2025\";\"ISBN 9780306406157\"\n"
+        );
+
+        // Defaults leave out internal fields
+        let mut out = vec![];
+        write_csv(&conn, [Ok(&item)], &mut out, &CsvOptions::default()).unwrap();
+        let header = String::from_utf8(out).unwrap();
+        assert!(header.starts_with("Name,Description,Category,"));
+        assert!(!header.contains("Deleted"));
+
+        assert!(options.set("headers", "fancy").is_err());
+        options.columns.push("tags".to_string());
+        options.date_format = "%Q".to_string();
+        let profile = ExportProfile {
+            name: "Excel".to_string(),
+            options,
+            ..Default::default()
+        };
+        let err = save_export_profile(&conn, &profile)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("'tags'") && err.contains("'%Q'"));
+    }
+
+    #[test]
+    fn test_export_profiles() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let mut profile = ExportProfile {
+            name: "Insurance".to_string(),
+            ..Default::default()
+        };
+        profile
+            .options
+            .set("columns", "name,estimated_value")
+            .unwrap();
+        let id = save_export_profile(&conn, &profile).unwrap();
+
+        profile.options.set("delimiter", "tab").unwrap();
+        assert_eq!(save_export_profile(&conn, &profile).unwrap(), id);
+        assert_eq!(get_export_profiles(&conn).unwrap().len(), 1);
+        let saved = get_export_profile(&conn, "insurance").unwrap().unwrap();
+        assert_eq!(saved.options, profile.options);
+        assert_eq!(saved.options.delimiter, '\t');
+
+        assert!(delete_export_profile(&conn, "Insurance").unwrap());
+        assert!(get_export_profile(&conn, "Insurance").unwrap().is_none());
+    }
 }
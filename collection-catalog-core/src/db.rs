@@ -1,7 +1,13 @@
-use crate::models::{Item, ItemAction, ItemCategory, ItemFilter};
+use crate::attachments::record_image_metadata;
+use crate::condition::canonical_grade;
+use crate::creators::{CreatorRole, link_creators_by_name};
+use crate::fuzzy_date::{FuzzyDate, made_after_for_age, made_by_for_age};
+use crate::measure::{Dimensions, Weight, dimensions_mm};
+use crate::models::{FilterExpr, Item, ItemAction, ItemCategory, ItemFilter};
+use crate::money::Money;
 use anyhow::{Result as AnyResult, anyhow};
-use chrono::{Local, NaiveDate};
-use rusqlite::{Connection, Result, ToSql, params};
+use chrono::{Datelike, Local, NaiveDate};
+use rusqlite::{Connection, OptionalExtension, Result, ToSql, params};
 use std::collections::HashMap;
 use std::str::FromStr;
 
@@ -26,218 +32,870 @@ pub fn init_db(conn: &Connection) -> Result<()> {
         )",
         [],
     )?;
+    migrate(conn)
+}
+
+// Schema changes on top of the original `items` table, applied in order.
+// `PRAGMA user_version` records how many have already run against a database.
+const MIGRATIONS: &[fn(&Connection) -> Result<()>] = &[
+    migrate_money_columns,
+    migrate_location_and_attachments,
+    migrate_date_made,
+    migrate_condition,
+    migrate_measurements,
+    migrate_relations,
+    migrate_provenance_events,
+    migrate_creators,
+    migrate_saved_searches,
+    migrate_identifiers,
+    migrate_audits,
+    migrate_reminders,
+    migrate_beneficiaries,
+    migrate_wishlist,
+    migrate_templates,
+    migrate_image_metadata,
+    migrate_photo_hashes,
+    migrate_export_profiles,
+    backfill_image_metadata,
+    migrate_photo_hash_checks,
+];
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.unchecked_transaction()?;
+        step(&tx)?;
+        tx.pragma_update(None, "user_version", (i + 1) as i64)?;
+        tx.commit()?;
+    }
     Ok(())
 }
 
-pub fn get_all_items(conn: &Connection) -> Result<Vec<Item>> {
-    let mut stmt = conn.prepare(
-        "SELECT
-            id, name, description, category, action,
-            date_added, last_updated, 
-            age_years, date_acquired, purchase_price,
-            estimated_value, creator, working, provenance, deleted
-        FROM items
-        WHERE deleted = 0",
+// REAL prices become integer minor units plus a currency code. Existing values were
+// entered without a currency and are assumed to be in dollars.
+fn migrate_money_columns(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE items ADD COLUMN purchase_price_minor INTEGER;
+        ALTER TABLE items ADD COLUMN purchase_price_currency TEXT;
+        ALTER TABLE items ADD COLUMN estimated_value_minor INTEGER;
+        ALTER TABLE items ADD COLUMN estimated_value_currency TEXT;
+
+        UPDATE items
+        SET purchase_price_minor = CAST(ROUND(purchase_price * 100) AS INTEGER),
+            purchase_price_currency = 'USD'
+        WHERE purchase_price IS NOT NULL;
+        UPDATE items
+        SET estimated_value_minor = CAST(ROUND(estimated_value * 100) AS INTEGER),
+            estimated_value_currency = 'USD'
+        WHERE estimated_value IS NOT NULL;
+
+        ALTER TABLE items DROP COLUMN purchase_price;
+        ALTER TABLE items DROP COLUMN estimated_value;
+
+        CREATE TABLE settings (
+            key     TEXT PRIMARY KEY,
+            value   TEXT NOT NULL
+        );
+        CREATE TABLE exchange_rates (
+            currency    TEXT PRIMARY KEY,
+            rate        TEXT NOT NULL,
+            updated     TEXT NOT NULL
+        );",
+    )
+}
+
+fn migrate_location_and_attachments(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE items ADD COLUMN location TEXT;
+
+        CREATE TABLE attachments (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            path        TEXT NOT NULL,
+            caption     TEXT,
+            date_added  TEXT NOT NULL
+        );
+        CREATE INDEX idx_attachments_item ON attachments(item_id);",
+    )
+}
+
+// `age_years` went stale every year. Replace it with a "date made" stored as text plus the
+// earliest/latest days it can mean, so range filters can respect its uncertainty. Ages are
+// assumed to have been entered the last time the item was updated.
+fn migrate_date_made(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE items ADD COLUMN date_made TEXT;
+        ALTER TABLE items ADD COLUMN date_made_from TEXT;
+        ALTER TABLE items ADD COLUMN date_made_to TEXT;
+
+        UPDATE items
+        SET date_made = CAST(strftime('%Y', last_updated) - age_years AS TEXT),
+            date_made_from = printf('%04d-01-01', strftime('%Y', last_updated) - age_years),
+            date_made_to = printf('%04d-12-31', strftime('%Y', last_updated) - age_years)
+        WHERE age_years IS NOT NULL;
+
+        ALTER TABLE items DROP COLUMN age_years;",
+    )
+}
+
+fn migrate_condition(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE items ADD COLUMN condition TEXT;
+        ALTER TABLE items ADD COLUMN condition_notes TEXT;
+
+        CREATE TABLE condition_scales (
+            category    TEXT PRIMARY KEY,
+            grades      TEXT NOT NULL
+        );
+        CREATE TABLE condition_reports (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            date        TEXT NOT NULL,
+            grade       TEXT NOT NULL,
+            notes       TEXT
+        );
+        CREATE INDEX idx_condition_reports_item ON condition_reports(item_id);
+
+        ALTER TABLE attachments ADD COLUMN condition_report_id INTEGER
+            REFERENCES condition_reports(id);",
+    )
+}
+
+fn migrate_measurements(conn: &Connection) -> Result<()> {
+    // Measurements are kept in mm and grams so range filters work across units
+    conn.execute_batch(
+        "ALTER TABLE items ADD COLUMN length_mm REAL;
+        ALTER TABLE items ADD COLUMN width_mm REAL;
+        ALTER TABLE items ADD COLUMN height_mm REAL;
+        ALTER TABLE items ADD COLUMN dimension_unit TEXT;
+        ALTER TABLE items ADD COLUMN weight_g REAL;
+        ALTER TABLE items ADD COLUMN weight_unit TEXT;
+        ALTER TABLE items ADD COLUMN quantity INTEGER;",
+    )
+}
+
+fn migrate_relations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE item_relations (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            related_id  INTEGER NOT NULL REFERENCES items(id),
+            kind        TEXT NOT NULL,
+            UNIQUE (item_id, related_id, kind)
+        );
+        CREATE INDEX idx_item_relations_related ON item_relations(related_id);",
+    )
+}
+
+fn migrate_provenance_events(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE provenance_events (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            position    INTEGER NOT NULL,
+            role        TEXT NOT NULL,
+            name        TEXT NOT NULL,
+            date_from   TEXT,
+            date_to     TEXT,
+            location    TEXT,
+            source      TEXT
+        );
+        CREATE INDEX idx_provenance_events_item ON provenance_events(item_id);
+
+        ALTER TABLE items ADD COLUMN provenance_chain TEXT;",
+    )
+}
+
+fn migrate_creators(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE creators (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            name        TEXT NOT NULL,
+            aliases     TEXT,
+            born        TEXT,
+            died        TEXT,
+            role        TEXT
+        );
+        CREATE TABLE item_creators (
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            creator_id  INTEGER NOT NULL REFERENCES creators(id),
+            role        TEXT NOT NULL,
+            position    INTEGER NOT NULL,
+            PRIMARY KEY (item_id, creator_id)
+        );
+        CREATE INDEX idx_item_creators_creator ON item_creators(creator_id);",
     )?;
 
-    let item_iter = stmt.query_map([], |row| {
-        Ok(Item {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            description: row.get(2)?,
-            category: ItemCategory::from_str(row.get::<_, String>(3)?.as_str())
-                .unwrap_or(ItemCategory::Other),
-            action: ItemAction::from_str(row.get::<_, String>(4)?.as_str())
-                .unwrap_or(ItemAction::Keep),
-            date_added: NaiveDate::parse_from_str(&row.get::<_, String>(5)?, "%Y-%m-%d").unwrap(),
-            last_updated: NaiveDate::parse_from_str(&row.get::<_, String>(6)?, "%Y-%m-%d").unwrap(),
-            age_years: row.get(7)?,
-            date_acquired: row
-                .get::<_, Option<String>>(8)?
-                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
-            purchase_price: row.get(9)?,
-            estimated_value: row.get(10)?,
-            creator: row.get(11)?,
-            working: row.get::<_, Option<i32>>(12)?.map(|b| b != 0),
-            provenance: row.get(13)?,
-            deleted: row.get::<_, i32>(14)? != 0,
-        })
-    })?;
+    // Existing free text becomes one creator per distinct name, merged later with the
+    // duplicate finder where spellings differ. The first spelling of a name wins, and roles
+    // are the defaults for each category as of this migration.
+    conn.execute_batch(
+        "CREATE TEMP TABLE migrate_creator_names AS
+        WITH RECURSIVE split(item_id, category, position, name, rest) AS (
+            SELECT id, category, 0, '', creator || ';' FROM items WHERE TRIM(creator) != ''
+            UNION ALL
+            SELECT item_id, category, position + 1,
+                TRIM(substr(rest, 1, instr(rest, ';') - 1)),
+                substr(rest, instr(rest, ';') + 1)
+            FROM split WHERE rest != ''
+        )
+        SELECT item_id, category, position, name FROM split WHERE name != '';
+
+        INSERT INTO creators (name)
+        SELECT name FROM migrate_creator_names n
+        WHERE NOT EXISTS (
+            SELECT 1 FROM migrate_creator_names e
+            WHERE e.name = n.name COLLATE NOCASE
+            AND (e.item_id, e.position) < (n.item_id, n.position)
+        )
+        ORDER BY item_id, position;
+
+        INSERT OR IGNORE INTO item_creators (item_id, creator_id, role, position)
+        SELECT n.item_id, c.id,
+            CASE n.category
+                WHEN 'Book' THEN 'Author'
+                WHEN 'ElectronicDevice' THEN 'Manufacturer'
+                ELSE 'Maker'
+            END,
+            n.position
+        FROM migrate_creator_names n JOIN creators c ON c.name = n.name COLLATE NOCASE
+        ORDER BY n.item_id, n.position;
+
+        UPDATE items SET creator = (
+            SELECT group_concat(name, '; ') FROM (
+                SELECT c.name FROM item_creators ic JOIN creators c ON c.id = ic.creator_id
+                WHERE ic.item_id = items.id ORDER BY ic.position
+            )
+        ) WHERE id IN (SELECT item_id FROM migrate_creator_names);
+
+        DROP TABLE migrate_creator_names;",
+    )
+}
+
+fn migrate_saved_searches(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE saved_searches (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            name            TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            filter          TEXT NOT NULL,
+            relative_dates  TEXT NOT NULL,
+            last_updated    TEXT NOT NULL
+        );",
+    )
+}
+
+// `normalized` lets an ISBN-10 match the same book stored under its ISBN-13
+fn migrate_identifiers(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE item_identifiers (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            kind        TEXT NOT NULL,
+            value       TEXT NOT NULL,
+            normalized  TEXT NOT NULL,
+            UNIQUE(item_id, normalized)
+        );
+        CREATE INDEX idx_item_identifiers_normalized ON item_identifiers(normalized);",
+    )
+}
+
+// `expected` is whether the item was in the audit's scope when it started
+fn migrate_audits(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE audits (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            name        TEXT NOT NULL,
+            scope       TEXT NOT NULL,
+            started     TEXT NOT NULL,
+            finished    TEXT
+        );
+        CREATE TABLE audit_items (
+            audit_id    INTEGER NOT NULL REFERENCES audits(id),
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            expected    INTEGER NOT NULL,
+            found_on    TEXT,
+            PRIMARY KEY (audit_id, item_id)
+        );
+
+        ALTER TABLE items ADD COLUMN last_audited TEXT;",
+    )
+}
+
+// A recurring reminder keeps one row, with `due` moved on each time it's done
+fn migrate_reminders(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE reminders (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            item_id     INTEGER NOT NULL REFERENCES items(id),
+            kind        TEXT NOT NULL,
+            due         TEXT NOT NULL,
+            recurrence  TEXT,
+            note        TEXT,
+            last_done   TEXT
+        );
+        CREATE INDEX idx_reminders_item ON reminders(item_id);
+        CREATE INDEX idx_reminders_due ON reminders(due);",
+    )
+}
+
+fn migrate_beneficiaries(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE beneficiaries (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            name            TEXT NOT NULL,
+            relationship    TEXT,
+            contact         TEXT,
+            notes           TEXT
+        );
+        CREATE TABLE assignments (
+            item_id         INTEGER NOT NULL REFERENCES items(id),
+            beneficiary_id  INTEGER NOT NULL REFERENCES beneficiaries(id),
+            priority        INTEGER,
+            note            TEXT,
+            PRIMARY KEY (item_id, beneficiary_id)
+        );
+        CREATE INDEX idx_assignments_beneficiary ON assignments(beneficiary_id);",
+    )
+}
+
+fn migrate_wishlist(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE items ADD COLUMN wishlist INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE items ADD COLUMN target_price_minor INTEGER;
+        ALTER TABLE items ADD COLUMN target_price_currency TEXT;
+        ALTER TABLE items ADD COLUMN wishlist_priority INTEGER;
+        ALTER TABLE items ADD COLUMN wishlist_notes TEXT;",
+    )
+}
+
+fn migrate_templates(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE item_templates (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            name            TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            defaults        TEXT NOT NULL,
+            last_updated    TEXT NOT NULL
+        );",
+    )
+}
+
+fn migrate_image_metadata(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE attachments ADD COLUMN captured TEXT;
+        ALTER TABLE attachments ADD COLUMN camera TEXT;",
+    )
+}
+
+// 64-bit hashes are stored as their signed equivalent
+fn migrate_photo_hashes(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE attachments ADD COLUMN photo_hash INTEGER;")
+}
+
+// Photos attached before capture dates and cameras were kept have them read from their files,
+// once. Files that are missing or have no EXIF data are left without.
+pub(crate) fn backfill_image_metadata(conn: &Connection) -> Result<()> {
+    let mut stmt =
+        conn.prepare("SELECT id, path FROM attachments WHERE captured IS NULL AND camera IS NULL")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    for (id, path) in rows {
+        record_image_metadata(conn, id, std::path::Path::new(&path))?;
+    }
+    Ok(())
+}
+
+// Attachments that have been tried are marked so `hash_photos` doesn't read unhashable files
+// again. Blank pictures all hashed to 0 and matched each other, so those hashes are dropped
+// and made again.
+fn migrate_photo_hash_checks(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE attachments ADD COLUMN photo_hashed INTEGER NOT NULL DEFAULT 0;
+        UPDATE attachments SET photo_hash = NULL WHERE photo_hash = 0;
+        UPDATE attachments SET photo_hashed = 1 WHERE photo_hash IS NOT NULL;",
+    )
+}
+
+fn migrate_export_profiles(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE export_profiles (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            name            TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            options         TEXT NOT NULL,
+            last_updated    TEXT NOT NULL
+        );",
+    )
+}
+
+pub fn get_all_items(conn: &Connection) -> Result<Vec<Item>> {
+    let mut stmt = conn.prepare("SELECT * FROM items WHERE deleted = 0 AND wishlist = 0")?;
+
+    let item_iter = stmt.query_map([], Item::from_row)?;
 
     let items: Vec<Item> = item_iter.filter_map(Result::ok).collect();
     Ok(items)
 }
 
 pub fn get_item_by_id(conn: &Connection, id: i32) -> Result<Option<Item>> {
-    let mut stmt = conn.prepare(
-        "SELECT
-            id, name, description, category, action,
-            date_added, last_updated, 
-            age_years, date_acquired, purchase_price,
-            estimated_value, creator, working, provenance, deleted
-        FROM items
-        WHERE id = ?1",
-    )?;
+    let mut stmt = conn.prepare("SELECT * FROM items WHERE id = ?1")?;
 
     let mut rows = stmt.query(params![id])?;
 
     if let Some(row) = rows.next()? {
-        Ok(Some(Item {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            description: row.get(2)?,
-            category: ItemCategory::from_str(row.get::<_, String>(3)?.as_str())
-                .unwrap_or(ItemCategory::Other),
-            action: ItemAction::from_str(row.get::<_, String>(4)?.as_str())
-                .unwrap_or(ItemAction::Keep),
-            date_added: NaiveDate::parse_from_str(&row.get::<_, String>(5)?, "%Y-%m-%d").unwrap(),
-            last_updated: NaiveDate::parse_from_str(&row.get::<_, String>(6)?, "%Y-%m-%d").unwrap(),
-            age_years: row.get(7)?,
-            date_acquired: row
-                .get::<_, Option<String>>(8)?
-                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
-            purchase_price: row.get(9)?,
-            estimated_value: row.get(10)?,
-            creator: row.get(11)?,
-            working: row.get::<_, Option<i32>>(12)?.map(|b| b != 0),
-            provenance: row.get(13)?,
-            deleted: row.get::<_, i32>(14)? != 0,
-        }))
+        Ok(Some(Item::from_row(row)?))
     } else {
         Ok(None)
     }
 }
 
-pub fn get_filtered_items(conn: &Connection, filter: ItemFilter) -> rusqlite::Result<Vec<Item>> {
-    let mut sql = String::from("SELECT * FROM items WHERE 1=1");
-    let mut param_values: Vec<(String, Box<dyn ToSql>)> = Vec::new();
+/// Items read one at a time from an open SQLite cursor.
+pub type ItemStream<'a> = dyn Iterator<Item = Result<Item>> + 'a;
+
+/// Items matching `filter`. Wishlist items are only included by the parts of the filter that
+/// ask about them, such as `wishlist: Some(true)` or a target price, so `wishlist or Book`
+/// doesn't also match wishlist books.
+pub fn get_filtered_items(
+    conn: &Connection,
+    filter: impl Into<FilterExpr>,
+) -> rusqlite::Result<Vec<Item>> {
+    stream_filtered_items(conn, filter, |items| items.collect())
+}
+
+/// Like `get_filtered_items`, but hands the items to `f` as they are read instead of
+/// collecting them, so exporting a large catalog doesn't hold it all in memory.
+pub fn stream_filtered_items<R, E: From<rusqlite::Error>>(
+    conn: &Connection,
+    filter: impl Into<FilterExpr>,
+    f: impl FnOnce(&mut ItemStream<'_>) -> Result<R, E>,
+) -> Result<R, E> {
+    let filter = exclude_wishlist(filter.into());
+    let mut builder = FilterSql::default();
+    let sql = format!("SELECT * FROM items WHERE ({})", builder.expr(&filter));
+
+    // Prepare named params: Vec<(&str, &dyn ToSql)>
+    let params: Vec<(&str, &dyn ToSql)> = builder
+        .params
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_ref() as &dyn ToSql))
+        .collect();
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query_map(&params[..], Item::from_row)?;
+    f(&mut rows)
+}
 
-    // Helper macro to append filters
-    macro_rules! push_filter {
-        ($opt:expr_2021, $field:expr_2021, $param:expr_2021, $op:expr_2021) => {
-            if let Some(val) = $opt.clone() {
-                let name = format!(":{}", $param);
-                sql.push_str(&format!(" AND {} {} {}", $field, $op, &name));
-                param_values.push((name, Box::new(val)));
+// Leaves wishlist items out of every branch of `expr` that doesn't ask about them
+fn exclude_wishlist(expr: FilterExpr) -> FilterExpr {
+    let asks_elsewhere = |parts: &[FilterExpr]| {
+        parts
+            .iter()
+            .any(|p| !matches!(p, FilterExpr::Any(_)) && mentions_wishlist(p))
+    };
+    match expr {
+        FilterExpr::Any(parts) => {
+            FilterExpr::Any(parts.into_iter().map(exclude_wishlist).collect())
+        }
+        // Only `or` groups that ask about the wishlist are split up, as the other terms apply
+        // to every branch
+        FilterExpr::All(parts)
+            if parts.iter().any(mentions_wishlist) && !asks_elsewhere(&parts) =>
+        {
+            FilterExpr::All(
+                parts
+                    .into_iter()
+                    .map(|p| match p {
+                        FilterExpr::Any(_) if mentions_wishlist(&p) => exclude_wishlist(p),
+                        p => p,
+                    })
+                    .collect(),
+            )
+        }
+        expr if mentions_wishlist(&expr) => expr,
+        expr => FilterExpr::All(vec![
+            expr,
+            ItemFilter {
+                wishlist: Some(false),
+                ..Default::default()
             }
-        };
+            .into(),
+        ]),
     }
+}
 
-    macro_rules! push_like {
-        ($opt:expr_2021, $field:expr_2021) => {
-            if let Some(val) = $opt.clone() {
-                let name = format!(":{}", $field);
-                sql.push_str(&format!(" AND {} LIKE {}", $field, &name));
-                param_values.push((name, Box::new(format!("%{}%", val))));
+fn mentions_wishlist(expr: &FilterExpr) -> bool {
+    match expr {
+        FilterExpr::All(parts) | FilterExpr::Any(parts) => parts.iter().any(mentions_wishlist),
+        FilterExpr::Not(inner) => mentions_wishlist(inner),
+        FilterExpr::Fields(filter) => {
+            filter.wishlist.is_some()
+                || filter.target_price_min.is_some()
+                || filter.target_price_max.is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Builds the WHERE clause for `get_filtered_items`. Parameter names are numbered so the same
+/// field can appear in several filters of one expression.
+#[derive(Default)]
+struct FilterSql {
+    params: Vec<(String, Box<dyn ToSql>)>,
+}
+
+impl FilterSql {
+    fn param(&mut self, name: &str, value: impl ToSql + 'static) -> String {
+        let name = format!(":{}_{}", name, self.params.len());
+        self.params.push((name.clone(), Box::new(value)));
+        name
+    }
+
+    fn expr(&mut self, expr: &FilterExpr) -> String {
+        let join = |b: &mut Self, parts: &[FilterExpr], op: &str, empty: &str| {
+            if parts.is_empty() {
+                return empty.to_string();
+            }
+            let parts: Vec<String> = parts.iter().map(|e| format!("({})", b.expr(e))).collect();
+            parts.join(op)
+        };
+        let one_of = |b: &mut Self, column: &str, param: &str, values: Vec<String>| {
+            if values.is_empty() {
+                return "0".to_string();
             }
+            let names: Vec<String> = values.into_iter().map(|v| b.param(param, v)).collect();
+            format!("{} IN ({})", column, names.join(", "))
         };
+        match expr {
+            FilterExpr::All(parts) => join(self, parts, " AND ", "1"),
+            FilterExpr::Any(parts) => join(self, parts, " OR ", "0"),
+            // Conditions on empty columns are NULL rather than false, so count them as not
+            // matching before negating
+            FilterExpr::Not(inner) => format!("NOT COALESCE(({}), 0)", self.expr(inner)),
+            FilterExpr::CategoryIn(categories) => one_of(
+                self,
+                "category",
+                "category",
+                categories.iter().map(|c| c.to_string()).collect(),
+            ),
+            FilterExpr::ActionIn(actions) => one_of(
+                self,
+                "action",
+                "action",
+                actions.iter().map(|a| a.to_string()).collect(),
+            ),
+            FilterExpr::ConditionIn(grades) => {
+                let grades = grades.iter().map(|g| g.to_lowercase()).collect();
+                one_of(self, "lower(condition)", "condition", grades)
+            }
+            FilterExpr::IsNull(field) => format!("{} IS NULL", field.column()),
+            FilterExpr::Fields(filter) => self.fields(filter),
+        }
     }
 
-    // LIKE filters
-    push_like!(filter.name_contains, "name");
-    push_like!(filter.description_contains, "description");
-    push_like!(filter.creator_contains, "creator");
-    push_like!(filter.provenance_contains, "provenance");
-
-    // Exact match filters (Enums and bools)
-    push_filter!(
-        filter.category.map(|c| c.to_string()),
-        "category",
-        "category",
-        "="
-    );
-    push_filter!(
-        filter.action.map(|a| a.to_string()),
-        "action",
-        "action",
-        "="
-    );
-    push_filter!(filter.working, "working", "working", "=");
-    push_filter!(filter.deleted, "deleted", "deleted", "=");
-
-    // Date range filters
-    push_filter!(
-        filter.date_added_min.map(|d| d.to_string()),
-        "date_added",
-        "date_added_min",
-        ">="
-    );
-    push_filter!(
-        filter.date_added_max.map(|d| d.to_string()),
-        "date_added",
-        "date_added_max",
-        "<="
-    );
-    push_filter!(
-        filter.last_updated_min.map(|d| d.to_string()),
-        "last_updated",
-        "last_updated_min",
-        ">="
-    );
-    push_filter!(
-        filter.last_updated_max.map(|d| d.to_string()),
-        "last_updated",
-        "last_updated_max",
-        "<="
-    );
-    push_filter!(
-        filter.date_acquired_min.map(|d| d.to_string()),
-        "date_acquired",
-        "date_acquired_min",
-        ">="
-    );
-    push_filter!(
-        filter.date_acquired_max.map(|d| d.to_string()),
-        "date_acquired",
-        "date_acquired_max",
-        "<="
-    );
-
-    // Numeric range filters
-    push_filter!(filter.age_years_min, "age_years", "age_years_min", ">=");
-    push_filter!(filter.age_years_max, "age_years", "age_years_max", "<=");
-    push_filter!(
-        filter.purchase_price_min,
-        "purchase_price",
-        "purchase_price_min",
-        ">="
-    );
-    push_filter!(
-        filter.purchase_price_max,
-        "purchase_price",
-        "purchase_price_max",
-        "<="
-    );
-    push_filter!(
-        filter.estimated_value_min,
-        "estimated_value",
-        "estimated_value_min",
-        ">="
-    );
-    push_filter!(
-        filter.estimated_value_max,
-        "estimated_value",
-        "estimated_value_max",
-        "<="
-    );
+    // Every field set in `filter`, combined with AND
+    fn fields(&mut self, filter: &ItemFilter) -> String {
+        let mut sql = String::from("1=1");
 
-    // Prepare named params: Vec<(&str, &dyn ToSql)>
-    let params: Vec<(&str, &dyn ToSql)> = param_values
-        .iter()
-        .map(|(k, v)| (k.as_str(), v.as_ref() as &dyn ToSql))
-        .collect();
+        // Helper macro to append filters
+        macro_rules! push_filter {
+            ($opt:expr_2021, $field:expr_2021, $param:expr_2021, $op:expr_2021) => {
+                if let Some(val) = $opt.clone() {
+                    let name = self.param(&$param, val);
+                    sql.push_str(&format!(" AND {} {} {}", $field, $op, &name));
+                }
+            };
+        }
 
-    let mut stmt = conn.prepare(&sql)?;
-    let rows = stmt.query_map(&params[..], Item::from_row)?;
-    let items = rows.collect::<Result<Vec<_>, _>>()?;
-    Ok(items)
+        macro_rules! push_like {
+            ($opt:expr_2021, $field:expr_2021) => {
+                if let Some(val) = $opt.clone() {
+                    let name = self.param($field, format!("%{}%", val));
+                    sql.push_str(&format!(" AND {} LIKE {}", $field, &name));
+                }
+            };
+        }
+
+        macro_rules! push_money {
+            ($opt:expr_2021, $field:expr_2021, $param:expr_2021, $op:expr_2021) => {
+                if let Some(val) = $opt {
+                    let name = self.param($param, val.minor_units());
+                    let currency = self.param(
+                        &format!("{}_currency", $param),
+                        val.currency().code().to_string(),
+                    );
+                    sql.push_str(&format!(
+                        " AND {}_minor {} {} AND {}_currency = {}",
+                        $field, $op, &name, $field, &currency
+                    ));
+                }
+            };
+        }
+
+        // LIKE filters
+        push_like!(filter.name_contains, "name");
+        push_like!(filter.description_contains, "description");
+        // Creator text also matches the aliases of linked creators
+        if let Some(text) = filter.creator_contains.clone() {
+            let name = self.param("creator", like_contains(&text));
+            sql.push_str(&format!(
+                " AND (creator LIKE {name} ESCAPE '\\' OR EXISTS (
+                SELECT 1 FROM item_creators ic JOIN creators c ON c.id = ic.creator_id
+                WHERE ic.item_id = items.id
+                AND (c.name LIKE {name} ESCAPE '\\' OR c.aliases LIKE {name} ESCAPE '\\')
+            ))"
+            ));
+        }
+        if let Some(creator_id) = filter.creator_id {
+            let name = self.param("creator_id", creator_id);
+            sql.push_str(&format!(
+                " AND EXISTS (SELECT 1 FROM item_creators ic
+            WHERE ic.item_id = items.id AND ic.creator_id = {name})"
+            ));
+        }
+        push_like!(filter.provenance_contains, "provenance");
+        push_like!(filter.location_contains, "location");
+        if let Some(text) = filter.provenance_event_contains.clone() {
+            let name = self.param("provenance_event", like_contains(&text));
+            sql.push_str(&format!(
+                " AND EXISTS (SELECT 1 FROM provenance_events p WHERE p.item_id = items.id
+            AND (p.name LIKE {name} ESCAPE '\\' OR p.location LIKE {name} ESCAPE '\\'
+                OR p.source LIKE {name} ESCAPE '\\' OR p.role LIKE {name} ESCAPE '\\'))"
+            ));
+        }
+
+        // Exact match filters (Enums and bools)
+        push_filter!(
+            filter.category.as_ref().map(|c| c.to_string()),
+            "category",
+            "category",
+            "="
+        );
+        push_filter!(
+            filter.action.as_ref().map(|a| a.to_string()),
+            "action",
+            "action",
+            "="
+        );
+        push_filter!(filter.working, "working", "working", "=");
+        push_filter!(
+            filter.condition,
+            "condition COLLATE NOCASE",
+            "condition",
+            "="
+        );
+        push_filter!(filter.deleted, "deleted", "deleted", "=");
+        push_filter!(filter.wishlist, "wishlist", "wishlist", "=");
+
+        // Date range filters
+        push_filter!(
+            filter.date_added_min.map(|d| d.to_string()),
+            "date_added",
+            "date_added_min",
+            ">="
+        );
+        push_filter!(
+            filter.date_added_max.map(|d| d.to_string()),
+            "date_added",
+            "date_added_max",
+            "<="
+        );
+        push_filter!(
+            filter.last_updated_min.map(|d| d.to_string()),
+            "last_updated",
+            "last_updated_min",
+            ">="
+        );
+        push_filter!(
+            filter.last_updated_max.map(|d| d.to_string()),
+            "last_updated",
+            "last_updated_max",
+            "<="
+        );
+        push_filter!(
+            filter.date_acquired_min.map(|d| d.to_string()),
+            "date_acquired",
+            "date_acquired_min",
+            ">="
+        );
+        push_filter!(
+            filter.date_acquired_max.map(|d| d.to_string()),
+            "date_acquired",
+            "date_acquired_max",
+            "<="
+        );
+
+        // Numeric range filters
+        // Date made is a range. By default an item matches when any part of its range falls
+        // inside the filter; `date_made_strict` requires the whole range to fit.
+        let strict = filter.date_made_strict.unwrap_or(false);
+        // Out of range dates can't be parsed, only built directly, and nothing can match them
+        if filter
+            .date_made_min
+            .into_iter()
+            .chain(filter.date_made_max)
+            .any(|d| !d.in_range())
+        {
+            sql.push_str(" AND 0");
+        }
+        push_filter!(
+            filter
+                .date_made_min
+                .and_then(|d| d.earliest())
+                .map(|d| d.to_string()),
+            if strict {
+                "date_made_from"
+            } else {
+                "date_made_to"
+            },
+            "date_made_min",
+            ">="
+        );
+        push_filter!(
+            filter
+                .date_made_max
+                .and_then(|d| d.latest())
+                .map(|d| d.to_string()),
+            if strict {
+                "date_made_to"
+            } else {
+                "date_made_from"
+            },
+            "date_made_max",
+            "<="
+        );
+
+        // Age is derived from date made as of today. An age older than the earliest
+        // representable date matches nothing as a minimum and every dated item as a maximum.
+        let today = Local::now().date_naive();
+        let (oldest_column, newest_column) = if strict {
+            ("date_made_to", "date_made_from")
+        } else {
+            ("date_made_from", "date_made_to")
+        };
+        if let Some(years) = filter.age_years_min {
+            match made_by_for_age(today, years) {
+                Some(date) => {
+                    push_filter!(Some(date.to_string()), oldest_column, "age_years_min", "<=")
+                }
+                None => sql.push_str(" AND 0"),
+            }
+        }
+        if let Some(years) = filter.age_years_max {
+            match made_after_for_age(today, years) {
+                Some(date) => {
+                    push_filter!(Some(date.to_string()), newest_column, "age_years_max", ">=")
+                }
+                None => sql.push_str(&format!(" AND {} IS NOT NULL", newest_column)),
+            }
+        }
+
+        push_money!(
+            filter.purchase_price_min,
+            "purchase_price",
+            "purchase_price_min",
+            ">="
+        );
+        push_money!(
+            filter.purchase_price_max,
+            "purchase_price",
+            "purchase_price_max",
+            "<="
+        );
+        push_money!(
+            filter.estimated_value_min,
+            "estimated_value",
+            "estimated_value_min",
+            ">="
+        );
+        push_money!(
+            filter.estimated_value_max,
+            "estimated_value",
+            "estimated_value_max",
+            "<="
+        );
+        push_money!(
+            filter.target_price_min,
+            "target_price",
+            "target_price_min",
+            ">="
+        );
+        push_money!(
+            filter.target_price_max,
+            "target_price",
+            "target_price_max",
+            "<="
+        );
+
+        for (min, max, column) in [
+            (filter.length_min, filter.length_max, "length_mm"),
+            (filter.width_min, filter.width_max, "width_mm"),
+            (filter.height_min, filter.height_max, "height_mm"),
+        ] {
+            push_filter!(
+                min.map(|l| l.millimetres()),
+                column,
+                format!("{}_min", column),
+                ">="
+            );
+            push_filter!(
+                max.map(|l| l.millimetres()),
+                column,
+                format!("{}_max", column),
+                "<="
+            );
+        }
+        push_filter!(
+            filter.weight_min.map(|w| w.grams()),
+            "weight_g",
+            "weight_g_min",
+            ">="
+        );
+        push_filter!(
+            filter.weight_max.map(|w| w.grams()),
+            "weight_g",
+            "weight_g_max",
+            "<="
+        );
+        // An unset quantity is a single piece
+        push_filter!(
+            filter.quantity_min,
+            "COALESCE(quantity, 1)",
+            "quantity_min",
+            ">="
+        );
+        push_filter!(
+            filter.quantity_max,
+            "COALESCE(quantity, 1)",
+            "quantity_max",
+            "<="
+        );
+        sql
+    }
 }
 
-pub fn add_item(conn: &Connection, item: &Item) -> AnyResult<()> {
+// A LIKE pattern matching `text` anywhere, with its own `%` and `_` taken literally. Use with
+// `ESCAPE '\'`.
+pub(crate) fn like_contains(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Adds `item` and returns its id.
+pub fn add_item(conn: &Connection, item: &Item) -> AnyResult<i32> {
     item.validate()
         .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
 
     let today = Local::now().date_naive();
+    let condition = item
+        .condition
+        .as_deref()
+        .map(|grade| canonical_grade(conn, &item.category, grade))
+        .transpose()?;
+    let mm = item.dimensions.as_ref().map(dimensions_mm);
 
     conn.execute(
         "INSERT INTO items (
@@ -247,15 +905,37 @@ pub fn add_item(conn: &Connection, item: &Item) -> AnyResult<()> {
             action,
             date_added,
             last_updated,
-            age_years,
+            date_made,
+            date_made_from,
+            date_made_to,
             date_acquired,
-            purchase_price,
-            estimated_value,
+            purchase_price_minor,
+            purchase_price_currency,
+            estimated_value_minor,
+            estimated_value_currency,
             creator,
             working,
             provenance,
-            deleted
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            location,
+            condition,
+            condition_notes,
+            length_mm,
+            width_mm,
+            height_mm,
+            dimension_unit,
+            weight_g,
+            weight_unit,
+            quantity,
+            deleted,
+            wishlist,
+            target_price_minor,
+            target_price_currency,
+            wishlist_priority,
+            wishlist_notes
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+            ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33
+        )",
         params![
             item.name,
             item.description,
@@ -265,18 +945,55 @@ pub fn add_item(conn: &Connection, item: &Item) -> AnyResult<()> {
             today.to_string(),
             // item.date_added.to_string(),
             // item.last_updated.to_string(),
-            item.age_years,
+            item.date_made.map(|d| d.to_string()),
+            item.date_made
+                .and_then(|d| d.earliest())
+                .map(|d| d.to_string()),
+            item.date_made
+                .and_then(|d| d.latest())
+                .map(|d| d.to_string()),
             item.date_acquired.map(|d| d.to_string()),
-            item.purchase_price,
-            item.estimated_value,
+            item.purchase_price.map(|m| m.minor_units()),
+            item.purchase_price.map(|m| m.currency().to_string()),
+            item.estimated_value.map(|m| m.minor_units()),
+            item.estimated_value.map(|m| m.currency().to_string()),
             item.creator,
             item.working.map(|b| b as i32), //SQLite has no bool type
             item.provenance,
+            item.location,
+            condition,
+            item.condition_notes,
+            mm.map(|d| d[0]),
+            mm.map(|d| d[1]),
+            mm.map(|d| d[2]),
+            item.dimensions.map(|d| d.unit.to_string()),
+            item.weight.map(|w| w.grams()),
+            item.weight.map(|w| w.unit.to_string()),
+            item.quantity,
             item.deleted as i32,
+            item.wishlist as i32,
+            item.target_price.map(|m| m.minor_units()),
+            item.target_price.map(|m| m.currency().to_string()),
+            item.wishlist_priority,
+            item.wishlist_notes,
         ],
     )?;
 
-    Ok(())
+    let id = conn.last_insert_rowid() as i32;
+
+    if let Some(grade) = &condition {
+        record_condition(conn, id, grade, &item.condition_notes)?;
+    }
+    if item.creator.is_some() {
+        link_creators_by_name(
+            conn,
+            id,
+            item.creator.as_deref(),
+            CreatorRole::default_for(&item.category),
+        )?;
+    }
+
+    Ok(id)
 }
 
 pub fn update_item(conn: &Connection, item: &Item) -> AnyResult<()> {
@@ -284,6 +1001,32 @@ pub fn update_item(conn: &Connection, item: &Item) -> AnyResult<()> {
         .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
 
     let today = Local::now().date_naive();
+    let mm = item.dimensions.as_ref().map(dimensions_mm);
+    let previous: Option<(Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT condition, condition_notes FROM items WHERE id = ?1",
+            params![item.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    // Only a new or changed grade has to be on the category's scale, so an item whose scale
+    // or category changed since it was graded can still be saved
+    let condition = match item.condition.as_deref() {
+        Some(grade) if previous.as_ref().and_then(|(c, _)| c.as_deref()) == Some(grade) => {
+            Some(grade.to_string())
+        }
+        grade => grade
+            .map(|grade| canonical_grade(conn, &item.category, grade))
+            .transpose()?,
+    };
+    let previous_creator: Option<String> = conn
+        .query_row(
+            "SELECT creator FROM items WHERE id = ?1",
+            params![item.id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
 
     conn.execute(
         "UPDATE items SET
@@ -293,15 +1036,34 @@ pub fn update_item(conn: &Connection, item: &Item) -> AnyResult<()> {
             action = ?4,
             date_added = ?5,
             last_updated = ?6,
-            age_years = ?7,
-            date_acquired = ?8,
-            purchase_price = ?9,
-            estimated_value = ?10,
-            creator = ?11,
-            working = ?12,
-            provenance = ?13,
-            deleted = ?14
-        WHERE id = ?15",
+            date_made = ?7,
+            date_made_from = ?8,
+            date_made_to = ?9,
+            date_acquired = ?10,
+            purchase_price_minor = ?11,
+            purchase_price_currency = ?12,
+            estimated_value_minor = ?13,
+            estimated_value_currency = ?14,
+            creator = ?15,
+            working = ?16,
+            provenance = ?17,
+            location = ?18,
+            condition = ?19,
+            condition_notes = ?20,
+            length_mm = ?21,
+            width_mm = ?22,
+            height_mm = ?23,
+            dimension_unit = ?24,
+            weight_g = ?25,
+            weight_unit = ?26,
+            quantity = ?27,
+            deleted = ?28,
+            wishlist = ?29,
+            target_price_minor = ?30,
+            target_price_currency = ?31,
+            wishlist_priority = ?32,
+            wishlist_notes = ?33
+        WHERE id = ?34",
         params![
             item.name,
             item.description,
@@ -310,18 +1072,72 @@ pub fn update_item(conn: &Connection, item: &Item) -> AnyResult<()> {
             item.date_added.to_string(),
             // item.last_updated.to_string(),
             today.to_string(), // override last_updated
-            item.age_years,
+            item.date_made.map(|d| d.to_string()),
+            item.date_made
+                .and_then(|d| d.earliest())
+                .map(|d| d.to_string()),
+            item.date_made
+                .and_then(|d| d.latest())
+                .map(|d| d.to_string()),
             item.date_acquired.map(|d| d.to_string()),
-            item.purchase_price,
-            item.estimated_value,
+            item.purchase_price.map(|m| m.minor_units()),
+            item.purchase_price.map(|m| m.currency().to_string()),
+            item.estimated_value.map(|m| m.minor_units()),
+            item.estimated_value.map(|m| m.currency().to_string()),
             item.creator,
             item.working.map(|b| b as i32), //SQLite has no bool type
             item.provenance,
+            item.location,
+            condition,
+            item.condition_notes,
+            mm.map(|d| d[0]),
+            mm.map(|d| d[1]),
+            mm.map(|d| d[2]),
+            item.dimensions.map(|d| d.unit.to_string()),
+            item.weight.map(|w| w.grams()),
+            item.weight.map(|w| w.unit.to_string()),
+            item.quantity,
             item.deleted as i32,
+            item.wishlist as i32,
+            item.target_price.map(|m| m.minor_units()),
+            item.target_price.map(|m| m.currency().to_string()),
+            item.wishlist_priority,
+            item.wishlist_notes,
             item.id,
         ],
     )?;
 
+    // Edited creator text is matched to creator records again
+    if item.creator != previous_creator {
+        link_creators_by_name(
+            conn,
+            item.id,
+            item.creator.as_deref(),
+            CreatorRole::default_for(&item.category),
+        )?;
+    }
+
+    // Edits to the condition are kept in the item's condition history
+    if let Some(grade) = &condition
+        && previous != Some((condition.clone(), item.condition_notes.clone()))
+    {
+        record_condition(conn, item.id, grade, &item.condition_notes)?;
+    }
+
+    Ok(())
+}
+
+fn record_condition(
+    conn: &Connection,
+    item_id: i32,
+    grade: &str,
+    notes: &Option<String>,
+) -> Result<()> {
+    let today = Local::now().date_naive();
+    conn.execute(
+        "INSERT INTO condition_reports (item_id, date, grade, notes) VALUES (?1, ?2, ?3, ?4)",
+        params![item_id, today.to_string(), grade, notes],
+    )?;
     Ok(())
 }
 
@@ -344,37 +1160,452 @@ pub fn update_item_fields(
     updates: HashMap<&str, String>,
 ) -> AnyResult<()> {
     // Step 1: Fetch current item
-    let item_option = get_item_by_id(conn, id)?;
-    let mut item = item_option.unwrap();
+    let mut item = get_item_by_id(conn, id)?.ok_or_else(|| anyhow!("Item {} not found", id))?;
 
     // Step 2: Apply updates
     for (field, value) in updates {
-        match field {
-            "name" => item.name = value,
-            "description" => item.description = value,
-            "category" => {
-                item.category = ItemCategory::from_str(&value).unwrap_or(ItemCategory::Other)
-            }
-            "action" => item.action = ItemAction::from_str(&value).unwrap_or(ItemAction::Keep),
-            "date_added" => item.date_added = NaiveDate::parse_from_str(&value, "%Y-%m-%d")?,
-            "last_updated" => item.last_updated = NaiveDate::parse_from_str(&value, "%Y-%m-%d")?,
-            "age_years" => item.age_years = Some(value.parse::<u32>()?),
-            "date_acquired" => {
-                item.date_acquired = Some(NaiveDate::parse_from_str(&value, "%Y-%m-%d")?)
-            }
-            "purchase_price" => item.purchase_price = Some(value.parse::<f64>()?),
-            "estimated_value" => item.estimated_value = Some(value.parse::<f64>()?),
-            "creator" => item.creator = Some(value),
-            "working" => item.working = Some(value.parse::<bool>()?),
-            "provenance" => item.provenance = Some(value),
-            "deleted" => item.deleted = value.parse::<bool>()?,
-            _ => return Err(anyhow!("Unknown field: {}", field)),
-        }
+        set_item_field(&mut item, field, value)?;
     }
 
     // Always bump last_updated to "now" when updating
     item.last_updated = chrono::Utc::now().date_naive();
 
     // Step 3: Call core update
-    update_item(&conn, &item)
+    update_item(conn, &item)
+}
+
+/// Sets one field of `item` from its text form, as used by `update_item_fields` and item
+/// templates.
+pub(crate) fn set_item_field(item: &mut Item, field: &str, value: String) -> AnyResult<()> {
+    match field {
+        "name" => item.name = value,
+        "description" => item.description = value,
+        "category" => {
+            item.category = ItemCategory::from_str(&value)
+                .map_err(|_| anyhow!("Validation failed: unknown category '{}'", value))?
+        }
+        "action" => {
+            item.action = ItemAction::from_str(&value)
+                .map_err(|_| anyhow!("Validation failed: unknown action '{}'", value))?
+        }
+        "date_added" => item.date_added = NaiveDate::parse_from_str(&value, "%Y-%m-%d")?,
+        "last_updated" => item.last_updated = NaiveDate::parse_from_str(&value, "%Y-%m-%d")?,
+        "date_made" => item.date_made = Some(FuzzyDate::from_str(&value).map_err(|e| anyhow!(e))?),
+        // Kept for older callers: an age becomes the year the item was made
+        "age_years" => {
+            let year = Local::now()
+                .date_naive()
+                .year()
+                .checked_sub(value.parse::<i32>()?)
+                .ok_or_else(|| anyhow!("Validation failed: age {} is out of range", value))?;
+            item.date_made = Some(FuzzyDate::Year(year))
+        }
+        "date_acquired" => {
+            item.date_acquired = Some(NaiveDate::parse_from_str(&value, "%Y-%m-%d")?)
+        }
+        // A bare number keeps the item's existing currency
+        "purchase_price" => {
+            let currency = item
+                .purchase_price
+                .map(|m| m.currency())
+                .unwrap_or_default();
+            item.purchase_price =
+                Some(Money::parse_with_default(&value, currency).map_err(|e| anyhow!(e))?)
+        }
+        "estimated_value" => {
+            let currency = item
+                .estimated_value
+                .map(|m| m.currency())
+                .unwrap_or_default();
+            item.estimated_value =
+                Some(Money::parse_with_default(&value, currency).map_err(|e| anyhow!(e))?)
+        }
+        "creator" => item.creator = Some(value),
+        "working" => item.working = Some(value.parse::<bool>()?),
+        "provenance" => item.provenance = Some(value),
+        "location" => item.location = Some(value),
+        "condition" => item.condition = Some(value),
+        "condition_notes" => item.condition_notes = Some(value),
+        "dimensions" => {
+            item.dimensions = Some(Dimensions::from_str(&value).map_err(|e| anyhow!(e))?)
+        }
+        "weight" => item.weight = Some(Weight::from_str(&value).map_err(|e| anyhow!(e))?),
+        "quantity" => item.quantity = Some(value.parse::<u32>()?),
+        "deleted" => item.deleted = value.parse::<bool>()?,
+        "wishlist" => item.wishlist = value.parse::<bool>()?,
+        "target_price" => {
+            let currency = item.target_price.map(|m| m.currency()).unwrap_or_default();
+            item.target_price =
+                Some(Money::parse_with_default(&value, currency).map_err(|e| anyhow!(e))?)
+        }
+        "wishlist_priority" => item.wishlist_priority = Some(value.parse::<u32>()?),
+        "wishlist_notes" => item.wishlist_notes = Some(value),
+        _ => return Err(anyhow!("Unknown field: {}", field)),
+    }
+    Ok(())
+}
+
+// Ids of the items a bulk change applies to. Deleted items are left out unless the filter
+// asks for them, and a filter that would match the whole catalog is refused.
+fn matching_ids(conn: &Connection, mut filter: ItemFilter) -> AnyResult<Vec<i32>> {
+    let deleted = filter.deleted.take();
+    if filter.is_empty() {
+        return Err(anyhow!(
+            "Refusing to change every item; give at least one filter"
+        ));
+    }
+    filter.deleted = Some(deleted.unwrap_or(false));
+    Ok(get_filtered_items(conn, filter)?
+        .into_iter()
+        .map(|item| item.id)
+        .collect())
+}
+
+/// Applies the same `updates` as `update_item_fields` to every item matching `filter`, all or
+/// nothing. With `dry_run` nothing is changed. Returns the ids of the matching items. Deleted
+/// items only match when `filter.deleted` asks for them, and an empty filter is an error.
+pub fn bulk_update(
+    conn: &Connection,
+    filter: ItemFilter,
+    updates: HashMap<&str, String>,
+    dry_run: bool,
+) -> AnyResult<Vec<i32>> {
+    if updates.is_empty() {
+        return Err(anyhow!("No fields to update"));
+    }
+    let ids = matching_ids(conn, filter)?;
+    if dry_run {
+        return Ok(ids);
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for &id in &ids {
+        update_item_fields(&tx, id, updates.clone()).map_err(|e| anyhow!("Item {}: {}", id, e))?;
+    }
+    tx.commit()?;
+    Ok(ids)
+}
+
+/// Soft-deletes every item matching `filter`, all or nothing. With `dry_run` nothing is
+/// changed. Returns the ids of the matching items. An empty filter is an error, as for
+/// `bulk_update`.
+pub fn bulk_soft_delete(
+    conn: &Connection,
+    filter: ItemFilter,
+    dry_run: bool,
+) -> AnyResult<Vec<i32>> {
+    let ids = matching_ids(conn, filter)?;
+    if dry_run {
+        return Ok(ids);
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for &id in &ids {
+        soft_delete_item(&tx, id)?;
+    }
+    tx.commit()?;
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OptionalField;
+
+    #[test]
+    fn test_migrate_real_prices_to_money() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL,
+                description TEXT NOT NULL, category TEXT NOT NULL, action TEXT NOT NULL,
+                date_added TEXT NOT NULL, last_updated TEXT NOT NULL, age_years INTEGER,
+                date_acquired TEXT, purchase_price REAL, estimated_value REAL, creator TEXT,
+                working WORKING, provenance TEXT, deleted INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO items (name, description, category, action, date_added, last_updated,
+                purchase_price, estimated_value, age_years, creator)
+            VALUES ('Lamp', 'Brass', 'Decor', 'Keep', '2025-01-01', '2025-01-01', 19.99, NULL, 100,
+                'Smith, John'),
+            ('Atlas', 'Maps', 'Book', 'Keep', '2025-01-01', '2025-01-01', NULL, NULL, NULL,
+                'Mary Jones;smith, john ; ');",
+        )
+        .unwrap();
+
+        init_db(&conn).unwrap();
+        let item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(item.purchase_price.unwrap().to_string(), "19.99 USD");
+        assert_eq!(item.estimated_value, None);
+        assert_eq!(item.date_made, Some(FuzzyDate::Year(1925)));
+        assert!(item.age_years.unwrap() >= 100);
+        let creators = crate::creators::get_item_creators(&conn, 1).unwrap();
+        assert_eq!(creators[0].creator.name, "Smith, John");
+        assert_eq!(creators[0].role, CreatorRole::Maker);
+        let atlas = get_item_by_id(&conn, 2).unwrap().unwrap();
+        assert_eq!(atlas.creator.as_deref(), Some("Mary Jones; Smith, John"));
+        let creators = crate::creators::get_item_creators(&conn, 2).unwrap();
+        assert_eq!(creators.len(), 2);
+        assert_eq!(creators[1].creator.name, "Smith, John");
+        assert_eq!(creators[1].role, CreatorRole::Author);
+        assert_eq!(crate::creators::get_creators(&conn).unwrap().len(), 2);
+
+        // Running again is a no-op
+        init_db(&conn).unwrap();
+    }
+
+    #[test]
+    fn test_filter_expressions() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, category, location) in [
+            ("Atlas", ItemCategory::Book, Some("Study")),
+            ("Clock", ItemCategory::Antique, None),
+            ("Kettle", ItemCategory::Kitchenware, Some("Kitchen")),
+            ("Novel", ItemCategory::Book, None),
+        ] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                category,
+                location: location.map(String::from),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        soft_delete_item(&conn, 4).unwrap();
+        let names = |filter: FilterExpr| -> Vec<String> {
+            get_filtered_items(&conn, filter)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.name)
+                .collect()
+        };
+        let deleted = || ItemFilter {
+            deleted: Some(true),
+            ..Default::default()
+        };
+
+        // Books or antiques, not deleted
+        let books_or_antiques =
+            FilterExpr::CategoryIn(vec![ItemCategory::Book, ItemCategory::Antique]);
+        assert_eq!(
+            names(books_or_antiques.and(!deleted())),
+            vec!["Atlas", "Clock"]
+        );
+        assert_eq!(
+            names(FilterExpr::IsNull(OptionalField::Location).or(deleted())),
+            vec!["Clock", "Novel"]
+        );
+        // Empty columns don't match a condition, so they do match its negation
+        let in_study = ItemFilter {
+            location_contains: Some("Study".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(names(!in_study).len(), 3);
+        assert_eq!(names(FilterExpr::Any(vec![])).len(), 0);
+
+        // A plain filter is still accepted where an expression is expected
+        let json: FilterExpr = serde_json::from_str(r#"{"name_contains": "kett"}"#).unwrap();
+        assert_eq!(names(json), vec!["Kettle"]);
+        let json: FilterExpr = serde_json::from_str(
+            r#"{"any": [{"category_in": ["Kitchenware"]}, {"is_null": "location"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(names(json), vec!["Clock", "Kettle", "Novel"]);
+
+        // A misspelt tag or field is an error rather than an empty filter matching everything
+        for typo in [
+            r#"{"alll": [{"category_in": ["Book"]}]}"#,
+            r#"{"name_contain": "kett"}"#,
+            r#"{"any": [{"categroy_in": ["Book"]}]}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<FilterExpr>(typo).is_err(),
+                "{}",
+                typo
+            );
+        }
+    }
+
+    #[test]
+    fn test_bulk_update_is_all_or_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, working) in [("Radio", false), ("Television", false), ("Lamp", true)] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                category: ItemCategory::ElectronicDevice,
+                working: Some(working),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        let broken = || ItemFilter {
+            category: Some(ItemCategory::ElectronicDevice),
+            working: Some(false),
+            ..Default::default()
+        };
+        let sell = HashMap::from([("action", "Sell".to_string())]);
+
+        let preview = bulk_update(&conn, broken(), sell.clone(), true).unwrap();
+        assert_eq!(preview, vec![1, 2]);
+        assert_eq!(
+            get_item_by_id(&conn, 1)
+                .unwrap()
+                .unwrap()
+                .action
+                .to_string(),
+            "Keep"
+        );
+
+        assert_eq!(
+            bulk_update(&conn, broken(), sell, false).unwrap(),
+            vec![1, 2]
+        );
+        let selling = ItemFilter {
+            action: Some(ItemAction::Sell),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_items(&conn, selling).unwrap().len(), 2);
+
+        // One bad value rolls back the whole batch
+        let bad = HashMap::from([
+            ("name", "Portable".to_string()),
+            ("quantity", "0".to_string()),
+        ]);
+        assert!(bulk_update(&conn, broken(), bad, false).is_err());
+        assert_eq!(get_item_by_id(&conn, 1).unwrap().unwrap().name, "Radio");
+
+        assert_eq!(
+            bulk_soft_delete(&conn, broken(), false).unwrap(),
+            vec![1, 2]
+        );
+        assert!(get_item_by_id(&conn, 2).unwrap().unwrap().deleted);
+        assert!(!get_item_by_id(&conn, 3).unwrap().unwrap().deleted);
+
+        // Deleted items aren't touched again, and nothing is changed without a filter
+        assert!(bulk_soft_delete(&conn, broken(), true).unwrap().is_empty());
+        let deleted = ItemFilter {
+            deleted: Some(true),
+            ..broken()
+        };
+        assert_eq!(bulk_soft_delete(&conn, deleted, true).unwrap(), vec![1, 2]);
+        assert!(bulk_soft_delete(&conn, ItemFilter::default(), false).is_err());
+        let only_deleted = ItemFilter {
+            deleted: Some(false),
+            ..Default::default()
+        };
+        let keep = HashMap::from([("action", "Keep".to_string())]);
+        assert!(bulk_update(&conn, only_deleted, keep, false).is_err());
+        assert!(!get_item_by_id(&conn, 3).unwrap().unwrap().deleted);
+    }
+
+    #[test]
+    fn test_measurement_filters_convert_units() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let table = Item {
+            name: "Table".to_string(),
+            description: "Oak table".to_string(),
+            dimensions: Some(Dimensions::from_str("48x30x29 in").unwrap()),
+            weight: Some(Weight::from_str("40 lb").unwrap()),
+            ..Default::default()
+        };
+        add_item(&conn, &table).unwrap();
+
+        let item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(item.dimensions, table.dimensions);
+        assert_eq!(item.weight, table.weight);
+        assert_eq!(item.pieces(), 1);
+
+        let count = |filter: ItemFilter| get_filtered_items(&conn, filter).unwrap().len();
+        let length = |s: &str| Some(s.parse().unwrap());
+        assert_eq!(
+            count(ItemFilter {
+                length_min: length("1.2 m"),
+                height_max: length("75 cm"),
+                ..Default::default()
+            }),
+            1
+        );
+        assert_eq!(
+            count(ItemFilter {
+                weight_max: Some(Weight::from_str("15 kg").unwrap()),
+                ..Default::default()
+            }),
+            0
+        );
+        assert_eq!(
+            count(ItemFilter {
+                quantity_min: Some(2),
+                ..Default::default()
+            }),
+            0
+        );
+    }
+
+    #[test]
+    fn test_date_made_filter_respects_uncertainty() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, made) in [("Clock", "c.1875±10"), ("Desk", "1890s"), ("Radio", "1952")] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                date_made: Some(FuzzyDate::from_str(made).unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        let names = |filter: ItemFilter| -> Vec<String> {
+            get_filtered_items(&conn, filter)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.name)
+                .collect()
+        };
+
+        let mut filter = ItemFilter {
+            date_made_min: Some(FuzzyDate::Year(1880)),
+            date_made_max: Some(FuzzyDate::Year(1900)),
+            ..Default::default()
+        };
+        assert_eq!(names(filter), vec!["Clock", "Desk"]);
+
+        filter = ItemFilter {
+            date_made_min: Some(FuzzyDate::Year(1880)),
+            date_made_max: Some(FuzzyDate::Year(1900)),
+            date_made_strict: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(names(filter), vec!["Desk"]);
+
+        filter = ItemFilter {
+            age_years_max: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(names(filter), vec!["Radio"]);
+
+        // Ages past the earliest representable date don't overflow
+        filter = ItemFilter {
+            age_years_min: Some(u32::MAX),
+            ..Default::default()
+        };
+        assert!(names(filter).is_empty());
+        filter = ItemFilter {
+            age_years_max: Some(u32::MAX),
+            ..Default::default()
+        };
+        assert_eq!(names(filter).len(), 3);
+
+        for age in ["-2147483000", "500000"] {
+            let ages = HashMap::from([("age_years", age.to_string())]);
+            let err = update_item_fields(&conn, 1, ages).unwrap_err().to_string();
+            assert!(err.starts_with("Validation failed"), "{}", err);
+        }
+        let name = HashMap::from([("name", "Gone".to_string())]);
+        let err = update_item_fields(&conn, 99, name).unwrap_err().to_string();
+        assert_eq!(err, "Item 99 not found");
+    }
 }
@@ -0,0 +1,746 @@
+use crate::creators::names_match;
+use crate::db::{get_filtered_items, get_item_by_id, soft_delete_item, update_item};
+use crate::images::hash_distance;
+use crate::models::{Item, ItemFilter};
+use crate::money::Money;
+use crate::provenance::refresh_summary;
+use crate::relations::move_relations;
+use anyhow::{Result as AnyResult, anyhow};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Pairs scoring below this are not reported by `find_duplicates`.
+pub const DEFAULT_MIN_SCORE: f64 = 0.6;
+
+/// Photos whose hashes are at most this many bits apart are taken to be the same picture.
+pub const DEFAULT_PHOTO_DISTANCE: u32 = 8;
+
+// Pairs whose names are this far apart are not compared any further
+const NAME_CUTOFF: f64 = 0.3;
+
+// Letters of each name word used as a blocking key, so "clock" and "clocks" share one
+const BLOCK_PREFIX: usize = 4;
+
+/// Two items that look like the same thing, with a score from 0 to 1 and the signals that
+/// contributed to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCandidate {
+    pub item: Item,
+    pub other: Item,
+    pub score: f64,
+    pub reasons: Vec<String>,
+    /// The closest pair of photos, when the items have near-identical ones
+    pub photo_match: Option<PhotoMatch>,
+}
+
+/// Photos on two different items that look alike.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhotoMatch {
+    pub item_id: i32,
+    pub attachment_id: i32,
+    pub other_item_id: i32,
+    pub other_attachment_id: i32,
+    /// Bits differing between the photos' perceptual hashes, 0 for the same picture
+    pub distance: u32,
+}
+
+impl PhotoMatch {
+    fn swapped(self) -> Self {
+        PhotoMatch {
+            item_id: self.other_item_id,
+            attachment_id: self.other_attachment_id,
+            other_item_id: self.item_id,
+            other_attachment_id: self.attachment_id,
+            distance: self.distance,
+        }
+    }
+}
+
+// Lowercase words with punctuation and a leading article dropped, so "The Hobbit (1st ed.)"
+// and "hobbit 1st ed" compare equal
+fn normalize(text: &str) -> Vec<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    match words.split_first() {
+        Some((first, rest)) if ["the", "a", "an"].contains(&first.as_str()) && !rest.is_empty() => {
+            rest.to_vec()
+        }
+        _ => words,
+    }
+}
+
+// Keys an item shares with its likely duplicates: the start of each word of its name longer
+// than two letters, and its identifiers. Only items sharing a key are compared.
+fn blocking_keys(item: &Item, identifiers: &[String]) -> Vec<String> {
+    let words = normalize(&item.name);
+    let mut keys: Vec<String> = words
+        .iter()
+        .filter(|w| w.chars().count() > 2)
+        .map(|w| format!("name:{}", w.chars().take(BLOCK_PREFIX).collect::<String>()))
+        .collect();
+    if keys.is_empty() && !words.is_empty() {
+        keys.push(format!("name:{}", words.join(" ")));
+    }
+    keys.extend(identifiers.iter().map(|i| format!("id:{}", i)));
+    keys
+}
+
+// Dice coefficient over character bigrams of the normalized text
+fn name_similarity(a: &str, b: &str) -> f64 {
+    let bigrams = |s: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = normalize(s).join(" ").chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let (a, mut b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let total = a.len() + b.len();
+    let mut shared = 0;
+    for pair in &a {
+        if let Some(pos) = b.iter().position(|p| p == pair) {
+            b.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / total as f64
+}
+
+// Jaccard index of the words longer than two letters
+fn word_overlap(a: &str, b: &str) -> f64 {
+    let words =
+        |s: &str| -> HashSet<String> { normalize(s).into_iter().filter(|w| w.len() > 2).collect() };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+// 1 for equal amounts falling to 0 when one is double the other. Amounts in different
+// currencies are not compared.
+fn price_similarity(a: Money, b: Money) -> Option<f64> {
+    if a.currency() != b.currency() {
+        return None;
+    }
+    let (a, b) = (a.minor_units() as f64, b.minor_units() as f64);
+    if a.max(b) == 0.0 {
+        return Some(1.0);
+    }
+    Some((1.0 - (a - b).abs() / a.max(b)).max(0.0))
+}
+
+/// Scores how likely two items are to be the same thing. Name, description and category are
+/// always compared; creator and price only count when both items have them, so sparse
+/// records aren't penalised for what they're missing.
+pub fn duplicate_score(item: &Item, other: &Item) -> (f64, Vec<String>) {
+    let mut reasons = vec![];
+    let mut weighted = 0.0;
+    let mut weights = 0.0;
+    let mut signal = |weight: f64, similarity: f64, reason: String| {
+        weighted += weight * similarity;
+        weights += weight;
+        if similarity >= 0.5 {
+            reasons.push(reason);
+        }
+    };
+
+    let name = name_similarity(&item.name, &other.name);
+    signal(0.4, name, format!("name {:.0}% similar", name * 100.0));
+
+    let description = word_overlap(&item.description, &other.description);
+    signal(
+        0.2,
+        description,
+        format!("description {:.0}% similar", description * 100.0),
+    );
+
+    let same_category = item.category.to_string() == other.category.to_string();
+    signal(
+        0.1,
+        same_category as u8 as f64,
+        format!("both {}", item.category),
+    );
+
+    if let (Some(a), Some(b)) = (&item.creator, &other.creator) {
+        let similarity = if a.eq_ignore_ascii_case(b) || names_match(a, b) {
+            1.0
+        } else {
+            name_similarity(a, b)
+        };
+        signal(0.15, similarity, "same creator".to_string());
+    }
+
+    let price = |i: &Item| i.estimated_value.or(i.purchase_price);
+    if let (Some(a), Some(b)) = (price(item), price(other))
+        && let Some(similarity) = price_similarity(a, b)
+    {
+        signal(0.15, similarity, format!("prices {} and {}", a, b));
+    }
+
+    (weighted / weights, reasons)
+}
+
+/// Pairs of photos on different non-deleted items whose perceptual hashes are at most
+/// `max_distance` bits apart, closest first. Photos are hashed as they're attached;
+/// `hash_photos` hashes older ones.
+pub fn find_similar_photos(conn: &Connection, max_distance: u32) -> AnyResult<Vec<PhotoMatch>> {
+    let mut stmt = conn.prepare(
+        "SELECT a.id, a.item_id, a.photo_hash FROM attachments a
+        JOIN items i ON i.id = a.item_id
+        WHERE a.photo_hash IS NOT NULL AND i.deleted = 0
+        ORDER BY a.id",
+    )?;
+    let photos = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut matches = vec![];
+    for (i, &(attachment_id, item_id, hash)) in photos.iter().enumerate() {
+        for &(other_attachment_id, other_item_id, other_hash) in &photos[i + 1..] {
+            let distance = hash_distance(hash, other_hash);
+            if item_id != other_item_id && distance <= max_distance {
+                matches.push(PhotoMatch {
+                    item_id,
+                    attachment_id,
+                    other_item_id,
+                    other_attachment_id,
+                    distance,
+                });
+            }
+        }
+    }
+    matches.sort_by_key(|m| m.distance);
+    Ok(matches)
+}
+
+/// Pairs of non-deleted items scoring at least `min_score`, most likely duplicates first.
+/// Items with near-identical photos are paired however different their details are.
+pub fn find_duplicates(conn: &Connection, min_score: f64) -> AnyResult<Vec<DuplicateCandidate>> {
+    let items = get_filtered_items(
+        conn,
+        ItemFilter {
+            deleted: Some(false),
+            ..Default::default()
+        },
+    )?;
+
+    // The closest photos of each pair of items
+    let pair = |a: i32, b: i32| (a.min(b), a.max(b));
+    let mut photo_matches = HashMap::new();
+    for photo in find_similar_photos(conn, DEFAULT_PHOTO_DISTANCE)? {
+        photo_matches
+            .entry(pair(photo.item_id, photo.other_item_id))
+            .or_insert(photo);
+    }
+
+    // Comparing every pair is quadratic, so only pairs sharing a name word or identifier,
+    // or with matching photos, are scored
+    let mut identifiers: HashMap<i32, Vec<String>> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT item_id, normalized FROM item_identifiers")?;
+    for row in stmt.query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get(1)?)))? {
+        let (item_id, normalized) = row?;
+        identifiers.entry(item_id).or_default().push(normalized);
+    }
+    let mut blocks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, item) in items.iter().enumerate() {
+        let ids = identifiers
+            .get(&item.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for key in blocking_keys(item, ids) {
+            let block = blocks.entry(key).or_default();
+            if block.last() != Some(&index) {
+                block.push(index);
+            }
+        }
+    }
+    let mut pairs: HashSet<(usize, usize)> = blocks
+        .values()
+        .flat_map(|block| {
+            block
+                .iter()
+                .enumerate()
+                .flat_map(|(n, &i)| block[n + 1..].iter().map(move |&j| (i, j)))
+        })
+        .collect();
+    let index: HashMap<i32, usize> = items.iter().enumerate().map(|(n, i)| (i.id, n)).collect();
+    for &(a, b) in photo_matches.keys() {
+        if let (Some(&i), Some(&j)) = (index.get(&a), index.get(&b)) {
+            pairs.insert((i.min(j), i.max(j)));
+        }
+    }
+    let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+    pairs.sort_unstable();
+
+    let mut candidates = vec![];
+    for (i, j) in pairs {
+        let (item, other) = (&items[i], &items[j]);
+        let photo_match = photo_matches.get(&pair(item.id, other.id)).cloned();
+        if photo_match.is_none() && name_similarity(&item.name, &other.name) < NAME_CUTOFF {
+            continue;
+        }
+        let (mut score, mut reasons) = duplicate_score(item, other);
+        let photo_match = photo_match.map(|photo| {
+            // The same picture is strong evidence on its own
+            score = score.max(1.0 - photo.distance as f64 / 64.0);
+            reasons.push(match photo.distance {
+                0 => "same photo".to_string(),
+                d => format!("photos {} bits apart", d),
+            });
+            if photo.item_id == item.id {
+                photo
+            } else {
+                photo.swapped()
+            }
+        });
+        if score >= min_score {
+            candidates.push(DuplicateCandidate {
+                item: item.clone(),
+                other: other.clone(),
+                score,
+                reasons,
+                photo_match,
+            });
+        }
+    }
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(candidates)
+}
+
+/// An item field that `merge_items` can take from the merged item instead of the kept one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeField {
+    Name,
+    Description,
+    Category,
+    Action,
+    Creator,
+    Provenance,
+    Location,
+    DateMade,
+    DateAcquired,
+    PurchasePrice,
+    EstimatedValue,
+    Working,
+    Condition,
+    Dimensions,
+    Weight,
+    Quantity,
+}
+
+impl fmt::Display for MergeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MergeField::Name => "name",
+            MergeField::Description => "description",
+            MergeField::Category => "category",
+            MergeField::Action => "action",
+            MergeField::Creator => "creator",
+            MergeField::Provenance => "provenance",
+            MergeField::Location => "location",
+            MergeField::DateMade => "date_made",
+            MergeField::DateAcquired => "date_acquired",
+            MergeField::PurchasePrice => "purchase_price",
+            MergeField::EstimatedValue => "estimated_value",
+            MergeField::Working => "working",
+            MergeField::Condition => "condition",
+            MergeField::Dimensions => "dimensions",
+            MergeField::Weight => "weight",
+            MergeField::Quantity => "quantity",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MergeField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "name" => Ok(MergeField::Name),
+            "description" => Ok(MergeField::Description),
+            "category" => Ok(MergeField::Category),
+            "action" => Ok(MergeField::Action),
+            "creator" => Ok(MergeField::Creator),
+            "provenance" => Ok(MergeField::Provenance),
+            "location" => Ok(MergeField::Location),
+            "date_made" => Ok(MergeField::DateMade),
+            "date_acquired" => Ok(MergeField::DateAcquired),
+            "purchase_price" => Ok(MergeField::PurchasePrice),
+            "estimated_value" => Ok(MergeField::EstimatedValue),
+            "working" => Ok(MergeField::Working),
+            "condition" => Ok(MergeField::Condition),
+            "dimensions" => Ok(MergeField::Dimensions),
+            "weight" => Ok(MergeField::Weight),
+            "quantity" => Ok(MergeField::Quantity),
+            _ => Err(format!("Unknown merge field: '{}'", s)),
+        }
+    }
+}
+
+// The kept item's fields, with `take` fields and any it's missing filled from `merged`
+fn combine(keep: &Item, merged: &Item, take: &[MergeField]) -> Item {
+    let mut item = keep.clone();
+    let taken = |field| take.contains(&field);
+    macro_rules! combine_optional {
+        ($($field:ident => $variant:ident),*) => {$(
+            if taken(MergeField::$variant) || item.$field.is_none() {
+                item.$field = merged.$field.clone();
+            }
+        )*};
+    }
+    combine_optional!(
+        creator => Creator,
+        provenance => Provenance,
+        location => Location,
+        date_made => DateMade,
+        date_acquired => DateAcquired,
+        purchase_price => PurchasePrice,
+        estimated_value => EstimatedValue,
+        working => Working,
+        dimensions => Dimensions,
+        weight => Weight,
+        quantity => Quantity
+    );
+    if taken(MergeField::Condition) || item.condition.is_none() {
+        item.condition = merged.condition.clone();
+        item.condition_notes = merged.condition_notes.clone();
+    }
+    if taken(MergeField::Name) {
+        item.name = merged.name.clone();
+    }
+    if taken(MergeField::Description) {
+        item.description = merged.description.clone();
+    }
+    if taken(MergeField::Category) {
+        item.category = merged.category.clone();
+    }
+    if taken(MergeField::Action) {
+        item.action = merged.action.clone();
+    }
+    item.date_added = keep.date_added.min(merged.date_added);
+    item
+}
+
+/// Merges `merge_id` into `keep_id`. The kept item keeps its own field values except for
+/// the `take` fields, and fills any empty fields from the merged item. Attachments, condition
+/// history, provenance, relations and beneficiary assignments move to the kept item, and the
+/// merged item is soft-deleted. Returns the updated kept item.
+pub fn merge_items(
+    conn: &Connection,
+    keep_id: i32,
+    merge_id: i32,
+    take: &[MergeField],
+) -> AnyResult<Item> {
+    if keep_id == merge_id {
+        return Err(anyhow!("Cannot merge an item into itself"));
+    }
+    let keep =
+        get_item_by_id(conn, keep_id)?.ok_or_else(|| anyhow!("Item {} not found", keep_id))?;
+    let merged =
+        get_item_by_id(conn, merge_id)?.ok_or_else(|| anyhow!("Item {} not found", merge_id))?;
+    if merged.deleted {
+        return Err(anyhow!("Item {} is already deleted", merge_id));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE attachments SET item_id = ?1 WHERE item_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "UPDATE condition_reports SET item_id = ?1 WHERE item_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "UPDATE reminders SET item_id = ?1 WHERE item_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+
+    // Assignments move unless the beneficiary already has the kept item
+    tx.execute(
+        "UPDATE OR IGNORE assignments SET item_id = ?1 WHERE item_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "DELETE FROM assignments WHERE item_id = ?1",
+        params![merge_id],
+    )?;
+
+    // Identifiers the kept item already has are dropped
+    tx.execute(
+        "UPDATE OR IGNORE item_identifiers SET item_id = ?1 WHERE item_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "DELETE FROM item_identifiers WHERE item_id = ?1",
+        params![merge_id],
+    )?;
+
+    // The merged chain is appended after the kept one
+    tx.execute(
+        "UPDATE provenance_events SET
+            item_id = ?1,
+            position = position + (
+                SELECT COALESCE(MAX(position), 0) FROM provenance_events WHERE item_id = ?1
+            )
+        WHERE item_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    refresh_summary(&tx, keep_id)?;
+
+    move_relations(&tx, keep_id, merge_id)?;
+
+    // Creator links follow the combined creator text
+    update_item(&tx, &combine(&keep, &merged, take))?;
+    soft_delete_item(&tx, merge_id)?;
+    tx.commit()?;
+
+    get_item_by_id(conn, keep_id)?.ok_or_else(|| anyhow!("Item {} not found", keep_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachments::{add_attachment, get_attachments};
+    use crate::db::{add_item, init_db};
+    use crate::identifiers::{add_identifier, get_identifiers};
+    use crate::images::hash_photos;
+    use crate::models::ItemCategory;
+    use crate::provenance::{ProvenanceEvent, append_provenance_event, get_provenance};
+    use crate::relations::{RelationKind, add_relation, get_related_items};
+    use crate::reminders::{Reminder, add_reminder, get_reminders};
+    use chrono::NaiveDate;
+
+    fn item(name: &str, description: &str, creator: Option<&str>, value: Option<&str>) -> Item {
+        Item {
+            name: name.to_string(),
+            description: description.to_string(),
+            category: ItemCategory::Book,
+            creator: creator.map(str::to_string),
+            estimated_value: value.map(|v| v.parse().unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scores_likely_duplicates_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for i in [
+            item(
+                "The Hobbit",
+                "First edition hardback",
+                Some("Tolkien, J.R.R."),
+                Some("100 USD"),
+            ),
+            item(
+                "Hobbit",
+                "Hardback, first edition",
+                Some("J. R. R. Tolkien"),
+                Some("95 USD"),
+            ),
+            item("Hobbit figurine", "Painted resin", None, Some("10 USD")),
+            item("Garden rake", "Wooden handle", None, None),
+        ] {
+            add_item(&conn, &i).unwrap();
+        }
+
+        let candidates = find_duplicates(&conn, DEFAULT_MIN_SCORE).unwrap();
+        assert_eq!(candidates.len(), 1);
+        let best = &candidates[0];
+        assert_eq!((best.item.id, best.other.id), (1, 2));
+        assert!(best.reasons.contains(&"same creator".to_string()));
+    }
+
+    #[test]
+    fn test_merge_moves_history_and_deletes_loser() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        add_item(&conn, &item("Hobbit", "Hardback", None, Some("100 USD"))).unwrap();
+        add_item(
+            &conn,
+            &Item {
+                location: Some("Study".to_string()),
+                ..item(
+                    "The Hobbit",
+                    "First edition",
+                    Some("Tolkien"),
+                    Some("120 USD"),
+                )
+            },
+        )
+        .unwrap();
+        add_attachment(&conn, 2, "hobbit.jpg", None).unwrap();
+        for id in [1, 2] {
+            add_identifier(&conn, id, &"9780261102217".parse().unwrap()).unwrap();
+        }
+        add_identifier(&conn, 2, &"0-306-40615-2".parse().unwrap()).unwrap();
+        let reminder = Reminder {
+            item_id: 2,
+            due: NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+            ..Default::default()
+        };
+        add_reminder(&conn, &reminder).unwrap();
+        for (id, name) in [(1, "Shop"), (2, "Grandfather")] {
+            append_provenance_event(
+                &conn,
+                &ProvenanceEvent {
+                    item_id: id,
+                    name: name.to_string(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        let kept = merge_items(&conn, 1, 2, &[MergeField::Description]).unwrap();
+        assert_eq!(kept.name, "Hobbit");
+        assert_eq!(kept.description, "First edition");
+        assert_eq!(kept.location.as_deref(), Some("Study"));
+        assert_eq!(kept.creator.as_deref(), Some("Tolkien"));
+        assert_eq!(kept.estimated_value.unwrap().to_string(), "100.00 USD");
+        assert_eq!(get_attachments(&conn, 1).unwrap().len(), 1);
+        let chain: Vec<String> = get_provenance(&conn, 1)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(chain, vec!["Shop", "Grandfather"]);
+        assert_eq!(get_identifiers(&conn, 1).unwrap().len(), 2);
+        assert!(get_identifiers(&conn, 2).unwrap().is_empty());
+        assert_eq!(get_reminders(&conn, 1).unwrap().len(), 1);
+        assert!(get_item_by_id(&conn, 2).unwrap().unwrap().deleted);
+        assert!(merge_items(&conn, 1, 2, &[]).is_err());
+    }
+
+    #[test]
+    fn test_merge_drops_relations_that_would_cycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for name in ["Hobbit", "The Hobbit", "Box set", "Map"] {
+            add_item(&conn, &item(name, "Hardback", None, None)).unwrap();
+        }
+        add_relation(&conn, 3, RelationKind::SetMember, 1).unwrap();
+        add_relation(&conn, 2, RelationKind::SetMember, 3).unwrap();
+        add_relation(&conn, 1, RelationKind::Related, 2).unwrap();
+        add_relation(&conn, 4, RelationKind::PartOf, 2).unwrap();
+        add_relation(&conn, 4, RelationKind::Related, 1).unwrap();
+        add_relation(&conn, 2, RelationKind::Related, 4).unwrap();
+
+        merge_items(&conn, 1, 2, &[]).unwrap();
+        let mut links: Vec<(String, String)> = get_related_items(&conn, 1)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.label, r.item.name))
+            .collect();
+        links.sort();
+        assert_eq!(
+            links,
+            vec![
+                ("Has part".to_string(), "Map".to_string()),
+                ("Related to".to_string(), "Map".to_string()),
+                ("Set contains".to_string(), "Box set".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only_items_sharing_a_key_are_compared() {
+        let hobbit = item("The Hobbit", "", None, None);
+        assert_eq!(blocking_keys(&hobbit, &[]), vec!["name:hobb"]);
+        let keys = blocking_keys(&item("A to Z", "", None, None), &["9780261102217".into()]);
+        assert_eq!(keys, vec!["name:to z", "id:9780261102217"]);
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for name in ["Hobbits", "The Hobbit", "Rake"] {
+            add_item(&conn, &item(name, "Hardback", None, None)).unwrap();
+        }
+        let candidates = find_duplicates(&conn, 0.0).unwrap();
+        let pairs: Vec<(i32, i32)> = candidates.iter().map(|c| (c.item.id, c.other.id)).collect();
+        assert_eq!(pairs, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_near_identical_photos_pair_items() {
+        use image::{Rgb, RgbImage};
+
+        let dir = std::env::temp_dir().join(format!("catalog-phash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // The same stripes photographed twice at different sizes, and a different picture
+        let stripes = |width: u32, height: u32| {
+            RgbImage::from_fn(width, height, |x, _| {
+                let band = (x * 6 / width) as u8;
+                Rgb([band * 40, 90, 255 - band * 40])
+            })
+        };
+        stripes(360, 240).save(dir.join("clock.png")).unwrap();
+        stripes(180, 120).save(dir.join("timepiece.jpg")).unwrap();
+        RgbImage::from_fn(300, 300, |_, y| Rgb([(y % 256) as u8, 40, 40]))
+            .save(dir.join("teaset.jpg"))
+            .unwrap();
+        // Blank pictures aren't hashed, so they don't all match each other
+        for blank in ["lamp.png", "vase.png"] {
+            RgbImage::from_pixel(100, 100, Rgb([250, 250, 250]))
+                .save(dir.join(blank))
+                .unwrap();
+        }
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, photo) in [
+            ("Grandmother's clock", "clock.png"),
+            ("Mantel timepiece", "timepiece.jpg"),
+            ("Tea set", "teaset.jpg"),
+            ("Lamp", "lamp.png"),
+            ("Vase", "vase.png"),
+            ("Rug", "rug.jpg"),
+        ] {
+            let id = add_item(&conn, &item(name, "Inherited", None, None)).unwrap();
+            add_attachment(&conn, id, &dir.join(photo).to_string_lossy(), None).unwrap();
+        }
+
+        let photos = find_similar_photos(&conn, DEFAULT_PHOTO_DISTANCE).unwrap();
+        assert_eq!(photos.len(), 1);
+        assert_eq!((photos[0].item_id, photos[0].other_item_id), (1, 2));
+
+        let candidates = find_duplicates(&conn, DEFAULT_MIN_SCORE).unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].other.name, "Mantel timepiece");
+        assert!(candidates[0].photo_match.is_some());
+
+        // Files that couldn't be hashed, such as the missing rug photo, aren't tried again
+        conn.execute(
+            "UPDATE attachments SET photo_hash = NULL, photo_hashed = 0",
+            [],
+        )
+        .unwrap();
+        assert_eq!(hash_photos(&conn).unwrap(), 3);
+        assert_eq!(hash_photos(&conn).unwrap(), 0);
+
+        // Deleted items' photos aren't matched
+        soft_delete_item(&conn, 2).unwrap();
+        let photos = find_similar_photos(&conn, 64).unwrap();
+        assert!(
+            photos
+                .iter()
+                .all(|m| m.item_id != 2 && m.other_item_id != 2)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{Months, NaiveDate};
+use serde::de;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Uncertainty assumed for "circa 1890" when none is given.
+pub const DEFAULT_CIRCA_YEARS: u32 = 5;
+
+/// A date known only as precisely as the evidence allows, e.g. `1890-05-03`, `1890-05`,
+/// `1890`, `1970s` or `c.1890±10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuzzyDate {
+    Day(NaiveDate),
+    Month {
+        year: i32,
+        month: u32,
+    },
+    Year(i32),
+    /// First year of the decade, e.g. 1970 for the 1970s
+    Decade(i32),
+    Circa {
+        year: i32,
+        uncertainty: u32,
+    },
+}
+
+impl FuzzyDate {
+    /// Earliest day the date could refer to. `None` if that's outside the range of dates
+    /// `NaiveDate` can hold, e.g. for `c.1890±4000000000`; parsing rejects such dates.
+    pub fn earliest(&self) -> Option<NaiveDate> {
+        match *self {
+            FuzzyDate::Day(d) => Some(d),
+            FuzzyDate::Month { year, month } => ymd(year, month, 1),
+            FuzzyDate::Year(year) | FuzzyDate::Decade(year) => ymd(year, 1, 1),
+            FuzzyDate::Circa { year, uncertainty } => {
+                ymd(year.checked_sub_unsigned(uncertainty)?, 1, 1)
+            }
+        }
+    }
+
+    /// Latest day the date could refer to. `None` if it's out of range, like `earliest()`.
+    pub fn latest(&self) -> Option<NaiveDate> {
+        match *self {
+            FuzzyDate::Day(d) => Some(d),
+            FuzzyDate::Month { year, month } => ymd(year, month, 1)?
+                .checked_add_months(Months::new(1))?
+                .pred_opt(),
+            FuzzyDate::Year(year) => ymd(year, 12, 31),
+            FuzzyDate::Decade(year) => ymd(year.checked_add(9)?, 12, 31),
+            FuzzyDate::Circa { year, uncertainty } => {
+                ymd(year.checked_add_unsigned(uncertainty)?, 12, 31)
+            }
+        }
+    }
+
+    /// Single best-guess day, used to derive an age.
+    pub fn representative(&self) -> Option<NaiveDate> {
+        match *self {
+            FuzzyDate::Day(d) => Some(d),
+            FuzzyDate::Month { year, month } => ymd(year, month, 1),
+            FuzzyDate::Year(year) | FuzzyDate::Circa { year, .. } => ymd(year, 1, 1),
+            FuzzyDate::Decade(year) => ymd(year.checked_add(5)?, 1, 1),
+        }
+    }
+
+    /// Whether every day the date can refer to is a valid `NaiveDate`.
+    pub fn in_range(&self) -> bool {
+        self.earliest().is_some() && self.latest().is_some() && self.representative().is_some()
+    }
+
+    /// Age in whole years as of `today`, based on `representative()`.
+    pub fn age_years(&self, today: NaiveDate) -> Option<u32> {
+        today.years_since(self.representative()?)
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, FuzzyDate::Day(_))
+    }
+}
+
+impl fmt::Display for FuzzyDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuzzyDate::Day(d) => write!(f, "{}", d.format("%Y-%m-%d")),
+            FuzzyDate::Month { year, month } => write!(f, "{:04}-{:02}", year, month),
+            FuzzyDate::Year(year) => write!(f, "{}", year),
+            FuzzyDate::Decade(year) => write!(f, "{}s", year),
+            FuzzyDate::Circa { year, uncertainty } => write!(f, "c.{}±{}", year, uncertainty),
+        }
+    }
+}
+
+impl FromStr for FuzzyDate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let date = parse(s)?;
+        if !date.in_range() {
+            return Err(format!("Date out of range: '{}'", s));
+        }
+        Ok(date)
+    }
+}
+
+// Any date of the accepted shapes, whether or not it is in range
+fn parse(s: &str) -> Result<FuzzyDate, String> {
+    let err = || format!("Invalid date: '{}'", s);
+    let text = s.trim().to_ascii_lowercase();
+
+    for prefix in ["circa", "ca.", "ca", "c."] {
+        if let Some(rest) = text.strip_prefix(prefix) {
+            let (year, uncertainty) = match rest.split_once(['±', '+']) {
+                Some((year, rest)) => {
+                    let years = rest.trim_start_matches("/-").trim();
+                    (year, years.parse().map_err(|_| err())?)
+                }
+                None => (rest, DEFAULT_CIRCA_YEARS),
+            };
+            let year = year.trim().parse().map_err(|_| err())?;
+            return Ok(FuzzyDate::Circa { year, uncertainty });
+        }
+    }
+
+    if let Some(decade) = text.strip_suffix("'s").or(text.strip_suffix('s')) {
+        let year: i32 = decade.parse().map_err(|_| err())?;
+        if year % 10 != 0 {
+            return Err(err());
+        }
+        return Ok(FuzzyDate::Decade(year));
+    }
+
+    let parts: Vec<&str> = text.split('-').collect();
+    match parts.as_slice() {
+        [year] => Ok(FuzzyDate::Year(year.parse().map_err(|_| err())?)),
+        [year, month] => {
+            let year = year.parse().map_err(|_| err())?;
+            let month = month.parse().map_err(|_| err())?;
+            if !(1..=12).contains(&month) {
+                return Err(err());
+            }
+            Ok(FuzzyDate::Month { year, month })
+        }
+        [_, _, _] => Ok(FuzzyDate::Day(
+            NaiveDate::parse_from_str(&text, "%Y-%m-%d").map_err(|_| err())?,
+        )),
+        _ => Err(err()),
+    }
+}
+
+impl Serialize for FuzzyDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FuzzyDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        FuzzyDate::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Latest date an item can have been made to be at least `years` old on `today`. `None` when
+/// that's before the earliest representable date, so no item can be that old.
+pub(crate) fn made_by_for_age(today: NaiveDate, years: u32) -> Option<NaiveDate> {
+    today.checked_sub_months(Months::new(years.checked_mul(12)?))
+}
+
+/// Earliest date an item can have been made to be at most `years` old on `today`. `None` when
+/// that's before the earliest representable date, so every dated item is young enough.
+pub(crate) fn made_after_for_age(today: NaiveDate, years: u32) -> Option<NaiveDate> {
+    made_by_for_age(today, years.checked_add(1)?)?.succ_opt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for (input, expected) in [
+            ("1890-05-03", "1890-05-03"),
+            ("1890-5", "1890-05"),
+            ("1890", "1890"),
+            ("1970s", "1970s"),
+            ("circa 1890", "c.1890±5"),
+            ("c. 1890 +/- 10", "c.1890±10"),
+            ("c.1890±2", "c.1890±2"),
+        ] {
+            let date = FuzzyDate::from_str(input).unwrap();
+            assert_eq!(date.to_string(), expected, "{}", input);
+            assert_eq!(FuzzyDate::from_str(&date.to_string()).unwrap(), date);
+        }
+        assert!(FuzzyDate::from_str("1975s").is_err());
+        assert!(FuzzyDate::from_str("1890-13").is_err());
+        assert!(FuzzyDate::from_str("someday").is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let decade = FuzzyDate::from_str("1970s").unwrap();
+        assert_eq!(decade.earliest(), ymd(1970, 1, 1));
+        assert_eq!(decade.latest(), ymd(1979, 12, 31));
+
+        let month = FuzzyDate::from_str("2024-02").unwrap();
+        assert_eq!(month.latest(), ymd(2024, 2, 29));
+
+        let circa = FuzzyDate::from_str("c.1890±10").unwrap();
+        assert_eq!(circa.earliest(), ymd(1880, 1, 1));
+        assert_eq!(circa.latest(), ymd(1900, 12, 31));
+    }
+
+    #[test]
+    fn test_age_does_not_go_stale() {
+        let made = FuzzyDate::Year(1990);
+        let today = |year| ymd(year, 6, 1).unwrap();
+        assert_eq!(made.age_years(today(2025)), Some(35));
+        assert_eq!(made.age_years(today(2026)), Some(36));
+        assert_eq!(FuzzyDate::Year(2100).age_years(today(2025)), None);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        for input in [
+            "c.1890±4294967295",
+            "c.2147483647±1",
+            "2147483640s",
+            "300000",
+        ] {
+            assert!(FuzzyDate::from_str(input).is_err(), "{}", input);
+        }
+        let huge = FuzzyDate::Circa {
+            year: i32::MAX,
+            uncertainty: u32::MAX,
+        };
+        assert_eq!(huge.earliest(), None);
+        assert_eq!(huge.latest(), None);
+        assert!(!huge.in_range());
+
+        let today = ymd(2025, 6, 1).unwrap();
+        assert_eq!(made_by_for_age(today, u32::MAX), None);
+        assert_eq!(made_after_for_age(today, u32::MAX), None);
+        assert_eq!(made_by_for_age(today, 25), ymd(2000, 6, 1));
+    }
+}
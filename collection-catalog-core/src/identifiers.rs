@@ -0,0 +1,287 @@
+use crate::models::Item;
+use rusqlite::{Connection, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IdentifierKind {
+    /// ISBN-10 or ISBN-13
+    Isbn,
+    /// UPC-A, 12 digits
+    Upc,
+    /// EAN-13 or EAN-8
+    Ean,
+}
+
+impl fmt::Display for IdentifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IdentifierKind::Isbn => "ISBN",
+            IdentifierKind::Upc => "UPC",
+            IdentifierKind::Ean => "EAN",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for IdentifierKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "ISBN" => Ok(IdentifierKind::Isbn),
+            "UPC" => Ok(IdentifierKind::Upc),
+            "EAN" => Ok(IdentifierKind::Ean),
+            _ => Err(format!("Unknown identifier type: '{}'", s)),
+        }
+    }
+}
+
+/// A product code with a valid check digit, stored without hyphens or spaces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Identifier {
+    pub kind: IdentifierKind,
+    pub value: String,
+}
+
+// Weights alternate 3, 1 counting left from the check digit, for every GTIN length
+fn gtin_valid(digits: &str) -> bool {
+    let values: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    if values.len() != digits.len() || values.len() < 2 {
+        return false;
+    }
+    let (check, body) = values.split_last().unwrap();
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+    (10 - sum % 10) % 10 == *check
+}
+
+fn isbn10_valid(code: &str) -> bool {
+    let chars: Vec<char> = code.chars().collect();
+    if chars.len() != 10 {
+        return false;
+    }
+    let mut sum = 0;
+    for (i, c) in chars.iter().enumerate() {
+        let value = match c {
+            'X' if i == 9 => 10,
+            c => match c.to_digit(10) {
+                Some(d) => d,
+                None => return false,
+            },
+        };
+        sum += value * (10 - i as u32);
+    }
+    sum % 11 == 0
+}
+
+fn is_bookland(code: &str) -> bool {
+    code.len() == 13 && (code.starts_with("978") || code.starts_with("979"))
+}
+
+impl Identifier {
+    /// Checks `code` as the given kind, ignoring hyphens and spaces.
+    pub fn new(kind: IdentifierKind, code: &str) -> Result<Self, String> {
+        let value: String = code
+            .chars()
+            .filter(|c| !matches!(c, '-' | ' '))
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let valid = match kind {
+            IdentifierKind::Isbn => {
+                isbn10_valid(&value) || (is_bookland(&value) && gtin_valid(&value))
+            }
+            IdentifierKind::Upc => value.len() == 12 && gtin_valid(&value),
+            IdentifierKind::Ean => matches!(value.len(), 8 | 13) && gtin_valid(&value),
+        };
+        if valid {
+            Ok(Identifier { kind, value })
+        } else {
+            Err(format!("Invalid {}: '{}'", kind, code))
+        }
+    }
+
+    /// The ISBN-13 of a book. A 978/979 EAN-13 already is one, and an ISBN-10 is converted.
+    pub fn isbn13(&self) -> Option<String> {
+        match self.kind {
+            IdentifierKind::Isbn if self.value.len() == 10 => {
+                let body = format!("978{}", &self.value[..9]);
+                let sum: u32 = body
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| c.to_digit(10).unwrap() * if i % 2 == 0 { 1 } else { 3 })
+                    .sum();
+                Some(format!("{}{}", body, (10 - sum % 10) % 10))
+            }
+            IdentifierKind::Isbn | IdentifierKind::Ean if is_bookland(&self.value) => {
+                Some(self.value.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// The form used to match codes with each other: ISBN-13 for books, otherwise the
+    /// 13 digit EAN, with UPC-A codes padded with a leading zero.
+    pub fn normalized(&self) -> String {
+        match (self.isbn13(), self.kind) {
+            (Some(isbn), _) => isbn,
+            (None, IdentifierKind::Upc) => format!("0{}", self.value),
+            (None, _) => self.value.clone(),
+        }
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let kind: String = row.get("kind")?;
+        Ok(Identifier {
+            kind: kind.parse().map_err(|e: String| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+            })?,
+            value: row.get("value")?,
+        })
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.value)
+    }
+}
+
+/// Works out the kind from the code: `ISBN 0-306-40615-2`, a plain ISBN-10 or
+/// 978/979 EAN-13 is an ISBN, 12 digits a UPC and other 8 or 13 digit codes an EAN.
+impl FromStr for Identifier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((kind, code)) = s.split_once([' ', ':'])
+            && let Ok(kind) = kind.parse()
+        {
+            return Identifier::new(kind, code.trim());
+        }
+        let digits: String = s.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+        let kind = match digits.len() {
+            10 => IdentifierKind::Isbn,
+            13 if is_bookland(&digits) => IdentifierKind::Isbn,
+            12 => IdentifierKind::Upc,
+            _ => IdentifierKind::Ean,
+        };
+        Identifier::new(kind, s)
+    }
+}
+
+/// An identifier stored against an item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemIdentifier {
+    pub id: i32,
+    pub item_id: i32,
+    pub identifier: Identifier,
+}
+
+impl ItemIdentifier {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ItemIdentifier {
+            id: row.get("id")?,
+            item_id: row.get("item_id")?,
+            identifier: Identifier::from_row(row)?,
+        })
+    }
+}
+
+/// Stores `identifier` against an item. Adding the same code twice keeps one copy.
+pub fn add_identifier(conn: &Connection, item_id: i32, identifier: &Identifier) -> Result<i32> {
+    conn.execute(
+        "INSERT OR IGNORE INTO item_identifiers (item_id, kind, value, normalized)
+        VALUES (?1, ?2, ?3, ?4)",
+        params![
+            item_id,
+            identifier.kind.to_string(),
+            identifier.value,
+            identifier.normalized()
+        ],
+    )?;
+    conn.query_row(
+        "SELECT id FROM item_identifiers WHERE item_id = ?1 AND normalized = ?2",
+        params![item_id, identifier.normalized()],
+        |row| row.get(0),
+    )
+}
+
+pub fn get_identifiers(conn: &Connection, item_id: i32) -> Result<Vec<ItemIdentifier>> {
+    let mut stmt = conn.prepare("SELECT * FROM item_identifiers WHERE item_id = ?1 ORDER BY id")?;
+    let rows = stmt.query_map(params![item_id], ItemIdentifier::from_row)?;
+    rows.collect()
+}
+
+pub fn remove_identifier(conn: &Connection, id: i32) -> Result<()> {
+    conn.execute("DELETE FROM item_identifiers WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Items with the same code, so an ISBN-10 finds the book stored under its ISBN-13. Deleted
+/// items are left out.
+pub fn find_items_by_identifier(conn: &Connection, identifier: &Identifier) -> Result<Vec<Item>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM items WHERE deleted = 0 AND id IN
+            (SELECT item_id FROM item_identifiers WHERE normalized = ?1)
+        ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![identifier.normalized()], Item::from_row)?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db, soft_delete_item};
+
+    #[test]
+    fn test_identifier_checksums() {
+        let isbn: Identifier = "0-306-40615-2".parse().unwrap();
+        assert_eq!(isbn.kind, IdentifierKind::Isbn);
+        assert_eq!(isbn.isbn13().unwrap(), "9780306406157");
+        assert!("0-306-40615-3".parse::<Identifier>().is_err());
+        assert_eq!(
+            "978-0-306-40615-7".parse::<Identifier>().unwrap().kind,
+            IdentifierKind::Isbn
+        );
+        assert_eq!(
+            "036000291452".parse::<Identifier>().unwrap().normalized(),
+            "0036000291452"
+        );
+        assert!("4006381333931".parse::<Identifier>().is_ok());
+        assert!("96385074".parse::<Identifier>().is_ok());
+        assert!(Identifier::new(IdentifierKind::Upc, "4006381333931").is_err());
+        assert!("123".parse::<Identifier>().is_err());
+        assert_eq!(
+            "isbn 080442957X".parse::<Identifier>().unwrap().to_string(),
+            "ISBN 080442957X"
+        );
+    }
+
+    #[test]
+    fn test_find_items_by_identifier() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let item = Item {
+            name: "Hobbit".to_string(),
+            description: "test".to_string(),
+            ..Default::default()
+        };
+        let id = add_item(&conn, &item).unwrap();
+        let isbn13 = "9780306406157".parse().unwrap();
+        add_identifier(&conn, id, &isbn13).unwrap();
+        add_identifier(&conn, id, &isbn13).unwrap();
+        assert_eq!(get_identifiers(&conn, id).unwrap().len(), 1);
+
+        let isbn10 = "0306406152".parse().unwrap();
+        assert_eq!(find_items_by_identifier(&conn, &isbn10).unwrap().len(), 1);
+        soft_delete_item(&conn, id).unwrap();
+        assert!(find_items_by_identifier(&conn, &isbn10).unwrap().is_empty());
+    }
+}
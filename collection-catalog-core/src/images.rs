@@ -0,0 +1,432 @@
+//! Thumbnails and EXIF details for photo attachments.
+//!
+//! JPEG, PNG and WebP files are turned upright using their EXIF orientation before anything
+//! else is done with them. Thumbnails are written as JPEG files named by attachment id and size
+//! in a cache directory chosen by the caller, and made again when the photo changes.
+
+use crate::attachments::{Attachment, get_attachments};
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::{NaiveDate, NaiveDateTime};
+use exif::{In, Tag, Value};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The longest side of a thumbnail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThumbnailSize {
+    /// For lists of items
+    #[default]
+    Small,
+    /// For an item's photo gallery
+    Medium,
+    /// For viewing one photo
+    Large,
+}
+
+impl ThumbnailSize {
+    pub fn pixels(&self) -> u32 {
+        match self {
+            ThumbnailSize::Small => 96,
+            ThumbnailSize::Medium => 256,
+            ThumbnailSize::Large => 1024,
+        }
+    }
+}
+
+impl fmt::Display for ThumbnailSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ThumbnailSize::Small => "small",
+            ThumbnailSize::Medium => "medium",
+            ThumbnailSize::Large => "large",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ThumbnailSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "small" => Ok(ThumbnailSize::Small),
+            "medium" => Ok(ThumbnailSize::Medium),
+            "large" => Ok(ThumbnailSize::Large),
+            _ => Err(format!("Unknown thumbnail size: '{}'", s)),
+        }
+    }
+}
+
+/// What a photo's file says about it. Width and height are for the upright photo.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub width: u32,
+    pub height: u32,
+    /// When the photo was taken, in the camera's local time
+    pub captured: Option<NaiveDateTime>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+}
+
+impl ImageMetadata {
+    /// Make and model together, without repeating a make the model already starts with.
+    pub fn camera(&self) -> Option<String> {
+        match (&self.camera_make, &self.camera_model) {
+            (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => {
+                Some(model.clone())
+            }
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.clone().or(model.clone()),
+        }
+    }
+}
+
+/// Whether thumbnails and metadata can be read from the file, going by its extension.
+pub fn is_supported_image(path: &Path) -> bool {
+    ImageFormat::from_path(path)
+        .is_ok_and(|f| matches!(f, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP))
+}
+
+fn open_decoder(path: &Path) -> AnyResult<impl ImageDecoder> {
+    if !is_supported_image(path) {
+        return Err(anyhow!(
+            "{} is not a JPEG, PNG or WebP image",
+            path.display()
+        ));
+    }
+    Ok(ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?)
+}
+
+/// Loads a photo turned the right way up.
+pub fn load_image(path: &Path) -> AnyResult<DynamicImage> {
+    let mut decoder = open_decoder(path)?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn exif_text(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    match &field.value {
+        Value::Ascii(parts) => {
+            let text = String::from_utf8_lossy(parts.first()?);
+            let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+            (!text.is_empty()).then(|| text.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn exif_date(exif: &exif::Exif, tag: Tag) -> Option<NaiveDateTime> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(parts) = &field.value else {
+        return None;
+    };
+    let dt = exif::DateTime::from_ascii(parts.first()?).ok()?;
+    NaiveDate::from_ymd_opt(dt.year.into(), dt.month.into(), dt.day.into())?.and_hms_opt(
+        dt.hour.into(),
+        dt.minute.into(),
+        dt.second.into(),
+    )
+}
+
+/// Reads the size, capture date and camera of a photo. Photos without EXIF data only have a
+/// size.
+pub fn read_image_metadata(path: &Path) -> AnyResult<ImageMetadata> {
+    let mut decoder = open_decoder(path)?;
+    let (width, height) = decoder.dimensions();
+    let exif = decoder
+        .exif_metadata()?
+        .and_then(|raw| exif::Reader::new().read_raw(raw).ok());
+    let Some(exif) = exif else {
+        return Ok(ImageMetadata {
+            width,
+            height,
+            ..Default::default()
+        });
+    };
+
+    // Orientations 5 to 8 turn the photo on its side
+    let sideways = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .is_some_and(|o| (5..=8).contains(&o));
+    let (width, height) = if sideways {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    Ok(ImageMetadata {
+        width,
+        height,
+        captured: exif_date(&exif, Tag::DateTimeOriginal)
+            .or_else(|| exif_date(&exif, Tag::DateTimeDigitized))
+            .or_else(|| exif_date(&exif, Tag::DateTime)),
+        camera_make: exif_text(&exif, Tag::Make),
+        camera_model: exif_text(&exif, Tag::Model),
+        lens: exif_text(&exif, Tag::LensModel),
+    })
+}
+
+// Grey levels between the darkest and lightest parts of a picture, below which it's too
+// plain to hash
+const MIN_HASH_CONTRAST: u8 = 8;
+
+/// A 64-bit difference hash of a photo. The same picture at another size or quality, or
+/// lightly edited, hashes only a few bits differently. `None` for a blank or nearly uniform
+/// picture, which would otherwise match every other one.
+pub fn perceptual_hash(image: &DynamicImage) -> Option<u64> {
+    // Each bit says whether a pixel is darker than the one to its right in a 9x8 grey copy
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let (darkest, lightest) = small.pixels().fold((u8::MAX, u8::MIN), |(lo, hi), p| {
+        (lo.min(p.0[0]), hi.max(p.0[0]))
+    });
+    if lightest - darkest < MIN_HASH_CONTRAST {
+        return None;
+    }
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let darker = small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | darker as u64;
+        }
+    }
+    Some(hash)
+}
+
+/// How many bits two perceptual hashes differ by, from 0 (the same picture) to 64.
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Hashes photo attachments that haven't been tried yet, such as ones attached before hashes
+/// were kept. Files that can't be hashed aren't tried again. Returns how many were hashed.
+pub fn hash_photos(conn: &Connection) -> AnyResult<usize> {
+    let mut stmt = conn.prepare("SELECT id, path FROM attachments WHERE photo_hashed = 0")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut hashed = 0;
+    for (id, path) in rows {
+        if record_photo_hash(conn, id, Path::new(&path))? {
+            hashed += 1;
+        }
+    }
+    Ok(hashed)
+}
+
+/// Stores the perceptual hash of an attachment and marks it as tried. Files that aren't
+/// photos, can't be read or are blank are left without a hash. Returns whether one was stored.
+pub(crate) fn record_photo_hash(conn: &Connection, id: i32, path: &Path) -> rusqlite::Result<bool> {
+    let hash = match is_supported_image(path) {
+        true => load_image(path)
+            .ok()
+            .and_then(|image| perceptual_hash(&image)),
+        false => None,
+    };
+    conn.execute(
+        "UPDATE attachments SET photo_hash = ?2, photo_hashed = 1 WHERE id = ?1",
+        params![id, hash.map(|h| h as i64)],
+    )?;
+    Ok(hash.is_some())
+}
+
+/// Where the thumbnail of an attachment at a size is cached.
+pub fn thumbnail_path(cache_dir: &Path, attachment_id: i32, size: ThumbnailSize) -> PathBuf {
+    cache_dir.join(format!("{}-{}.jpg", attachment_id, size))
+}
+
+// JPEG has no transparency, so transparent parts become white
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// The cached thumbnail of an image attachment, made first if it's missing or older than the
+/// photo.
+pub fn thumbnail(
+    attachment: &Attachment,
+    size: ThumbnailSize,
+    cache_dir: &Path,
+) -> AnyResult<PathBuf> {
+    let source = Path::new(&attachment.path);
+    let path = thumbnail_path(cache_dir, attachment.id, size);
+    let source_modified = fs::metadata(source)?.modified()?;
+    if fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|cached| cached >= source_modified)
+    {
+        return Ok(path);
+    }
+
+    let small = shrink(load_image(source)?, size);
+    fs::create_dir_all(cache_dir)?;
+    flatten(&small).save_with_format(&path, ImageFormat::Jpeg)?;
+    Ok(path)
+}
+
+fn shrink(image: DynamicImage, size: ThumbnailSize) -> DynamicImage {
+    let pixels = size.pixels();
+    if image.width() > pixels || image.height() > pixels {
+        image.thumbnail(pixels, pixels)
+    } else {
+        image
+    }
+}
+
+/// A photo of any supported format shrunk to a size and encoded as JPEG, without touching the
+/// thumbnail cache. For embedding photos in reports.
+pub fn jpeg_thumbnail(path: &Path, size: ThumbnailSize) -> AnyResult<Vec<u8>> {
+    let small = shrink(load_image(path)?, size);
+    let mut data = Cursor::new(vec![]);
+    flatten(&small).write_to(&mut data, ImageFormat::Jpeg)?;
+    Ok(data.into_inner())
+}
+
+/// An item's first photo whose file is still there, if it has one.
+pub fn item_photo(conn: &Connection, item_id: i32) -> rusqlite::Result<Option<Attachment>> {
+    Ok(get_attachments(conn, item_id)?
+        .into_iter()
+        .find(|a| is_supported_image(Path::new(&a.path)) && Path::new(&a.path).exists()))
+}
+
+/// The thumbnail of an item's first photo, if it has one.
+pub fn item_thumbnail(
+    conn: &Connection,
+    item_id: i32,
+    size: ThumbnailSize,
+    cache_dir: &Path,
+) -> AnyResult<Option<PathBuf>> {
+    item_photo(conn, item_id)?
+        .map(|photo| thumbnail(&photo, size, cache_dir))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachments::add_attachment;
+    use crate::db::{add_item, backfill_image_metadata, init_db};
+    use crate::models::Item;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ExtendedColorType, ImageEncoder};
+
+    // A little-endian TIFF block with a make, model, orientation and capture date
+    fn exif_block(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        let entry = |tiff: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32| {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(kind.to_le_bytes());
+            tiff.extend(count.to_le_bytes());
+            tiff.extend(value.to_le_bytes());
+        };
+        // IFD0 at 8 with 4 entries ends at 62, the Exif IFD with 1 entry ends at 80
+        tiff.extend(4u16.to_le_bytes());
+        entry(&mut tiff, 0x010F, 2, 6, 80);
+        entry(&mut tiff, 0x0110, 2, 13, 86);
+        entry(&mut tiff, 0x0112, 3, 1, orientation as u32);
+        entry(&mut tiff, 0x8769, 4, 1, 62);
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(1u16.to_le_bytes());
+        entry(&mut tiff, 0x9003, 2, 20, 99);
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"Canon\0Canon EOS 5D\x002023:06:14 09:30:00\0");
+        tiff
+    }
+
+    fn write_jpeg(path: &Path, width: u32, height: u32, exif: Option<Vec<u8>>) {
+        let pixels = RgbImage::from_pixel(width, height, Rgb([200, 40, 40]));
+        let mut encoder = JpegEncoder::new(fs::File::create(path).unwrap());
+        if let Some(exif) = exif {
+            encoder.set_exif_metadata(exif).unwrap();
+        }
+        encoder
+            .write_image(&pixels, width, height, ExtendedColorType::Rgb8)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_metadata_and_upright_thumbnails() {
+        let dir = std::env::temp_dir().join(format!("catalog-images-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("photo.jpg");
+        write_jpeg(&photo, 400, 200, Some(exif_block(6)));
+
+        let metadata = read_image_metadata(&photo).unwrap();
+        assert_eq!((metadata.width, metadata.height), (200, 400));
+        // A plain red picture is too featureless to hash
+        assert_eq!(perceptual_hash(&load_image(&photo).unwrap()), None);
+        assert_eq!(
+            metadata.captured.unwrap().to_string(),
+            "2023-06-14 09:30:00"
+        );
+        assert_eq!(metadata.camera().as_deref(), Some("Canon EOS 5D"));
+
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let item = Item {
+            name: "Camera".to_string(),
+            description: "Photographed".to_string(),
+            ..Default::default()
+        };
+        let item_id = add_item(&conn, &item).unwrap();
+        add_attachment(&conn, item_id, "/missing/receipt.pdf", None).unwrap();
+        let id = add_attachment(&conn, item_id, &photo.to_string_lossy(), None).unwrap();
+        let attachment = &get_attachments(&conn, item_id).unwrap()[1];
+        assert_eq!(attachment.camera.as_deref(), Some("Canon EOS 5D"));
+        assert!(attachment.captured.is_some());
+
+        // Photos attached before the details were kept get them when the database is upgraded
+        conn.execute("UPDATE attachments SET captured = NULL, camera = NULL", [])
+            .unwrap();
+        backfill_image_metadata(&conn).unwrap();
+        let attachment = &get_attachments(&conn, item_id).unwrap()[1];
+        assert_eq!(attachment.camera.as_deref(), Some("Canon EOS 5D"));
+
+        let cache = dir.join("thumbnails");
+        let path = item_thumbnail(&conn, item_id, ThumbnailSize::Small, &cache)
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, thumbnail_path(&cache, id, ThumbnailSize::Small));
+        let thumb = image::open(&path).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (48, 96));
+
+        // Smaller photos aren't enlarged, and ones without EXIF keep their size
+        let plain = dir.join("plain.jpg");
+        write_jpeg(&plain, 30, 20, None);
+        let metadata = read_image_metadata(&plain).unwrap();
+        assert_eq!(
+            (metadata.width, metadata.height, metadata.captured),
+            (30, 20, None)
+        );
+        let attachment = Attachment {
+            id: 99,
+            path: plain.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let thumb = image::open(thumbnail(&attachment, ThumbnailSize::Large, &cache).unwrap());
+        assert_eq!(thumb.unwrap().width(), 30);
+        assert!(read_image_metadata(Path::new("notes.txt")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+//! JSON export, written one item at a time so a streamed catalog never has to fit in memory.
+//!
+//! The output is an array with one item object per line, with the same fields as `Item`.
+
+use crate::models::Item;
+use anyhow::Result as AnyResult;
+use rusqlite::Result;
+use std::borrow::Borrow;
+use std::io::{BufWriter, Write};
+
+/// Writes items to `out` as they arrive, e.g. from `stream_filtered_items`. Returns how many
+/// items were written.
+pub fn write_json<W: Write>(
+    items: impl IntoIterator<Item = Result<impl Borrow<Item>>>,
+    out: W,
+) -> AnyResult<usize> {
+    let mut out = BufWriter::new(out);
+    let mut count = 0;
+    out.write_all(b"[")?;
+    for item in items {
+        out.write_all(if count == 0 { b"\n  " } else { b",\n  " })?;
+        serde_json::to_writer(&mut out, item?.borrow())?;
+        count += 1;
+    }
+    out.write_all(if count == 0 { b"]\n" } else { b"\n]\n" })?;
+    out.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db, stream_filtered_items};
+    use crate::models::{ItemCategory, ItemFilter};
+    use crate::query::{parse_query, stream_query_items};
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_write_streamed_items() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for name in ["Atlas", "Almanac", "Clock"] {
+            let item = Item {
+                name: name.to_string(),
+                description: "desc".to_string(),
+                category: if name == "Clock" {
+                    ItemCategory::Antique
+                } else {
+                    ItemCategory::Book
+                },
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+
+        let mut out = vec![];
+        let count = stream_filtered_items(&conn, ItemFilter::default(), |items| {
+            write_json(items, &mut out)
+        })
+        .unwrap();
+        assert_eq!(count, 3);
+        let items: Vec<Item> = serde_json::from_slice(&out).unwrap();
+        let names: Vec<&str> = items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Atlas", "Almanac", "Clock"]);
+
+        let query = parse_query("category:Book").unwrap();
+        let mut out = vec![];
+        assert_eq!(
+            stream_query_items(&conn, &query, |items| write_json(items, &mut out)).unwrap(),
+            2
+        );
+
+        let mut out = vec![];
+        write_json(std::iter::empty::<Result<Item>>(), &mut out).unwrap();
+        assert_eq!(out, b"[]\n");
+    }
+}
@@ -0,0 +1,588 @@
+use crate::db::get_filtered_items;
+use crate::models::{Item, ItemFilter};
+use crate::pdf::{Font, PdfDocument, wrap_text};
+use anyhow::{Result as AnyResult, anyhow};
+use qrcode::{Color, EcLevel, QrCode};
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+const LABEL_SCHEME: &str = "collection-catalog://";
+
+/// Identifies this catalog on its labels, so a label scanned into a different catalog isn't
+/// mistaken for one of its items. Created the first time it's needed.
+pub fn get_catalog_id(conn: &Connection) -> Result<String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO settings (key, value)
+        VALUES ('catalog_id', lower(hex(randomblob(8))))",
+        [],
+    )?;
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = 'catalog_id'",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// What a label's QR code holds, written `collection-catalog://<catalog id>/item/<item id>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelCode {
+    pub catalog_id: String,
+    pub item_id: i32,
+}
+
+impl fmt::Display for LabelCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}/item/{}",
+            LABEL_SCHEME, self.catalog_id, self.item_id
+        )
+    }
+}
+
+impl FromStr for LabelCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Not a catalog label: '{}'", s);
+        let (catalog_id, item_id) = s
+            .trim()
+            .strip_prefix(LABEL_SCHEME)
+            .and_then(|rest| rest.split_once("/item/"))
+            .ok_or_else(err)?;
+        if catalog_id.is_empty() {
+            return Err(err());
+        }
+        Ok(LabelCode {
+            catalog_id: catalog_id.to_string(),
+            item_id: item_id.parse().map_err(|_| err())?,
+        })
+    }
+}
+
+/// A sheet of labels, measured in points (72 to the inch) from the top left of the page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelTemplate {
+    pub name: String,
+    pub page_width: f32,
+    pub page_height: f32,
+    pub columns: u32,
+    pub rows: u32,
+    pub label_width: f32,
+    pub label_height: f32,
+    /// Page edge to the first label
+    pub margin_left: f32,
+    pub margin_top: f32,
+    /// Distance from one label's left or top edge to the next one's, including the gap
+    pub pitch_x: f32,
+    pub pitch_y: f32,
+}
+
+const INCH: f32 = 72.0;
+const MM: f32 = 72.0 / 25.4;
+
+impl LabelTemplate {
+    /// Names of the built in templates.
+    pub const BUILTIN: &[&str] = &["avery-5160", "avery-5163", "avery-l7160"];
+
+    /// A built in template, by name with or without the `avery-` prefix.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let name = name.strip_prefix("avery-").unwrap_or(&name);
+        let template = |name: &str, page: (f32, f32), grid: (u32, u32), size, margin, pitch| {
+            let ((label_width, label_height), (margin_left, margin_top), (pitch_x, pitch_y)) =
+                (size, margin, pitch);
+            LabelTemplate {
+                name: name.to_string(),
+                page_width: page.0,
+                page_height: page.1,
+                columns: grid.0,
+                rows: grid.1,
+                label_width,
+                label_height,
+                margin_left,
+                margin_top,
+                pitch_x,
+                pitch_y,
+            }
+        };
+        let letter = (8.5 * INCH, 11.0 * INCH);
+        let a4 = (210.0 * MM, 297.0 * MM);
+        match name {
+            // 30 address labels, 1" x 2 5/8"
+            "5160" => Some(template(
+                "avery-5160",
+                letter,
+                (3, 10),
+                (2.625 * INCH, 1.0 * INCH),
+                (0.1875 * INCH, 0.5 * INCH),
+                (2.75 * INCH, 1.0 * INCH),
+            )),
+            // 10 shipping labels, 2" x 4", for boxes
+            "5163" => Some(template(
+                "avery-5163",
+                letter,
+                (2, 5),
+                (4.0 * INCH, 2.0 * INCH),
+                (0.15625 * INCH, 0.5 * INCH),
+                (4.1875 * INCH, 2.0 * INCH),
+            )),
+            // 21 A4 labels, 63.5 x 38.1 mm
+            "l7160" => Some(template(
+                "avery-l7160",
+                a4,
+                (3, 7),
+                (63.5 * MM, 38.1 * MM),
+                (7.25 * MM, 15.15 * MM),
+                (66.04 * MM, 38.1 * MM),
+            )),
+            _ => None,
+        }
+    }
+
+    /// A built in template, or one read from a JSON file when `name` ends in `.json`.
+    pub fn find(name: &str) -> AnyResult<Self> {
+        if name.to_ascii_lowercase().ends_with(".json") {
+            let text = fs::read_to_string(name)
+                .map_err(|e| anyhow!("Cannot read label template {}: {}", name, e))?;
+            let template: LabelTemplate = serde_json::from_str(&text)
+                .map_err(|e| anyhow!("Invalid label template {}: {}", name, e))?;
+            return Ok(template);
+        }
+        Self::builtin(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown label template '{}', expected one of {} or a .json file",
+                name,
+                Self::BUILTIN.join(", ")
+            )
+        })
+    }
+
+    pub fn per_page(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.columns == 0 || self.rows == 0 {
+            errors.push("A label sheet needs at least one row and column.".to_string());
+        }
+        if self.label_width <= 0.0 || self.label_height <= 0.0 {
+            errors.push("Label width and height must be positive.".to_string());
+        }
+        if self.pitch_x < self.label_width || self.pitch_y < self.label_height {
+            errors.push("Labels cannot overlap each other.".to_string());
+        }
+        let last = |margin: f32, count: u32, pitch: f32, size: f32| {
+            margin + count.saturating_sub(1) as f32 * pitch + size
+        };
+        // Half a point of slack for templates measured in millimetres
+        if last(
+            self.margin_left,
+            self.columns,
+            self.pitch_x,
+            self.label_width,
+        ) > self.page_width + 0.5
+            || last(self.margin_top, self.rows, self.pitch_y, self.label_height)
+                > self.page_height + 0.5
+        {
+            errors.push("Labels don't fit on the page.".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl Default for LabelTemplate {
+    fn default() -> Self {
+        LabelTemplate::builtin("avery-5160").unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LabelOptions {
+    pub template: LabelTemplate,
+    /// Labels to leave blank at the start of the first sheet, to reuse a partly used one
+    pub skip: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelFormat {
+    Pdf,
+    Svg,
+}
+
+impl LabelFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".pdf") {
+            Some(LabelFormat::Pdf)
+        } else if lower.ends_with(".svg") {
+            Some(LabelFormat::Svg)
+        } else {
+            None
+        }
+    }
+}
+
+// Blank modules kept around the QR code so scanners can find its edges
+const QUIET_MODULES: usize = 2;
+
+struct TextLine {
+    x: f32,
+    /// Baseline, from the top of the page
+    y: f32,
+    size: f32,
+    font: Font,
+    text: String,
+}
+
+/// One label's QR modules and text, positioned from the top left of its page.
+struct LabelParts {
+    page: usize,
+    module: f32,
+    /// Top left corner of each dark module
+    modules: Vec<(f32, f32)>,
+    lines: Vec<TextLine>,
+}
+
+fn layout_label(
+    item: &Item,
+    catalog_id: &str,
+    template: &LabelTemplate,
+    slot: usize,
+) -> AnyResult<LabelParts> {
+    let per_page = template.per_page();
+    let (page, index) = (slot / per_page, slot % per_page);
+    let column = (index % template.columns as usize) as f32;
+    let row = (index / template.columns as usize) as f32;
+    let left = template.margin_left + column * template.pitch_x;
+    let top = template.margin_top + row * template.pitch_y;
+    let (width, height) = (template.label_width, template.label_height);
+    let padding = (width.min(height) * 0.08).clamp(3.0, 8.0);
+
+    let code = LabelCode {
+        catalog_id: catalog_id.to_string(),
+        item_id: item.id,
+    };
+    let qr = QrCode::with_error_correction_level(code.to_string(), EcLevel::M)
+        .map_err(|e| anyhow!("Cannot make a QR code for item {}: {}", item.id, e))?;
+    let qr_size = (height - 2.0 * padding).min(width - 2.0 * padding);
+    let module = qr_size / (qr.width() + 2 * QUIET_MODULES) as f32;
+    let colors = qr.to_colors();
+    let modules = colors
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == Color::Dark)
+        .map(|(i, _)| {
+            let (x, y) = (
+                i % qr.width() + QUIET_MODULES,
+                i / qr.width() + QUIET_MODULES,
+            );
+            (
+                left + padding + x as f32 * module,
+                top + padding + y as f32 * module,
+            )
+        })
+        .collect();
+
+    // Name and id beside the code, when there's room for them
+    let mut lines = vec![];
+    let text_left = left + 2.0 * padding + qr_size;
+    let text_width = left + width - padding - text_left;
+    if text_width >= 30.0 {
+        let size = (height / 6.0).clamp(6.0, 11.0);
+        let id_line = size * 1.2;
+        let max_lines = (((height - 2.0 * padding - id_line) / (size * 1.2)) as usize).max(1);
+        let mut name = wrap_text(&item.name, size, text_width);
+        if name.len() > max_lines {
+            name.truncate(max_lines);
+            name[max_lines - 1].push_str("...");
+        }
+        let mut y = top + padding + size;
+        for text in name {
+            lines.push(TextLine {
+                x: text_left,
+                y,
+                size,
+                font: Font::Bold,
+                text,
+            });
+            y += size * 1.2;
+        }
+        lines.push(TextLine {
+            x: text_left,
+            y,
+            size: size * 0.9,
+            font: Font::Regular,
+            text: format!("#{}", item.id),
+        });
+    }
+    Ok(LabelParts {
+        page,
+        module,
+        modules,
+        lines,
+    })
+}
+
+fn layout_labels(
+    items: &[Item],
+    catalog_id: &str,
+    options: &LabelOptions,
+) -> AnyResult<Vec<LabelParts>> {
+    options
+        .template
+        .validate()
+        .map_err(|errs| anyhow!("Invalid label template: {}", errs.join("; ")))?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| layout_label(item, catalog_id, &options.template, options.skip + i))
+        .collect()
+}
+
+fn page_count(labels: &[LabelParts]) -> usize {
+    labels.last().map_or(1, |l| l.page + 1)
+}
+
+pub fn render_labels_pdf(
+    items: &[Item],
+    catalog_id: &str,
+    options: &LabelOptions,
+) -> AnyResult<Vec<u8>> {
+    let labels = layout_labels(items, catalog_id, options)?;
+    let template = &options.template;
+    let mut doc = PdfDocument::with_page_size(template.page_width, template.page_height);
+    for _ in 0..page_count(&labels) {
+        doc.add_page();
+    }
+    // PDF measures up from the bottom of the page
+    let flip = |y: f32| template.page_height - y;
+    for label in &labels {
+        for (x, y) in &label.modules {
+            doc.rect(
+                label.page,
+                *x,
+                flip(*y + label.module),
+                label.module,
+                label.module,
+            );
+        }
+        for line in &label.lines {
+            doc.text(
+                label.page,
+                line.x,
+                flip(line.y),
+                line.size,
+                line.font,
+                &line.text,
+            );
+        }
+    }
+    Ok(doc.to_bytes())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One SVG document per sheet.
+pub fn render_labels_svg(
+    items: &[Item],
+    catalog_id: &str,
+    options: &LabelOptions,
+) -> AnyResult<Vec<String>> {
+    let labels = layout_labels(items, catalog_id, options)?;
+    let template = &options.template;
+    let mut pages: Vec<String> = (0..page_count(&labels))
+        .map(|_| {
+            format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}pt\" height=\"{h}pt\" \
+                 viewBox=\"0 0 {w} {h}\">\n",
+                w = template.page_width,
+                h = template.page_height
+            )
+        })
+        .collect();
+    for label in &labels {
+        let out = &mut pages[label.page];
+        // Modules are drawn slightly oversized so no hairline gaps show between them
+        let path: String = label
+            .modules
+            .iter()
+            .map(|(x, y)| {
+                format!(
+                    "M{:.2} {:.2}h{m:.3}v{m:.3}h-{m:.3}z",
+                    x,
+                    y,
+                    m = label.module + 0.01
+                )
+            })
+            .collect();
+        out.push_str(&format!("<path d=\"{}\" fill=\"#000\"/>\n", path));
+        for line in &label.lines {
+            let weight = match line.font {
+                Font::Bold => " font-weight=\"bold\"",
+                Font::Regular => "",
+            };
+            out.push_str(&format!(
+                "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Helvetica, Arial, sans-serif\" \
+                 font-size=\"{:.1}\"{}>{}</text>\n",
+                line.x,
+                line.y,
+                line.size,
+                weight,
+                escape_xml(&line.text)
+            ));
+        }
+    }
+    for page in &mut pages {
+        page.push_str("</svg>\n");
+    }
+    Ok(pages)
+}
+
+/// Writes labels for the items matching `filter` as a PDF, or as SVG with one file per
+/// sheet: `path` for the first and `name-2.svg` and so on for the rest. Deleted items are
+/// left out unless the filter asks for them. Returns the number of labels.
+pub fn write_labels(
+    conn: &Connection,
+    mut filter: ItemFilter,
+    options: &LabelOptions,
+    path: &str,
+) -> AnyResult<usize> {
+    let format = LabelFormat::from_path(path)
+        .ok_or_else(|| anyhow!("Label path must end in .pdf or .svg: {}", path))?;
+    if filter.deleted.is_none() {
+        filter.deleted = Some(false);
+    }
+    let items = get_filtered_items(conn, filter)?;
+    let catalog_id = get_catalog_id(conn)?;
+    match format {
+        LabelFormat::Pdf => fs::write(path, render_labels_pdf(&items, &catalog_id, options)?)?,
+        LabelFormat::Svg => {
+            let stem = Path::new(path).with_extension("");
+            for (i, page) in render_labels_svg(&items, &catalog_id, options)?
+                .into_iter()
+                .enumerate()
+            {
+                match i {
+                    0 => fs::write(path, page)?,
+                    _ => fs::write(format!("{}-{}.svg", stem.display(), i + 1), page)?,
+                }
+            }
+        }
+    }
+    Ok(items.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db};
+
+    #[test]
+    fn test_label_code_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let catalog_id = get_catalog_id(&conn).unwrap();
+        assert_eq!(catalog_id.len(), 16);
+        assert_eq!(get_catalog_id(&conn).unwrap(), catalog_id);
+
+        let code = LabelCode {
+            catalog_id,
+            item_id: 42,
+        };
+        assert_eq!(code.to_string().parse::<LabelCode>().unwrap(), code);
+        assert!(
+            "collection-catalog://abc/item/x"
+                .parse::<LabelCode>()
+                .is_err()
+        );
+        assert!("https://example.com".parse::<LabelCode>().is_err());
+    }
+
+    #[test]
+    fn test_render_label_sheets() {
+        let items: Vec<Item> = (1..=35)
+            .map(|id| Item {
+                id,
+                name: format!("Box of assorted kitchen utensils {}", id),
+                description: "test".to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let options = LabelOptions {
+            skip: 2,
+            ..Default::default()
+        };
+
+        // 35 labels after 2 skipped need two sheets of 30
+        let pages = render_labels_svg(&items, "abc", &options).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].contains("#1</text>"));
+        assert!(pages[1].contains("#35</text>"));
+
+        let pdf = render_labels_pdf(&items, "abc", &options).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains(" re f"));
+
+        // A fourth column runs off the page
+        let options = LabelOptions {
+            template: LabelTemplate {
+                columns: 4,
+                ..Default::default()
+            },
+            skip: 0,
+        };
+        assert!(render_labels_pdf(&items, "abc", &options).is_err());
+    }
+
+    #[test]
+    fn test_write_labels_skips_deleted() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for name in ["Lamp", "Chair"] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        crate::db::soft_delete_item(&conn, 2).unwrap();
+        let path = std::env::temp_dir().join(format!("labels-{}.pdf", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let count = write_labels(
+            &conn,
+            ItemFilter::default(),
+            &LabelOptions::default(),
+            &path,
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+        assert!(fs::read(&path).unwrap().starts_with(b"%PDF"));
+        fs::remove_file(&path).unwrap();
+        assert!(
+            write_labels(
+                &conn,
+                ItemFilter::default(),
+                &LabelOptions::default(),
+                "x.png"
+            )
+            .is_err()
+        );
+    }
+}
@@ -1,10 +1,115 @@
+pub mod attachments;
+pub mod audit;
+pub mod barcode;
+pub mod beneficiaries;
+pub mod condition;
+pub mod creators;
 pub mod csv_export;
 pub mod db;
+pub mod dedupe;
+pub mod fuzzy_date;
+pub mod identifiers;
+pub mod images;
+pub mod json_export;
+pub mod labels;
+pub mod measure;
+pub mod metadata;
 pub mod models;
+pub mod money;
+mod pdf;
+pub mod provenance;
+pub mod query;
+pub mod relations;
+pub mod reminders;
+pub mod report;
+pub mod saved_search;
+pub mod spreadsheet;
+pub mod stats;
+pub mod templates;
+pub mod wishlist;
 
-pub use csv_export::export_to_csv;
+pub use attachments::{
+    Attachment, add_attachment, delete_attachment, get_attachment, get_attachments,
+};
+pub use audit::{
+    Audit, AuditMark, AuditReport, audit_report, delete_audit, finish_audit, get_audit, get_audits,
+    mark_found, resolve_scan, start_audit, unmark_found,
+};
+pub use barcode::{decode_barcodes, scan_barcode_file, scan_item_attachments};
+pub use beneficiaries::{
+    AssignedItem, Assignment, AssignmentConflict, Beneficiary, BeneficiaryList, ItemBeneficiary,
+    add_beneficiary, assign_item, assignment_conflicts, beneficiary_list, beneficiary_lists,
+    delete_beneficiary, find_beneficiary, get_beneficiaries, get_beneficiary,
+    get_item_beneficiaries, render_beneficiaries_csv, render_beneficiaries_html,
+    render_beneficiaries_pdf, unassign_item, update_beneficiary, write_beneficiary_report,
+};
+pub use condition::{
+    ConditionReport, add_condition_photo, add_condition_report, get_condition_history,
+    get_condition_scale, set_condition_scale,
+};
+pub use creators::{
+    Creator, CreatorRole, CreatorStats, ItemCreator, add_creator, duplicate_creator_candidates,
+    find_creator, get_creator, get_creator_stats, get_creators, get_item_creators, link_creator,
+    merge_creators, unlink_creator, update_creator,
+};
+pub use csv_export::{
+    CSV_COLUMNS, CsvHeaders, CsvOptions, CsvQuoting, ExportProfile, delete_export_profile,
+    export_to_csv, export_to_csv_with_options, get_export_profile, get_export_profiles,
+    save_export_profile, write_csv,
+};
 pub use db::{
-    add_item, get_all_items, get_filtered_items, get_item_by_id, init_db, soft_delete_item,
-    update_item, update_item_fields,
+    ItemStream, add_item, bulk_soft_delete, bulk_update, get_all_items, get_filtered_items,
+    get_item_by_id, init_db, soft_delete_item, stream_filtered_items, update_item,
+    update_item_fields,
+};
+pub use dedupe::{
+    DEFAULT_MIN_SCORE, DEFAULT_PHOTO_DISTANCE, DuplicateCandidate, MergeField, PhotoMatch,
+    duplicate_score, find_duplicates, find_similar_photos, merge_items,
+};
+pub use fuzzy_date::FuzzyDate;
+pub use identifiers::{
+    Identifier, IdentifierKind, ItemIdentifier, add_identifier, find_items_by_identifier,
+    get_identifiers, remove_identifier,
+};
+pub use images::{
+    ImageMetadata, ThumbnailSize, hash_distance, hash_photos, item_photo, item_thumbnail,
+    load_image, perceptual_hash, read_image_metadata, thumbnail, thumbnail_path,
+};
+pub use json_export::write_json;
+pub use labels::{
+    LabelCode, LabelFormat, LabelOptions, LabelTemplate, get_catalog_id, render_labels_pdf,
+    render_labels_svg, write_labels,
+};
+pub use measure::{Dimensions, Length, LengthUnit, Weight, WeightUnit};
+pub use metadata::{Metadata, MetadataProvider, OfflineProvider, lookup_metadata};
+pub use models::{FilterExpr, Item, ItemAction, ItemCategory, ItemFilter, OptionalField};
+pub use money::{
+    CodePosition, Currency, Money, MoneyFormat, Rate, get_exchange_rates, get_home_currency,
+    get_money_format, set_exchange_rate, set_home_currency, set_money_format,
+};
+pub use provenance::{
+    ProvenanceEvent, ProvenanceRole, append_provenance_event, delete_provenance_event,
+    get_provenance, get_provenance_event, move_provenance_event, provenance_summary,
+    update_provenance_event,
+};
+pub use query::{Query, QueryError, parse_query, query_items, stream_query_items};
+pub use relations::{
+    ItemRelation, RelatedItem, RelationKind, add_relation, create_set, get_related_items,
+    get_set_members, get_set_stats, remove_relation,
+};
+pub use reminders::{
+    DueReminder, Recurrence, Reminder, ReminderKind, add_reminder, complete_reminder,
+    delete_reminder, due_reminders, export_reminders_ics, get_reminder, get_reminders,
+    overdue_reminders, reminders_to_ics, update_reminder,
+};
+pub use report::{ReportGrouping, ReportOptions, write_report};
+pub use saved_search::{
+    DateExpr, DateUnit, SavedSearch, delete_saved_search, get_saved_search, get_saved_searches,
+    run_saved_search, save_search,
+};
+pub use spreadsheet::{SpreadsheetFormat, SpreadsheetOptions, export_to_spreadsheet};
+pub use stats::{CatalogStats, get_stats};
+pub use templates::{
+    ItemTemplate, clone_item, delete_template, get_template, get_templates, save_template,
 };
-pub use models::{Item, ItemAction, ItemCategory, ItemFilter};
+pub use wishlist::{acquire_wishlist_item, get_wishlist};
@@ -0,0 +1,361 @@
+use rusqlite::Row;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    Millimetre,
+    #[default]
+    Centimetre,
+    Metre,
+    Inch,
+    Foot,
+}
+
+impl LengthUnit {
+    /// Millimetres in one of this unit.
+    pub fn millimetres(&self) -> f64 {
+        match self {
+            LengthUnit::Millimetre => 1.0,
+            LengthUnit::Centimetre => 10.0,
+            LengthUnit::Metre => 1000.0,
+            LengthUnit::Inch => 25.4,
+            LengthUnit::Foot => 304.8,
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            LengthUnit::Millimetre => "mm",
+            LengthUnit::Centimetre => "cm",
+            LengthUnit::Metre => "m",
+            LengthUnit::Inch => "in",
+            LengthUnit::Foot => "ft",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl FromStr for LengthUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mm" => Ok(LengthUnit::Millimetre),
+            "cm" => Ok(LengthUnit::Centimetre),
+            "m" => Ok(LengthUnit::Metre),
+            "in" | "\"" => Ok(LengthUnit::Inch),
+            "ft" | "'" => Ok(LengthUnit::Foot),
+            _ => Err(format!("Unknown length unit: '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WeightUnit {
+    Gram,
+    #[default]
+    Kilogram,
+    Ounce,
+    Pound,
+}
+
+impl WeightUnit {
+    /// Grams in one of this unit.
+    pub fn grams(&self) -> f64 {
+        match self {
+            WeightUnit::Gram => 1.0,
+            WeightUnit::Kilogram => 1000.0,
+            WeightUnit::Ounce => 28.349523125,
+            WeightUnit::Pound => 453.59237,
+        }
+    }
+}
+
+impl fmt::Display for WeightUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            WeightUnit::Gram => "g",
+            WeightUnit::Kilogram => "kg",
+            WeightUnit::Ounce => "oz",
+            WeightUnit::Pound => "lb",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl FromStr for WeightUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "g" => Ok(WeightUnit::Gram),
+            "kg" => Ok(WeightUnit::Kilogram),
+            "oz" => Ok(WeightUnit::Ounce),
+            "lb" | "lbs" => Ok(WeightUnit::Pound),
+            _ => Err(format!("Unknown weight unit: '{}'", s)),
+        }
+    }
+}
+
+// Conversions are kept to 3 decimal places so values round trip through text cleanly
+fn round3(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+// Splits "12.5kg" or "12.5 kg" into the number and unit
+fn split_unit(s: &str) -> Option<(f64, &str)> {
+    let s = s.trim();
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    Some((s[..end].parse().ok()?, s[end..].trim()))
+}
+
+/// A single measurement such as `75 cm`, used for dimension range filters.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Length {
+    pub value: f64,
+    pub unit: LengthUnit,
+}
+
+impl Length {
+    pub fn millimetres(&self) -> f64 {
+        self.value * self.unit.millimetres()
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = split_unit(s).ok_or_else(|| format!("Invalid length: '{}'", s))?;
+        Ok(Length {
+            value,
+            unit: unit.parse()?,
+        })
+    }
+}
+
+/// Length × width × height in one unit, written `120x60x75 cm`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Dimensions {
+    pub length: f64,
+    pub width: f64,
+    pub height: f64,
+    pub unit: LengthUnit,
+}
+
+impl Dimensions {
+    pub fn convert(&self, unit: LengthUnit) -> Dimensions {
+        let factor = self.unit.millimetres() / unit.millimetres();
+        Dimensions {
+            length: round3(self.length * factor),
+            width: round3(self.width * factor),
+            height: round3(self.height * factor),
+            unit,
+        }
+    }
+
+    /// Volume in cubic metres.
+    pub fn volume_m3(&self) -> f64 {
+        let m = self.convert(LengthUnit::Metre);
+        m.length * m.width * m.height
+    }
+
+    pub fn is_valid(&self) -> bool {
+        [self.length, self.width, self.height]
+            .iter()
+            .all(|v| v.is_finite() && *v > 0.0)
+    }
+}
+
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}x{} {}",
+            self.length, self.width, self.height, self.unit
+        )
+    }
+}
+
+impl FromStr for Dimensions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid dimensions: '{}'. Expected e.g. 120x60x75 cm", s);
+        let parts: Vec<&str> = s.split(['x', 'X', '×']).collect();
+        let [length, width, height] = parts.as_slice() else {
+            return Err(err());
+        };
+        // The unit follows the last number
+        let (height, unit) = split_unit(height).ok_or_else(err)?;
+        Ok(Dimensions {
+            length: length.trim().parse().map_err(|_| err())?,
+            width: width.trim().parse().map_err(|_| err())?,
+            height,
+            unit: unit.parse()?,
+        })
+    }
+}
+
+/// A weight such as `12.5 kg`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Weight {
+    pub value: f64,
+    pub unit: WeightUnit,
+}
+
+impl Weight {
+    pub fn grams(&self) -> f64 {
+        self.value * self.unit.grams()
+    }
+
+    pub fn convert(&self, unit: WeightUnit) -> Weight {
+        Weight {
+            value: round3(self.grams() / unit.grams()),
+            unit,
+        }
+    }
+
+    pub fn times(&self, quantity: u32) -> Weight {
+        Weight {
+            value: round3(self.value * quantity as f64),
+            unit: self.unit,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.value.is_finite() && self.value > 0.0
+    }
+}
+
+impl fmt::Display for Weight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+impl FromStr for Weight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = split_unit(s).ok_or_else(|| format!("Invalid weight: '{}'", s))?;
+        Ok(Weight {
+            value,
+            unit: unit.parse()?,
+        })
+    }
+}
+
+// Measurements are (de)serialized as their text form, like Money
+macro_rules! serde_as_string {
+    ($($ty:ty),*) => {$(
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+                <$ty as std::str::FromStr>::from_str(&s).map_err(serde::de::Error::custom)
+            }
+        }
+    )*};
+}
+
+pub(crate) use serde_as_string;
+
+serde_as_string!(Length, Dimensions, Weight);
+
+/// Dimensions in millimetres, the unit they are stored and filtered in.
+pub(crate) fn dimensions_mm(dimensions: &Dimensions) -> [f64; 3] {
+    let mm = dimensions.convert(LengthUnit::Millimetre);
+    [mm.length, mm.width, mm.height]
+}
+
+/// Dimensions are stored in millimetres (so they can be range filtered) plus the unit they
+/// were entered in.
+pub(crate) fn dimensions_from_row(row: &Row) -> rusqlite::Result<Option<Dimensions>> {
+    let (Some(length), Some(width), Some(height)) = (
+        row.get::<_, Option<f64>>("length_mm")?,
+        row.get::<_, Option<f64>>("width_mm")?,
+        row.get::<_, Option<f64>>("height_mm")?,
+    ) else {
+        return Ok(None);
+    };
+    let unit = row
+        .get::<_, Option<String>>("dimension_unit")?
+        .and_then(|u| u.parse().ok())
+        .unwrap_or_default();
+    let mm = Dimensions {
+        length,
+        width,
+        height,
+        unit: LengthUnit::Millimetre,
+    };
+    Ok(Some(mm.convert(unit)))
+}
+
+/// Weights are stored in grams plus the unit they were entered in.
+pub(crate) fn weight_from_row(row: &Row) -> rusqlite::Result<Option<Weight>> {
+    let Some(grams) = row.get::<_, Option<f64>>("weight_g")? else {
+        return Ok(None);
+    };
+    let unit = row
+        .get::<_, Option<String>>("weight_unit")?
+        .and_then(|u| u.parse().ok())
+        .unwrap_or_default();
+    let weight = Weight {
+        value: grams,
+        unit: WeightUnit::Gram,
+    };
+    Ok(Some(weight.convert(unit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let dims = Dimensions::from_str("120 x 60 x 75.5cm").unwrap();
+        assert_eq!(dims.to_string(), "120x60x75.5 cm");
+        assert_eq!(Dimensions::from_str(&dims.to_string()).unwrap(), dims);
+        assert!(Dimensions::from_str("120x60 cm").is_err());
+        assert!(Dimensions::from_str("120x60x75 furlongs").is_err());
+
+        let weight = Weight::from_str("12.5kg").unwrap();
+        assert_eq!(weight.to_string(), "12.5 kg");
+        assert_eq!(Length::from_str("1.5m").unwrap().millimetres(), 1500.0);
+    }
+
+    #[test]
+    fn test_conversion() {
+        let dims = Dimensions::from_str("10x20x30 in").unwrap();
+        assert_eq!(
+            dims.convert(LengthUnit::Centimetre).to_string(),
+            "25.4x50.8x76.2 cm"
+        );
+        assert_eq!(
+            dims.convert(LengthUnit::Millimetre)
+                .convert(LengthUnit::Inch),
+            dims
+        );
+
+        let weight = Weight::from_str("2 lb").unwrap();
+        assert_eq!(weight.convert(WeightUnit::Kilogram).to_string(), "0.907 kg");
+        assert_eq!(weight.convert(WeightUnit::Ounce).to_string(), "32 oz");
+    }
+}
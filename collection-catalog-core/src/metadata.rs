@@ -0,0 +1,304 @@
+//! Looking up a title, creators and other details for an ISBN or barcode, to prefill a new
+//! item.
+
+use crate::fuzzy_date::FuzzyDate;
+use crate::identifiers::{Identifier, IdentifierKind};
+use crate::models::{Item, ItemCategory};
+use anyhow::{Result as AnyResult, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Where the offline provider looks for its dump file unless told otherwise.
+pub const DEFAULT_DUMP_FILE: &str = "metadata.jsonl";
+
+/// What a provider knows about a code.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    pub title: String,
+    /// Authors, artists or makers, in credit order
+    #[serde(default)]
+    pub creators: Vec<String>,
+    pub publisher: Option<String>,
+    pub date: Option<FuzzyDate>,
+    pub description: Option<String>,
+    pub category: Option<ItemCategory>,
+    /// The provider that supplied it
+    #[serde(default)]
+    pub source: String,
+}
+
+impl Metadata {
+    /// A new item filled in from the metadata, to be checked before it's added.
+    pub fn draft_item(&self, identifier: &Identifier) -> Item {
+        // Item names are limited to 50 bytes, so a long title also goes in the description
+        let title = self.title.trim();
+        let mut end = title.len().min(50);
+        while !title.is_char_boundary(end) {
+            end -= 1;
+        }
+        let name = title[..end].trim_end().to_string();
+        let truncated = end < title.len();
+        let description = self.description.clone().unwrap_or_else(|| {
+            let mut parts = vec![];
+            if truncated {
+                parts.push(title.to_string());
+            }
+            parts.extend(self.publisher.clone());
+            parts.push(identifier.to_string());
+            parts.join(", ")
+        });
+        Item {
+            name,
+            description,
+            category: self.category.clone().unwrap_or(match identifier.kind {
+                IdentifierKind::Isbn => ItemCategory::Book,
+                _ => ItemCategory::Other,
+            }),
+            // Separated the way creator links split them
+            creator: Some(self.creators.join("; ")).filter(|c| !c.is_empty()),
+            date_made: self.date,
+            ..Default::default()
+        }
+    }
+}
+
+/// A source of metadata, such as a local dump file or an online catalogue.
+pub trait MetadataProvider {
+    fn name(&self) -> &str;
+
+    /// `None` when the provider doesn't know the code.
+    fn lookup(&self, identifier: &Identifier) -> AnyResult<Option<Metadata>>;
+}
+
+/// The first result from `providers`, tried in order.
+pub fn lookup_metadata(
+    providers: &[&dyn MetadataProvider],
+    identifier: &Identifier,
+) -> AnyResult<Option<Metadata>> {
+    for provider in providers {
+        if let Some(mut metadata) = provider.lookup(identifier)? {
+            metadata.source = provider.name().to_string();
+            return Ok(Some(metadata));
+        }
+    }
+    Ok(None)
+}
+
+/// A line of a dump file. Field names follow common open catalogue exports, which may give
+/// several codes for one record.
+#[derive(Debug, Deserialize)]
+struct DumpRecord {
+    #[serde(default)]
+    code: Codes,
+    #[serde(default)]
+    isbn: Codes,
+    #[serde(default)]
+    isbn_13: Codes,
+    #[serde(default)]
+    isbn_10: Codes,
+    #[serde(default)]
+    upc: Codes,
+    #[serde(default)]
+    ean: Codes,
+    title: String,
+    #[serde(default, alias = "authors")]
+    creators: Vec<String>,
+    #[serde(default)]
+    publisher: Option<String>,
+    #[serde(default, alias = "publish_date", alias = "year")]
+    date: Option<serde_json::Value>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+// A single code, or a list of them as in Open Library's `"isbn_13": ["9780261103344"]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(untagged)]
+enum Codes {
+    #[default]
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+impl DumpRecord {
+    fn codes(&self) -> impl Iterator<Item = &String> {
+        [
+            &self.code,
+            &self.isbn,
+            &self.isbn_13,
+            &self.isbn_10,
+            &self.upc,
+            &self.ean,
+        ]
+        .into_iter()
+        .flat_map(|codes| match codes {
+            Codes::None => &[][..],
+            Codes::One(code) => std::slice::from_ref(code),
+            Codes::Many(codes) => &codes[..],
+        })
+    }
+}
+
+// Publication dates come as `1937`, `1937-09-21` or `September 21, 1937`
+fn parse_dump_date(value: &serde_json::Value) -> Option<FuzzyDate> {
+    let text = match value {
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        _ => return None,
+    };
+    FuzzyDate::from_str(&text).ok().or_else(|| {
+        text.split(|c: char| !c.is_ascii_digit())
+            .find(|part| part.len() == 4)
+            .and_then(|year| FuzzyDate::from_str(year).ok())
+    })
+}
+
+/// Looks codes up in a local JSON Lines dump, one record per line such as
+/// `{"isbn": "9780261103344", "title": "The Hobbit", "authors": ["J.R.R. Tolkien"],
+/// "publisher": "HarperCollins", "publish_date": "1991"}`, so it works without a network.
+/// Lines that aren't records or have no valid code are skipped.
+pub struct OfflineProvider {
+    records: HashMap<String, Metadata>,
+}
+
+impl OfflineProvider {
+    /// Reads the whole dump into memory so lookups are quick.
+    pub fn open(path: &Path) -> AnyResult<Self> {
+        let file = File::open(path)
+            .map_err(|e| anyhow!("Cannot open metadata dump {}: {}", path.display(), e))?;
+        let mut records = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            let Ok(record) = serde_json::from_str::<DumpRecord>(&line?) else {
+                continue;
+            };
+            let codes: Vec<String> = record
+                .codes()
+                .filter_map(|code| code.parse::<Identifier>().ok())
+                .map(|identifier| identifier.normalized())
+                .collect();
+            if codes.is_empty() {
+                continue;
+            }
+            let metadata = Metadata {
+                title: record.title,
+                creators: record.creators,
+                publisher: record.publisher,
+                date: record.date.as_ref().and_then(parse_dump_date),
+                description: record.description,
+                category: record.category.and_then(|c| c.parse().ok()),
+                source: String::new(),
+            };
+            for code in codes {
+                records.insert(code, metadata.clone());
+            }
+        }
+        Ok(OfflineProvider { records })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl MetadataProvider for OfflineProvider {
+    fn name(&self) -> &str {
+        "offline dump"
+    }
+
+    fn lookup(&self, identifier: &Identifier) -> AnyResult<Option<Metadata>> {
+        Ok(self.records.get(&identifier.normalized()).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creators::get_item_creators;
+    use crate::db::{add_item, init_db};
+    use std::io::Write;
+
+    #[test]
+    fn test_offline_lookup_prefills_item() {
+        let path = std::env::temp_dir().join(format!("metadata-{}.jsonl", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        writeln!(
+            file,
+            r#"{{"isbn": "9780261103344", "title": "The Hobbit", "authors": ["J.R.R. Tolkien"], "publish_date": "September 21, 1937"}}"#
+        )
+        .unwrap();
+        writeln!(file, "not a record").unwrap();
+        writeln!(
+            file,
+            r#"{{"upc": "036000291452", "title": "Jell-O Gelatin Dessert Mix In A Flavour With A Very Long Name", "publisher": "Kraft"}}"#
+        )
+        .unwrap();
+        writeln!(
+            file,
+            r#"{{"isbn_10": ["0306406152"], "isbn_13": ["9780306406157"], "title": "Signal Processing", "authors": ["Alan Oppenheim", "Ronald Schafer"]}}"#
+        )
+        .unwrap();
+        writeln!(
+            file,
+            r#"{{"isbn_10": "080442957X", "isbn_13": "9780804429573", "title": "Tao Te Ching"}}"#
+        )
+        .unwrap();
+        drop(file);
+
+        let provider = OfflineProvider::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(provider.len(), 4);
+
+        // An ISBN-10 finds the record stored under its ISBN-13
+        let isbn: Identifier = "0261103342".parse().unwrap();
+        let metadata = lookup_metadata(&[&provider], &isbn).unwrap().unwrap();
+        assert_eq!(metadata.source, "offline dump");
+        let item = metadata.draft_item(&isbn);
+        assert_eq!(item.name, "The Hobbit");
+        assert_eq!(item.creator.as_deref(), Some("J.R.R. Tolkien"));
+        assert_eq!(item.category.to_string(), "Book");
+        assert_eq!(item.date_made.unwrap().to_string(), "1937");
+        assert!(item.validate().is_ok());
+
+        let upc: Identifier = "036000291452".parse().unwrap();
+        let item = provider.lookup(&upc).unwrap().unwrap().draft_item(&upc);
+        assert_eq!(item.name.chars().count(), 50);
+        assert!(item.description.starts_with("Jell-O Gelatin"));
+        assert!(item.validate().is_ok());
+
+        // Records giving both forms, as plain codes or as lists
+        for code in ["0306406152", "9780804429573"] {
+            assert!(provider.lookup(&code.parse().unwrap()).unwrap().is_some());
+        }
+
+        // Each author becomes a creator of their own
+        let isbn: Identifier = "0306406152".parse().unwrap();
+        let item = provider.lookup(&isbn).unwrap().unwrap().draft_item(&isbn);
+        assert_eq!(
+            item.creator.as_deref(),
+            Some("Alan Oppenheim; Ronald Schafer")
+        );
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let id = add_item(&conn, &item).unwrap();
+        let creators: Vec<String> = get_item_creators(&conn, id)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.creator.name)
+            .collect();
+        assert_eq!(creators, vec!["Alan Oppenheim", "Ronald Schafer"]);
+
+        let unknown: Identifier = "4006381333931".parse().unwrap();
+        assert!(provider.lookup(&unknown).unwrap().is_none());
+    }
+}
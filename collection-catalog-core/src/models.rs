@@ -1,10 +1,13 @@
-use chrono::NaiveDate;
+use crate::fuzzy_date::FuzzyDate;
+use crate::measure::{Dimensions, Length, Weight, dimensions_from_row, weight_from_row};
+use crate::money::{Money, money_from_row};
+use chrono::{Local, NaiveDate};
 use rusqlite::Row;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ItemAction {
     #[default]
     Keep,
@@ -36,7 +39,7 @@ impl FromStr for ItemAction {
     }
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ItemCategory {
     #[default]
     Antique,
@@ -106,16 +109,52 @@ pub struct Item {
     pub deleted: bool,
 
     // Optional fields
+    pub date_made: Option<FuzzyDate>,
+    /// Derived from `date_made` when the item is loaded, never stored
+    #[serde(skip_deserializing)]
     pub age_years: Option<u32>,
     pub date_acquired: Option<NaiveDate>,
-    pub purchase_price: Option<f64>,
-    pub estimated_value: Option<f64>,
+    pub purchase_price: Option<Money>,
+    pub estimated_value: Option<Money>,
     pub creator: Option<String>,
     pub working: Option<bool>,
     pub provenance: Option<String>,
+    pub location: Option<String>,
+    /// Current grade on the category's condition scale, see `condition::get_condition_scale`
+    pub condition: Option<String>,
+    pub condition_notes: Option<String>,
+    pub dimensions: Option<Dimensions>,
+    pub weight: Option<Weight>,
+    /// Number of pieces in a set, unset meaning one
+    pub quantity: Option<u32>,
+
+    /// Something being looked for rather than owned, see the `wishlist` module. Wishlist items
+    /// are left out of listings and totals unless a filter asks for them.
+    #[serde(default)]
+    pub wishlist: bool,
+    /// The most worth paying for a wishlist item
+    pub target_price: Option<Money>,
+    /// 1 for the most wanted
+    pub wishlist_priority: Option<u32>,
+    pub wishlist_notes: Option<String>,
+
+    /// `weight` × `quantity`, derived when the item is loaded
+    #[serde(skip_deserializing)]
+    pub total_weight: Option<Weight>,
+    /// Text summary of the ownership chain, kept up to date by the `provenance` module
+    #[serde(skip_deserializing)]
+    pub provenance_chain: Option<String>,
+    /// When the item was last found in an inventory audit, kept up to date by the `audit` module
+    #[serde(skip_deserializing)]
+    pub last_audited: Option<NaiveDate>,
 }
 
 impl Item {
+    /// Quantity with unset treated as a single piece.
+    pub fn pieces(&self) -> u32 {
+        self.quantity.unwrap_or(1)
+    }
+
     pub fn validate(&self) -> Result<(), Vec<String>> {
         let mut errors = vec![];
 
@@ -132,16 +171,55 @@ impl Item {
         }
 
         // Optional: price & value must be non-negative
-        if let Some(price) = self.purchase_price {
-            if price < 0.0 {
-                errors.push("Purchase price cannot be negative.".to_string());
-            }
+        if let Some(price) = self.purchase_price
+            && price.is_negative()
+        {
+            errors.push("Purchase price cannot be negative.".to_string());
         }
 
-        if let Some(value) = self.estimated_value {
-            if value < 0.0 {
-                errors.push("Estimated value cannot be negative.".to_string());
-            }
+        if let Some(value) = self.estimated_value
+            && value.is_negative()
+        {
+            errors.push("Estimated value cannot be negative.".to_string());
+        }
+
+        // Optional: date made can't be entirely in the future
+        if let Some(made) = self.date_made
+            && !made.in_range()
+        {
+            errors.push("Date made is out of range.".to_string());
+        }
+        if let Some(made) = self.date_made.and_then(|d| d.earliest())
+            && made > Local::now().date_naive()
+        {
+            errors.push("Date made cannot be in the future.".to_string());
+        }
+
+        // Optional: measurements must be positive
+        if let Some(dimensions) = self.dimensions
+            && !dimensions.is_valid()
+        {
+            errors.push("Dimensions must be greater than zero.".to_string());
+        }
+
+        if let Some(weight) = self.weight
+            && !weight.is_valid()
+        {
+            errors.push("Weight must be greater than zero.".to_string());
+        }
+
+        if self.quantity == Some(0) {
+            errors.push("Quantity must be at least 1.".to_string());
+        }
+
+        if let Some(target) = self.target_price
+            && target.is_negative()
+        {
+            errors.push("Target price cannot be negative.".to_string());
+        }
+
+        if self.wishlist_priority == Some(0) {
+            errors.push("Wishlist priority starts at 1.".to_string());
         }
 
         if errors.is_empty() {
@@ -152,6 +230,12 @@ impl Item {
     }
 
     pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let date_made = row
+            .get::<_, Option<String>>("date_made")?
+            .and_then(|s| FuzzyDate::from_str(&s).ok());
+        let weight = weight_from_row(row)?;
+        let quantity: Option<u32> = row.get("quantity")?;
+
         Ok(Item {
             id: row.get("id")?,
             name: row.get("name")?,
@@ -171,32 +255,59 @@ impl Item {
             deleted: row.get("deleted")?,
 
             // Optional fields
-            age_years: row.get("age_years")?,
+            date_made,
+            age_years: date_made.and_then(|d| d.age_years(Local::now().date_naive())),
             date_acquired: row
                 .get::<_, Option<String>>("date_acquired")?
                 .map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-            purchase_price: row.get("purchase_price")?,
-            estimated_value: row.get("estimated_value")?,
+            purchase_price: money_from_row(row, "purchase_price")?,
+            estimated_value: money_from_row(row, "estimated_value")?,
             creator: row.get("creator")?,
             working: row.get("working")?,
             provenance: row.get("provenance")?,
+            location: row.get("location")?,
+            condition: row.get("condition")?,
+            condition_notes: row.get("condition_notes")?,
+            dimensions: dimensions_from_row(row)?,
+            weight,
+            quantity,
+            wishlist: row.get("wishlist")?,
+            target_price: money_from_row(row, "target_price")?,
+            wishlist_priority: row.get("wishlist_priority")?,
+            wishlist_notes: row.get("wishlist_notes")?,
+            total_weight: weight.map(|w| w.times(quantity.unwrap_or(1))),
+            provenance_chain: row.get("provenance_chain")?,
+            last_audited: row
+                .get::<_, Option<String>>("last_audited")?
+                .and_then(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").ok()),
         })
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Unknown fields are rejected when deserializing, so a misspelt field is an error rather
+/// than a filter that matches everything.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ItemFilter {
     // Partial string matches
     pub name_contains: Option<String>,
     pub description_contains: Option<String>,
+    /// Matches the display text and the names and aliases of linked creators
     pub creator_contains: Option<String>,
+    pub creator_id: Option<i32>,
     pub provenance_contains: Option<String>,
+    pub location_contains: Option<String>,
+    /// Matches any provenance event's name, role, location or source
+    pub provenance_event_contains: Option<String>,
 
     // Enums / Exact matches
     pub category: Option<ItemCategory>,
     pub action: Option<ItemAction>,
     pub working: Option<bool>,
+    pub condition: Option<String>,
     pub deleted: Option<bool>,
+    /// Unset in every part of a filter leaves wishlist items out, see `get_filtered_items`
+    pub wishlist: Option<bool>,
 
     // Date filters
     pub date_added_min: Option<NaiveDate>,
@@ -206,13 +317,210 @@ pub struct ItemFilter {
     pub date_acquired_min: Option<NaiveDate>,
     pub date_acquired_max: Option<NaiveDate>,
 
+    // Date made filters, see `get_filtered_items` for how uncertainty is handled
+    pub date_made_min: Option<FuzzyDate>,
+    pub date_made_max: Option<FuzzyDate>,
+    pub date_made_strict: Option<bool>,
+
     // Numeric filers
     pub age_years_min: Option<u32>,
     pub age_years_max: Option<u32>,
-    pub purchase_price_min: Option<f64>,
-    pub purchase_price_max: Option<f64>,
-    pub estimated_value_min: Option<f64>,
-    pub estimated_value_max: Option<f64>,
+    // Money filters only match items priced in the same currency
+    pub purchase_price_min: Option<Money>,
+    pub purchase_price_max: Option<Money>,
+    pub estimated_value_min: Option<Money>,
+    pub estimated_value_max: Option<Money>,
+    pub target_price_min: Option<Money>,
+    pub target_price_max: Option<Money>,
+    // Measurement filters compare in a common unit, so any unit can be given
+    pub length_min: Option<Length>,
+    pub length_max: Option<Length>,
+    pub width_min: Option<Length>,
+    pub width_max: Option<Length>,
+    pub height_min: Option<Length>,
+    pub height_max: Option<Length>,
+    pub weight_min: Option<Weight>,
+    pub weight_max: Option<Weight>,
+    pub quantity_min: Option<u32>,
+    pub quantity_max: Option<u32>,
+}
+
+impl ItemFilter {
+    /// Whether no field is set, so the filter matches every item.
+    pub fn is_empty(&self) -> bool {
+        *self == ItemFilter::default()
+    }
+
+    /// Items matching both this filter and `other`.
+    pub fn and(self, other: impl Into<FilterExpr>) -> FilterExpr {
+        FilterExpr::from(self).and(other)
+    }
+
+    /// Items matching either this filter or `other`.
+    pub fn or(self, other: impl Into<FilterExpr>) -> FilterExpr {
+        FilterExpr::from(self).or(other)
+    }
+}
+
+impl std::ops::Not for ItemFilter {
+    type Output = FilterExpr;
+
+    fn not(self) -> FilterExpr {
+        !FilterExpr::from(self)
+    }
+}
+
+/// Optional item fields that can be checked for having no value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionalField {
+    Creator,
+    Working,
+    Provenance,
+    Location,
+    Condition,
+    ConditionNotes,
+    DateMade,
+    DateAcquired,
+    PurchasePrice,
+    EstimatedValue,
+    Dimensions,
+    Weight,
+    Quantity,
+}
+
+impl OptionalField {
+    pub const ALL: [OptionalField; 13] = [
+        OptionalField::Creator,
+        OptionalField::Working,
+        OptionalField::Provenance,
+        OptionalField::Location,
+        OptionalField::Condition,
+        OptionalField::ConditionNotes,
+        OptionalField::DateMade,
+        OptionalField::DateAcquired,
+        OptionalField::PurchasePrice,
+        OptionalField::EstimatedValue,
+        OptionalField::Dimensions,
+        OptionalField::Weight,
+        OptionalField::Quantity,
+    ];
+
+    /// The column that is null when the field has no value.
+    pub(crate) fn column(&self) -> &'static str {
+        match self {
+            OptionalField::Creator => "creator",
+            OptionalField::Working => "working",
+            OptionalField::Provenance => "provenance",
+            OptionalField::Location => "location",
+            OptionalField::Condition => "condition",
+            OptionalField::ConditionNotes => "condition_notes",
+            OptionalField::DateMade => "date_made",
+            OptionalField::DateAcquired => "date_acquired",
+            OptionalField::PurchasePrice => "purchase_price_minor",
+            OptionalField::EstimatedValue => "estimated_value_minor",
+            OptionalField::Dimensions => "length_mm",
+            OptionalField::Weight => "weight_g",
+            OptionalField::Quantity => "quantity",
+        }
+    }
+}
+
+impl fmt::Display for OptionalField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OptionalField::Creator => "creator",
+            OptionalField::Working => "working",
+            OptionalField::Provenance => "provenance",
+            OptionalField::Location => "location",
+            OptionalField::Condition => "condition",
+            OptionalField::ConditionNotes => "condition_notes",
+            OptionalField::DateMade => "date_made",
+            OptionalField::DateAcquired => "date_acquired",
+            OptionalField::PurchasePrice => "purchase_price",
+            OptionalField::EstimatedValue => "estimated_value",
+            OptionalField::Dimensions => "dimensions",
+            OptionalField::Weight => "weight",
+            OptionalField::Quantity => "quantity",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for OptionalField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OptionalField::ALL
+            .into_iter()
+            .find(|field| field.to_string() == s)
+            .ok_or_else(|| format!("Unknown optional field: '{}'", s))
+    }
+}
+
+/// A filter built up from `ItemFilter`s and other conditions with all/any/not.
+///
+/// Serialized as `{"all": [...]}`, `{"any": [...]}`, `{"not": ...}`, `{"category_in": [...]}`,
+/// `{"action_in": [...]}`, `{"condition_in": [...]}`, `{"is_null": "location"}`, or a plain
+/// `ItemFilter` object, so anything that sent an `ItemFilter` can send a `FilterExpr`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterExpr {
+    /// Every one must match, an empty list matches everything
+    All(Vec<FilterExpr>),
+    /// At least one must match, an empty list matches nothing
+    Any(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+    CategoryIn(Vec<ItemCategory>),
+    ActionIn(Vec<ItemAction>),
+    /// Condition grades, ignoring case
+    ConditionIn(Vec<String>),
+    IsNull(OptionalField),
+    /// Every field set in the filter must match
+    #[serde(untagged)]
+    Fields(Box<ItemFilter>),
+}
+
+impl Default for FilterExpr {
+    fn default() -> Self {
+        FilterExpr::All(vec![])
+    }
+}
+
+impl From<ItemFilter> for FilterExpr {
+    fn from(filter: ItemFilter) -> Self {
+        FilterExpr::Fields(Box::new(filter))
+    }
+}
+
+impl FilterExpr {
+    pub fn and(self, other: impl Into<FilterExpr>) -> FilterExpr {
+        match self {
+            FilterExpr::All(mut parts) => {
+                parts.push(other.into());
+                FilterExpr::All(parts)
+            }
+            expr => FilterExpr::All(vec![expr, other.into()]),
+        }
+    }
+
+    pub fn or(self, other: impl Into<FilterExpr>) -> FilterExpr {
+        match self {
+            FilterExpr::Any(mut parts) => {
+                parts.push(other.into());
+                FilterExpr::Any(parts)
+            }
+            expr => FilterExpr::Any(vec![expr, other.into()]),
+        }
+    }
+}
+
+impl std::ops::Not for FilterExpr {
+    type Output = FilterExpr;
+
+    fn not(self) -> FilterExpr {
+        FilterExpr::Not(Box::new(self))
+    }
 }
 
 #[cfg(test)]
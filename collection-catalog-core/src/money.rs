@@ -0,0 +1,660 @@
+use anyhow::{Result as AnyResult, anyhow};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// ISO 4217 currency code, e.g. `USD`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+
+    pub fn code(&self) -> &str {
+        // Always three ASCII uppercase letters, see `FromStr`
+        std::str::from_utf8(&self.0).unwrap()
+    }
+
+    /// Number of digits after the decimal point for the currency's minor unit.
+    pub fn minor_digits(&self) -> u32 {
+        match self.code() {
+            "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF"
+            | "UGX" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+            "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::USD
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_ascii_uppercase();
+        let bytes: [u8; 3] = code
+            .as_bytes()
+            .try_into()
+            .map_err(|_| format!("Invalid currency code: '{}'", s))?;
+        if !bytes.iter().all(u8::is_ascii_uppercase) {
+            return Err(format!("Invalid currency code: '{}'", s));
+        }
+        Ok(Currency(bytes))
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Currency::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Fixed-point amount of money, stored as an integer count of the currency's minor unit
+/// (cents for USD) so sums and exports never pick up floating point noise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn from_minor(minor: i64, currency: Currency) -> Self {
+        Money { minor, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Money { minor: 0, currency }
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor < 0
+    }
+
+    /// Adds two amounts of the same currency. Returns `None` on a currency mismatch or overflow.
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Money::from_minor(
+            self.minor.checked_add(other.minor)?,
+            self.currency,
+        ))
+    }
+
+    /// Parses an amount such as `12.50`, `1,200 EUR` or `USD 3`, using `default` when the
+    /// text has no currency code.
+    pub fn parse_with_default(s: &str, default: Currency) -> Result<Money, String> {
+        let mut currency = default;
+        let mut number = None;
+        for part in s.split_whitespace() {
+            if part.chars().all(|c| c.is_ascii_alphabetic()) {
+                currency = Currency::from_str(part)?;
+            } else if number.replace(part).is_some() {
+                return Err(format!("Invalid amount: '{}'", s));
+            }
+        }
+        let number = number.ok_or_else(|| format!("Invalid amount: '{}'", s))?;
+        let minor = parse_minor(&number.replace(',', ""), currency.minor_digits())
+            .ok_or_else(|| format!("Invalid amount for {}: '{}'", currency, s))?;
+        Ok(Money::from_minor(minor, currency))
+    }
+
+    /// Converts to `to` using a rate expressed as units of `to` per unit of this currency,
+    /// rounding half away from zero to the target's minor unit.
+    pub fn convert(&self, to: Currency, rate: Rate) -> Money {
+        let den = 10i128.pow(self.currency.minor_digits()) * Rate::SCALE as i128;
+        let minor = (self.minor as i128)
+            .checked_mul(rate.0 as i128)
+            .and_then(|n| n.checked_mul(10i128.pow(to.minor_digits())))
+            .map_or(if self.minor < 0 { i128::MIN } else { i128::MAX }, |num| {
+                div_round(num, den)
+            });
+        Money::from_minor(clamp_minor(minor), to)
+    }
+
+    pub fn format(&self, fmt: &MoneyFormat) -> String {
+        let digits = self.currency.minor_digits();
+        let scale = 10u64.pow(digits);
+        let abs = self.minor.unsigned_abs();
+
+        let whole = (abs / scale).to_string();
+        let mut out = String::new();
+        if self.minor < 0 {
+            out.push('-');
+        }
+        for (i, c) in whole.chars().enumerate() {
+            if i > 0
+                && (whole.len() - i).is_multiple_of(3)
+                && let Some(sep) = fmt.group_separator
+            {
+                out.push(sep);
+            }
+            out.push(c);
+        }
+        if digits > 0 {
+            out.push(fmt.decimal_separator);
+            out.push_str(&format!("{:0width$}", abs % scale, width = digits as usize));
+        }
+
+        match fmt.code_position {
+            CodePosition::Before => format!("{} {}", self.currency, out),
+            CodePosition::After => format!("{} {}", out, self.currency),
+            CodePosition::Hidden => out,
+        }
+    }
+}
+
+/// Canonical form, e.g. `1234.50 USD`. Round-trips through `FromStr`.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&MoneyFormat::canonical()))
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Money::parse_with_default(s, Currency::default())
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MoneyVisitor;
+
+        impl Visitor<'_> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an amount such as \"12.50 USD\" or a number")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+                Money::from_str(v).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            // The UI sends plain JS numbers; round them to the minor unit on the way in
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+                let currency = Currency::default();
+                let minor = (v * 10f64.powi(currency.minor_digits() as i32)).round();
+                Ok(Money::from_minor(minor as i64, currency))
+            }
+        }
+
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodePosition {
+    Before,
+    After,
+    Hidden,
+}
+
+/// How amounts are rendered for display (reports, CLI output).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoneyFormat {
+    pub decimal_separator: char,
+    pub group_separator: Option<char>,
+    pub code_position: CodePosition,
+}
+
+impl MoneyFormat {
+    /// Machine-readable format used for storage and exports.
+    pub fn canonical() -> Self {
+        MoneyFormat {
+            decimal_separator: '.',
+            group_separator: None,
+            code_position: CodePosition::After,
+        }
+    }
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        MoneyFormat {
+            decimal_separator: '.',
+            group_separator: Some(','),
+            code_position: CodePosition::Before,
+        }
+    }
+}
+
+/// Exchange rate with eight decimal places of precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rate(i64);
+
+impl Rate {
+    const DIGITS: u32 = 8;
+    const SCALE: i64 = 10i64.pow(Rate::DIGITS);
+
+    pub fn one() -> Self {
+        Rate(Rate::SCALE)
+    }
+
+    /// This rate re-expressed per unit of `via`, e.g. USD per EUR divided by USD per GBP gives
+    /// GBP per EUR. Never rounds down to zero.
+    pub fn per(self, via: Rate) -> Rate {
+        let scaled = div_round(self.0 as i128 * Rate::SCALE as i128, via.0 as i128);
+        Rate(clamp_minor(scaled).max(1))
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = format!("{}.{:08}", self.0 / Rate::SCALE, self.0 % Rate::SCALE);
+        write!(f, "{}", s.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_minor(s.trim(), Rate::DIGITS) {
+            Some(v) if v > 0 => Ok(Rate(v)),
+            _ => Err(format!("Invalid exchange rate: '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub currency: Currency,
+    /// Units of the home currency per one unit of `currency`
+    pub rate: String,
+    pub updated: String,
+}
+
+// Sums are kept wider than `Money` and only clamped to its range when read back out, so
+// large totals saturate instead of overflowing
+fn clamp_minor(minor: i128) -> i64 {
+    minor.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// Running totals per currency.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MoneyTotals(BTreeMap<Currency, i128>);
+
+impl MoneyTotals {
+    pub fn add(&mut self, amount: Money) {
+        let total = self.0.entry(amount.currency).or_insert(0);
+        *total = total.saturating_add(amount.minor as i128);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn amounts(&self) -> Vec<Money> {
+        self.0
+            .iter()
+            .map(|(c, minor)| Money::from_minor(clamp_minor(*minor), *c))
+            .collect()
+    }
+
+    /// Sums everything in `home`. Currencies without a rate are returned in the error.
+    pub fn total_in(
+        &self,
+        home: Currency,
+        rates: &[(Currency, Rate)],
+    ) -> Result<Money, Vec<Currency>> {
+        let mut total: i128 = 0;
+        let mut missing = vec![];
+        for amount in self.amounts() {
+            let rate = if amount.currency == home {
+                Some(Rate::one())
+            } else {
+                rates
+                    .iter()
+                    .find(|(c, _)| *c == amount.currency)
+                    .map(|(_, r)| *r)
+            };
+            match rate {
+                Some(r) => total += amount.convert(home, r).minor as i128,
+                None => missing.push(amount.currency),
+            }
+        }
+        if missing.is_empty() {
+            Ok(Money::from_minor(clamp_minor(total), home))
+        } else {
+            Err(missing)
+        }
+    }
+}
+
+impl<'a> Extend<&'a Option<Money>> for MoneyTotals {
+    fn extend<T: IntoIterator<Item = &'a Option<Money>>>(&mut self, iter: T) {
+        for amount in iter.into_iter().flatten() {
+            self.add(*amount);
+        }
+    }
+}
+
+/// Reads a money value stored as `<prefix>_minor` / `<prefix>_currency` columns.
+pub(crate) fn money_from_row(row: &Row, prefix: &str) -> rusqlite::Result<Option<Money>> {
+    let minor: Option<i64> = row.get(format!("{}_minor", prefix).as_str())?;
+    let currency: Option<String> = row.get(format!("{}_currency", prefix).as_str())?;
+    Ok(minor.map(|m| {
+        Money::from_minor(
+            m,
+            currency
+                .and_then(|c| Currency::from_str(&c).ok())
+                .unwrap_or_default(),
+        )
+    }))
+}
+
+pub fn get_home_currency(conn: &Connection) -> Result<Currency> {
+    let code: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'home_currency'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(code
+        .and_then(|c| Currency::from_str(&c).ok())
+        .unwrap_or_default())
+}
+
+/// Changes the home currency. Stored rates are relative to the home currency, so they are
+/// converted through the new home currency's rate; without one the change is refused.
+pub fn set_home_currency(conn: &Connection, currency: Currency) -> AnyResult<()> {
+    let old = get_home_currency(conn)?;
+    let rates = get_rate_table(conn)?;
+    if currency != old && !rates.is_empty() {
+        let Some(&(_, via)) = rates.iter().find(|(c, _)| *c == currency) else {
+            return Err(anyhow!(
+                "No exchange rate for {} to convert the stored rates with. Set one with `rate {} <rate>` first.",
+                currency,
+                currency
+            ));
+        };
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM exchange_rates WHERE currency = ?1",
+            params![currency.code()],
+        )?;
+        set_exchange_rate(&tx, old, Rate::one().per(via))?;
+        for (other, rate) in rates {
+            if other != currency {
+                set_exchange_rate(&tx, other, rate.per(via))?;
+            }
+        }
+        write_home_currency(&tx, currency)?;
+        tx.commit()?;
+        return Ok(());
+    }
+    write_home_currency(conn, currency)?;
+    Ok(())
+}
+
+fn write_home_currency(conn: &Connection, currency: Currency) -> Result<()> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES ('home_currency', ?1)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![currency.code()],
+    )?;
+    Ok(())
+}
+
+/// The display format for amounts, `MoneyFormat::default()` until one is saved.
+pub fn get_money_format(conn: &Connection) -> AnyResult<MoneyFormat> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'money_format'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(match json {
+        Some(json) => serde_json::from_str(&json)?,
+        None => MoneyFormat::default(),
+    })
+}
+
+pub fn set_money_format(conn: &Connection, fmt: &MoneyFormat) -> AnyResult<()> {
+    if fmt.group_separator == Some(fmt.decimal_separator) {
+        return Err(anyhow!(
+            "Validation failed: the group and decimal separators must differ"
+        ));
+    }
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES ('money_format', ?1)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![serde_json::to_string(fmt)?],
+    )?;
+    Ok(())
+}
+
+pub fn set_exchange_rate(conn: &Connection, currency: Currency, rate: Rate) -> Result<()> {
+    let today = chrono::Local::now().date_naive();
+    conn.execute(
+        "INSERT INTO exchange_rates (currency, rate, updated) VALUES (?1, ?2, ?3)
+        ON CONFLICT(currency) DO UPDATE SET rate = excluded.rate, updated = excluded.updated",
+        params![currency.code(), rate.to_string(), today.to_string()],
+    )?;
+    Ok(())
+}
+
+pub fn get_exchange_rates(conn: &Connection) -> Result<Vec<ExchangeRate>> {
+    let mut stmt =
+        conn.prepare("SELECT currency, rate, updated FROM exchange_rates ORDER BY currency")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ExchangeRate {
+            currency: Currency::from_str(&row.get::<_, String>(0)?).unwrap_or_default(),
+            rate: row.get(1)?,
+            updated: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Exchange rates in a form ready for `MoneyTotals::total_in`.
+pub fn get_rate_table(conn: &Connection) -> Result<Vec<(Currency, Rate)>> {
+    Ok(get_exchange_rates(conn)?
+        .into_iter()
+        .filter_map(|r| Some((r.currency, Rate::from_str(&r.rate).ok()?)))
+        .collect())
+}
+
+/// Parses a decimal string into an integer scaled by `10^digits`, rejecting anything that
+/// would lose precision.
+fn parse_minor(s: &str, digits: u32) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && frac.is_empty()
+        || frac.len() > digits as usize
+        || !whole
+            .chars()
+            .chain(frac.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole: i64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let frac: i64 = format!("{:0<width$}", frac, width = digits as usize)
+        .parse()
+        .unwrap_or(0);
+    let value = whole.checked_mul(10i64.pow(digits))?.checked_add(frac)?;
+    Some(if negative { -value } else { value })
+}
+
+fn div_round(num: i128, den: i128) -> i128 {
+    let q = num / den;
+    let r = num % den;
+    if r.abs() * 2 >= den.abs() {
+        q + num.signum() * den.signum()
+    } else {
+        q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eur() -> Currency {
+        Currency::from_str("EUR").unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display_round_trip() {
+        let m = Money::from_str("1,234.5 eur").unwrap();
+        assert_eq!(m.minor_units(), 123450);
+        assert_eq!(m.currency(), eur());
+        assert_eq!(m.to_string(), "1234.50 EUR");
+        assert_eq!(Money::from_str(&m.to_string()).unwrap(), m);
+
+        let yen = Money::from_str("JPY 500").unwrap();
+        assert_eq!(yen.to_string(), "500 JPY");
+        assert!(Money::from_str("JPY 500.5").is_err());
+        assert!(Money::from_str("12.345").is_err());
+    }
+
+    #[test]
+    fn test_totals_have_no_rounding_noise() {
+        let mut totals = MoneyTotals::default();
+        for _ in 0..10 {
+            totals.add(Money::from_str("0.10").unwrap());
+        }
+        assert_eq!(totals.amounts(), vec![Money::from_str("1.00 USD").unwrap()]);
+    }
+
+    #[test]
+    fn test_total_in_home_currency() {
+        let mut totals = MoneyTotals::default();
+        totals.add(Money::from_str("10.00 USD").unwrap());
+        totals.add(Money::from_str("10.00 EUR").unwrap());
+        let rates = vec![(eur(), Rate::from_str("1.085").unwrap())];
+        assert_eq!(
+            totals.total_in(Currency::USD, &rates).unwrap().to_string(),
+            "20.85 USD"
+        );
+        assert_eq!(totals.total_in(Currency::USD, &[]), Err(vec![eur()]));
+    }
+
+    #[test]
+    fn test_totals_saturate() {
+        let mut totals = MoneyTotals::default();
+        totals.add(Money::from_minor(i64::MAX, Currency::USD));
+        totals.add(Money::from_minor(i64::MAX, Currency::USD));
+        assert_eq!(
+            totals.amounts(),
+            vec![Money::from_minor(i64::MAX, Currency::USD)]
+        );
+        assert!(totals.total_in(Currency::USD, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_home_currency_change_converts_rates() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::init_db(&conn).unwrap();
+        let gbp = Currency::from_str("GBP").unwrap();
+        set_exchange_rate(&conn, eur(), Rate::from_str("1.1").unwrap()).unwrap();
+        assert!(set_home_currency(&conn, gbp).is_err());
+        assert_eq!(get_home_currency(&conn).unwrap(), Currency::USD);
+
+        set_exchange_rate(&conn, gbp, Rate::from_str("1.25").unwrap()).unwrap();
+        set_home_currency(&conn, gbp).unwrap();
+        assert_eq!(get_home_currency(&conn).unwrap(), gbp);
+        let rates = get_rate_table(&conn).unwrap();
+        assert_eq!(
+            rates,
+            vec![
+                (eur(), Rate::from_str("0.88").unwrap()),
+                (Currency::USD, Rate::from_str("0.8").unwrap()),
+            ]
+        );
+
+        let fmt = MoneyFormat {
+            decimal_separator: ',',
+            group_separator: Some('.'),
+            code_position: CodePosition::After,
+        };
+        assert_eq!(get_money_format(&conn).unwrap(), MoneyFormat::default());
+        set_money_format(&conn, &fmt).unwrap();
+        assert_eq!(get_money_format(&conn).unwrap(), fmt);
+        assert!(
+            set_money_format(
+                &conn,
+                &MoneyFormat {
+                    group_separator: Some(','),
+                    ..fmt
+                }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let m = Money::from_str("-1234567.8 EUR").unwrap();
+        assert_eq!(m.format(&MoneyFormat::default()), "EUR -1,234,567.80");
+        let fmt = MoneyFormat {
+            decimal_separator: ',',
+            group_separator: Some('.'),
+            code_position: CodePosition::After,
+        };
+        assert_eq!(m.format(&fmt), "-1.234.567,80 EUR");
+    }
+}
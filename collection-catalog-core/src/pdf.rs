@@ -0,0 +1,326 @@
+// Minimal PDF 1.4 writer used by the printable reports. It only knows the standard
+// Helvetica fonts, lines and baseline JPEG images, which is all the reports need and
+// avoids pulling in a full PDF library.
+
+pub(crate) const PAGE_WIDTH: f32 = 612.0; // US Letter, in points
+pub(crate) const PAGE_HEIGHT: f32 = 792.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Font {
+    Regular,
+    Bold,
+}
+
+struct JpegImage {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    components: u8,
+}
+
+pub(crate) struct PdfDocument {
+    pages: Vec<Vec<u8>>,
+    images: Vec<JpegImage>,
+    page_size: (f32, f32),
+}
+
+impl Default for PdfDocument {
+    fn default() -> Self {
+        PdfDocument {
+            pages: vec![],
+            images: vec![],
+            page_size: (PAGE_WIDTH, PAGE_HEIGHT),
+        }
+    }
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A document with pages `width` by `height` points instead of US Letter.
+    pub fn with_page_size(width: f32, height: f32) -> Self {
+        PdfDocument {
+            page_size: (width, height),
+            ..Self::default()
+        }
+    }
+
+    pub fn add_page(&mut self) -> usize {
+        self.pages.push(Vec::new());
+        self.pages.len() - 1
+    }
+
+    pub fn text(&mut self, page: usize, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        let font = match font {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        };
+        let out = &mut self.pages[page];
+        out.extend_from_slice(
+            format!("BT /{} {} Tf {:.2} {:.2} Td (", font, size, x, y).as_bytes(),
+        );
+        for c in text.chars() {
+            match encode_win_ansi(c) {
+                b'(' | b')' | b'\\' => {
+                    out.push(b'\\');
+                    out.push(c as u8);
+                }
+                b => out.push(b),
+            }
+        }
+        out.extend_from_slice(b") Tj ET\n");
+    }
+
+    pub fn line(&mut self, page: usize, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.pages[page].extend_from_slice(
+            format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", x1, y1, x2, y2).as_bytes(),
+        );
+    }
+
+    /// Fills a black rectangle with its lower left corner at `(x, y)`.
+    pub fn rect(&mut self, page: usize, x: f32, y: f32, w: f32, h: f32) {
+        self.pages[page]
+            .extend_from_slice(format!("{:.2} {:.2} {:.2} {:.2} re f\n", x, y, w, h).as_bytes());
+    }
+
+    /// Registers a JPEG for drawing. Returns `None` if the data isn't a JPEG we can embed.
+    pub fn add_jpeg(&mut self, data: Vec<u8>) -> Option<usize> {
+        let (width, height, components) = jpeg_info(&data)?;
+        self.images.push(JpegImage {
+            data,
+            width,
+            height,
+            components,
+        });
+        Some(self.images.len() - 1)
+    }
+
+    pub fn image_size(&self, image: usize) -> (u32, u32) {
+        let img = &self.images[image];
+        (img.width, img.height)
+    }
+
+    /// Draws an image with its lower left corner at `(x, y)`.
+    pub fn image(&mut self, page: usize, image: usize, x: f32, y: f32, w: f32, h: f32) {
+        self.pages[page].extend_from_slice(
+            format!(
+                "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q\n",
+                w, h, x, y, image
+            )
+            .as_bytes(),
+        );
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Object layout: 1 catalog, 2 page tree, 3-4 fonts, then images, then a
+        // (page, content) pair per page.
+        let first_image = 5;
+        let first_page = first_image + self.images.len();
+        let page_ids: Vec<usize> = (0..self.pages.len()).map(|i| first_page + i * 2).collect();
+
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = vec![];
+        let mut object = |out: &mut Vec<u8>, body: &[u8]| {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        };
+
+        object(&mut out, b"<< /Type /Catalog /Pages 2 0 R >>");
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        object(
+            &mut out,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                page_ids.len()
+            )
+            .as_bytes(),
+        );
+        for name in ["Helvetica", "Helvetica-Bold"] {
+            object(
+                &mut out,
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    name
+                )
+                .as_bytes(),
+            );
+        }
+
+        for img in &self.images {
+            let color_space = match img.components {
+                1 => "/DeviceGray",
+                4 => "/DeviceCMYK",
+                _ => "/DeviceRGB",
+            };
+            let mut body = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} \
+                 /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                img.width,
+                img.height,
+                color_space,
+                img.data.len()
+            )
+            .into_bytes();
+            body.extend_from_slice(&img.data);
+            body.extend_from_slice(b"\nendstream");
+            object(&mut out, &body);
+        }
+
+        let xobjects: Vec<String> = (0..self.images.len())
+            .map(|i| format!("/Im{} {} 0 R", i, first_image + i))
+            .collect();
+        for (i, content) in self.pages.iter().enumerate() {
+            object(
+                &mut out,
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R >> /XObject << {} >> >> >>",
+                    self.page_size.0,
+                    self.page_size.1,
+                    page_ids[i] + 1,
+                    xobjects.join(" ")
+                )
+                .as_bytes(),
+            );
+            let mut body = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\nendstream");
+            object(&mut out, &body);
+        }
+
+        let xref = out.len();
+        out.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes(),
+        );
+        for offset in &offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                offsets.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+/// Approximate width of `text` in points, close enough to wrap lines.
+pub(crate) fn text_width(text: &str, size: f32) -> f32 {
+    text.chars()
+        .map(|c| match c {
+            ' ' | 'i' | 'j' | 'l' | 'I' | '.' | ',' | '\'' | '!' | ':' | ';' | '|' => 0.28,
+            'f' | 't' | 'r' | '(' | ')' | '-' => 0.33,
+            'm' | 'w' | 'M' | 'W' => 0.85,
+            'A'..='Z' => 0.68,
+            _ => 0.55,
+        })
+        .sum::<f32>()
+        * size
+}
+
+/// Splits `text` into lines no wider than `max_width` points.
+pub(crate) fn wrap_text(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && text_width(&candidate, size) > max_width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn encode_win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\u{A0}'..='\u{FF}' => c as u32 as u8,
+        '€' => 0x80,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        _ => b'?',
+    }
+}
+
+/// Width, height and component count from a JPEG's start-of-frame header.
+fn jpeg_info(data: &[u8]) -> Option<(u32, u32, u8)> {
+    if data.get(0..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        let len = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        // SOF0-SOF15, excluding DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let seg = data.get(i + 4..i + 2 + len)?;
+            let height = u16::from_be_bytes([*seg.get(1)?, *seg.get(2)?]) as u32;
+            let width = u16::from_be_bytes([*seg.get(3)?, *seg.get(4)?]) as u32;
+            return Some((width, height, *seg.get(5)?));
+        }
+        i += 2 + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_structure() {
+        let mut doc = PdfDocument::new();
+        let page = doc.add_page();
+        doc.text(page, 50.0, 700.0, 12.0, Font::Bold, "Lamp (brass) – €20");
+        let bytes = doc.to_bytes();
+        let text = String::from_utf8_lossy(&bytes);
+
+        assert!(bytes.starts_with(b"%PDF-1.4"));
+        assert!(text.contains("/Count 1"));
+        assert!(text.contains("Lamp \\(brass\\)"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        let lines = wrap_text("one two three four five six", 10.0, 60.0);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| text_width(l, 10.0) <= 60.0));
+        assert_eq!(lines.join(" "), "one two three four five six");
+    }
+
+    #[test]
+    fn test_rejects_non_jpeg() {
+        let mut doc = PdfDocument::new();
+        assert!(doc.add_jpeg(b"\x89PNG\r\n".to_vec()).is_none());
+    }
+}
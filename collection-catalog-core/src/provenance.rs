@@ -0,0 +1,368 @@
+use crate::fuzzy_date::FuzzyDate;
+use anyhow::{Result as AnyResult, anyhow};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProvenanceRole {
+    #[default]
+    Owner,
+    Dealer,
+    Auction,
+}
+
+impl fmt::Display for ProvenanceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ProvenanceRole::Owner => "Owner",
+                ProvenanceRole::Dealer => "Dealer",
+                ProvenanceRole::Auction => "Auction",
+            }
+        )
+    }
+}
+
+impl FromStr for ProvenanceRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "owner" => Ok(ProvenanceRole::Owner),
+            "dealer" => Ok(ProvenanceRole::Dealer),
+            "auction" => Ok(ProvenanceRole::Auction),
+            _ => Err(format!("Unknown provenance role: '{}'", s)),
+        }
+    }
+}
+
+/// One link in an item's ownership chain. Events are kept in chain order by `position`,
+/// oldest first.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceEvent {
+    #[serde(default)]
+    pub id: i32,
+    pub item_id: i32,
+    #[serde(default)]
+    pub position: i32,
+    pub role: ProvenanceRole,
+    /// Person, firm or auction house
+    pub name: String,
+    pub date_from: Option<FuzzyDate>,
+    pub date_to: Option<FuzzyDate>,
+    pub location: Option<String>,
+    /// Supporting document, e.g. a sale catalogue and lot number or a letter
+    pub source: Option<String>,
+}
+
+impl ProvenanceEvent {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.name.trim().is_empty() {
+            errors.push("Provenance name cannot be empty.".to_string());
+        }
+        if let (Some(from), Some(to)) = (self.date_from, self.date_to)
+            && from.earliest() > to.latest()
+        {
+            errors.push("Provenance 'from' date is after its 'to' date.".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let date = |column: &str| -> rusqlite::Result<Option<FuzzyDate>> {
+            Ok(row
+                .get::<_, Option<String>>(column)?
+                .and_then(|s| FuzzyDate::from_str(&s).ok()))
+        };
+        Ok(ProvenanceEvent {
+            id: row.get("id")?,
+            item_id: row.get("item_id")?,
+            position: row.get("position")?,
+            role: ProvenanceRole::from_str(&row.get::<_, String>("role")?).unwrap_or_default(),
+            name: row.get("name")?,
+            date_from: date("date_from")?,
+            date_to: date("date_to")?,
+            location: row.get("location")?,
+            source: row.get("source")?,
+        })
+    }
+}
+
+/// Reads like "Owner: Lady Anne Smith, Bath, 1890-1921 (source: Sotheby's 1921, lot 42)".
+impl fmt::Display for ProvenanceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.role, self.name)?;
+        if let Some(location) = &self.location {
+            write!(f, ", {}", location)?;
+        }
+        match (self.date_from, self.date_to) {
+            (Some(from), Some(to)) => write!(f, ", {}-{}", from, to)?,
+            (Some(from), None) => write!(f, ", from {}", from)?,
+            (None, Some(to)) => write!(f, ", until {}", to)?,
+            (None, None) => {}
+        }
+        if let Some(source) = &self.source {
+            write!(f, " (source: {})", source)?;
+        }
+        Ok(())
+    }
+}
+
+/// The whole chain on one line, oldest first.
+pub fn provenance_summary(events: &[ProvenanceEvent]) -> String {
+    events
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+pub fn get_provenance(conn: &Connection, item_id: i32) -> Result<Vec<ProvenanceEvent>> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM provenance_events WHERE item_id = ?1 ORDER BY position, id")?;
+    let rows = stmt.query_map(params![item_id], ProvenanceEvent::from_row)?;
+    rows.collect()
+}
+
+pub fn get_provenance_event(conn: &Connection, event_id: i32) -> Result<Option<ProvenanceEvent>> {
+    conn.query_row(
+        "SELECT * FROM provenance_events WHERE id = ?1",
+        params![event_id],
+        ProvenanceEvent::from_row,
+    )
+    .optional()
+}
+
+// The chain is mirrored onto the item as text so it shows up in CSV exports and listings
+pub(crate) fn refresh_summary(conn: &Connection, item_id: i32) -> Result<()> {
+    let events = get_provenance(conn, item_id)?;
+    let summary = Some(provenance_summary(&events)).filter(|s| !s.is_empty());
+    conn.execute(
+        "UPDATE items SET provenance_chain = ?1 WHERE id = ?2",
+        params![summary, item_id],
+    )?;
+    Ok(())
+}
+
+// Renumbers an item's events 1..n in their current order
+fn renumber(conn: &Connection, item_id: i32, ordered_ids: &[i32]) -> Result<()> {
+    for (index, id) in ordered_ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE provenance_events SET position = ?1 WHERE id = ?2",
+            params![index as i32 + 1, id],
+        )?;
+    }
+    refresh_summary(conn, item_id)
+}
+
+/// Adds an event to the end of the item's chain. Returns the event id.
+pub fn append_provenance_event(conn: &Connection, event: &ProvenanceEvent) -> AnyResult<i32> {
+    event
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO provenance_events
+            (item_id, position, role, name, date_from, date_to, location, source)
+        VALUES (
+            ?1,
+            (SELECT COALESCE(MAX(position), 0) + 1 FROM provenance_events WHERE item_id = ?1),
+            ?2, ?3, ?4, ?5, ?6, ?7
+        )",
+        params![
+            event.item_id,
+            event.role.to_string(),
+            event.name.trim(),
+            event.date_from.map(|d| d.to_string()),
+            event.date_to.map(|d| d.to_string()),
+            event.location,
+            event.source,
+        ],
+    )?;
+    let id = tx.last_insert_rowid() as i32;
+    refresh_summary(&tx, event.item_id)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Saves changes to an existing event. Its position is changed with `move_provenance_event`.
+pub fn update_provenance_event(conn: &Connection, event: &ProvenanceEvent) -> AnyResult<()> {
+    event
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+
+    let tx = conn.unchecked_transaction()?;
+    let changed = tx.execute(
+        "UPDATE provenance_events SET
+            role = ?1, name = ?2, date_from = ?3, date_to = ?4, location = ?5, source = ?6
+        WHERE id = ?7",
+        params![
+            event.role.to_string(),
+            event.name.trim(),
+            event.date_from.map(|d| d.to_string()),
+            event.date_to.map(|d| d.to_string()),
+            event.location,
+            event.source,
+            event.id,
+        ],
+    )?;
+    if changed == 0 {
+        return Err(anyhow!("Provenance event {} not found", event.id));
+    }
+    let item_id: i32 = tx.query_row(
+        "SELECT item_id FROM provenance_events WHERE id = ?1",
+        params![event.id],
+        |row| row.get(0),
+    )?;
+    refresh_summary(&tx, item_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Moves an event to `position` (1 = oldest) within its item's chain.
+pub fn move_provenance_event(conn: &Connection, event_id: i32, position: usize) -> AnyResult<()> {
+    let item_id: i32 = conn
+        .query_row(
+            "SELECT item_id FROM provenance_events WHERE id = ?1",
+            params![event_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("Provenance event {} not found", event_id))?;
+
+    let mut ids: Vec<i32> = get_provenance(conn, item_id)?
+        .into_iter()
+        .map(|e| e.id)
+        .filter(|&id| id != event_id)
+        .collect();
+    let index = position.clamp(1, ids.len() + 1) - 1;
+    ids.insert(index, event_id);
+
+    let tx = conn.unchecked_transaction()?;
+    renumber(&tx, item_id, &ids)?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn delete_provenance_event(conn: &Connection, event_id: i32) -> AnyResult<()> {
+    let tx = conn.unchecked_transaction()?;
+    let item_id: i32 = tx
+        .query_row(
+            "SELECT item_id FROM provenance_events WHERE id = ?1",
+            params![event_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("Provenance event {} not found", event_id))?;
+    tx.execute(
+        "DELETE FROM provenance_events WHERE id = ?1",
+        params![event_id],
+    )?;
+    let ids: Vec<i32> = get_provenance(&tx, item_id)?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    renumber(&tx, item_id, &ids)?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, get_filtered_items, get_item_by_id, init_db};
+    use crate::models::{Item, ItemFilter};
+
+    fn event(name: &str, role: ProvenanceRole, from: Option<&str>) -> ProvenanceEvent {
+        ProvenanceEvent {
+            item_id: 1,
+            role,
+            name: name.to_string(),
+            date_from: from.map(|d| d.parse().unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_chain_order_and_summary() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let item = Item {
+            name: "Clock".to_string(),
+            description: "Bracket clock".to_string(),
+            ..Default::default()
+        };
+        add_item(&conn, &item).unwrap();
+
+        let first = append_provenance_event(
+            &conn,
+            &ProvenanceEvent {
+                location: Some("Bath".to_string()),
+                date_to: Some("1921".parse().unwrap()),
+                ..event("Lady Smith", ProvenanceRole::Owner, Some("c.1890"))
+            },
+        )
+        .unwrap();
+        let auction = append_provenance_event(
+            &conn,
+            &ProvenanceEvent {
+                source: Some("Catalogue, lot 42".to_string()),
+                ..event("Sotheby's", ProvenanceRole::Auction, Some("1921-06"))
+            },
+        )
+        .unwrap();
+        let dealer =
+            append_provenance_event(&conn, &event("J. Brown", ProvenanceRole::Dealer, None))
+                .unwrap();
+
+        // Dealer found to have sold it before the auction
+        move_provenance_event(&conn, dealer, 2).unwrap();
+        let ids: Vec<i32> = get_provenance(&conn, 1)
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![first, dealer, auction]);
+
+        delete_provenance_event(&conn, dealer).unwrap();
+        let item = get_item_by_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(
+            item.provenance_chain.as_deref(),
+            Some(
+                "Owner: Lady Smith, Bath, c.1890±5-1921 -> \
+                 Auction: Sotheby's, from 1921-06 (source: Catalogue, lot 42)"
+            )
+        );
+
+        let filter = ItemFilter {
+            provenance_event_contains: Some("lot 42".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(get_filtered_items(&conn, filter).unwrap().len(), 1);
+        // Wildcards in the search text are taken literally
+        let filter = ItemFilter {
+            provenance_event_contains: Some("lot_42".to_string()),
+            ..Default::default()
+        };
+        assert!(get_filtered_items(&conn, filter).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dates_must_be_in_order() {
+        let bad = ProvenanceEvent {
+            date_to: Some("1850".parse().unwrap()),
+            ..event("Someone", ProvenanceRole::Owner, Some("1900"))
+        };
+        assert!(bad.validate().is_err());
+    }
+}
@@ -0,0 +1,948 @@
+//! A small text query language for finding items, e.g.
+//!
+//! ```text
+//! category:Book value>100 (creator:tolkien OR creator:lewis) -deleted
+//! ```
+//!
+//! Terms next to each other must all match; `OR` between terms matches either, `-term` or
+//! `NOT term` negates, and parentheses group. A term is one of:
+//!
+//! * `field:value` - contains for text fields, equals for everything else. Numbers, money,
+//!   dates and measurements also take ranges written `low..high`, with either end optional.
+//! * `field=value` - exact match, ignoring case for text.
+//! * `field>value`, `>=`, `<`, `<=` - comparisons on numbers, money, dates and measurements.
+//! * A bare word or `"quoted phrase"`, matched against name and description. The names of
+//!   yes/no fields (`working`, `deleted`, `wishlist`) match items where that field is true.
+//!
+//! Wishlist items are left out unless the query mentions a wishlist field, and deleted items
+//! unless it mentions `deleted`.
+//!
+//! Values containing spaces are quoted: `added>"last 30 days"`, `value<"50 EUR"`.
+
+use crate::db::{ItemStream, like_contains};
+use crate::fuzzy_date::{FuzzyDate, made_after_for_age, made_by_for_age};
+use crate::measure::{Length, LengthUnit, Weight, WeightUnit};
+use crate::models::Item;
+use crate::money::{Currency, Money, get_home_currency};
+use crate::saved_search::DateExpr;
+use anyhow::Result as AnyResult;
+use chrono::{Local, NaiveDate};
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// A problem with a query, at a 0-based character offset into the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        QueryError {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    /// Free text, `:` is a contains match
+    Text,
+    /// Fixed set of values such as category, matched ignoring case
+    Enum,
+    Bool,
+    Integer,
+    Money,
+    Date,
+    /// Date made, stored as the earliest and latest dates it could be
+    DateMade,
+    /// Derived from date made
+    Age,
+    Length,
+    Weight,
+}
+
+/// A queryable item field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    name: &'static str,
+    column: &'static str,
+    kind: FieldKind,
+}
+
+impl Field {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+const fn field(name: &'static str, column: &'static str, kind: FieldKind) -> Field {
+    Field { name, column, kind }
+}
+
+/// Every queryable field. Money columns are prefixes of their `_minor`/`_currency` pair.
+const FIELDS: &[Field] = &[
+    field("id", "id", FieldKind::Integer),
+    field("name", "name", FieldKind::Text),
+    field("description", "description", FieldKind::Text),
+    field("category", "category", FieldKind::Enum),
+    field("action", "action", FieldKind::Enum),
+    field("creator", "creator", FieldKind::Text),
+    field("provenance", "provenance", FieldKind::Text),
+    field("location", "location", FieldKind::Text),
+    field("condition", "condition", FieldKind::Text),
+    field("condition_notes", "condition_notes", FieldKind::Text),
+    field("working", "working", FieldKind::Bool),
+    field("deleted", "deleted", FieldKind::Bool),
+    field("wishlist", "wishlist", FieldKind::Bool),
+    field("date_added", "date_added", FieldKind::Date),
+    field("last_updated", "last_updated", FieldKind::Date),
+    field("date_acquired", "date_acquired", FieldKind::Date),
+    field("date_made", "date_made", FieldKind::DateMade),
+    field("age_years", "date_made", FieldKind::Age),
+    field("purchase_price", "purchase_price", FieldKind::Money),
+    field("estimated_value", "estimated_value", FieldKind::Money),
+    field("target_price", "target_price", FieldKind::Money),
+    field("wishlist_priority", "wishlist_priority", FieldKind::Integer),
+    field("length", "length_mm", FieldKind::Length),
+    field("width", "width_mm", FieldKind::Length),
+    field("height", "height_mm", FieldKind::Length),
+    field("weight", "weight_g", FieldKind::Weight),
+    field("quantity", "COALESCE(quantity, 1)", FieldKind::Integer),
+];
+
+/// Shorter names accepted in queries.
+const ALIASES: &[(&str, &str)] = &[
+    ("added", "date_added"),
+    ("updated", "last_updated"),
+    ("acquired", "date_acquired"),
+    ("made", "date_made"),
+    ("age", "age_years"),
+    ("price", "purchase_price"),
+    ("value", "estimated_value"),
+    ("notes", "condition_notes"),
+    ("target", "target_price"),
+    ("priority", "wishlist_priority"),
+];
+
+pub fn find_field(name: &str) -> Option<Field> {
+    let name = name.to_ascii_lowercase();
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name.as_str(), |(_, field)| field);
+    FIELDS.iter().find(|f| f.name == name).copied()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `:`, contains for text and equals otherwise
+    Matches,
+    Equals,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Op::Matches => ":",
+            Op::Equals => "=",
+            Op::Greater => ">",
+            Op::GreaterOrEqual => ">=",
+            Op::Less => "<",
+            Op::LessOrEqual => "<=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// A typed value from a term. Money keeps its text so a bare amount can take the home
+/// currency, and relative dates are worked out when the query runs.
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Text(String),
+    Bool(bool),
+    Integer(i64),
+    Money(String),
+    Date(DateValue),
+    Length(Length),
+    Weight(Weight),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateValue {
+    Fuzzy(FuzzyDate),
+    Relative(DateExpr),
+}
+
+impl DateValue {
+    fn span(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+        match self {
+            DateValue::Fuzzy(date) => date
+                .earliest()
+                .zip(date.latest())
+                .ok_or_else(|| format!("Date out of range: '{}'", date)),
+            DateValue::Relative(expr) => {
+                let date = expr.resolve(today)?;
+                Ok((date, date))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    field: Option<Field>,
+    op: Op,
+    /// One operand, or two for a `low..high` range with either end optional
+    low: Option<Operand>,
+    high: Option<Operand>,
+    range: bool,
+    position: usize,
+}
+
+/// A parsed query, see the module docs for the syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    /// A bare word or quoted phrase
+    Word(String),
+    Field {
+        name: String,
+        op: Op,
+        value: String,
+        value_position: usize,
+    },
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn new(text: &str) -> Self {
+        Lexer {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn ends_word(c: char) -> bool {
+        c.is_whitespace() || c == '(' || c == ')'
+    }
+
+    // A quoted string starting at the current `"`, without the quotes
+    fn quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(QueryError::new(start, "Unclosed quote")),
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek().filter(|&c| !Self::ends_word(c)) {
+            word.push(c);
+            self.pos += 1;
+        }
+        word
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        let (op, len) = match (self.peek()?, self.chars.get(self.pos + 1)) {
+            (':', _) => (Op::Matches, 1),
+            ('=', _) => (Op::Equals, 1),
+            ('>', Some('=')) => (Op::GreaterOrEqual, 2),
+            ('>', _) => (Op::Greater, 1),
+            ('<', Some('=')) => (Op::LessOrEqual, 2),
+            ('<', _) => (Op::Less, 1),
+            _ => return None,
+        };
+        self.pos += len;
+        Some(op)
+    }
+
+    fn tokens(mut self) -> Result<Vec<(Token, usize)>, QueryError> {
+        let mut tokens = vec![];
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            let token = match c {
+                c if c.is_whitespace() => {
+                    self.pos += 1;
+                    continue;
+                }
+                '(' => {
+                    self.pos += 1;
+                    Token::LParen
+                }
+                ')' => {
+                    self.pos += 1;
+                    Token::RParen
+                }
+                '-' if self
+                    .chars
+                    .get(self.pos + 1)
+                    .is_some_and(|&c| !Self::ends_word(c)) =>
+                {
+                    self.pos += 1;
+                    Token::Not
+                }
+                '"' => Token::Word(self.quoted()?),
+                _ => {
+                    let name_end = self.chars[self.pos..]
+                        .iter()
+                        .position(|&c| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .map_or(self.chars.len(), |i| self.pos + i);
+                    let name: String = self.chars[self.pos..name_end].iter().collect();
+                    self.pos = name_end;
+                    match self.op().filter(|_| !name.is_empty()) {
+                        Some(op) => {
+                            let value_position = self.pos;
+                            let value = match self.peek() {
+                                Some('"') => self.quoted()?,
+                                _ => self.word(),
+                            };
+                            if value.is_empty() {
+                                return Err(QueryError::new(
+                                    value_position,
+                                    format!("Missing value after '{}{}'", name, op),
+                                ));
+                            }
+                            Token::Field {
+                                name,
+                                op,
+                                value,
+                                value_position,
+                            }
+                        }
+                        None => {
+                            self.pos = start;
+                            match self.word().as_str() {
+                                "AND" => Token::And,
+                                "OR" => Token::Or,
+                                "NOT" => Token::Not,
+                                word => Token::Word(word.to_string()),
+                            }
+                        }
+                    }
+                }
+            };
+            tokens.push((token, start));
+        }
+        Ok(tokens)
+    }
+}
+
+// Measurements without a unit take the one the app defaults to
+fn parse_length(s: &str) -> Result<Length, String> {
+    Length::from_str(s).or_else(|e| match s.trim().parse::<f64>() {
+        Ok(value) => Ok(Length {
+            value,
+            unit: LengthUnit::default(),
+        }),
+        Err(_) => Err(e),
+    })
+}
+
+fn parse_weight(s: &str) -> Result<Weight, String> {
+    Weight::from_str(s).or_else(|e| match s.trim().parse::<f64>() {
+        Ok(value) => Ok(Weight {
+            value,
+            unit: WeightUnit::default(),
+        }),
+        Err(_) => Err(e),
+    })
+}
+
+// Allows `100EUR` as well as `100 EUR`
+fn money_text(s: &str) -> String {
+    match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) if i > 0 => format!("{} {}", &s[..i], &s[i..]),
+        _ => s.to_string(),
+    }
+}
+
+fn parse_operand(field: &Field, text: &str) -> Result<Operand, String> {
+    match field.kind {
+        FieldKind::Text | FieldKind::Enum => Ok(Operand::Text(text.to_string())),
+        FieldKind::Bool => match text.to_ascii_lowercase().as_str() {
+            "true" | "yes" => Ok(Operand::Bool(true)),
+            "false" | "no" => Ok(Operand::Bool(false)),
+            _ => Err(format!("Expected true or false for '{}'", field.name)),
+        },
+        FieldKind::Integer | FieldKind::Age => text
+            .parse()
+            .map(Operand::Integer)
+            .map_err(|_| format!("Expected a whole number for '{}'", field.name)),
+        FieldKind::Money => {
+            let text = money_text(text);
+            Money::parse_with_default(&text, Currency::default())?;
+            Ok(Operand::Money(text))
+        }
+        FieldKind::Date | FieldKind::DateMade => FuzzyDate::from_str(text)
+            .map(DateValue::Fuzzy)
+            .or_else(|_| DateExpr::from_str(text).map(DateValue::Relative))
+            .map(Operand::Date)
+            .map_err(|_| format!("Invalid date for '{}': '{}'", field.name, text)),
+        FieldKind::Length => parse_length(text).map(Operand::Length),
+        FieldKind::Weight => parse_weight(text).map(Operand::Weight),
+    }
+}
+
+fn parse_term(
+    name: &str,
+    op: Op,
+    value: &str,
+    position: usize,
+    value_position: usize,
+) -> Result<Term, QueryError> {
+    let field = find_field(name)
+        .ok_or_else(|| QueryError::new(position, format!("Unknown field '{}'", name)))?;
+    let comparable = !matches!(
+        field.kind,
+        FieldKind::Text | FieldKind::Enum | FieldKind::Bool
+    );
+    if !comparable && !matches!(op, Op::Matches | Op::Equals) {
+        return Err(QueryError::new(
+            value_position - 1,
+            format!("'{}' can't be compared with '{}'", field.name, op),
+        ));
+    }
+
+    let operand = |text: &str, offset: usize| -> Result<Option<Operand>, QueryError> {
+        if text.is_empty() {
+            return Ok(None);
+        }
+        parse_operand(&field, text)
+            .map(Some)
+            .map_err(|e| QueryError::new(value_position + offset, e))
+    };
+    let (low, high, range) = match value.split_once("..") {
+        Some((low, high)) if comparable && op == Op::Matches => {
+            if low.is_empty() && high.is_empty() {
+                return Err(QueryError::new(value_position, "Empty range"));
+            }
+            (
+                operand(low, 0)?,
+                operand(high, low.chars().count() + 2)?,
+                true,
+            )
+        }
+        _ => (operand(value, 0)?, None, false),
+    };
+    Ok(Term {
+        field: Some(field),
+        op,
+        low,
+        high,
+        range,
+        position,
+    })
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, p)| *p)
+    }
+
+    fn or_expr(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.and_expr()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            terms.push(self.and_expr()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Query::Or(terms)
+        })
+    }
+
+    fn and_expr(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::RParen) => break,
+                Some(Token::And) => self.pos += 1,
+                _ => {}
+            }
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Query::And(terms)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Query, QueryError> {
+        let position = self.position();
+        let Some((token, _)) = self.tokens.get(self.pos).cloned() else {
+            return Err(QueryError::new(position, "Expected a search term"));
+        };
+        self.pos += 1;
+        match token {
+            Token::LParen => {
+                let query = self.or_expr()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(QueryError::new(position, "Unclosed '('"));
+                }
+                self.pos += 1;
+                Ok(query)
+            }
+            Token::Word(word) => {
+                // The name of a yes/no field on its own means that field is true
+                let flag = find_field(&word).filter(|f| f.kind == FieldKind::Bool);
+                Ok(Query::Term(Term {
+                    field: flag,
+                    op: Op::Matches,
+                    low: Some(match flag {
+                        Some(_) => Operand::Bool(true),
+                        None => Operand::Text(word),
+                    }),
+                    high: None,
+                    range: false,
+                    position,
+                }))
+            }
+            Token::Field {
+                name,
+                op,
+                value,
+                value_position,
+            } => Ok(Query::Term(parse_term(
+                &name,
+                op,
+                &value,
+                position,
+                value_position,
+            )?)),
+            Token::RParen => Err(QueryError::new(position, "Unexpected ')'")),
+            Token::Or | Token::And => Err(QueryError::new(
+                position,
+                "Expected a search term before OR/AND",
+            )),
+            Token::Not => unreachable!("handled in unary"),
+        }
+    }
+}
+
+/// Parses a query. An empty query matches every item.
+pub fn parse_query(text: &str) -> Result<Query, QueryError> {
+    let tokens = Lexer::new(text).tokens()?;
+    if tokens.is_empty() {
+        return Ok(Query::And(vec![]));
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: text.chars().count(),
+    };
+    let query = parser.or_expr()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(Token::RParen) => Err(QueryError::new(parser.position(), "Unexpected ')'")),
+        Some(_) => Err(QueryError::new(parser.position(), "Unexpected term")),
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_query(s)
+    }
+}
+
+/// Builds a WHERE clause with `?` placeholders, one parameter per placeholder.
+struct SqlBuilder {
+    home: Currency,
+    today: NaiveDate,
+    params: Vec<Value>,
+}
+
+impl SqlBuilder {
+    fn param(&mut self, value: impl Into<Value>) -> &'static str {
+        self.params.push(value.into());
+        "?"
+    }
+
+    fn query(&mut self, query: &Query) -> Result<String, QueryError> {
+        let join = |b: &mut Self, parts: &[Query], op: &str, empty: &str| {
+            if parts.is_empty() {
+                return Ok(empty.to_string());
+            }
+            let parts = parts
+                .iter()
+                .map(|q| b.query(q).map(|s| format!("({})", s)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(parts.join(op))
+        };
+        match query {
+            Query::And(parts) => join(self, parts, " AND ", "1"),
+            Query::Or(parts) => join(self, parts, " OR ", "0"),
+            // NULL columns count as not matching, so their negation matches
+            Query::Not(inner) => Ok(format!("NOT COALESCE(({}), 0)", self.query(inner)?)),
+            Query::Term(term) => self.term(term),
+        }
+    }
+
+    fn term(&mut self, term: &Term) -> Result<String, QueryError> {
+        let Some(field) = term.field else {
+            let Some(Operand::Text(text)) = &term.low else {
+                unreachable!("bare terms are text");
+            };
+            let pattern = like_contains(text);
+            let (a, b) = (self.param(pattern.clone()), self.param(pattern));
+            return Ok(format!(
+                "name LIKE {} ESCAPE '\\' OR description LIKE {} ESCAPE '\\'",
+                a, b
+            ));
+        };
+
+        if term.range {
+            let mut parts = vec![];
+            if let Some(low) = &term.low {
+                parts.push(self.compare(&field, Op::GreaterOrEqual, low, term.position)?);
+            }
+            if let Some(high) = &term.high {
+                parts.push(self.compare(&field, Op::LessOrEqual, high, term.position)?);
+            }
+            return Ok(parts.join(" AND "));
+        }
+        let operand = term.low.as_ref().expect("single terms have a value");
+        self.compare(&field, term.op, operand, term.position)
+    }
+
+    fn compare(
+        &mut self,
+        field: &Field,
+        op: Op,
+        operand: &Operand,
+        position: usize,
+    ) -> Result<String, QueryError> {
+        let column = field.column;
+        let sql_op = match op {
+            Op::Matches | Op::Equals => "=",
+            Op::Greater => ">",
+            Op::GreaterOrEqual => ">=",
+            Op::Less => "<",
+            Op::LessOrEqual => "<=",
+        };
+        Ok(match operand {
+            Operand::Text(text) if field.kind == FieldKind::Text && op == Op::Matches => {
+                format!(
+                    "{} LIKE {} ESCAPE '\\'",
+                    column,
+                    self.param(like_contains(text))
+                )
+            }
+            Operand::Text(text) => {
+                format!("{} = {} COLLATE NOCASE", column, self.param(text.clone()))
+            }
+            Operand::Bool(value) => format!("{} = {}", column, self.param(*value as i64)),
+            Operand::Integer(years) if field.kind == FieldKind::Age => self.age(op, *years),
+            Operand::Integer(n) => format!("{} {} {}", column, sql_op, self.param(*n)),
+            Operand::Money(text) => {
+                let money = Money::parse_with_default(text, self.home)
+                    .map_err(|e| QueryError::new(position, e))?;
+                format!(
+                    "{}_minor {} {} AND {}_currency = {}",
+                    column,
+                    sql_op,
+                    self.param(money.minor_units()),
+                    column,
+                    self.param(money.currency().code().to_string())
+                )
+            }
+            Operand::Date(date) => {
+                let (earliest, latest) = date
+                    .span(self.today)
+                    .map_err(|e| QueryError::new(position, e))?;
+                let (earliest, latest) = (earliest.to_string(), latest.to_string());
+                // Date made is itself a range, matched where the two could overlap
+                let (from, to) = if field.kind == FieldKind::DateMade {
+                    ("date_made_from", "date_made_to")
+                } else {
+                    (column, column)
+                };
+                match op {
+                    Op::Matches | Op::Equals => format!(
+                        "{} <= {} AND {} >= {}",
+                        from,
+                        self.param(latest),
+                        to,
+                        self.param(earliest)
+                    ),
+                    Op::Greater => format!("{} > {}", to, self.param(latest)),
+                    Op::GreaterOrEqual => format!("{} >= {}", to, self.param(earliest)),
+                    Op::Less => format!("{} < {}", from, self.param(earliest)),
+                    Op::LessOrEqual => format!("{} <= {}", from, self.param(latest)),
+                }
+            }
+            Operand::Length(length) => self.measure(column, op, length.millimetres()),
+            Operand::Weight(weight) => self.measure(column, op, weight.grams()),
+        })
+    }
+
+    // Measurements are converted on the way in, so equality allows for rounding
+    fn measure(&mut self, column: &str, op: Op, value: f64) -> String {
+        match op {
+            Op::Matches | Op::Equals => format!(
+                "{} BETWEEN {} AND {}",
+                column,
+                self.param(value - 0.01),
+                self.param(value + 0.01)
+            ),
+            Op::Greater => format!("{} > {}", column, self.param(value)),
+            Op::GreaterOrEqual => format!("{} >= {}", column, self.param(value)),
+            Op::Less => format!("{} < {}", column, self.param(value)),
+            Op::LessOrEqual => format!("{} <= {}", column, self.param(value)),
+        }
+    }
+
+    // Age is derived from date made as of today, matched like the `age_years` filters
+    fn age(&mut self, op: Op, years: i64) -> String {
+        let years = years.clamp(0, u32::MAX as i64 - 1) as u32;
+        // Ages older than the earliest representable date: nothing is at least that old, and
+        // every dated item is at most that old
+        let at_least = |b: &mut Self, y: u32| match made_by_for_age(b.today, y) {
+            Some(date) => format!("date_made_from <= {}", b.param(date.to_string())),
+            None => "0".to_string(),
+        };
+        let at_most = |b: &mut Self, y: u32| match made_after_for_age(b.today, y) {
+            Some(date) => format!("date_made_to >= {}", b.param(date.to_string())),
+            None => "date_made_to IS NOT NULL".to_string(),
+        };
+        match op {
+            Op::Matches | Op::Equals => {
+                format!("{} AND {}", at_least(self, years), at_most(self, years))
+            }
+            Op::Greater => at_least(self, years + 1),
+            Op::GreaterOrEqual => at_least(self, years),
+            Op::Less if years == 0 => "0".to_string(),
+            Op::Less => at_most(self, years - 1),
+            Op::LessOrEqual => at_most(self, years),
+        }
+    }
+}
+
+impl Query {
+    /// Compiles to a WHERE clause over `items` with `?` placeholders and their values.
+    /// Amounts without a currency are taken to be in `home`, and relative dates are worked
+    /// out for `today`.
+    pub fn to_sql(
+        &self,
+        home: Currency,
+        today: NaiveDate,
+    ) -> Result<(String, Vec<Value>), QueryError> {
+        let mut builder = SqlBuilder {
+            home,
+            today,
+            params: vec![],
+        };
+        let sql = builder.query(self)?;
+        Ok((sql, builder.params))
+    }
+
+    // Whether any term is about one of `fields`. Wishlist and deleted items are left out
+    // unless the query mentions them.
+    fn mentions(&self, fields: &[&str]) -> bool {
+        match self {
+            Query::And(parts) | Query::Or(parts) => parts.iter().any(|p| p.mentions(fields)),
+            Query::Not(inner) => inner.mentions(fields),
+            Query::Term(term) => term.field.is_some_and(|f| fields.contains(&f.name)),
+        }
+    }
+}
+
+/// Items matching `query`, amounts without a currency being in the home currency.
+pub fn query_items(conn: &Connection, query: &Query) -> AnyResult<Vec<Item>> {
+    stream_query_items(conn, query, |items| {
+        Ok(items.collect::<Result<Vec<_>, _>>()?)
+    })
+}
+
+/// Like `query_items`, but hands the items to `f` as they are read, see
+/// `stream_filtered_items`.
+pub fn stream_query_items<R>(
+    conn: &Connection,
+    query: &Query,
+    f: impl FnOnce(&mut ItemStream<'_>) -> AnyResult<R>,
+) -> AnyResult<R> {
+    let home = get_home_currency(conn)?;
+    let (sql, params) = query.to_sql(home, Local::now().date_naive())?;
+    let mut sql = format!("SELECT * FROM items WHERE ({})", sql);
+    if !query.mentions(&["wishlist", "target_price", "wishlist_priority"]) {
+        sql.push_str(" AND wishlist = 0");
+    }
+    if !query.mentions(&["deleted"]) {
+        sql.push_str(" AND deleted = 0");
+    }
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query_map(rusqlite::params_from_iter(params), Item::from_row)?;
+    f(&mut rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db, soft_delete_item};
+    use crate::models::ItemCategory;
+
+    fn names(conn: &Connection, query: &str) -> Vec<String> {
+        let query = parse_query(query).unwrap();
+        query_items(conn, &query)
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_query_items() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, category, creator, value, made) in [
+            (
+                "The Hobbit",
+                ItemCategory::Book,
+                "J.R.R. Tolkien",
+                "150",
+                "1937",
+            ),
+            ("Narnia", ItemCategory::Book, "C.S. Lewis", "120", "1950"),
+            ("Dune", ItemCategory::Book, "Frank Herbert", "80", "1965"),
+            (
+                "Mantel clock",
+                ItemCategory::Antique,
+                "Unknown",
+                "300",
+                "1890s",
+            ),
+        ] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                category,
+                creator: Some(creator.to_string()),
+                estimated_value: Some(value.parse().unwrap()),
+                date_made: Some(made.parse().unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        soft_delete_item(&conn, 2).unwrap();
+
+        assert_eq!(
+            names(
+                &conn,
+                "category:book value>100 (creator:tolkien OR creator:lewis) -deleted"
+            ),
+            vec!["The Hobbit"]
+        );
+        assert_eq!(names(&conn, "made:1930..1960 deleted"), vec!["Narnia"]);
+        assert_eq!(names(&conn, "made<1900"), vec!["Mantel clock"]);
+        assert_eq!(
+            names(&conn, "value:..100 OR clock"),
+            vec!["Dune", "Mantel clock"]
+        );
+        assert_eq!(
+            names(&conn, "NOT category=Book age>=100"),
+            vec!["Mantel clock"]
+        );
+        assert_eq!(names(&conn, "value>\"100 EUR\"").len(), 0);
+        assert_eq!(names(&conn, "").len(), 3);
+        assert_eq!(names(&conn, "narnia"), Vec::<String>::new());
+        assert_eq!(names(&conn, "narnia OR -deleted").len(), 4);
+        assert_eq!(names(&conn, "age>99999999999").len(), 0);
+        assert_eq!(names(&conn, "age<99999999999").len(), 3);
+        assert_eq!(names(&conn, "%"), Vec::<String>::new());
+        assert_eq!(names(&conn, "name:_"), Vec::<String>::new());
+        assert_eq!(names(&conn, "mantel_clock"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let error = |q: &str| parse_query(q).unwrap_err();
+        assert_eq!(error("category:Book tag:signed").position, 14);
+        assert_eq!(error("value>cheap").position, 6);
+        assert_eq!(error("name>b").message, "'name' can't be compared with '>'");
+        assert_eq!(error("(a OR b").position, 0);
+        assert_eq!(error("a b)").position, 3);
+        assert_eq!(error("name:\"open").position, 5);
+        assert_eq!(error("made:1900..later").position, 11);
+        assert_eq!(
+            error("age>old").to_string(),
+            "Expected a whole number for 'age_years' at column 5"
+        );
+
+        // Relative dates too far back fail when worked out, not with a panic
+        let query = parse_query("added>\"last 4294967295 years\"").unwrap();
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let error = query.to_sql(Currency::USD, today).unwrap_err();
+        assert_eq!(error.message, "Date out of range: 'last 4294967295 years'");
+    }
+}
@@ -0,0 +1,395 @@
+use crate::models::Item;
+use crate::money::{get_home_currency, get_rate_table};
+use crate::stats::{CatalogStats, compute_stats};
+use anyhow::{Result as AnyResult, anyhow};
+use rusqlite::{Connection, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How one item relates to another. Relations are stored as `item -> kind -> related`, so
+/// "the shade is part-of the lamp" or "the cup is a set-member of the tea set".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelationKind {
+    PartOf,
+    SetMember,
+    Related,
+    ReplacedBy,
+}
+
+impl RelationKind {
+    /// Part-of and set-member both say one item contains another, and are walked together
+    /// when collecting a set or checking for cycles.
+    pub fn is_containment(&self) -> bool {
+        matches!(self, RelationKind::PartOf | RelationKind::SetMember)
+    }
+
+    /// Label read from the `item` side, e.g. "Part of".
+    pub fn label(&self) -> &'static str {
+        match self {
+            RelationKind::PartOf => "Part of",
+            RelationKind::SetMember => "Member of set",
+            RelationKind::Related => "Related to",
+            RelationKind::ReplacedBy => "Replaced by",
+        }
+    }
+
+    /// Label read from the `related` side, e.g. "Has part".
+    pub fn inverse_label(&self) -> &'static str {
+        match self {
+            RelationKind::PartOf => "Has part",
+            RelationKind::SetMember => "Set contains",
+            RelationKind::Related => "Related to",
+            RelationKind::ReplacedBy => "Replaces",
+        }
+    }
+}
+
+impl fmt::Display for RelationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RelationKind::PartOf => "part-of",
+            RelationKind::SetMember => "set-member",
+            RelationKind::Related => "related",
+            RelationKind::ReplacedBy => "replaced-by",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for RelationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "part-of" | "partof" => Ok(RelationKind::PartOf),
+            "set-member" | "setmember" => Ok(RelationKind::SetMember),
+            "related" => Ok(RelationKind::Related),
+            "replaced-by" | "replacedby" => Ok(RelationKind::ReplacedBy),
+            _ => Err(format!("Unknown relation: '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemRelation {
+    pub id: i32,
+    pub item_id: i32,
+    pub related_id: i32,
+    pub kind: RelationKind,
+}
+
+impl ItemRelation {
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(ItemRelation {
+            id: row.get("id")?,
+            item_id: row.get("item_id")?,
+            related_id: row.get("related_id")?,
+            kind: RelationKind::from_str(&row.get::<_, String>("kind")?)
+                .unwrap_or(RelationKind::Related),
+        })
+    }
+}
+
+/// An item seen from another item's page, with the relation described from that side.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelatedItem {
+    pub relation_id: i32,
+    pub kind: RelationKind,
+    pub label: String,
+    pub item: Item,
+}
+
+// Every item reachable from `start` by following `kinds` relations forwards
+fn reachable(conn: &Connection, start: i32, kinds: &[RelationKind]) -> Result<Vec<i32>> {
+    let kinds: Vec<String> = kinds.iter().map(|k| format!("'{}'", k)).collect();
+    let sql = format!(
+        "WITH RECURSIVE reach(id) AS (
+            SELECT ?1
+            UNION
+            SELECT r.related_id FROM item_relations r JOIN reach ON r.item_id = reach.id
+            WHERE r.kind IN ({})
+        )
+        SELECT id FROM reach WHERE id != ?1",
+        kinds.join(", ")
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![start], |row| row.get(0))?;
+    rows.collect()
+}
+
+// Whether linking `item_id` to `related_id` would make an item (indirectly) contain or
+// replace itself
+fn would_cycle(
+    conn: &Connection,
+    item_id: i32,
+    kind: RelationKind,
+    related_id: i32,
+) -> Result<bool> {
+    let walked = match kind {
+        RelationKind::PartOf | RelationKind::SetMember => {
+            vec![RelationKind::PartOf, RelationKind::SetMember]
+        }
+        RelationKind::ReplacedBy => vec![RelationKind::ReplacedBy],
+        RelationKind::Related => return Ok(false),
+    };
+    Ok(reachable(conn, related_id, &walked)?.contains(&item_id))
+}
+
+// "Related" has no direction, so it is stored once with the lower id first
+fn stored_order(kind: RelationKind, item_id: i32, related_id: i32) -> (i32, i32) {
+    if kind == RelationKind::Related {
+        (item_id.min(related_id), item_id.max(related_id))
+    } else {
+        (item_id, related_id)
+    }
+}
+
+/// Links `item_id` to `related_id`. Containment and replacement relations are refused when
+/// they would make an item (indirectly) contain or replace itself. Returns the relation id.
+pub fn add_relation(
+    conn: &Connection,
+    item_id: i32,
+    kind: RelationKind,
+    related_id: i32,
+) -> AnyResult<i32> {
+    if item_id == related_id {
+        return Err(anyhow!("An item cannot be related to itself"));
+    }
+    for id in [item_id, related_id] {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM items WHERE id = ?1)",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(anyhow!("Item {} not found", id));
+        }
+    }
+
+    if would_cycle(conn, item_id, kind, related_id)? {
+        return Err(anyhow!(
+            "Linking item {} {} item {} would create a cycle",
+            item_id,
+            kind,
+            related_id
+        ));
+    }
+
+    let (item_id, related_id) = stored_order(kind, item_id, related_id);
+    conn.execute(
+        "INSERT INTO item_relations (item_id, related_id, kind) VALUES (?1, ?2, ?3)",
+        params![item_id, related_id, kind.to_string()],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            anyhow!(
+                "Items {} and {} are already linked as {}",
+                item_id,
+                related_id,
+                kind
+            )
+        }
+        e => e.into(),
+    })?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+/// Moves the relations of `merge_id` over to `keep_id` when merging duplicates. Relations that
+/// would now link the kept item to itself, repeat one it already has or close a cycle are
+/// dropped.
+pub(crate) fn move_relations(conn: &Connection, keep_id: i32, merge_id: i32) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM item_relations WHERE item_id = ?1 OR related_id = ?1 ORDER BY id",
+    )?;
+    let relations = stmt
+        .query_map(params![merge_id], ItemRelation::from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    conn.execute(
+        "DELETE FROM item_relations WHERE item_id = ?1 OR related_id = ?1",
+        params![merge_id],
+    )?;
+
+    let moved = |id| if id == merge_id { keep_id } else { id };
+    for relation in relations {
+        let (item_id, related_id) = (moved(relation.item_id), moved(relation.related_id));
+        if item_id == related_id || would_cycle(conn, item_id, relation.kind, related_id)? {
+            continue;
+        }
+        let (item_id, related_id) = stored_order(relation.kind, item_id, related_id);
+        conn.execute(
+            "INSERT OR IGNORE INTO item_relations (id, item_id, related_id, kind)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![relation.id, item_id, related_id, relation.kind.to_string()],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn remove_relation(conn: &Connection, relation_id: i32) -> Result<()> {
+    conn.execute(
+        "DELETE FROM item_relations WHERE id = ?1",
+        params![relation_id],
+    )?;
+    Ok(())
+}
+
+/// Relations touching `item_id` in either direction, with the other item loaded. Relations to
+/// deleted items are left out.
+pub fn get_related_items(conn: &Connection, item_id: i32) -> Result<Vec<RelatedItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id AS relation_id, r.kind AS relation_kind, r.item_id = ?1 AS outgoing, i.*
+        FROM item_relations r
+        JOIN items i ON i.id = CASE WHEN r.item_id = ?1 THEN r.related_id ELSE r.item_id END
+        WHERE (r.item_id = ?1 OR r.related_id = ?1) AND i.deleted = 0
+        ORDER BY r.kind, i.name",
+    )?;
+    let rows = stmt.query_map(params![item_id], |row| {
+        let kind = RelationKind::from_str(&row.get::<_, String>("relation_kind")?)
+            .unwrap_or(RelationKind::Related);
+        let outgoing: bool = row.get("outgoing")?;
+        Ok(RelatedItem {
+            relation_id: row.get("relation_id")?,
+            kind,
+            label: if outgoing {
+                kind.label()
+            } else {
+                kind.inverse_label()
+            }
+            .to_string(),
+            item: Item::from_row(row)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Makes each of `member_ids` a member of the set item `set_id`, all or nothing.
+pub fn create_set(conn: &Connection, set_id: i32, member_ids: &[i32]) -> AnyResult<Vec<i32>> {
+    let tx = conn.unchecked_transaction()?;
+    let ids = member_ids
+        .iter()
+        .map(|&member| add_relation(&tx, member, RelationKind::SetMember, set_id))
+        .collect::<AnyResult<Vec<_>>>()?;
+    tx.commit()?;
+    Ok(ids)
+}
+
+/// Every item contained in `set_id`, following part-of and set-member links down through
+/// nested sets. Deleted items and wishlist entries for missing pieces are left out.
+pub fn get_set_members(conn: &Connection, set_id: i32) -> Result<Vec<Item>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE members(id) AS (
+            SELECT item_id FROM item_relations
+            WHERE related_id = ?1 AND kind IN ('part-of', 'set-member')
+            UNION
+            SELECT r.item_id FROM item_relations r JOIN members ON r.related_id = members.id
+            WHERE r.kind IN ('part-of', 'set-member')
+        )
+        SELECT * FROM items
+        WHERE id IN (SELECT id FROM members) AND deleted = 0 AND wishlist = 0
+        ORDER BY name",
+    )?;
+    let rows = stmt.query_map(params![set_id], Item::from_row)?;
+    rows.collect()
+}
+
+/// Count and value totals for a set: the set item itself plus every member, each counted once.
+pub fn get_set_stats(conn: &Connection, set_id: i32) -> AnyResult<CatalogStats> {
+    let set = crate::db::get_item_by_id(conn, set_id)?
+        .ok_or_else(|| anyhow!("Item {} not found", set_id))?;
+    let mut items = get_set_members(conn, set_id)?;
+    items.push(set);
+
+    let home = get_home_currency(conn)?;
+    let rates = get_rate_table(conn)?;
+    Ok(compute_stats(&items, home, &rates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db, soft_delete_item};
+    use crate::money::Money;
+
+    fn setup(names: &[&str]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for name in names {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                estimated_value: Some(Money::from_str("10.00 USD").unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_nested_set_members_and_value() {
+        let conn = setup(&["Chess set", "Board", "Pieces", "King", "Spare board"]);
+        create_set(&conn, 1, &[2, 3]).unwrap();
+        add_relation(&conn, 4, RelationKind::PartOf, 3).unwrap();
+        add_relation(&conn, 5, RelationKind::Related, 2).unwrap();
+        // A missing piece on the wishlist isn't counted
+        let queen = Item {
+            name: "Queen".to_string(),
+            description: "test".to_string(),
+            estimated_value: Some(Money::from_str("10.00 USD").unwrap()),
+            wishlist: true,
+            ..Default::default()
+        };
+        let queen = add_item(&conn, &queen).unwrap();
+        add_relation(&conn, queen, RelationKind::PartOf, 3).unwrap();
+
+        let names: Vec<String> = get_set_members(&conn, 1)
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, vec!["Board", "King", "Pieces"]);
+
+        let stats = get_set_stats(&conn, 1).unwrap();
+        assert_eq!(stats.item_count, 4);
+        assert_eq!(stats.estimated_total_home.unwrap().to_string(), "40.00 USD");
+
+        let related = get_related_items(&conn, 3).unwrap();
+        let labels: Vec<(&str, &str)> = related
+            .iter()
+            .map(|r| (r.label.as_str(), r.item.name.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("Has part", "King"),
+                ("Has part", "Queen"),
+                ("Member of set", "Chess set")
+            ]
+        );
+
+        soft_delete_item(&conn, 4).unwrap();
+        soft_delete_item(&conn, queen).unwrap();
+        let related = get_related_items(&conn, 3).unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].item.name, "Chess set");
+    }
+
+    #[test]
+    fn test_cycles_are_refused() {
+        let conn = setup(&["A", "B", "C"]);
+        add_relation(&conn, 1, RelationKind::PartOf, 2).unwrap();
+        add_relation(&conn, 2, RelationKind::SetMember, 3).unwrap();
+        assert!(add_relation(&conn, 3, RelationKind::PartOf, 1).is_err());
+        assert!(add_relation(&conn, 1, RelationKind::PartOf, 1).is_err());
+
+        add_relation(&conn, 1, RelationKind::ReplacedBy, 2).unwrap();
+        assert!(add_relation(&conn, 2, RelationKind::ReplacedBy, 1).is_err());
+
+        // Related links have no direction and can't be duplicated
+        add_relation(&conn, 3, RelationKind::Related, 1).unwrap();
+        assert!(add_relation(&conn, 1, RelationKind::Related, 3).is_err());
+    }
+}
@@ -0,0 +1,504 @@
+//! Reminders attached to items, such as servicing a tool, a warranty running out or an
+//! insured piece being due for re-appraisal, with an iCalendar export for calendar apps.
+
+use crate::labels::get_catalog_id;
+use crate::measure::serde_as_string;
+use crate::saved_search::DateUnit;
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::{Days, Months, NaiveDate, Utc};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReminderKind {
+    Maintenance,
+    Warranty,
+    Appraisal,
+    #[default]
+    Other,
+}
+
+impl fmt::Display for ReminderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReminderKind::Maintenance => "Maintenance",
+            ReminderKind::Warranty => "Warranty",
+            ReminderKind::Appraisal => "Appraisal",
+            ReminderKind::Other => "Other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ReminderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "maintenance" | "service" => Ok(ReminderKind::Maintenance),
+            "warranty" => Ok(ReminderKind::Warranty),
+            "appraisal" => Ok(ReminderKind::Appraisal),
+            "other" => Ok(ReminderKind::Other),
+            _ => Err(format!(
+                "Unknown reminder kind: '{}'. Expected maintenance, warranty, appraisal or other",
+                s
+            )),
+        }
+    }
+}
+
+/// How often a reminder repeats, written like `every 6 months`, `2 years` or `yearly`.
+/// At most `MAX_EVERY` units apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    pub every: u32,
+    pub unit: DateUnit,
+}
+
+impl Recurrence {
+    pub const MAX_EVERY: u32 = 1000;
+
+    /// The next date after `date`, or `None` past the end of the calendar.
+    pub fn after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self.unit {
+            DateUnit::Day => date.checked_add_days(Days::new(self.every as u64)),
+            DateUnit::Week => date.checked_add_days(Days::new(7 * self.every as u64)),
+            DateUnit::Month => date.checked_add_months(Months::new(self.every)),
+            DateUnit::Year => date.checked_add_months(Months::new(self.every.checked_mul(12)?)),
+        }
+    }
+
+    fn rrule(&self) -> String {
+        let freq = match self.unit {
+            DateUnit::Day => "DAILY",
+            DateUnit::Week => "WEEKLY",
+            DateUnit::Month => "MONTHLY",
+            DateUnit::Year => "YEARLY",
+        };
+        format!("FREQ={};INTERVAL={}", freq, self.every)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.every {
+            1 => write!(f, "every {}", self.unit.to_string().trim_end_matches('s')),
+            n => write!(f, "every {} {}", n, self.unit),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || {
+            format!(
+                "Invalid recurrence: '{}'. Expected e.g. every 6 months, yearly",
+                s
+            )
+        };
+        let lower = s.trim().to_ascii_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        let (every, unit) = match words.as_slice() {
+            ["daily"] => (1, DateUnit::Day),
+            ["weekly"] => (1, DateUnit::Week),
+            ["monthly"] => (1, DateUnit::Month),
+            ["yearly"] | ["annually"] => (1, DateUnit::Year),
+            ["every", unit] => (1, unit.parse().map_err(|_| err())?),
+            ["every", n, unit] | [n, unit] => (
+                n.parse().map_err(|_| err())?,
+                unit.parse().map_err(|_| err())?,
+            ),
+            _ => return Err(err()),
+        };
+        if every == 0 {
+            return Err(err());
+        }
+        if every > Recurrence::MAX_EVERY {
+            return Err(format!(
+                "Invalid recurrence: '{}'. At most every {} {}",
+                s,
+                Recurrence::MAX_EVERY,
+                unit
+            ));
+        }
+        Ok(Recurrence { every, unit })
+    }
+}
+
+serde_as_string!(Recurrence);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: i32,
+    pub item_id: i32,
+    pub kind: ReminderKind,
+    pub due: NaiveDate,
+    /// Unset for a one-off reminder
+    pub recurrence: Option<Recurrence>,
+    pub note: Option<String>,
+    /// When it was last done. A one-off reminder that has been done is closed.
+    pub last_done: Option<NaiveDate>,
+}
+
+impl Reminder {
+    /// Whether the reminder still needs doing.
+    pub fn is_open(&self) -> bool {
+        self.recurrence.is_some() || self.last_done.is_none()
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let conversion = |i, e: String| {
+            rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, e.into())
+        };
+        let date = |i, s: String| {
+            NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(|e| conversion(i, e.to_string()))
+        };
+        Ok(Reminder {
+            id: row.get("id")?,
+            item_id: row.get("item_id")?,
+            kind: row
+                .get::<_, String>("kind")?
+                .parse()
+                .map_err(|e| conversion(2, e))?,
+            due: date(3, row.get("due")?)?,
+            recurrence: row
+                .get::<_, Option<String>>("recurrence")?
+                .map(|s| s.parse().map_err(|e| conversion(4, e)))
+                .transpose()?,
+            note: row.get("note")?,
+            last_done: row
+                .get::<_, Option<String>>("last_done")?
+                .map(|s| date(6, s))
+                .transpose()?,
+        })
+    }
+}
+
+pub fn add_reminder(conn: &Connection, reminder: &Reminder) -> AnyResult<i32> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM items WHERE id = ?1)",
+        params![reminder.item_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(anyhow!("Item {} not found", reminder.item_id));
+    }
+    conn.execute(
+        "INSERT INTO reminders (item_id, kind, due, recurrence, note, last_done)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            reminder.item_id,
+            reminder.kind.to_string(),
+            reminder.due.to_string(),
+            reminder.recurrence.map(|r| r.to_string()),
+            reminder.note,
+            reminder.last_done.map(|d| d.to_string()),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+pub fn update_reminder(conn: &Connection, reminder: &Reminder) -> AnyResult<()> {
+    let updated = conn.execute(
+        "UPDATE reminders SET kind = ?1, due = ?2, recurrence = ?3, note = ?4, last_done = ?5
+        WHERE id = ?6",
+        params![
+            reminder.kind.to_string(),
+            reminder.due.to_string(),
+            reminder.recurrence.map(|r| r.to_string()),
+            reminder.note,
+            reminder.last_done.map(|d| d.to_string()),
+            reminder.id,
+        ],
+    )?;
+    if updated == 0 {
+        return Err(anyhow!("Reminder {} not found", reminder.id));
+    }
+    Ok(())
+}
+
+pub fn delete_reminder(conn: &Connection, id: i32) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM reminders WHERE id = ?1", params![id])? > 0)
+}
+
+pub fn get_reminder(conn: &Connection, id: i32) -> Result<Option<Reminder>> {
+    conn.query_row(
+        "SELECT * FROM reminders WHERE id = ?1",
+        params![id],
+        Reminder::from_row,
+    )
+    .optional()
+}
+
+/// An item's reminders, soonest first.
+pub fn get_reminders(conn: &Connection, item_id: i32) -> Result<Vec<Reminder>> {
+    let mut stmt = conn.prepare("SELECT * FROM reminders WHERE item_id = ?1 ORDER BY due, id")?;
+    let rows = stmt.query_map(params![item_id], Reminder::from_row)?;
+    rows.collect()
+}
+
+/// Records the reminder as done on `date`. A recurring reminder comes round again one
+/// interval after `date`, so servicing late pushes the next service back too.
+pub fn complete_reminder(conn: &Connection, id: i32, date: NaiveDate) -> AnyResult<Reminder> {
+    let mut reminder =
+        get_reminder(conn, id)?.ok_or_else(|| anyhow!("Reminder {} not found", id))?;
+    reminder.last_done = Some(date);
+    if let Some(recurrence) = reminder.recurrence {
+        reminder.due = recurrence.after(date).ok_or_else(|| {
+            anyhow!(
+                "Reminder {} can't repeat: the next date is out of range",
+                id
+            )
+        })?;
+    }
+    update_reminder(conn, &reminder)?;
+    Ok(reminder)
+}
+
+/// A reminder with the name of its item, for lists across the catalog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DueReminder {
+    pub reminder: Reminder,
+    pub item_name: String,
+}
+
+impl DueReminder {
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.reminder.due < today
+    }
+}
+
+// Open reminders on items that haven't been deleted
+fn open_reminders(conn: &Connection, until: Option<NaiveDate>) -> Result<Vec<DueReminder>> {
+    let mut stmt = conn.prepare(
+        "SELECT reminders.*, items.name AS item_name
+        FROM reminders JOIN items ON items.id = reminders.item_id
+        WHERE items.deleted = 0
+            AND (reminders.recurrence IS NOT NULL OR reminders.last_done IS NULL)
+            AND (?1 IS NULL OR reminders.due <= ?1)
+        ORDER BY reminders.due, reminders.id",
+    )?;
+    let rows = stmt.query_map(params![until.map(|d| d.to_string())], |row| {
+        Ok(DueReminder {
+            reminder: Reminder::from_row(row)?,
+            item_name: row.get("item_name")?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Open reminders due on or before `until`, including overdue ones, soonest first.
+pub fn due_reminders(conn: &Connection, until: NaiveDate) -> Result<Vec<DueReminder>> {
+    open_reminders(conn, Some(until))
+}
+
+/// Open reminders whose due date has passed.
+pub fn overdue_reminders(conn: &Connection, today: NaiveDate) -> Result<Vec<DueReminder>> {
+    match today.pred_opt() {
+        Some(yesterday) => due_reminders(conn, yesterday),
+        None => Ok(vec![]),
+    }
+}
+
+// Commas, semicolons and backslashes are escaped in iCalendar text, and newlines written \n
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// Lines longer than 75 bytes are folded onto continuation lines starting with a space
+fn fold_ics(line: &str, out: &mut String) {
+    let mut rest = line;
+    let mut limit = 75;
+    while rest.len() > limit {
+        let mut end = limit;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.push_str(&rest[..end]);
+        out.push_str("\r\n ");
+        rest = &rest[end..];
+        // The leading space counts towards the next line
+        limit = 74;
+    }
+    out.push_str(rest);
+    out.push_str("\r\n");
+}
+
+/// An iCalendar file with an all-day event for each reminder, repeating for recurring ones.
+/// `catalog_id` keeps event ids unique when more than one catalog is exported. Reminders due
+/// on the last representable day are left out, as their event can't end.
+pub fn reminders_to_ics(reminders: &[DueReminder], catalog_id: &str) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Collection Catalog//Reminders//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for due in reminders {
+        let reminder = &due.reminder;
+        let day = |date: NaiveDate| date.format("%Y%m%d").to_string();
+        let Some(end) = reminder.due.checked_add_days(Days::new(1)) else {
+            continue;
+        };
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:reminder-{}-{}@collection-catalog",
+            reminder.id, catalog_id
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", day(reminder.due)));
+        lines.push(format!("DTEND;VALUE=DATE:{}", day(end)));
+        if let Some(recurrence) = reminder.recurrence {
+            lines.push(format!("RRULE:{}", recurrence.rrule()));
+        }
+        lines.push(format!(
+            "SUMMARY:{}",
+            escape_ics(&format!(
+                "{}: {} (#{})",
+                reminder.kind, due.item_name, reminder.item_id
+            ))
+        ));
+        if let Some(note) = &reminder.note {
+            lines.push(format!("DESCRIPTION:{}", escape_ics(note)));
+        }
+        lines.push(format!("CATEGORIES:{}", reminder.kind));
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        fold_ics(&line, &mut out);
+    }
+    out
+}
+
+/// Writes every open reminder to an .ics file. Returns the number of reminders.
+pub fn export_reminders_ics(conn: &Connection, path: &str) -> AnyResult<usize> {
+    let reminders = open_reminders(conn, None)?;
+    fs::write(path, reminders_to_ics(&reminders, &get_catalog_id(conn)?))?;
+    Ok(reminders.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db, soft_delete_item};
+    use crate::models::Item;
+
+    #[test]
+    fn test_recurrence() {
+        let every = |s: &str| Recurrence::from_str(s).unwrap();
+        assert_eq!(every("every 6 months").to_string(), "every 6 months");
+        assert_eq!(every("Yearly").to_string(), "every year");
+        assert_eq!(every("2 weeks").rrule(), "FREQ=WEEKLY;INTERVAL=2");
+        assert!(Recurrence::from_str("every 0 days").is_err());
+        assert!(Recurrence::from_str("fortnightly").is_err());
+
+        let jan31 = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        assert_eq!(
+            every("monthly").after(jan31).unwrap().to_string(),
+            "2025-02-28"
+        );
+        assert!(Recurrence::from_str("every 4294967295 years").is_err());
+        assert!(Recurrence::from_str("every 1001 days").is_err());
+        assert!(every("every 1000 years").after(NaiveDate::MAX).is_none());
+    }
+
+    #[test]
+    fn test_due_reminders_and_ics() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for name in ["Chainsaw", "Television", "Old lamp"] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        let date = |s: &str| NaiveDate::from_str(s).unwrap();
+        let service = add_reminder(
+            &conn,
+            &Reminder {
+                item_id: 1,
+                kind: ReminderKind::Maintenance,
+                due: date("2025-03-01"),
+                recurrence: Some("every 6 months".parse().unwrap()),
+                note: Some("Sharpen chain, replace bar oil; check, clean".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let warranty = Reminder {
+            item_id: 2,
+            kind: ReminderKind::Warranty,
+            due: date("2025-04-15"),
+            ..Default::default()
+        };
+        add_reminder(&conn, &warranty).unwrap();
+        // Deleted items' reminders are left out
+        add_reminder(
+            &conn,
+            &Reminder {
+                item_id: 3,
+                due: date("2025-01-01"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        soft_delete_item(&conn, 3).unwrap();
+        assert!(
+            add_reminder(
+                &conn,
+                &Reminder {
+                    item_id: 99,
+                    ..warranty.clone()
+                }
+            )
+            .is_err()
+        );
+
+        let today = date("2025-03-10");
+        let overdue = overdue_reminders(&conn, today).unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert!(overdue[0].is_overdue(today));
+        assert_eq!(overdue[0].item_name, "Chainsaw");
+        assert_eq!(due_reminders(&conn, date("2025-04-30")).unwrap().len(), 2);
+
+        // Servicing late moves the next service on from when it was done
+        let done = complete_reminder(&conn, service, today).unwrap();
+        assert_eq!(done.due, date("2025-09-10"));
+        assert!(done.is_open());
+        assert!(overdue_reminders(&conn, today).unwrap().is_empty());
+
+        let ics = reminders_to_ics(&open_reminders(&conn, None).unwrap(), "abc");
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250910\r\n"));
+        assert!(ics.contains("RRULE:FREQ=MONTHLY;INTERVAL=6\r\n"));
+        assert!(ics.contains("SUMMARY:Warranty: Television (#2)\r\n"));
+        assert!(ics.contains("bar oil\\; check\\, clean"));
+        assert!(ics.lines().all(|line| line.len() <= 75));
+
+        // A reminder due on the last day there is can't end, so it's left out
+        let mut last = open_reminders(&conn, None).unwrap();
+        last[0].reminder.due = NaiveDate::MAX;
+        let ics = reminders_to_ics(&last, "abc");
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+
+        let one_off = due_reminders(&conn, date("2025-04-30")).unwrap()[0].clone();
+        complete_reminder(&conn, one_off.reminder.id, today).unwrap();
+        assert!(due_reminders(&conn, date("2025-04-30")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,607 @@
+use crate::attachments::{Attachment, get_attachments};
+use crate::db::get_filtered_items;
+use crate::images::{ThumbnailSize, jpeg_thumbnail};
+use crate::models::{Item, ItemFilter};
+use crate::money::{Money, MoneyFormat, MoneyTotals, get_home_currency, get_rate_table};
+use crate::pdf::{Font, PAGE_HEIGHT, PAGE_WIDTH, PdfDocument, wrap_text};
+use crate::provenance::{ProvenanceEvent, get_provenance};
+use crate::stats::{CatalogStats, compute_stats};
+use anyhow::{Result as AnyResult, anyhow};
+use base64::Engine;
+use chrono::{Local, NaiveDate};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportGrouping {
+    #[default]
+    Category,
+    Location,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".pdf") {
+            Some(ReportFormat::Pdf)
+        } else if lower.ends_with(".html") || lower.ends_with(".htm") {
+            Some(ReportFormat::Html)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub title: String,
+    pub grouping: ReportGrouping,
+    pub include_photos: bool,
+    pub money_format: MoneyFormat,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            title: "Insurance Inventory".to_string(),
+            grouping: ReportGrouping::Category,
+            include_photos: true,
+            money_format: MoneyFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportEntry {
+    pub item: Item,
+    pub photos: Vec<Attachment>,
+    pub provenance: Vec<ProvenanceEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportGroup {
+    pub name: String,
+    pub entries: Vec<ReportEntry>,
+    pub purchase_totals: Vec<Money>,
+    pub estimated_totals: Vec<Money>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InventoryReport {
+    pub title: String,
+    pub generated: NaiveDate,
+    pub groups: Vec<ReportGroup>,
+    pub stats: CatalogStats,
+}
+
+/// Collects the items matching `filter`, grouped for the report. Deleted items are left
+/// out unless the filter asks for them explicitly.
+pub fn build_report(
+    conn: &Connection,
+    mut filter: ItemFilter,
+    options: &ReportOptions,
+) -> AnyResult<InventoryReport> {
+    filter.deleted.get_or_insert(false);
+    let mut items = get_filtered_items(conn, filter)?;
+    items.sort_by_key(|i| i.name.to_lowercase());
+
+    let stats = compute_stats(&items, get_home_currency(conn)?, &get_rate_table(conn)?);
+
+    let mut grouped: BTreeMap<String, Vec<ReportEntry>> = BTreeMap::new();
+    for item in items {
+        let name = match options.grouping {
+            ReportGrouping::Category => item.category.to_string(),
+            ReportGrouping::Location => item
+                .location
+                .clone()
+                .filter(|l| !l.trim().is_empty())
+                .unwrap_or_else(|| "Unspecified location".to_string()),
+        };
+        let photos = if options.include_photos {
+            get_attachments(conn, item.id)?
+                .into_iter()
+                .filter(Attachment::is_image)
+                .collect()
+        } else {
+            vec![]
+        };
+        let provenance = get_provenance(conn, item.id)?;
+        grouped.entry(name).or_default().push(ReportEntry {
+            item,
+            photos,
+            provenance,
+        });
+    }
+
+    let groups = grouped
+        .into_iter()
+        .map(|(name, entries)| {
+            let mut purchase = MoneyTotals::default();
+            let mut estimated = MoneyTotals::default();
+            purchase.extend(entries.iter().map(|e| &e.item.purchase_price));
+            estimated.extend(entries.iter().map(|e| &e.item.estimated_value));
+            ReportGroup {
+                name,
+                entries,
+                purchase_totals: purchase.amounts(),
+                estimated_totals: estimated.amounts(),
+            }
+        })
+        .collect();
+
+    Ok(InventoryReport {
+        title: options.title.clone(),
+        generated: Local::now().date_naive(),
+        groups,
+        stats,
+    })
+}
+
+pub fn render_html(report: &InventoryReport, options: &ReportOptions) -> String {
+    let fmt = &options.money_format;
+    let mut html = String::new();
+    html.push_str(&format!(
+        "<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\" />\n<title>{}</title>\n\
+         <style>\n{}</style>\n</head>\n<body>\n",
+        escape_html(&report.title),
+        REPORT_CSS
+    ));
+    html.push_str(&format!(
+        "<h1>{}</h1>\n<p class=\"meta\">Generated {} &middot; {} items</p>\n",
+        escape_html(&report.title),
+        report.generated,
+        report.stats.item_count
+    ));
+
+    for group in &report.groups {
+        html.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n<table>\n<thead><tr><th>Photos</th><th>Item</th>\
+             <th>Acquired</th><th>Purchase Price</th><th>Estimated Value</th></tr></thead>\n<tbody>\n",
+            escape_html(&group.name)
+        ));
+        for entry in &group.entries {
+            let item = &entry.item;
+            let photos: String = entry
+                .photos
+                .iter()
+                .filter_map(|p| {
+                    let data = jpeg_thumbnail(Path::new(&p.path), ThumbnailSize::Medium).ok()?;
+                    Some(format!(
+                        "<img src=\"data:image/jpeg;base64,{}\" alt=\"{}\" />",
+                        base64::engine::general_purpose::STANDARD.encode(data),
+                        escape_html(p.caption.as_deref().unwrap_or(&item.name))
+                    ))
+                })
+                .collect();
+            let provenance: String = entry
+                .provenance
+                .iter()
+                .map(|e| format!("<li>{}</li>", escape_html(&e.to_string())))
+                .collect();
+            html.push_str(&format!(
+                "<tr><td class=\"photos\">{}</td><td><strong>{}</strong><br />{}{}{}</td>\
+                 <td>{}</td><td class=\"money\">{}</td><td class=\"money\">{}</td></tr>\n",
+                photos,
+                escape_html(&item.name),
+                escape_html(&item.description),
+                item_details(item, options.grouping)
+                    .map(|d| format!("<br /><span class=\"meta\">{}</span>", escape_html(&d)))
+                    .unwrap_or_default(),
+                if provenance.is_empty() {
+                    provenance
+                } else {
+                    format!("<ol class=\"provenance\">{}</ol>", provenance)
+                },
+                item.date_acquired
+                    .map(|d| d.to_string())
+                    .unwrap_or_default(),
+                item.purchase_price
+                    .map(|m| m.format(fmt))
+                    .unwrap_or_default(),
+                item.estimated_value
+                    .map(|m| m.format(fmt))
+                    .unwrap_or_default(),
+            ));
+        }
+        html.push_str(&format!(
+            "</tbody>\n<tfoot><tr><td colspan=\"3\">Subtotal</td><td class=\"money\">{}</td>\
+             <td class=\"money\">{}</td></tr></tfoot>\n</table>\n</section>\n",
+            join_amounts(&group.purchase_totals, fmt),
+            join_amounts(&group.estimated_totals, fmt)
+        ));
+    }
+
+    html.push_str("<section class=\"totals\">\n<h2>Totals</h2>\n<table>\n");
+    for (label, line) in total_lines(&report.stats, fmt) {
+        html.push_str(&format!(
+            "<tr><th>{}</th><td class=\"money\">{}</td></tr>\n",
+            label,
+            escape_html(&line)
+        ));
+    }
+    html.push_str("</table>\n</section>\n</body>\n</html>\n");
+    html
+}
+
+pub fn render_pdf(report: &InventoryReport, options: &ReportOptions) -> Vec<u8> {
+    let fmt = &options.money_format;
+    let mut out = PdfWriter::new();
+
+    out.line_of_text(&report.title, 18.0, Font::Bold, 0.0);
+    out.line_of_text(
+        &format!(
+            "Generated {}  -  {} items",
+            report.generated, report.stats.item_count
+        ),
+        9.0,
+        Font::Regular,
+        0.0,
+    );
+
+    for group in &report.groups {
+        out.gap(12.0);
+        out.ensure(60.0);
+        out.line_of_text(&group.name, 14.0, Font::Bold, 0.0);
+        out.rule();
+
+        for entry in &group.entries {
+            let item = &entry.item;
+            out.gap(6.0);
+            out.ensure(40.0);
+            out.line_of_text(&item.name, 11.0, Font::Bold, 0.0);
+
+            let mut facts = vec![];
+            if let Some(date) = item.date_acquired {
+                facts.push(format!("Acquired {}", date));
+            }
+            if let Some(price) = item.purchase_price {
+                facts.push(format!("Purchase price {}", price.format(fmt)));
+            }
+            if let Some(value) = item.estimated_value {
+                facts.push(format!("Estimated value {}", value.format(fmt)));
+            }
+            if !facts.is_empty() {
+                out.paragraph(&facts.join("   "), 9.0, Font::Regular, 12.0);
+            }
+            out.paragraph(&item.description, 9.0, Font::Regular, 12.0);
+            if let Some(details) = item_details(item, options.grouping) {
+                out.paragraph(&details, 8.0, Font::Regular, 12.0);
+            }
+            if !entry.provenance.is_empty() {
+                out.paragraph("Provenance:", 8.0, Font::Bold, 12.0);
+                for (n, event) in entry.provenance.iter().enumerate() {
+                    out.paragraph(&format!("{}. {}", n + 1, event), 8.0, Font::Regular, 20.0);
+                }
+            }
+
+            let images: Vec<usize> = entry
+                .photos
+                .iter()
+                .filter_map(|p| {
+                    let data = jpeg_thumbnail(Path::new(&p.path), ThumbnailSize::Medium).ok()?;
+                    out.doc.add_jpeg(data)
+                })
+                .collect();
+            out.thumbnails(&images, 12.0);
+        }
+
+        out.gap(4.0);
+        out.ensure(30.0);
+        out.rule();
+        out.line_of_text(
+            &format!(
+                "Subtotal: purchase {}   estimated {}",
+                join_amounts(&group.purchase_totals, fmt),
+                join_amounts(&group.estimated_totals, fmt)
+            ),
+            9.0,
+            Font::Bold,
+            0.0,
+        );
+    }
+
+    out.gap(16.0);
+    out.ensure(80.0);
+    out.line_of_text("Totals", 14.0, Font::Bold, 0.0);
+    out.rule();
+    for (label, line) in total_lines(&report.stats, fmt) {
+        out.paragraph(&format!("{}: {}", label, line), 10.0, Font::Regular, 0.0);
+    }
+
+    out.doc.to_bytes()
+}
+
+/// Builds the report and writes it to `path`, picking HTML or PDF from the file extension.
+/// Returns the number of items included.
+pub fn write_report(
+    conn: &Connection,
+    filter: ItemFilter,
+    options: &ReportOptions,
+    path: &str,
+) -> AnyResult<usize> {
+    let format = ReportFormat::from_path(path)
+        .ok_or_else(|| anyhow!("Report path must end in .html or .pdf: {}", path))?;
+    let report = build_report(conn, filter, options)?;
+    match format {
+        ReportFormat::Html => fs::write(path, render_html(&report, options))?,
+        ReportFormat::Pdf => fs::write(path, render_pdf(&report, options))?,
+    }
+    Ok(report.stats.item_count)
+}
+
+pub(crate) const REPORT_CSS: &str =
+    "body { font-family: Helvetica, Arial, sans-serif; font-size: 11pt; margin: 2em; }
+h1 { margin-bottom: 0; }
+.meta { color: #555; font-size: 9pt; }
+.provenance { margin: 4px 0 0; padding-left: 18px; font-size: 9pt; }
+section { page-break-inside: avoid; margin-top: 2em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ccc; padding: 4px 6px; text-align: left; vertical-align: top; }
+tfoot td { font-weight: bold; }
+.money { text-align: right; white-space: nowrap; }
+.photos img { max-width: 96px; max-height: 96px; margin: 2px; }
+@media print { section { page-break-before: auto; } }
+";
+
+const MARGIN: f32 = 50.0;
+const THUMBNAIL_SIZE: f32 = 72.0;
+
+// Tracks the current page and vertical position while laying out the PDF report.
+pub(crate) struct PdfWriter {
+    pub(crate) doc: PdfDocument,
+    page: usize,
+    y: f32,
+}
+
+impl PdfWriter {
+    pub(crate) fn new() -> Self {
+        let mut doc = PdfDocument::new();
+        let page = doc.add_page();
+        PdfWriter {
+            doc,
+            page,
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    pub(crate) fn ensure(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    pub(crate) fn new_page(&mut self) {
+        self.page = self.doc.add_page();
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    pub(crate) fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    pub(crate) fn line_of_text(&mut self, text: &str, size: f32, font: Font, indent: f32) {
+        self.ensure(size * 1.3);
+        self.y -= size * 1.3;
+        self.doc
+            .text(self.page, MARGIN + indent, self.y, size, font, text);
+    }
+
+    pub(crate) fn paragraph(&mut self, text: &str, size: f32, font: Font, indent: f32) {
+        let width = PAGE_WIDTH - 2.0 * MARGIN - indent;
+        for line in wrap_text(text, size, width) {
+            self.line_of_text(&line, size, font, indent);
+        }
+    }
+
+    pub(crate) fn rule(&mut self) {
+        self.y -= 3.0;
+        self.doc
+            .line(self.page, MARGIN, self.y, PAGE_WIDTH - MARGIN, self.y);
+    }
+
+    fn thumbnails(&mut self, images: &[usize], indent: f32) {
+        let per_row = ((PAGE_WIDTH - 2.0 * MARGIN - indent) / (THUMBNAIL_SIZE + 6.0)) as usize;
+        for row in images.chunks(per_row.max(1)) {
+            self.ensure(THUMBNAIL_SIZE + 6.0);
+            self.y -= THUMBNAIL_SIZE + 6.0;
+            for (i, &image) in row.iter().enumerate() {
+                // Fit inside a square box, keeping the aspect ratio
+                let (w, h) = self.doc.image_size(image);
+                let scale = THUMBNAIL_SIZE / w.max(h).max(1) as f32;
+                let x = MARGIN + indent + i as f32 * (THUMBNAIL_SIZE + 6.0);
+                self.doc.image(
+                    self.page,
+                    image,
+                    x,
+                    self.y,
+                    w as f32 * scale,
+                    h as f32 * scale,
+                );
+            }
+        }
+    }
+}
+
+// Secondary details that help identify an item, skipping whatever the report is grouped by.
+fn item_details(item: &Item, grouping: ReportGrouping) -> Option<String> {
+    let mut parts = vec![];
+    match grouping {
+        ReportGrouping::Category => {
+            if let Some(location) = &item.location {
+                parts.push(format!("Location: {}", location));
+            }
+        }
+        ReportGrouping::Location => parts.push(format!("Category: {}", item.category)),
+    }
+    if let Some(made) = item.date_made {
+        parts.push(format!("Made: {}", made));
+    }
+    if let Some(creator) = &item.creator {
+        parts.push(format!("Creator: {}", creator));
+    }
+    if let Some(provenance) = &item.provenance {
+        parts.push(format!("Origin: {}", provenance));
+    }
+    if let Some(condition) = &item.condition {
+        parts.push(format!("Condition: {}", condition));
+    }
+    if let Some(dimensions) = item.dimensions {
+        parts.push(format!("Size: {}", dimensions));
+    }
+    if let Some(weight) = item.weight {
+        parts.push(format!("Weight: {}", weight));
+    }
+    if item.pieces() > 1 {
+        parts.push(format!("Quantity: {}", item.pieces()));
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("; "))
+    }
+}
+
+pub(crate) fn total_lines(stats: &CatalogStats, fmt: &MoneyFormat) -> Vec<(&'static str, String)> {
+    let home = |total: Option<Money>| match total {
+        Some(t) => t.format(fmt),
+        None => format!(
+            "unavailable (missing {} exchange rate)",
+            stats
+                .missing_rates
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    vec![
+        ("Purchase price", join_amounts(&stats.purchase_totals, fmt)),
+        (
+            "Estimated value",
+            join_amounts(&stats.estimated_totals, fmt),
+        ),
+        (
+            "Purchase price (home currency)",
+            home(stats.purchase_total_home),
+        ),
+        (
+            "Estimated value (home currency)",
+            home(stats.estimated_total_home),
+        ),
+    ]
+}
+
+fn join_amounts(amounts: &[Money], fmt: &MoneyFormat) -> String {
+    if amounts.is_empty() {
+        return "-".to_string();
+    }
+    amounts
+        .iter()
+        .map(|m| m.format(fmt))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachments::add_attachment;
+    use crate::db::{add_item, init_db};
+    use crate::models::ItemCategory;
+    use std::str::FromStr;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, category, location, value) in [
+            ("Clock", ItemCategory::Antique, Some("Hall"), "150.00"),
+            ("Atlas", ItemCategory::Book, None, "40.25"),
+            ("Chair", ItemCategory::Antique, Some("Hall"), "99.75"),
+        ] {
+            let item = Item {
+                name: name.to_string(),
+                description: format!("{} <original>", name),
+                category,
+                location: location.map(String::from),
+                estimated_value: Some(Money::from_str(value).unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_build_report_groups_and_totals() {
+        let conn = setup();
+        let options = ReportOptions {
+            grouping: ReportGrouping::Location,
+            ..Default::default()
+        };
+        let report = build_report(&conn, ItemFilter::default(), &options).unwrap();
+
+        let names: Vec<&str> = report.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Hall", "Unspecified location"]);
+        assert_eq!(report.groups[0].entries[0].item.name, "Chair");
+        assert_eq!(
+            report.groups[0].estimated_totals,
+            vec![Money::from_str("249.75").unwrap()]
+        );
+        assert_eq!(
+            report.stats.estimated_total_home,
+            Some(Money::from_str("290.00").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_render_html_and_pdf() {
+        let conn = setup();
+        let options = ReportOptions::default();
+        let report = build_report(&conn, ItemFilter::default(), &options).unwrap();
+
+        let html = render_html(&report, &options);
+        assert!(html.contains("<h2>Antique</h2>"));
+        assert!(html.contains("Clock &lt;original&gt;"));
+        assert!(html.contains("USD 290.00"));
+
+        let pdf = render_pdf(&report, &options);
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_png_photos_embedded_as_thumbnails() {
+        let conn = setup();
+        let dir = std::env::temp_dir().join(format!("catalog-report-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("clock.png");
+        image::RgbaImage::from_pixel(600, 400, image::Rgba([200, 40, 40, 255]))
+            .save(&photo)
+            .unwrap();
+        add_attachment(&conn, 1, &photo.to_string_lossy(), None).unwrap();
+
+        let options = ReportOptions::default();
+        let report = build_report(&conn, ItemFilter::default(), &options).unwrap();
+        let html = render_html(&report, &options);
+        assert_eq!(html.matches("src=\"data:image/jpeg;base64,").count(), 1);
+        let pdf = render_pdf(&report, &options);
+        assert!(pdf.windows(4).any(|w| w == b"/Im0"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
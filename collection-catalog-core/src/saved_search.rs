@@ -0,0 +1,353 @@
+use crate::db::get_filtered_items;
+use crate::measure::serde_as_string;
+use crate::models::{Item, ItemFilter};
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Filter fields that can hold a `DateExpr` in a saved search.
+pub const RELATIVE_DATE_FIELDS: &[&str] = &[
+    "date_added_min",
+    "date_added_max",
+    "last_updated_min",
+    "last_updated_max",
+    "date_acquired_min",
+    "date_acquired_max",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateUnit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl fmt::Display for DateUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DateUnit::Day => "days",
+            DateUnit::Week => "weeks",
+            DateUnit::Month => "months",
+            DateUnit::Year => "years",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DateUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_end_matches('s') {
+            "day" => Ok(DateUnit::Day),
+            "week" => Ok(DateUnit::Week),
+            "month" => Ok(DateUnit::Month),
+            "year" => Ok(DateUnit::Year),
+            _ => Err(format!("Unknown date unit: '{}'", s)),
+        }
+    }
+}
+
+/// A date worked out when a saved search runs rather than when it was saved, written like
+/// `today`, `last 30 days`, `3 months ago`, `this month`, `this year` or a plain `2025-01-31`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateExpr {
+    Fixed(NaiveDate),
+    Today,
+    /// `last 30 days` and `30 days ago` both mean the date 30 days back
+    Ago(u32, DateUnit),
+    StartOfMonth,
+    StartOfYear,
+}
+
+impl DateExpr {
+    /// The date this means on `today`. Going back further than the earliest date `NaiveDate`
+    /// can hold is an error.
+    pub fn resolve(&self, today: NaiveDate) -> Result<NaiveDate, String> {
+        let date = match *self {
+            DateExpr::Fixed(date) => Some(date),
+            DateExpr::Today => Some(today),
+            DateExpr::Ago(n, DateUnit::Day) => today.checked_sub_days(Days::new(n as u64)),
+            DateExpr::Ago(n, DateUnit::Week) => today.checked_sub_days(Days::new(7 * n as u64)),
+            DateExpr::Ago(n, DateUnit::Month) => today.checked_sub_months(Months::new(n)),
+            DateExpr::Ago(n, DateUnit::Year) => n
+                .checked_mul(12)
+                .and_then(|months| today.checked_sub_months(Months::new(months))),
+            DateExpr::StartOfMonth => today.with_day(1),
+            DateExpr::StartOfYear => today.with_ordinal(1),
+        };
+        date.ok_or_else(|| format!("Date out of range: '{}'", self))
+    }
+}
+
+impl fmt::Display for DateExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateExpr::Fixed(date) => write!(f, "{}", date),
+            DateExpr::Today => write!(f, "today"),
+            DateExpr::Ago(n, unit) => write!(f, "last {} {}", n, unit),
+            DateExpr::StartOfMonth => write!(f, "this month"),
+            DateExpr::StartOfYear => write!(f, "this year"),
+        }
+    }
+}
+
+impl FromStr for DateExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || {
+            format!(
+                "Invalid date: '{}'. Expected e.g. 2025-01-31, last 30 days",
+                s
+            )
+        };
+        let lower = s.trim().to_ascii_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        match words.as_slice() {
+            ["today"] => Ok(DateExpr::Today),
+            ["yesterday"] => Ok(DateExpr::Ago(1, DateUnit::Day)),
+            ["this", "month"] | ["start", "of", "month"] => Ok(DateExpr::StartOfMonth),
+            ["this", "year"] | ["start", "of", "year"] => Ok(DateExpr::StartOfYear),
+            ["last", n, unit] | [n, unit, "ago"] => Ok(DateExpr::Ago(
+                n.parse().map_err(|_| err())?,
+                unit.parse().map_err(|_| err())?,
+            )),
+            [date] => NaiveDate::from_str(date)
+                .map(DateExpr::Fixed)
+                .map_err(|_| err()),
+            _ => Err(err()),
+        }
+    }
+}
+
+serde_as_string!(DateExpr);
+
+/// A named `ItemFilter`, run again whenever it's needed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    pub filter: ItemFilter,
+    /// Date filters worked out each time the search runs, keyed by filter field,
+    /// e.g. `date_added_min` => `this year`. These replace the same field in `filter`.
+    #[serde(default)]
+    pub relative_dates: BTreeMap<String, DateExpr>,
+}
+
+impl SavedSearch {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.name.trim().is_empty() {
+            errors.push("Search name cannot be empty.".to_string());
+        }
+        for field in self.relative_dates.keys() {
+            if !RELATIVE_DATE_FIELDS.contains(&field.as_str()) {
+                errors.push(format!("'{}' cannot hold a relative date.", field));
+            }
+        }
+        let today = Local::now().date_naive();
+        for expr in self.relative_dates.values() {
+            if let Err(e) = expr.resolve(today) {
+                errors.push(format!("{}.", e));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The filter with relative dates worked out for `today`.
+    pub fn filter_for(&self, today: NaiveDate) -> Result<ItemFilter, String> {
+        let mut filter = self.filter.clone();
+        for (field, expr) in &self.relative_dates {
+            let date = Some(expr.resolve(today)?);
+            match field.as_str() {
+                "date_added_min" => filter.date_added_min = date,
+                "date_added_max" => filter.date_added_max = date,
+                "last_updated_min" => filter.last_updated_min = date,
+                "last_updated_max" => filter.last_updated_max = date,
+                "date_acquired_min" => filter.date_acquired_min = date,
+                "date_acquired_max" => filter.date_acquired_max = date,
+                _ => {}
+            }
+        }
+        Ok(filter)
+    }
+
+    /// The filter with relative dates worked out for the current date.
+    pub fn current_filter(&self) -> Result<ItemFilter, String> {
+        self.filter_for(Local::now().date_naive())
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let json = |column: &str| -> rusqlite::Result<String> { row.get(column) };
+        let parse_err = |i, e: serde_json::Error| {
+            rusqlite::Error::FromSqlConversionFailure(i, rusqlite::types::Type::Text, e.into())
+        };
+        Ok(SavedSearch {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            filter: serde_json::from_str(&json("filter")?).map_err(|e| parse_err(2, e))?,
+            relative_dates: serde_json::from_str(&json("relative_dates")?)
+                .map_err(|e| parse_err(3, e))?,
+        })
+    }
+}
+
+/// Saves `search`, replacing any saved search with the same name. Returns its id.
+pub fn save_search(conn: &Connection, search: &SavedSearch) -> AnyResult<i32> {
+    search
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+
+    let today = Local::now().date_naive();
+    conn.execute(
+        "INSERT INTO saved_searches (name, filter, relative_dates, last_updated)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(name) DO UPDATE SET
+            filter = excluded.filter,
+            relative_dates = excluded.relative_dates,
+            last_updated = excluded.last_updated",
+        params![
+            search.name.trim(),
+            serde_json::to_string(&search.filter)?,
+            serde_json::to_string(&search.relative_dates)?,
+            today.to_string(),
+        ],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM saved_searches WHERE name = ?1",
+        params![search.name.trim()],
+        |row| row.get(0),
+    )?)
+}
+
+pub fn get_saved_searches(conn: &Connection) -> Result<Vec<SavedSearch>> {
+    let mut stmt = conn.prepare("SELECT * FROM saved_searches ORDER BY name COLLATE NOCASE")?;
+    let rows = stmt.query_map([], SavedSearch::from_row)?;
+    rows.collect()
+}
+
+/// Looks a search up by name, ignoring case.
+pub fn get_saved_search(conn: &Connection, name: &str) -> Result<Option<SavedSearch>> {
+    conn.query_row(
+        "SELECT * FROM saved_searches WHERE name = ?1",
+        params![name.trim()],
+        SavedSearch::from_row,
+    )
+    .optional()
+}
+
+/// Returns whether a search with that name existed.
+pub fn delete_saved_search(conn: &Connection, name: &str) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM saved_searches WHERE name = ?1",
+        params![name.trim()],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Runs a saved search with its relative dates worked out for today.
+pub fn run_saved_search(conn: &Connection, name: &str) -> AnyResult<Vec<Item>> {
+    let search =
+        get_saved_search(conn, name)?.ok_or_else(|| anyhow!("No saved search named '{}'", name))?;
+    let filter = search.current_filter().map_err(|e| anyhow!(e))?;
+    Ok(get_filtered_items(conn, filter)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, init_db};
+    use crate::models::ItemAction;
+
+    #[test]
+    fn test_date_expressions() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+        let resolve = |s: &str| {
+            DateExpr::from_str(s)
+                .unwrap()
+                .resolve(today)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(resolve("last 30 days"), "2025-03-01");
+        assert_eq!(resolve("2 weeks ago"), "2025-03-17");
+        assert_eq!(resolve("1 month ago"), "2025-02-28");
+        assert_eq!(resolve("This Year"), "2025-01-01");
+        assert_eq!(resolve("this month"), "2025-03-01");
+        assert_eq!(resolve("2024-12-25"), "2024-12-25");
+        assert_eq!(
+            DateExpr::from_str("30 days ago").unwrap().to_string(),
+            "last 30 days"
+        );
+        assert!(DateExpr::from_str("last fortnight").is_err());
+        for far in [
+            "last 4294967295 years",
+            "4294967295 months ago",
+            "last 4294967295 weeks",
+        ] {
+            assert!(DateExpr::from_str(far).unwrap().resolve(today).is_err());
+        }
+    }
+
+    #[test]
+    fn test_save_and_run_search() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, action, value) in [
+            ("Clock", ItemAction::Sell, "250 USD"),
+            ("Chair", ItemAction::Sell, "40 USD"),
+            ("Desk", ItemAction::Keep, "300 USD"),
+        ] {
+            let item = Item {
+                name: name.to_string(),
+                description: "test".to_string(),
+                action,
+                estimated_value: Some(value.parse().unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+
+        let search = SavedSearch {
+            name: "Sell over $100 this year".to_string(),
+            filter: ItemFilter {
+                action: Some(ItemAction::Sell),
+                estimated_value_min: Some("100 USD".parse().unwrap()),
+                ..Default::default()
+            },
+            relative_dates: BTreeMap::from([("date_added_min".to_string(), DateExpr::StartOfYear)]),
+            ..Default::default()
+        };
+        let id = save_search(&conn, &search).unwrap();
+        // Saving again under the same name replaces it
+        assert_eq!(save_search(&conn, &search).unwrap(), id);
+        assert_eq!(get_saved_searches(&conn).unwrap().len(), 1);
+
+        let names: Vec<String> = run_saved_search(&conn, "sell over $100 THIS YEAR")
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, vec!["Clock"]);
+
+        let bad = SavedSearch {
+            relative_dates: BTreeMap::from([("name".to_string(), DateExpr::Today)]),
+            ..search.clone()
+        };
+        assert!(save_search(&conn, &bad).is_err());
+
+        assert!(delete_saved_search(&conn, &search.name).unwrap());
+        assert!(run_saved_search(&conn, &search.name).is_err());
+    }
+}
@@ -0,0 +1,546 @@
+//! Spreadsheet export, for relatives who open the catalog in Excel or LibreOffice.
+//!
+//! Unlike `csv_export`, cells keep their types: dates are dates, prices are numbers formatted
+//! in their currency and yes/no fields are booleans. The header row is frozen, and a summary
+//! sheet with totals per category comes first.
+
+use crate::models::Item;
+use crate::money::{Money, get_home_currency, get_rate_table};
+use crate::report::escape_html;
+use crate::stats::{CatalogStats, compute_stats};
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadsheetFormat {
+    Xlsx,
+    Ods,
+}
+
+impl SpreadsheetFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".xlsx") {
+            Some(SpreadsheetFormat::Xlsx)
+        } else if lower.ends_with(".ods") {
+            Some(SpreadsheetFormat::Ods)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpreadsheetOptions {
+    /// One sheet of items per category instead of a single "Items" sheet
+    #[serde(default)]
+    pub sheet_per_category: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Money(Money),
+    Bool(bool),
+}
+
+impl Cell {
+    fn text(value: Option<impl ToString>) -> Self {
+        value.map_or(Cell::Empty, |v| Cell::Text(v.to_string()))
+    }
+
+    // Roughly how many characters wide the cell shows, for column widths
+    fn width(&self) -> usize {
+        match self {
+            Cell::Empty | Cell::Bool(_) => 5,
+            Cell::Text(text) => text.chars().count(),
+            Cell::Number(n) => n.to_string().len(),
+            Cell::Date(_) => 10,
+            Cell::Money(m) => m.to_string().len(),
+        }
+    }
+}
+
+fn money_value(money: &Money) -> f64 {
+    money.minor_units() as f64 / 10f64.powi(money.currency().minor_digits() as i32)
+}
+
+// e.g. `#,##0.00 "USD"`
+fn money_number_format(money: &Money) -> String {
+    let digits = money.currency().minor_digits() as usize;
+    let decimals = if digits == 0 {
+        String::new()
+    } else {
+        format!(".{}", "0".repeat(digits))
+    };
+    format!("#,##0{} \"{}\"", decimals, money.currency())
+}
+
+type Column = (&'static str, fn(&Item) -> Cell);
+
+const ITEM_COLUMNS: &[Column] = &[
+    ("ID", |i| Cell::Number(i.id as f64)),
+    ("Name", |i| Cell::Text(i.name.clone())),
+    ("Description", |i| Cell::Text(i.description.clone())),
+    ("Category", |i| Cell::Text(i.category.to_string())),
+    ("Action", |i| Cell::Text(i.action.to_string())),
+    ("Date Made", |i| Cell::text(i.date_made)),
+    ("Date Acquired", |i| {
+        i.date_acquired.map_or(Cell::Empty, Cell::Date)
+    }),
+    ("Purchase Price", |i| {
+        i.purchase_price.map_or(Cell::Empty, Cell::Money)
+    }),
+    ("Estimated Value", |i| {
+        i.estimated_value.map_or(Cell::Empty, Cell::Money)
+    }),
+    ("Creator", |i| Cell::text(i.creator.as_ref())),
+    ("Working", |i| i.working.map_or(Cell::Empty, Cell::Bool)),
+    ("Condition", |i| Cell::text(i.condition.as_ref())),
+    ("Provenance", |i| Cell::text(i.provenance.as_ref())),
+    ("Location", |i| Cell::text(i.location.as_ref())),
+    ("Dimensions", |i| Cell::text(i.dimensions)),
+    ("Weight", |i| Cell::text(i.weight)),
+    ("Quantity", |i| Cell::Number(i.pieces() as f64)),
+    ("Date Added", |i| Cell::Date(i.date_added)),
+    ("Last Updated", |i| Cell::Date(i.last_updated)),
+];
+
+struct Sheet {
+    name: String,
+    header: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Sheet {
+    fn items(name: &str, items: &[&Item]) -> Self {
+        Sheet {
+            name: name.to_string(),
+            header: ITEM_COLUMNS.iter().map(|(h, _)| h.to_string()).collect(),
+            rows: items
+                .iter()
+                .map(|item| ITEM_COLUMNS.iter().map(|(_, cell)| cell(item)).collect())
+                .collect(),
+        }
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        (0..self.header.len())
+            .map(|col| {
+                let longest = self.rows.iter().map(|row| row[col].width()).max();
+                longest
+                    .unwrap_or(0)
+                    .max(self.header[col].len())
+                    .clamp(6, 50)
+                    + 2
+            })
+            .collect()
+    }
+}
+
+fn summary_row(label: &str, stats: &CatalogStats) -> Vec<Cell> {
+    let total = |total: Option<Money>| match total {
+        Some(money) => Cell::Money(money),
+        None => Cell::Text(format!(
+            "Missing rates for {}",
+            stats
+                .missing_rates
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    };
+    vec![
+        Cell::Text(label.to_string()),
+        Cell::Number(stats.item_count as f64),
+        Cell::Number(stats.total_quantity as f64),
+        total(stats.purchase_total_home),
+        total(stats.estimated_total_home),
+    ]
+}
+
+// The summary sheet followed by the item sheets
+fn build_sheets(
+    conn: &Connection,
+    items: &[Item],
+    options: &SpreadsheetOptions,
+) -> AnyResult<Vec<Sheet>> {
+    let home = get_home_currency(conn)?;
+    let rates = get_rate_table(conn)?;
+    let mut by_category: BTreeMap<String, Vec<&Item>> = BTreeMap::new();
+    for item in items {
+        by_category
+            .entry(item.category.to_string())
+            .or_default()
+            .push(item);
+    }
+
+    let mut summary = Sheet {
+        name: "Summary".to_string(),
+        header: [
+            "Category",
+            "Items",
+            "Pieces",
+            "Purchase Total",
+            "Estimated Total",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows: vec![],
+    };
+    for (category, items) in &by_category {
+        let items: Vec<Item> = items.iter().map(|&i| i.clone()).collect();
+        summary
+            .rows
+            .push(summary_row(category, &compute_stats(&items, home, &rates)));
+    }
+    summary
+        .rows
+        .push(summary_row("Total", &compute_stats(items, home, &rates)));
+
+    let mut sheets = vec![summary];
+    if options.sheet_per_category {
+        for (category, items) in &by_category {
+            sheets.push(Sheet::items(category, items));
+        }
+    } else {
+        sheets.push(Sheet::items("Items", &items.iter().collect::<Vec<_>>()));
+    }
+    Ok(sheets)
+}
+
+fn write_xlsx(sheets: &[Sheet], path: &str) -> AnyResult<()> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    for sheet in sheets {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&sheet.name)?;
+        for (col, title) in sheet.header.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, title, &header)?;
+        }
+        for (i, row) in sheet.rows.iter().enumerate() {
+            let r = i as u32 + 1;
+            for (col, cell) in row.iter().enumerate() {
+                let c = col as u16;
+                match cell {
+                    Cell::Empty => {}
+                    Cell::Text(text) => {
+                        worksheet.write_string(r, c, text)?;
+                    }
+                    Cell::Number(n) => {
+                        worksheet.write_number(r, c, *n)?;
+                    }
+                    // Excel dates start in 1900, so older ones are written as text
+                    Cell::Date(d) => {
+                        let value = u16::try_from(d.year()).ok().and_then(|year| {
+                            ExcelDateTime::from_ymd(year, d.month() as u8, d.day() as u8).ok()
+                        });
+                        match value {
+                            Some(value) => {
+                                worksheet.write_datetime_with_format(r, c, &value, &date)?;
+                            }
+                            None => {
+                                worksheet.write_string(r, c, d.to_string())?;
+                            }
+                        }
+                    }
+                    Cell::Money(m) => {
+                        let format = Format::new().set_num_format(money_number_format(m));
+                        worksheet.write_number_with_format(r, c, money_value(m), &format)?;
+                    }
+                    Cell::Bool(b) => {
+                        worksheet.write_boolean(r, c, *b)?;
+                    }
+                }
+            }
+        }
+        for (col, width) in sheet.column_widths().into_iter().enumerate() {
+            worksheet.set_column_width(col as u16, width as f64)?;
+        }
+        worksheet.set_freeze_panes(1, 0)?;
+        if !sheet.rows.is_empty() {
+            worksheet.autofilter(0, 0, sheet.rows.len() as u32, sheet.header.len() as u16 - 1)?;
+        }
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+const ODS_NAMESPACES: &str = r#"xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:number="urn:oasis:names:tc:opendocument:xmlns:datastyle:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:config="urn:oasis:names:tc:opendocument:xmlns:config:1.0" office:version="1.2""#;
+
+// Styles for the header, dates and one number style per currency in use
+fn ods_styles(sheets: &[Sheet]) -> String {
+    let currencies: BTreeSet<_> = sheets
+        .iter()
+        .flat_map(|s| s.rows.iter().flatten())
+        .filter_map(|cell| match cell {
+            Cell::Money(m) => Some(m.currency()),
+            _ => None,
+        })
+        .collect();
+
+    let mut xml = String::from(
+        r#"<number:date-style style:name="N-date"><number:year number:style="long"/><number:text>-</number:text><number:month number:style="long"/><number:text>-</number:text><number:day number:style="long"/></number:date-style>
+<style:style style:name="ce-date" style:family="table-cell" style:parent-style-name="Default" style:data-style-name="N-date"/>
+<style:style style:name="ce-header" style:family="table-cell" style:parent-style-name="Default"><style:text-properties fo:font-weight="bold"/></style:style>
+"#,
+    );
+    for currency in currencies {
+        xml.push_str(&format!(
+            r#"<number:number-style style:name="N-{0}"><number:number number:decimal-places="{1}" number:min-decimal-places="{1}" number:min-integer-digits="1" number:grouping="true"/><number:text> {0}</number:text></number:number-style>
+<style:style style:name="ce-{0}" style:family="table-cell" style:parent-style-name="Default" style:data-style-name="N-{0}"/>
+"#,
+            currency,
+            currency.minor_digits()
+        ));
+    }
+    xml
+}
+
+fn ods_cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => "<table:table-cell/>".to_string(),
+        Cell::Text(text) => format!(
+            r#"<table:table-cell office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+            escape_html(text)
+        ),
+        Cell::Number(n) => format!(
+            r#"<table:table-cell office:value-type="float" office:value="{0}"><text:p>{0}</text:p></table:table-cell>"#,
+            n
+        ),
+        Cell::Date(d) => format!(
+            r#"<table:table-cell table:style-name="ce-date" office:value-type="date" office:date-value="{0}"><text:p>{0}</text:p></table:table-cell>"#,
+            d
+        ),
+        Cell::Money(m) => format!(
+            r#"<table:table-cell table:style-name="ce-{0}" office:value-type="currency" office:currency="{0}" office:value="{1}"><text:p>{2}</text:p></table:table-cell>"#,
+            m.currency(),
+            money_value(m),
+            m
+        ),
+        Cell::Bool(b) => format!(
+            r#"<table:table-cell office:value-type="boolean" office:boolean-value="{}"><text:p>{}</text:p></table:table-cell>"#,
+            b,
+            if *b { "TRUE" } else { "FALSE" }
+        ),
+    }
+}
+
+fn ods_content(sheets: &[Sheet]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-content {}>\n<office:automatic-styles>\n{}",
+        ODS_NAMESPACES,
+        ods_styles(sheets)
+    );
+    for (i, sheet) in sheets.iter().enumerate() {
+        for (col, width) in sheet.column_widths().into_iter().enumerate() {
+            xml.push_str(&format!(
+                r#"<style:style style:name="co{}-{}" style:family="table-column"><style:table-column-properties style:column-width="{:.2}cm"/></style:style>"#,
+                i,
+                col,
+                width as f64 * 0.2
+            ));
+        }
+    }
+    xml.push_str("\n</office:automatic-styles>\n<office:body><office:spreadsheet>\n");
+
+    for (i, sheet) in sheets.iter().enumerate() {
+        xml.push_str(&format!(
+            r#"<table:table table:name="{}">"#,
+            escape_html(&sheet.name)
+        ));
+        for col in 0..sheet.header.len() {
+            xml.push_str(&format!(
+                r#"<table:table-column table:style-name="co{}-{}"/>"#,
+                i, col
+            ));
+        }
+        xml.push_str("\n<table:table-header-rows><table:table-row>");
+        for title in &sheet.header {
+            xml.push_str(&format!(
+                r#"<table:table-cell table:style-name="ce-header" office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+                escape_html(title)
+            ));
+        }
+        xml.push_str("</table:table-row></table:table-header-rows>\n");
+        for row in &sheet.rows {
+            xml.push_str("<table:table-row>");
+            for cell in row {
+                xml.push_str(&ods_cell(cell));
+            }
+            xml.push_str("</table:table-row>\n");
+        }
+        xml.push_str("</table:table>\n");
+    }
+    xml.push_str("</office:spreadsheet></office:body></office:document-content>\n");
+    xml
+}
+
+// ODS keeps frozen rows in the view settings, one entry per sheet
+fn ods_settings(sheets: &[Sheet]) -> String {
+    let item = |name: &str, kind: &str, value: &str| {
+        format!(
+            r#"<config:config-item config:name="{}" config:type="{}">{}</config:config-item>"#,
+            name, kind, value
+        )
+    };
+    let mut tables = String::new();
+    for sheet in sheets {
+        tables.push_str(&format!(
+            r#"<config:config-item-map-entry config:name="{}">{}{}{}{}{}</config:config-item-map-entry>"#,
+            escape_html(&sheet.name),
+            item("VerticalSplitMode", "short", "2"),
+            item("VerticalSplitPosition", "int", "1"),
+            item("ActiveSplitRange", "short", "2"),
+            item("PositionTop", "int", "0"),
+            item("PositionBottom", "int", "1"),
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-settings {}><office:settings><config:config-item-set config:name=\"ooo:view-settings\"><config:config-item-map-indexed config:name=\"Views\"><config:config-item-map-entry>{}<config:config-item-map-named config:name=\"Tables\">{}</config:config-item-map-named></config:config-item-map-entry></config:config-item-map-indexed></config:config-item-set></office:settings></office:document-settings>\n",
+        ODS_NAMESPACES,
+        item("ViewId", "string", "view1"),
+        tables
+    )
+}
+
+const ODS_MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+fn write_ods(sheets: &[Sheet], path: &str) -> AnyResult<()> {
+    let manifest = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="{}"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+<manifest:file-entry manifest:full-path="settings.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#,
+        ODS_MIMETYPE
+    );
+
+    // The mimetype must come first and uncompressed so the file type can be sniffed
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(ODS_MIMETYPE.as_bytes())?;
+    zip.start_file("META-INF/manifest.xml", deflated)?;
+    zip.write_all(manifest.as_bytes())?;
+    zip.start_file("content.xml", deflated)?;
+    zip.write_all(ods_content(sheets).as_bytes())?;
+    zip.start_file("settings.xml", deflated)?;
+    zip.write_all(ods_settings(sheets).as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Writes `items` to an .xlsx or .ods file, chosen by the extension of `path`. Totals on the
+/// summary sheet are in the home currency.
+pub fn export_to_spreadsheet(
+    conn: &Connection,
+    items: &[Item],
+    path: &str,
+    options: &SpreadsheetOptions,
+) -> AnyResult<()> {
+    let format = SpreadsheetFormat::from_path(path)
+        .ok_or_else(|| anyhow!("Spreadsheet path must end in .xlsx or .ods: {}", path))?;
+    let sheets = build_sheets(conn, items, options)?;
+    match format {
+        SpreadsheetFormat::Xlsx => write_xlsx(&sheets, path),
+        SpreadsheetFormat::Ods => write_ods(&sheets, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::models::ItemCategory;
+    use std::io::Read;
+    use std::str::FromStr;
+
+    fn zip_entry(path: &str, name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_typed_cells_and_sheets() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let items = vec![
+            Item {
+                id: 1,
+                name: "Clock & Key".to_string(),
+                category: ItemCategory::Furniture,
+                estimated_value: Some(Money::from_str("120.50 USD").unwrap()),
+                working: Some(true),
+                date_acquired: NaiveDate::from_ymd_opt(1998, 4, 2),
+                ..Default::default()
+            },
+            Item {
+                id: 2,
+                name: "Atlas".to_string(),
+                category: ItemCategory::Book,
+                estimated_value: Some(Money::from_str("30 USD").unwrap()),
+                date_acquired: NaiveDate::from_ymd_opt(1850, 6, 1),
+                ..Default::default()
+            },
+        ];
+
+        let options = SpreadsheetOptions {
+            sheet_per_category: true,
+        };
+        let sheets = build_sheets(&conn, &items, &options).unwrap();
+        let names: Vec<&str> = sheets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Summary", "Book", "Furniture"]);
+        let total = sheets[0].rows.last().unwrap();
+        assert_eq!(total[1], Cell::Number(2.0));
+        assert_eq!(
+            total[4],
+            Cell::Money(Money::from_str("150.50 USD").unwrap())
+        );
+
+        let dir = std::env::temp_dir();
+        let ods = dir.join(format!("catalog-{}.ods", std::process::id()));
+        let ods = ods.to_str().unwrap();
+        export_to_spreadsheet(&conn, &items, ods, &options).unwrap();
+        assert_eq!(zip_entry(ods, "mimetype"), ODS_MIMETYPE);
+        let content = zip_entry(ods, "content.xml");
+        assert!(content.contains(r#"office:currency="USD" office:value="120.5""#));
+        assert!(content.contains(r#"office:date-value="1998-04-02""#));
+        assert!(content.contains(r#"office:boolean-value="true""#));
+        assert!(content.contains("Clock &amp; Key"));
+        assert!(zip_entry(ods, "settings.xml").contains("VerticalSplitPosition"));
+        std::fs::remove_file(ods).unwrap();
+
+        let xlsx = dir.join(format!("catalog-{}.xlsx", std::process::id()));
+        let xlsx = xlsx.to_str().unwrap();
+        export_to_spreadsheet(&conn, &items, xlsx, &SpreadsheetOptions::default()).unwrap();
+        assert!(zip_entry(xlsx, "xl/workbook.xml").contains(r#"name="Items""#));
+        assert!(zip_entry(xlsx, "xl/worksheets/sheet2.xml").contains("pane"));
+        assert!(zip_entry(xlsx, "xl/sharedStrings.xml").contains("1850-06-01"));
+        std::fs::remove_file(xlsx).unwrap();
+
+        assert!(export_to_spreadsheet(&conn, &items, "items.csv", &options).is_err());
+    }
+}
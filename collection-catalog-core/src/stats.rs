@@ -0,0 +1,145 @@
+use crate::db::get_filtered_items;
+use crate::measure::{Weight, WeightUnit};
+use crate::models::{Item, ItemFilter};
+use crate::money::{Currency, Money, MoneyTotals, Rate, get_home_currency, get_rate_table};
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CatalogStats {
+    pub item_count: usize,
+
+    // One entry per currency in use
+    pub purchase_totals: Vec<Money>,
+    pub estimated_totals: Vec<Money>,
+
+    // Totals converted with the exchange rate table. `None` when a rate is missing.
+    pub home_currency: Currency,
+    pub purchase_total_home: Option<Money>,
+    pub estimated_total_home: Option<Money>,
+    pub missing_rates: Vec<Currency>,
+
+    // Physical totals count every piece of a set. Quantities are u32, so the total is wider
+    pub total_quantity: u64,
+    pub total_weight: Option<Weight>,
+    pub total_volume_m3: f64,
+}
+
+pub fn compute_stats(items: &[Item], home: Currency, rates: &[(Currency, Rate)]) -> CatalogStats {
+    let mut purchase = MoneyTotals::default();
+    let mut estimated = MoneyTotals::default();
+    purchase.extend(items.iter().map(|i| &i.purchase_price));
+    estimated.extend(items.iter().map(|i| &i.estimated_value));
+
+    let mut missing_rates = vec![];
+    let mut convert = |totals: &MoneyTotals| match totals.total_in(home, rates) {
+        Ok(total) => Some(total),
+        Err(missing) => {
+            missing_rates.extend(missing);
+            None
+        }
+    };
+    let purchase_total_home = convert(&purchase);
+    let estimated_total_home = convert(&estimated);
+    missing_rates.sort();
+    missing_rates.dedup();
+
+    let total_weight = items
+        .iter()
+        .filter_map(|i| i.weight.map(|w| w.grams() * i.pieces() as f64))
+        .reduce(|a, b| a + b)
+        .map(|grams| {
+            Weight {
+                value: grams,
+                unit: WeightUnit::Gram,
+            }
+            .convert(WeightUnit::Kilogram)
+        });
+    let total_volume_m3 = items
+        .iter()
+        .filter_map(|i| i.dimensions.map(|d| d.volume_m3() * i.pieces() as f64))
+        .sum();
+
+    CatalogStats {
+        item_count: items.len(),
+        purchase_totals: purchase.amounts(),
+        estimated_totals: estimated.amounts(),
+        home_currency: home,
+        purchase_total_home,
+        estimated_total_home,
+        missing_rates,
+        total_quantity: items.iter().map(|i| i.pieces() as u64).sum(),
+        total_weight,
+        total_volume_m3,
+    }
+}
+
+pub fn get_stats(conn: &Connection, filter: ItemFilter) -> Result<CatalogStats> {
+    let items = get_filtered_items(conn, filter)?;
+    let home = get_home_currency(conn)?;
+    let rates = get_rate_table(conn)?;
+    Ok(compute_stats(&items, home, &rates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_compute_stats_converts_to_home_currency() {
+        let items = vec![
+            Item {
+                purchase_price: Some(Money::from_str("19.99 USD").unwrap()),
+                estimated_value: Some(Money::from_str("30.00 GBP").unwrap()),
+                ..Default::default()
+            },
+            Item {
+                purchase_price: Some(Money::from_str("0.01 USD").unwrap()),
+                ..Default::default()
+            },
+        ];
+        let gbp = Currency::from_str("GBP").unwrap();
+
+        let stats = compute_stats(&items, Currency::USD, &[]);
+        assert_eq!(stats.purchase_total_home.unwrap().to_string(), "20.00 USD");
+        assert_eq!(stats.estimated_total_home, None);
+        assert_eq!(stats.missing_rates, vec![gbp]);
+
+        let stats = compute_stats(
+            &items,
+            Currency::USD,
+            &[(gbp, Rate::from_str("1.25").unwrap())],
+        );
+        assert_eq!(stats.estimated_total_home.unwrap().to_string(), "37.50 USD");
+    }
+
+    #[test]
+    fn test_compute_stats_physical_totals() {
+        let items = vec![
+            Item {
+                weight: Some(Weight::from_str("500 g").unwrap()),
+                quantity: Some(6),
+                ..Default::default()
+            },
+            Item {
+                weight: Some(Weight::from_str("2 lb").unwrap()),
+                dimensions: Some("100x50x20 cm".parse().unwrap()),
+                ..Default::default()
+            },
+        ];
+
+        let stats = compute_stats(&items, Currency::USD, &[]);
+        assert_eq!(stats.total_quantity, 7);
+        assert_eq!(stats.total_weight.unwrap().to_string(), "3.907 kg");
+        assert!((stats.total_volume_m3 - 0.1).abs() < 1e-9);
+
+        // Quantities that fit on their own don't overflow the total
+        let many = Item {
+            quantity: Some(u32::MAX),
+            ..Default::default()
+        };
+        let stats = compute_stats(&[many.clone(), many], Currency::USD, &[]);
+        assert_eq!(stats.total_quantity, 2 * u32::MAX as u64);
+    }
+}
@@ -0,0 +1,235 @@
+//! Saved starting points for new items, and copying an existing item.
+//!
+//! A template holds default values for any editable item field, written the same way as for
+//! `update_item_fields`, e.g. `category` => `MineralSpecimen`, `provenance` => `Tucson show`.
+
+use crate::attachments::{add_attachment, get_attachments};
+use crate::db::{add_item, get_item_by_id, set_item_field};
+use crate::models::Item;
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::Local;
+use rusqlite::{Connection, OptionalExtension, Result, Row, params};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Fields that are set when an item is added rather than taken from a template.
+const RESERVED_FIELDS: &[&str] = &["date_added", "last_updated", "deleted"];
+
+/// Named defaults for new items.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ItemTemplate {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    /// Field values keyed by field name
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+}
+
+impl ItemTemplate {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        if self.name.trim().is_empty() {
+            errors.push("Template name cannot be empty.".to_string());
+        }
+        for (field, value) in &self.defaults {
+            if RESERVED_FIELDS.contains(&field.as_str()) {
+                errors.push(format!("'{}' cannot have a default.", field));
+            } else if let Err(e) = set_item_field(&mut Item::default(), field, value.clone()) {
+                errors.push(format!("{}: {}", field, e));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// A new item filled in from the defaults, to be completed before it's added.
+    pub fn draft_item(&self) -> AnyResult<Item> {
+        let mut item = Item::default();
+        for (field, value) in &self.defaults {
+            set_item_field(&mut item, field, value.clone())?;
+        }
+        Ok(item)
+    }
+
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let defaults: String = row.get("defaults")?;
+        Ok(ItemTemplate {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            defaults: serde_json::from_str(&defaults).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+            })?,
+        })
+    }
+}
+
+/// Saves `template`, replacing any template with the same name. Returns its id.
+pub fn save_template(conn: &Connection, template: &ItemTemplate) -> AnyResult<i32> {
+    template
+        .validate()
+        .map_err(|errs| anyhow!("Validation failed: {}", errs.join("; ")))?;
+
+    let today = Local::now().date_naive();
+    conn.execute(
+        "INSERT INTO item_templates (name, defaults, last_updated)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT(name) DO UPDATE SET
+            defaults = excluded.defaults,
+            last_updated = excluded.last_updated",
+        params![
+            template.name.trim(),
+            serde_json::to_string(&template.defaults)?,
+            today.to_string(),
+        ],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM item_templates WHERE name = ?1",
+        params![template.name.trim()],
+        |row| row.get(0),
+    )?)
+}
+
+pub fn get_templates(conn: &Connection) -> Result<Vec<ItemTemplate>> {
+    let mut stmt = conn.prepare("SELECT * FROM item_templates ORDER BY name COLLATE NOCASE")?;
+    let rows = stmt.query_map([], ItemTemplate::from_row)?;
+    rows.collect()
+}
+
+/// Looks a template up by name, ignoring case.
+pub fn get_template(conn: &Connection, name: &str) -> Result<Option<ItemTemplate>> {
+    conn.query_row(
+        "SELECT * FROM item_templates WHERE name = ?1",
+        params![name.trim()],
+        ItemTemplate::from_row,
+    )
+    .optional()
+}
+
+/// Returns whether a template with that name existed.
+pub fn delete_template(conn: &Connection, name: &str) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM item_templates WHERE name = ?1",
+        params![name.trim()],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Adds a copy of an item as a new record, keeping its creator links and roles. With
+/// `with_attachments` the copy also links to the same attachment files; photos from condition
+/// reports stay with the original, as do identifiers and provenance events. Returns the new id.
+pub fn clone_item(conn: &Connection, item_id: i32, with_attachments: bool) -> AnyResult<i32> {
+    let item = match get_item_by_id(conn, item_id)? {
+        Some(item) if !item.deleted => item,
+        _ => return Err(anyhow!("Item {} not found", item_id)),
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    let new_id = add_item(&tx, &item)?;
+    tx.execute(
+        "DELETE FROM item_creators WHERE item_id = ?1",
+        params![new_id],
+    )?;
+    tx.execute(
+        "INSERT INTO item_creators (item_id, creator_id, role, position)
+        SELECT ?1, creator_id, role, position FROM item_creators WHERE item_id = ?2",
+        params![new_id, item_id],
+    )?;
+    if with_attachments {
+        for attachment in get_attachments(&tx, item_id)? {
+            if attachment.condition_report_id.is_none() {
+                add_attachment(&tx, new_id, &attachment.path, attachment.caption.as_deref())?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(new_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::creators::get_item_creators;
+    use crate::db::init_db;
+    use crate::models::ItemCategory;
+
+    #[test]
+    fn test_template_defaults_and_validation() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let mut template = ItemTemplate {
+            name: "Minerals".to_string(),
+            defaults: BTreeMap::from([
+                ("category".to_string(), "MineralSpecimen".to_string()),
+                ("provenance".to_string(), "Tucson show".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let id = save_template(&conn, &template).unwrap();
+
+        template
+            .defaults
+            .insert("location".to_string(), "Cabinet 2".to_string());
+        assert_eq!(save_template(&conn, &template).unwrap(), id);
+        assert_eq!(get_templates(&conn).unwrap().len(), 1);
+
+        let mut item = get_template(&conn, "minerals")
+            .unwrap()
+            .unwrap()
+            .draft_item()
+            .unwrap();
+        assert!(matches!(item.category, ItemCategory::MineralSpecimen));
+        assert_eq!(item.location.as_deref(), Some("Cabinet 2"));
+        item.name = "Quartz".to_string();
+        item.description = "Clear point".to_string();
+        assert!(add_item(&conn, &item).is_ok());
+
+        template
+            .defaults
+            .insert("quantity".to_string(), "many".to_string());
+        template
+            .defaults
+            .insert("deleted".to_string(), "true".to_string());
+        template
+            .defaults
+            .insert("category".to_string(), "Mineral".to_string());
+        let err = save_template(&conn, &template).unwrap_err().to_string();
+        assert!(err.contains("quantity") && err.contains("'deleted'"));
+        assert!(err.contains("unknown category 'Mineral'"));
+
+        assert!(delete_template(&conn, "Minerals").unwrap());
+        assert!(get_template(&conn, "Minerals").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_clone_item_with_attachments() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        let item = Item {
+            name: "Amethyst".to_string(),
+            description: "Geode half".to_string(),
+            category: ItemCategory::MineralSpecimen,
+            creator: Some("Ann Smith".to_string()),
+            ..Default::default()
+        };
+        let id = add_item(&conn, &item).unwrap();
+        add_attachment(&conn, id, "photos/amethyst.jpg", Some("Front")).unwrap();
+
+        let plain = clone_item(&conn, id, false).unwrap();
+        let copy = clone_item(&conn, id, true).unwrap();
+        assert_ne!(plain, id);
+        let cloned = get_item_by_id(&conn, copy).unwrap().unwrap();
+        assert_eq!(cloned.name, "Amethyst");
+        assert_eq!(cloned.creator.as_deref(), Some("Ann Smith"));
+        assert_eq!(get_item_creators(&conn, copy).unwrap().len(), 1);
+        assert!(get_attachments(&conn, plain).unwrap().is_empty());
+        assert_eq!(
+            get_attachments(&conn, copy).unwrap()[0].path,
+            "photos/amethyst.jpg"
+        );
+        assert!(clone_item(&conn, 99, false).is_err());
+    }
+}
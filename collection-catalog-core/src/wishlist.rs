@@ -0,0 +1,151 @@
+//! Items being looked for, such as the missing pieces of a set.
+//!
+//! Wishlist entries are ordinary items with `wishlist` set, so they can carry the same details
+//! and relations as owned items. Listings, queries and totals leave them out unless the filter
+//! or query asks about the wishlist.
+
+use crate::db::{get_filtered_items, get_item_by_id, update_item};
+use crate::models::{Item, ItemFilter};
+use crate::money::Money;
+use anyhow::{Result as AnyResult, anyhow};
+use chrono::NaiveDate;
+use rusqlite::{Connection, Result};
+
+/// Wishlist items that haven't been deleted, most wanted first.
+pub fn get_wishlist(conn: &Connection) -> Result<Vec<Item>> {
+    let mut items = get_filtered_items(
+        conn,
+        ItemFilter {
+            wishlist: Some(true),
+            deleted: Some(false),
+            ..Default::default()
+        },
+    )?;
+    items.sort_by(|a, b| {
+        (a.wishlist_priority.is_none(), a.wishlist_priority)
+            .cmp(&(b.wishlist_priority.is_none(), b.wishlist_priority))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(items)
+}
+
+/// Turns a wishlist entry into an owned item acquired on `date`, recording the price paid if
+/// given. The wishlist fields are cleared. Returns the updated item.
+pub fn acquire_wishlist_item(
+    conn: &Connection,
+    item_id: i32,
+    date: NaiveDate,
+    price: Option<Money>,
+) -> AnyResult<Item> {
+    let mut item = match get_item_by_id(conn, item_id)? {
+        Some(item) if !item.deleted => item,
+        _ => return Err(anyhow!("Item {} not found", item_id)),
+    };
+    if !item.wishlist {
+        return Err(anyhow!("Item {} is not on the wishlist", item_id));
+    }
+
+    item.wishlist = false;
+    item.target_price = None;
+    item.wishlist_priority = None;
+    item.wishlist_notes = None;
+    item.date_acquired = Some(date);
+    if price.is_some() {
+        item.purchase_price = price;
+    }
+    update_item(conn, &item)?;
+
+    get_item_by_id(conn, item_id)?.ok_or_else(|| anyhow!("Item {} not found", item_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{add_item, get_all_items, init_db};
+    use crate::models::ItemCategory;
+    use crate::query::{parse_query, query_items};
+    use crate::stats::get_stats;
+    use std::str::FromStr;
+
+    fn names(items: Vec<Item>) -> Vec<String> {
+        items.into_iter().map(|i| i.name).collect()
+    }
+
+    #[test]
+    fn test_wishlist_excluded_by_default_and_acquired() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_db(&conn).unwrap();
+        for (name, wishlist, priority, value) in [
+            ("Teapot", false, None, "80"),
+            ("Sugar Bowl", true, Some(2), "40"),
+            ("Creamer", true, Some(1), "30"),
+        ] {
+            let item = Item {
+                name: name.to_string(),
+                description: "Blue willow".to_string(),
+                category: ItemCategory::Kitchenware,
+                estimated_value: Some(Money::from_str(value).unwrap()),
+                wishlist,
+                wishlist_priority: priority,
+                target_price: wishlist.then(|| Money::from_str("25").unwrap()),
+                ..Default::default()
+            };
+            add_item(&conn, &item).unwrap();
+        }
+
+        assert_eq!(names(get_all_items(&conn).unwrap()), vec!["Teapot"]);
+        assert_eq!(
+            names(get_filtered_items(&conn, ItemFilter::default()).unwrap()),
+            vec!["Teapot"]
+        );
+        let stats = get_stats(&conn, ItemFilter::default()).unwrap();
+        assert_eq!(
+            stats.estimated_total_home,
+            Some(Money::from_str("80").unwrap())
+        );
+        assert_eq!(
+            names(get_wishlist(&conn).unwrap()),
+            vec!["Creamer", "Sugar Bowl"]
+        );
+
+        // Asking about the wishlist anywhere in a filter or query includes it
+        let either = ItemFilter {
+            wishlist: Some(true),
+            ..Default::default()
+        }
+        .or(ItemFilter {
+            wishlist: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(get_filtered_items(&conn, either).unwrap().len(), 3);
+
+        // but only in the branches that ask
+        let wanted_or_cheap = ItemFilter {
+            wishlist: Some(true),
+            name_contains: Some("Sugar".to_string()),
+            ..Default::default()
+        }
+        .or(ItemFilter {
+            estimated_value_max: Some(Money::from_str("50").unwrap()),
+            ..Default::default()
+        });
+        assert_eq!(
+            names(get_filtered_items(&conn, wanted_or_cheap).unwrap()),
+            vec!["Sugar Bowl"]
+        );
+        let query = |text: &str| names(query_items(&conn, &parse_query(text).unwrap()).unwrap());
+        assert_eq!(query("willow"), vec!["Teapot"]);
+        assert_eq!(query("wishlist priority<2"), vec!["Creamer"]);
+        assert_eq!(query("target<=25").len(), 2);
+
+        let price = Money::from_str("22").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let item = acquire_wishlist_item(&conn, 3, date, Some(price)).unwrap();
+        assert!(!item.wishlist);
+        assert_eq!(item.target_price, None);
+        assert_eq!(item.purchase_price, Some(price));
+        assert_eq!(item.date_acquired, Some(date));
+        assert_eq!(names(get_wishlist(&conn).unwrap()), vec!["Sugar Bowl"]);
+        assert!(acquire_wishlist_item(&conn, 3, date, None).is_err());
+    }
+}
@@ -2,11 +2,36 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Mutex;
 
+use chrono::{Days, Local, NaiveDate};
 use collection_catalog_core::{
-    Item, ItemFilter, add_item, export_to_csv, get_all_items, get_filtered_items, get_item_by_id,
-    init_db, soft_delete_item, update_item_fields,
+    Assignment, AssignmentConflict, Attachment, Audit, AuditMark, AuditReport, Beneficiary,
+    BeneficiaryList, CSV_COLUMNS, CatalogStats, ConditionReport, Creator, CreatorRole, CreatorStats,
+    CsvOptions, DEFAULT_MIN_SCORE, DEFAULT_PHOTO_DISTANCE, DueReminder, DuplicateCandidate,
+    ExportProfile, FilterExpr, Identifier, ImageMetadata, Item, ItemBeneficiary, ItemCategory,
+    ItemCreator, ItemFilter, ItemIdentifier, ItemTemplate, LabelOptions, LabelTemplate, MergeField,
+    Money, MoneyFormat, OfflineProvider, PhotoMatch, ProvenanceEvent, RelatedItem, RelationKind,
+    Reminder, ReportGrouping, ReportOptions, SavedSearch, SpreadsheetFormat, SpreadsheetOptions,
+    ThumbnailSize, acquire_wishlist_item, add_beneficiary, add_condition_report, add_identifier,
+    add_item, add_relation, add_reminder, append_provenance_event, assign_item,
+    assignment_conflicts, audit_report, beneficiary_lists, bulk_soft_delete, bulk_update,
+    clone_item, complete_reminder, delete_audit, delete_beneficiary, delete_export_profile,
+    delete_provenance_event, delete_reminder, delete_saved_search, delete_template, due_reminders,
+    duplicate_creator_candidates, export_reminders_ics, export_to_spreadsheet, find_duplicates,
+    find_similar_photos, finish_audit, get_all_items, get_attachment, get_attachments, get_audits,
+    get_condition_history, get_condition_scale, get_creator_stats, get_creators, get_export_profile,
+    get_export_profiles, get_filtered_items, get_identifiers, get_item_beneficiaries,
+    get_item_by_id, get_item_creators, get_money_format, get_provenance, get_related_items,
+    get_reminders, get_saved_search, get_saved_searches, get_set_stats, get_template, get_templates,
+    get_wishlist, hash_photos, init_db, item_photo, link_creator, lookup_metadata, mark_found,
+    merge_creators, merge_items, metadata::DEFAULT_DUMP_FILE, move_provenance_event, parse_query,
+    query_items, read_image_metadata, remove_relation, save_export_profile, save_search,
+    save_template, scan_barcode_file, set_money_format, soft_delete_item, start_audit,
+    stream_filtered_items, thumbnail, unassign_item, unmark_found, update_beneficiary,
+    update_item_fields, update_provenance_event, write_beneficiary_report, write_csv, write_json,
+    write_labels, write_report,
 };
 use rusqlite::Connection;
 use tauri::{AppHandle, State, Manager};
@@ -15,6 +40,9 @@ use tauri_plugin_dialog::DialogExt;
 // Shared state wrapper
 struct DbState(Mutex<Connection>);
 
+// The offline metadata dump, read on the first lookup and kept for later ones
+struct MetadataState(Mutex<Option<OfflineProvider>>);
+
 #[tauri::command]
 fn list_items(db: State<DbState>) -> Result<Vec<Item>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -22,14 +50,15 @@ fn list_items(db: State<DbState>) -> Result<Vec<Item>, String> {
 }
 
 #[tauri::command]
-fn new_item(db: State<DbState>, item: Item) -> Result<(), String> {
+fn new_item(db: State<DbState>, item: Item) -> Result<i32, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     item.validate().map_err(|errs| errs.join(", "))?;
     Ok(add_item(&conn, &item).map_err(|e| e.to_string())?)
 }
 
+// Takes a plain ItemFilter object or an expression such as {"any": [...]}
 #[tauri::command]
-fn filter_items(db: State<DbState>, filter: ItemFilter) -> Result<Vec<Item>, String> {
+fn filter_items(db: State<DbState>, filter: FilterExpr) -> Result<Vec<Item>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     Ok(get_filtered_items(&*conn, filter).map_err(|e| e.to_string())?)
 }
@@ -60,26 +89,496 @@ fn delete_item(db: State<DbState>, id: i32) -> Result<(), String> {
     soft_delete_item(&*conn, id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn bulk_update_items(
+    db: State<DbState>,
+    filter: ItemFilter,
+    updates: HashMap<String, String>,
+    dry_run: bool,
+) -> Result<Vec<i32>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let borrowed: HashMap<&str, String> =
+        updates.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+    bulk_update(&conn, filter, borrowed, dry_run).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn bulk_delete_items(
+    db: State<DbState>,
+    filter: ItemFilter,
+    dry_run: bool,
+) -> Result<Vec<i32>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    bulk_soft_delete(&conn, filter, dry_run).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn export_filtered_items_to_csv(
     db: State<'_, DbState>,
     app_handle: AppHandle,
     filter: ItemFilter,
+    options: Option<SpreadsheetOptions>,
+    profile: Option<String>,
 ) -> Result<Option<String>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let items = get_filtered_items(&*conn, filter).map_err(|e| e.to_string())?;
+    // A missing profile is reported before the dialog opens
+    let csv_options = match profile {
+        Some(name) => {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            get_export_profile(&conn, &name)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("No export profile named '{}'", name))?
+                .options
+        }
+        None => CsvOptions::default(),
+    };
 
-    // Show "Save As" dialog
+    // Show "Save As" dialog, the chosen extension picks the format. Lock only once a path
+    // is picked, so the dialog doesn't block every other command.
     let save_path = app_handle
         .dialog()
         .file()
-        .set_title("Save CSV")
+        .set_title("Export Items")
         .add_filter("CSV file", &["csv"])
+        .add_filter("Excel workbook", &["xlsx"])
+        .add_filter("OpenDocument spreadsheet", &["ods"])
+        .add_filter("JSON file", &["json"])
+        .blocking_save_file();
+
+    if let Some(path) = save_path {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let path = path.to_string();
+        if SpreadsheetFormat::from_path(&path).is_some() {
+            let items = get_filtered_items(&*conn, filter).map_err(|e| e.to_string())?;
+            export_to_spreadsheet(&conn, &items, &path, &options.unwrap_or_default())
+                .map_err(|e| e.to_string())?;
+        } else {
+            // CSV and JSON are written as the items are read
+            let out = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+            let json = path.to_ascii_lowercase().ends_with(".json");
+            stream_filtered_items(&*conn, filter, |items| {
+                if json {
+                    write_json(items, out)
+                } else {
+                    write_csv(&conn, items, out, &csv_options)
+                }
+            })
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(Some(path))
+    } else {
+        // user cancelled
+        Ok(None)
+    }
+}
+
+#[tauri::command]
+async fn save_report(
+    db: State<'_, DbState>,
+    app_handle: AppHandle,
+    filter: ItemFilter,
+    grouping: ReportGrouping,
+) -> Result<Option<String>, String> {
+    // Show "Save As" dialog, the chosen extension picks the format. Lock only once a path
+    // is picked, so the dialog doesn't block every other command.
+    let save_path = app_handle
+        .dialog()
+        .file()
+        .set_title("Save Report")
+        .add_filter("PDF document", &["pdf"])
+        .add_filter("HTML page", &["html"])
+        .blocking_save_file();
+
+    if let Some(path) = save_path {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let options = ReportOptions {
+            grouping,
+            money_format: get_money_format(&conn).map_err(|e| e.to_string())?,
+            ..Default::default()
+        };
+        write_report(&conn, filter, &options, &path.to_string()).map_err(|e| e.to_string())?;
+        Ok(Some(path.to_string()))
+    } else {
+        // user cancelled
+        Ok(None)
+    }
+}
+
+// QR code labels for the filtered items, on a built in sheet template (Avery 5160 by default)
+#[tauri::command]
+async fn save_labels(
+    db: State<'_, DbState>,
+    app_handle: AppHandle,
+    filter: ItemFilter,
+    template: Option<String>,
+) -> Result<Option<String>, String> {
+    let template = match template {
+        Some(name) => LabelTemplate::find(&name).map_err(|e| e.to_string())?,
+        None => LabelTemplate::default(),
+    };
+
+    // Lock once a path is picked, so the dialog doesn't block other commands
+    let save_path = app_handle
+        .dialog()
+        .file()
+        .set_title("Save Labels")
+        .add_filter("PDF document", &["pdf"])
+        .add_filter("SVG image", &["svg"])
+        .blocking_save_file();
+
+    if let Some(path) = save_path {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let options = LabelOptions {
+            template,
+            ..Default::default()
+        };
+        write_labels(&conn, filter, &options, &path.to_string()).map_err(|e| e.to_string())?;
+        Ok(Some(path.to_string()))
+    } else {
+        // user cancelled
+        Ok(None)
+    }
+}
+
+#[tauri::command]
+fn condition_history(db: State<DbState>, item_id: i32) -> Result<Vec<ConditionReport>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_condition_history(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn record_condition(db: State<DbState>, report: ConditionReport) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add_condition_report(&conn, &report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn condition_scale(db: State<DbState>, category: ItemCategory) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_condition_scale(&conn, &category).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn related_items(db: State<DbState>, item_id: i32) -> Result<Vec<RelatedItem>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_related_items(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn link_items(
+    db: State<DbState>,
+    item_id: i32,
+    kind: RelationKind,
+    related_id: i32,
+) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add_relation(&conn, item_id, kind, related_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn unlink_items(db: State<DbState>, relation_id: i32) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    remove_relation(&conn, relation_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_stats(db: State<DbState>, set_id: i32) -> Result<CatalogStats, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_set_stats(&conn, set_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn item_provenance(db: State<DbState>, item_id: i32) -> Result<Vec<ProvenanceEvent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_provenance(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn append_provenance(db: State<DbState>, event: ProvenanceEvent) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    append_provenance_event(&conn, &event).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn edit_provenance(db: State<DbState>, event: ProvenanceEvent) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_provenance_event(&conn, &event).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn reorder_provenance(db: State<DbState>, event_id: i32, position: usize) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    move_provenance_event(&conn, event_id, position).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_provenance(db: State<DbState>, event_id: i32) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_provenance_event(&conn, event_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_creators(db: State<DbState>) -> Result<Vec<Creator>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_creators(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn item_creators(db: State<DbState>, item_id: i32) -> Result<Vec<ItemCreator>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_item_creators(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn link_item_creator(
+    db: State<DbState>,
+    item_id: i32,
+    creator_id: i32,
+    role: CreatorRole,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    link_creator(&conn, item_id, creator_id, role).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn creator_duplicates(db: State<DbState>) -> Result<Vec<(Creator, Creator)>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    duplicate_creator_candidates(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn merge_duplicate_creators(
+    db: State<DbState>,
+    keep_id: i32,
+    merge_id: i32,
+) -> Result<Creator, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    merge_creators(&conn, keep_id, merge_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn creator_stats(db: State<DbState>, filter: ItemFilter) -> Result<Vec<CreatorStats>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_creator_stats(&conn, filter).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn duplicate_items(
+    db: State<DbState>,
+    min_score: Option<f64>,
+) -> Result<Vec<DuplicateCandidate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    // Photos attached before hashes were kept are hashed first
+    hash_photos(&conn).map_err(|e| e.to_string())?;
+    find_duplicates(&conn, min_score.unwrap_or(DEFAULT_MIN_SCORE)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn similar_photos(db: State<DbState>, max_distance: Option<u32>) -> Result<Vec<PhotoMatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    hash_photos(&conn).map_err(|e| e.to_string())?;
+    find_similar_photos(&conn, max_distance.unwrap_or(DEFAULT_PHOTO_DISTANCE))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn merge_duplicate_items(
+    db: State<DbState>,
+    keep_id: i32,
+    merge_id: i32,
+    take: Vec<MergeField>,
+) -> Result<Item, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    merge_items(&conn, keep_id, merge_id, &take).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_saved_searches(db: State<DbState>) -> Result<Vec<SavedSearch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_saved_searches(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_current_search(db: State<DbState>, search: SavedSearch) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_search(&conn, &search).map_err(|e| e.to_string())
+}
+
+// The saved filter with relative dates worked out for today, ready for filter_items
+#[tauri::command]
+fn saved_search_filter(db: State<DbState>, name: String) -> Result<ItemFilter, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let search = get_saved_search(&conn, &name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No saved search named '{}'", name))?;
+    search.current_filter()
+}
+
+#[tauri::command]
+fn remove_saved_search(db: State<DbState>, name: String) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_saved_search(&conn, &name).map_err(|e| e.to_string())
+}
+
+// Items matching a text query such as `category:Book value>100 -deleted`
+#[tauri::command]
+fn search_items(db: State<DbState>, query: String) -> Result<Vec<Item>, String> {
+    let query = parse_query(&query).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_items(&conn, &query).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn item_identifiers(db: State<DbState>, item_id: i32) -> Result<Vec<ItemIdentifier>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_identifiers(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_item_identifier(db: State<DbState>, item_id: i32, code: String) -> Result<i32, String> {
+    let identifier = Identifier::from_str(&code)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add_identifier(&conn, item_id, &identifier).map_err(|e| e.to_string())
+}
+
+// Codes read from an image the user picks, e.g. a photo of the back of a book
+#[tauri::command]
+async fn scan_barcode_image(app_handle: AppHandle) -> Result<Vec<Identifier>, String> {
+    let image_path = app_handle
+        .dialog()
+        .file()
+        .set_title("Choose Barcode Image")
+        .add_filter("Image", &["jpg", "jpeg", "png"])
+        .blocking_pick_file();
+
+    match image_path.and_then(|p| p.into_path().ok()) {
+        Some(path) => scan_barcode_file(&path).map_err(|e| e.to_string()),
+        // user cancelled
+        None => Ok(vec![]),
+    }
+}
+
+// A new item filled in from the offline metadata dump kept next to the database
+#[tauri::command]
+fn lookup_item_draft(
+    app_handle: AppHandle,
+    offline: State<MetadataState>,
+    code: String,
+) -> Result<Option<Item>, String> {
+    let identifier = Identifier::from_str(&code)?;
+    let mut provider = offline.0.lock().map_err(|e| e.to_string())?;
+    if provider.is_none() {
+        let dump = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?
+            .join("data")
+            .join(DEFAULT_DUMP_FILE);
+        *provider = Some(OfflineProvider::open(&dump).map_err(|e| e.to_string())?);
+    }
+    let provider = provider.as_ref().unwrap();
+    let metadata = lookup_metadata(&[provider], &identifier).map_err(|e| e.to_string())?;
+    Ok(metadata.map(|m| m.draft_item(&identifier)))
+}
+
+#[tauri::command]
+fn list_audits(db: State<DbState>) -> Result<Vec<Audit>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_audits(&conn).map_err(|e| e.to_string())
+}
+
+// Starts an audit of everything the filter matches, e.g. `{"location_contains": "Attic"}`
+#[tauri::command]
+fn begin_audit(db: State<DbState>, name: String, filter: ItemFilter) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    start_audit(&conn, &name, filter).map_err(|e| e.to_string())
+}
+
+// `scan` is an item id or the text of a scanned label
+#[tauri::command]
+fn mark_audit_item(db: State<DbState>, audit_id: i32, scan: String) -> Result<AuditMark, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    mark_found(&conn, audit_id, &scan).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn unmark_audit_item(db: State<DbState>, audit_id: i32, item_id: i32) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    unmark_found(&conn, audit_id, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn audit_discrepancies(db: State<DbState>, audit_id: i32) -> Result<AuditReport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    audit_report(&conn, audit_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn close_audit(db: State<DbState>, audit_id: i32) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    finish_audit(&conn, audit_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_audit(db: State<DbState>, audit_id: i32) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_audit(&conn, audit_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn item_reminders(db: State<DbState>, item_id: i32) -> Result<Vec<Reminder>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_reminders(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_item_reminder(db: State<DbState>, reminder: Reminder) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add_reminder(&conn, &reminder).map_err(|e| e.to_string())
+}
+
+// Done today unless a date is given. Returns the reminder with its next due date.
+#[tauri::command]
+fn complete_item_reminder(
+    db: State<DbState>,
+    id: i32,
+    date: Option<NaiveDate>,
+) -> Result<Reminder, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let date = date.unwrap_or_else(|| Local::now().date_naive());
+    complete_reminder(&conn, id, date).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_reminder(db: State<DbState>, id: i32) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_reminder(&conn, id).map_err(|e| e.to_string())
+}
+
+// Overdue reminders and those due in the next week, shown when the app opens
+#[tauri::command]
+fn startup_reminders(db: State<DbState>) -> Result<Vec<DueReminder>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let until = Local::now().date_naive() + Days::new(7);
+    due_reminders(&conn, until).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn save_reminders_ics(
+    db: State<'_, DbState>,
+    app_handle: AppHandle,
+) -> Result<Option<String>, String> {
+    // Lock once a path is picked, so the dialog doesn't block other commands
+    let save_path = app_handle
+        .dialog()
+        .file()
+        .set_title("Export Reminders")
+        .add_filter("Calendar", &["ics"])
         .blocking_save_file();
 
-    // Convert the file path to a string and pass to export_to_csv
     if let Some(path) = save_path {
-        export_to_csv(&items, &path.to_string()).map_err(|e| e.to_string())?;
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        export_reminders_ics(&conn, &path.to_string()).map_err(|e| e.to_string())?;
         Ok(Some(path.to_string()))
     } else {
         // user cancelled
@@ -87,6 +586,231 @@ async fn export_filtered_items_to_csv(
     }
 }
 
+// Every beneficiary with their assigned items and totals
+#[tauri::command]
+fn list_beneficiaries(db: State<DbState>) -> Result<Vec<BeneficiaryList>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    beneficiary_lists(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn create_beneficiary(db: State<DbState>, beneficiary: Beneficiary) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    add_beneficiary(&conn, &beneficiary).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn edit_beneficiary(db: State<DbState>, beneficiary: Beneficiary) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    update_beneficiary(&conn, &beneficiary).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_beneficiary(db: State<DbState>, id: i32) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_beneficiary(&conn, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn item_beneficiaries(db: State<DbState>, item_id: i32) -> Result<Vec<ItemBeneficiary>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_item_beneficiaries(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn assign_to_beneficiary(db: State<DbState>, assignment: Assignment) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    assign_item(&conn, &assignment).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn unassign_from_beneficiary(
+    db: State<DbState>,
+    item_id: i32,
+    beneficiary_id: i32,
+) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    unassign_item(&conn, item_id, beneficiary_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn beneficiary_conflicts(db: State<DbState>) -> Result<Vec<AssignmentConflict>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    assignment_conflicts(&conn).map_err(|e| e.to_string())
+}
+
+// One person's list, or everyone's followed by any conflicts
+#[tauri::command]
+async fn save_beneficiary_report(
+    db: State<'_, DbState>,
+    app_handle: AppHandle,
+    beneficiary_id: Option<i32>,
+) -> Result<Option<String>, String> {
+    // Lock once a path is picked, so the dialog doesn't block other commands
+    let save_path = app_handle
+        .dialog()
+        .file()
+        .set_title("Save Beneficiary Report")
+        .add_filter("PDF document", &["pdf"])
+        .add_filter("HTML page", &["html"])
+        .add_filter("CSV", &["csv"])
+        .blocking_save_file();
+
+    if let Some(path) = save_path {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        write_beneficiary_report(&conn, beneficiary_id, &path.to_string())
+            .map_err(|e| e.to_string())?;
+        Ok(Some(path.to_string()))
+    } else {
+        // user cancelled
+        Ok(None)
+    }
+}
+
+// Most wanted first
+#[tauri::command]
+fn wishlist_items(db: State<DbState>) -> Result<Vec<Item>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_wishlist(&conn).map_err(|e| e.to_string())
+}
+
+// Acquired today unless a date is given
+#[tauri::command]
+fn acquire_wishlist(
+    db: State<DbState>,
+    item_id: i32,
+    price: Option<Money>,
+    date: Option<NaiveDate>,
+) -> Result<Item, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let date = date.unwrap_or_else(|| Local::now().date_naive());
+    acquire_wishlist_item(&conn, item_id, date, price).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_templates(db: State<DbState>) -> Result<Vec<ItemTemplate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_templates(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_item_template(db: State<DbState>, template: ItemTemplate) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_template(&conn, &template).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_template(db: State<DbState>, name: String) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_template(&conn, &name).map_err(|e| e.to_string())
+}
+
+// A new item filled in from a template, for the add form to complete
+#[tauri::command]
+fn template_draft(db: State<DbState>, name: String) -> Result<Item, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let template = get_template(&conn, &name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No template named '{}'", name))?;
+    template.draft_item().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clone_existing_item(
+    db: State<DbState>,
+    item_id: i32,
+    with_attachments: bool,
+) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    clone_item(&conn, item_id, with_attachments).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn item_attachments(db: State<DbState>, item_id: i32) -> Result<Vec<Attachment>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_attachments(&conn, item_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn photo_metadata(db: State<DbState>, attachment_id: i32) -> Result<ImageMetadata, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let attachment = get_attachment(&conn, attachment_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Attachment {} not found", attachment_id))?;
+    read_image_metadata(std::path::Path::new(&attachment.path)).map_err(|e| e.to_string())
+}
+
+// Serves thumb://localhost/item-5?size=small (an item's first photo) or attachment-12, so
+// pages can use thumbnails as <img> sources
+fn thumbnail_bytes(app: &AppHandle, uri: &tauri::http::Uri) -> Result<Vec<u8>, String> {
+    let size = match uri.query().and_then(|q| q.strip_prefix("size=")) {
+        Some(size) => ThumbnailSize::from_str(size)?,
+        None => ThumbnailSize::default(),
+    };
+    let cache_dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("thumbnails");
+    let target = uri.path().trim_start_matches('/');
+    let id = |text: &str| text.parse::<i32>().map_err(|e| e.to_string());
+
+    // The database is only locked to find the photo, not while the thumbnail is made
+    let attachment = {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Some(item_id) = target.strip_prefix("item-") {
+            item_photo(&conn, id(item_id)?)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Item {} has no photos", item_id))?
+        } else if let Some(attachment_id) = target.strip_prefix("attachment-") {
+            get_attachment(&conn, id(attachment_id)?)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Attachment {} not found", attachment_id))?
+        } else {
+            return Err(format!("Unknown thumbnail '{}'", target));
+        }
+    };
+    let path = thumbnail(&attachment, size, &cache_dir).map_err(|e| e.to_string())?;
+    std::fs::read(path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn list_export_profiles(db: State<DbState>) -> Result<Vec<ExportProfile>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_export_profiles(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_csv_export_profile(db: State<DbState>, profile: ExportProfile) -> Result<i32, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_export_profile(&conn, &profile).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_export_profile(db: State<DbState>, name: String) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    delete_export_profile(&conn, &name).map_err(|e| e.to_string())
+}
+
+// Column keys and headers for the profile editor
+#[tauri::command]
+fn csv_export_columns() -> Vec<(&'static str, &'static str)> {
+    CSV_COLUMNS.to_vec()
+}
+
+#[tauri::command]
+fn money_format(db: State<DbState>) -> Result<MoneyFormat, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_money_format(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_money_format(db: State<DbState>, format: MoneyFormat) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    set_money_format(&conn, &format).map_err(|e| e.to_string())
+}
+
 fn main() {
     
     tauri::Builder::default()
@@ -109,10 +833,28 @@ fn main() {
 
             // Make DB connection available to commands
             app.manage(DbState(std::sync::Mutex::new(conn)));
+            app.manage(MetadataState(std::sync::Mutex::new(None)));
 
             Ok(())
         })
         .plugin(tauri_plugin_dialog::init())
+        // Thumbnails are made off the main thread so a page full of them doesn't stall the UI
+        .register_asynchronous_uri_scheme_protocol("thumb", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            std::thread::spawn(move || {
+                let response = match thumbnail_bytes(&app, request.uri()) {
+                    Ok(bytes) => tauri::http::Response::builder()
+                        .header("Content-Type", "image/jpeg")
+                        .body(bytes)
+                        .unwrap(),
+                    Err(e) => tauri::http::Response::builder()
+                        .status(404)
+                        .body(e.into_bytes())
+                        .unwrap(),
+                };
+                responder.respond(response);
+            });
+        })
         .invoke_handler(tauri::generate_handler![
             list_items,
             new_item,
@@ -120,7 +862,78 @@ fn main() {
             get_item,
             update_item,
             delete_item,
-            export_filtered_items_to_csv
+            bulk_update_items,
+            bulk_delete_items,
+            export_filtered_items_to_csv,
+            save_report,
+            save_labels,
+            condition_history,
+            record_condition,
+            condition_scale,
+            related_items,
+            link_items,
+            unlink_items,
+            set_stats,
+            item_provenance,
+            append_provenance,
+            edit_provenance,
+            reorder_provenance,
+            remove_provenance,
+            list_creators,
+            item_creators,
+            link_item_creator,
+            creator_duplicates,
+            merge_duplicate_creators,
+            creator_stats,
+            duplicate_items,
+            merge_duplicate_items,
+            list_saved_searches,
+            save_current_search,
+            saved_search_filter,
+            remove_saved_search,
+            search_items,
+            item_identifiers,
+            add_item_identifier,
+            scan_barcode_image,
+            lookup_item_draft,
+            list_audits,
+            begin_audit,
+            mark_audit_item,
+            unmark_audit_item,
+            audit_discrepancies,
+            close_audit,
+            remove_audit,
+            item_reminders,
+            add_item_reminder,
+            complete_item_reminder,
+            remove_reminder,
+            startup_reminders,
+            save_reminders_ics,
+            list_beneficiaries,
+            create_beneficiary,
+            edit_beneficiary,
+            remove_beneficiary,
+            item_beneficiaries,
+            assign_to_beneficiary,
+            unassign_from_beneficiary,
+            beneficiary_conflicts,
+            save_beneficiary_report,
+            wishlist_items,
+            acquire_wishlist,
+            list_templates,
+            save_item_template,
+            remove_template,
+            template_draft,
+            clone_existing_item,
+            item_attachments,
+            photo_metadata,
+            similar_photos,
+            list_export_profiles,
+            save_csv_export_profile,
+            remove_export_profile,
+            csv_export_columns,
+            money_format,
+            save_money_format
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -17,13 +17,20 @@ document.getElementById("addItemForm").addEventListener("submit", async (e) => {
     description: { type: "string" },
     category: { type: "string" },
     action: { type: "string" },
-    age_years: { type: "int" },
+    date_made: { type: "string" }, // backend parses as fuzzy date
     date_acquired: { type: "string" }, // backend parses as date
-    purchase_price: { type: "float" },
-    estimated_value: { type: "float" },
+    purchase_price: { type: "string" }, // amount with optional currency code
+    estimated_value: { type: "string" },
     creator: { type: "string" },
     provenance: { type: "string" },
+    location: { type: "string" },
+    dimensions: { type: "string" }, // "LxWxH unit"
+    weight: { type: "string" }, // amount with unit
+    quantity: { type: "int" },
     working: { type: "bool" }, // checkbox
+    target_price: { type: "string" },
+    wishlist_priority: { type: "int" },
+    wishlist_notes: { type: "string" },
   };
 
   // Helper: add values, with special handling for booleans
@@ -57,8 +64,14 @@ document.getElementById("addItemForm").addEventListener("submit", async (e) => {
     addIfValue(id, raw, cfg.type);
   });
 
+  item.wishlist = document.getElementById("wishlist").checked;
+
   try {
-    await invoke("new_item", { item });
+    const id = await invoke("new_item", { item });
+    const code = document.getElementById("barcode").value.trim();
+    if (code) {
+      await invoke("add_item_identifier", { itemId: id, code });
+    }
       // Add success message to item-details replacing form
       let details = document.getElementById("item-details");
       details.innerHTML = "<p style='color: green; font-weight: bold;'>Item added successfully.</p>"
@@ -70,3 +83,101 @@ document.getElementById("addItemForm").addEventListener("submit", async (e) => {
     alert("Failed to add item: " + err);
   }
 });
+
+// Prefill the form from the offline metadata for a code
+async function lookupBarcode() {
+  const code = document.getElementById("barcode").value.trim();
+  if (!code) return;
+  try {
+    const draft = await invoke("lookup_item_draft", { code });
+    if (!draft) {
+      alert(`No details found for ${code}`);
+      return;
+    }
+    for (const field of ["name", "description", "category", "creator", "date_made"]) {
+      if (draft[field]) {
+        document.getElementById(field).value = draft[field];
+      }
+    }
+  } catch (err) {
+    console.error("Lookup failed:", err);
+    alert("Lookup failed: " + err);
+  }
+}
+
+document.getElementById("lookup-barcode").addEventListener("click", lookupBarcode);
+
+document.getElementById("scan-barcode").addEventListener("click", async () => {
+  try {
+    const codes = await invoke("scan_barcode_image");
+    if (codes.length === 0) {
+      alert("No barcode found in the image.");
+      return;
+    }
+    document.getElementById("barcode").value = codes[0].value;
+    await lookupBarcode();
+  } catch (err) {
+    console.error("Scan failed:", err);
+    alert("Scan failed: " + err);
+  }
+});
+
+// Fields a template can fill in; name and description are always typed for each item
+const TEMPLATE_FIELDS = [
+  "category", "action", "date_made", "purchase_price", "estimated_value", "creator", "working",
+  "provenance", "location", "dimensions", "weight", "quantity",
+];
+
+async function loadTemplates() {
+  const select = document.getElementById("template");
+  select.length = 1;
+  try {
+    const templates = await invoke("list_templates");
+    templates.forEach((template) => {
+      select.add(new Option(template.name, template.name));
+    });
+  } catch (err) {
+    console.error("Failed to load templates:", err);
+  }
+}
+
+document.getElementById("template").addEventListener("change", async (e) => {
+  const name = e.target.value;
+  if (!name) return;
+  try {
+    const draft = await invoke("template_draft", { name });
+    for (const field of TEMPLATE_FIELDS) {
+      if (draft[field] !== null && draft[field] !== undefined) {
+        document.getElementById(field).value = String(draft[field]);
+      }
+    }
+  } catch (err) {
+    alert("Failed to apply template: " + err);
+  }
+});
+
+document.getElementById("save-template").addEventListener("click", async () => {
+  const name = prompt("Template name:", document.getElementById("template").value);
+  if (!name) return;
+  const defaults = {};
+  for (const field of TEMPLATE_FIELDS) {
+    const value = document.getElementById(field).value.trim();
+    if (value) defaults[field] = value;
+  }
+  try {
+    await invoke("save_item_template", { template: { name, defaults } });
+    await loadTemplates();
+    document.getElementById("template").value = name;
+  } catch (err) {
+    alert("Failed to save template: " + err);
+  }
+});
+
+document.getElementById("delete-template").addEventListener("click", async () => {
+  const name = document.getElementById("template").value;
+  if (!name || !confirm(`Delete template '${name}'?`)) return;
+  await invoke("remove_template", { name });
+  await loadTemplates();
+});
+
+document.addEventListener("DOMContentLoaded", loadTemplates);
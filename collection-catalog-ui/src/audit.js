@@ -0,0 +1,132 @@
+const { invoke } = window.__TAURI__.core;
+
+const params = new URLSearchParams(window.location.search);
+const auditId = params.get("id") ? Number(params.get("id")) : null;
+
+document.addEventListener("DOMContentLoaded", async () => {
+  if (auditId === null) {
+    await loadAudits();
+  } else {
+    document.getElementById("audit-list-section").style.display = "none";
+    document.getElementById("audit-section").style.display = "block";
+    await loadReport();
+  }
+});
+
+async function loadAudits() {
+  const tbody = document.getElementById("audit-list");
+  tbody.innerHTML = "";
+  try {
+    const audits = await invoke("list_audits");
+    if (audits.length === 0) {
+      tbody.innerHTML = '<tr><td colspan="6">No audits yet.</td></tr>';
+    }
+    audits.forEach((audit) => {
+      const tr = document.createElement("tr");
+      tr.innerHTML = `
+        <td><a href="audit.html?id=${audit.id}">${audit.id}</a></td>
+        <td>${audit.name}</td>
+        <td>${audit.started}</td>
+        <td>${audit.finished || "Open"}</td>
+        <td>${audit.found} / ${audit.expected}</td>
+        <td>${audit.unexpected}</td>
+      `;
+      tbody.appendChild(tr);
+    });
+  } catch (err) {
+    console.error("Failed to load audits:", err);
+  }
+}
+
+document.getElementById("start-audit-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const name = document.getElementById("audit-name").value.trim();
+  const location = document.getElementById("audit-location").value.trim();
+  const filter = location ? { location_contains: location } : {};
+  try {
+    const id = await invoke("begin_audit", { name, filter });
+    window.location.href = `audit.html?id=${id}`;
+  } catch (err) {
+    alert("Failed to start audit: " + err);
+  }
+});
+
+function renderItems(tbodyId, items, canUnmark) {
+  const tbody = document.getElementById(tbodyId);
+  tbody.innerHTML = "";
+  items.forEach((item) => {
+    const tr = document.createElement("tr");
+    tr.innerHTML = `
+      <td><a href="item.html?id=${item.id}">${item.id}</a></td>
+      <td>${item.name}</td>
+      <td>${item.location || ""}</td>
+      <td></td>
+    `;
+    if (canUnmark) {
+      const btn = document.createElement("button");
+      btn.textContent = "Undo";
+      btn.addEventListener("click", async () => {
+        await invoke("unmark_audit_item", { auditId, itemId: item.id });
+        await loadReport();
+      });
+      tr.lastElementChild.appendChild(btn);
+    }
+    tbody.appendChild(tr);
+  });
+}
+
+async function loadReport() {
+  try {
+    const report = await invoke("audit_discrepancies", { auditId });
+    const audit = report.audit;
+    const open = !audit.finished;
+    document.getElementById("audit-title").textContent = `Audit ${audit.id}: ${audit.name}`;
+    document.getElementById("audit-summary").textContent =
+      `Started ${audit.started}${open ? "" : `, finished ${audit.finished}`}. ` +
+      `${audit.found} of ${audit.expected} found, ${report.missing.length} missing, ` +
+      `${report.unexpected.length} unexpected.`;
+    document.getElementById("scan-form").style.display = open ? "block" : "none";
+    document.getElementById("finish-audit").style.display = open ? "inline-block" : "none";
+    renderItems("missing-body", report.missing, false);
+    renderItems("unexpected-body", report.unexpected, open);
+    renderItems("found-body", report.found, open);
+  } catch (err) {
+    document.getElementById("audit-title").textContent = "Audit not found";
+    console.error("Failed to load audit:", err);
+  }
+}
+
+// A barcode scanner types the label text followed by Enter, which submits the form
+document.getElementById("scan-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const input = document.getElementById("scan-input");
+  const result = document.getElementById("scan-result");
+  const scan = input.value.trim();
+  input.value = "";
+  if (!scan) return;
+  try {
+    const mark = await invoke("mark_audit_item", { auditId, scan });
+    const note = mark.already_found ? " (already found)" : mark.expected ? "" : " (unexpected)";
+    result.textContent = `Found #${mark.item.id} ${mark.item.name}${note}`;
+    await loadReport();
+  } catch (err) {
+    result.textContent = `${scan}: ${err}`;
+  }
+  input.focus();
+});
+
+document.getElementById("finish-audit").addEventListener("click", async () => {
+  if (!confirm("Finish this audit? No more items can be marked found.")) return;
+  try {
+    await invoke("close_audit", { auditId });
+    await loadReport();
+  } catch (err) {
+    alert("Failed to finish audit: " + err);
+  }
+});
+
+document.getElementById("delete-audit").addEventListener("click", async () => {
+  if (!confirm("Delete this audit? Items keep their last audited dates.")) return;
+  await invoke("remove_audit", { auditId });
+  window.location.href = "audit.html";
+});
@@ -0,0 +1,140 @@
+const { invoke } = window.__TAURI__.core;
+
+let selectedId = null;
+
+document.addEventListener("DOMContentLoaded", async () => {
+  await loadBeneficiaries();
+  await loadConflicts();
+});
+
+async function loadBeneficiaries() {
+  const tbody = document.getElementById("beneficiary-list");
+  tbody.innerHTML = "";
+  try {
+    const lists = await invoke("list_beneficiaries");
+    if (lists.length === 0) {
+      tbody.innerHTML = '<tr><td colspan="5">No beneficiaries yet.</td></tr>';
+    }
+    lists.forEach((list) => {
+      const person = list.beneficiary;
+      const tr = document.createElement("tr");
+      tr.innerHTML = `
+        <td>${person.name}</td>
+        <td>${person.relationship || ""}</td>
+        <td>${list.stats.item_count}</td>
+        <td>${list.stats.estimated_total_home ?? ""}</td>
+        <td></td>
+      `;
+      const show = document.createElement("button");
+      show.textContent = "Show";
+      show.addEventListener("click", () => showList(list));
+      const remove = document.createElement("button");
+      remove.textContent = "Delete";
+      remove.addEventListener("click", async () => {
+        if (!confirm(`Delete ${person.name} and everything assigned to them?`)) return;
+        await invoke("remove_beneficiary", { id: person.id });
+        if (selectedId === person.id) {
+          document.getElementById("beneficiary-section").style.display = "none";
+        }
+        await loadBeneficiaries();
+        await loadConflicts();
+      });
+      tr.lastElementChild.append(show, remove);
+      tbody.appendChild(tr);
+      if (selectedId === person.id) showList(list);
+    });
+  } catch (err) {
+    console.error("Failed to load beneficiaries:", err);
+  }
+}
+
+function showList(list) {
+  const person = list.beneficiary;
+  selectedId = person.id;
+  document.getElementById("beneficiary-section").style.display = "block";
+  document.getElementById("beneficiary-title").textContent =
+    person.relationship ? `${person.name} (${person.relationship})` : person.name;
+  document.getElementById("beneficiary-summary").textContent =
+    [person.contact, person.notes].filter(Boolean).join(" - ");
+
+  const tbody = document.getElementById("assigned-body");
+  tbody.innerHTML = "";
+  list.items.forEach((assigned) => {
+    const item = assigned.item;
+    const tr = document.createElement("tr");
+    tr.innerHTML = `
+      <td>${assigned.priority ?? ""}</td>
+      <td><a href="item.html?id=${item.id}">${item.id}</a></td>
+      <td>${item.name}</td>
+      <td>${item.estimated_value ?? ""}</td>
+      <td>${assigned.note || ""}</td>
+      <td>${assigned.also_wanted_by.join(", ")}</td>
+      <td></td>
+    `;
+    const btn = document.createElement("button");
+    btn.textContent = "Unassign";
+    btn.addEventListener("click", async () => {
+      await invoke("unassign_from_beneficiary", { itemId: item.id, beneficiaryId: person.id });
+      await loadBeneficiaries();
+      await loadConflicts();
+    });
+    tr.lastElementChild.appendChild(btn);
+    tbody.appendChild(tr);
+  });
+}
+
+async function loadConflicts() {
+  const container = document.getElementById("conflict-list");
+  try {
+    const conflicts = await invoke("beneficiary_conflicts");
+    if (conflicts.length === 0) {
+      container.innerHTML = "<p>No conflicts.</p>";
+      return;
+    }
+    const rows = conflicts
+      .map((conflict) => {
+        const claims = conflict.claims
+          .map((c) => (c.priority ? `${c.beneficiary.name} (${c.priority})` : c.beneficiary.name))
+          .join(", ");
+        return `<tr><td><a href="item.html?id=${conflict.item.id}">${conflict.item.name}</a></td><td>${claims}</td></tr>`;
+      })
+      .join("");
+    container.innerHTML = `
+      <table border="1">
+        <tr><th>Item</th><th>Wanted By (priority)</th></tr>
+        ${rows}
+      </table>
+    `;
+  } catch (err) {
+    console.error("Failed to load conflicts:", err);
+  }
+}
+
+document.getElementById("beneficiary-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const beneficiary = {
+    name: document.getElementById("beneficiary-name").value.trim(),
+    relationship: document.getElementById("beneficiary-relationship").value.trim() || null,
+    contact: document.getElementById("beneficiary-contact").value.trim() || null,
+    notes: document.getElementById("beneficiary-notes").value.trim() || null,
+  };
+  try {
+    await invoke("create_beneficiary", { beneficiary });
+    e.target.reset();
+    await loadBeneficiaries();
+  } catch (err) {
+    alert("Failed to add beneficiary: " + err);
+  }
+});
+
+async function saveReport(beneficiaryId) {
+  try {
+    const path = await invoke("save_beneficiary_report", { beneficiaryId });
+    if (path) alert(`Report saved to ${path}`);
+  } catch (err) {
+    alert("Failed to save report: " + err);
+  }
+}
+
+document.getElementById("save-all-report").addEventListener("click", () => saveReport(null));
+document.getElementById("save-report").addEventListener("click", () => saveReport(selectedId));
@@ -0,0 +1,62 @@
+const { invoke, convertFileSrc } = window.__TAURI__.core;
+
+document.addEventListener("DOMContentLoaded", loadDuplicates);
+
+document.getElementById("score-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  await loadDuplicates();
+});
+
+// The matching photo when there is one, otherwise the item's first photo
+function photoCell(item, attachmentId) {
+  const target = attachmentId ? `attachment-${attachmentId}` : `item-${item.id}`;
+  return `
+    <img class="thumbnail" src="${convertFileSrc(target, "thumb")}?size=small" alt="" onerror="this.remove()">
+    <a href="item.html?id=${item.id}">#${item.id} ${item.name}</a>
+  `;
+}
+
+async function loadDuplicates() {
+  const tbody = document.getElementById("duplicate-list");
+  tbody.innerHTML = "";
+  const minScore = Number(document.getElementById("min-score").value) / 100;
+  try {
+    const candidates = await invoke("duplicate_items", { minScore });
+    document.getElementById("duplicate-summary").textContent =
+      `${candidates.length} possible duplicates.`;
+    candidates.forEach((candidate) => {
+      const photo = candidate.photo_match;
+      const tr = document.createElement("tr");
+      tr.innerHTML = `
+        <td>${Math.round(candidate.score * 100)}%</td>
+        <td>${photoCell(candidate.item, photo?.attachment_id)}</td>
+        <td>${photoCell(candidate.other, photo?.other_attachment_id)}</td>
+        <td>${candidate.reasons.join(", ")}</td>
+        <td></td>
+      `;
+      for (const [label, keep, merge] of [
+        ["Keep Left", candidate.item, candidate.other],
+        ["Keep Right", candidate.other, candidate.item],
+      ]) {
+        const btn = document.createElement("button");
+        btn.textContent = label;
+        btn.addEventListener("click", () => mergePair(keep, merge));
+        tr.lastElementChild.appendChild(btn);
+      }
+      tbody.appendChild(tr);
+    });
+  } catch (err) {
+    console.error("Failed to find duplicates:", err);
+    document.getElementById("duplicate-summary").textContent = `Failed to find duplicates: ${err}`;
+  }
+}
+
+async function mergePair(keep, merge) {
+  if (!confirm(`Merge #${merge.id} ${merge.name} into #${keep.id} ${keep.name}?`)) return;
+  try {
+    await invoke("merge_duplicate_items", { keepId: keep.id, mergeId: merge.id, take: [] });
+    await loadDuplicates();
+  } catch (err) {
+    alert("Failed to merge items: " + err);
+  }
+}
@@ -1,4 +1,9 @@
-const { invoke } = window.__TAURI__.core;
+const { invoke, convertFileSrc } = window.__TAURI__.core;
+
+// The thumb protocol's URL differs by platform, so let Tauri build it
+function thumbnailUrl(target, size = "small") {
+  return `${convertFileSrc(target, "thumb")}?size=${size}`;
+}
 
 let currentFilter = {}; // Defined globally so export-csv can use after created on DOMContentLoaded
 
@@ -24,16 +29,24 @@ function prettyCategory(code) {
 
 const exportBtn = document.getElementById("export-csv");
 exportBtn.disabled = true; // disable until results are loaded
+const reportBtn = document.getElementById("save-report");
+reportBtn.disabled = true;
+const labelsBtn = document.getElementById("save-labels");
+labelsBtn.disabled = true;
+const auditBtn = document.getElementById("start-audit");
+auditBtn.disabled = true;
+const bulkUpdateBtn = document.getElementById("bulk-update");
+const bulkDeleteBtn = document.getElementById("bulk-delete");
+bulkUpdateBtn.disabled = true;
+bulkDeleteBtn.disabled = true;
 
 document.addEventListener("DOMContentLoaded", async () => {
   const params = new URLSearchParams(window.location.search);
   const numeric_filters = [
     "age_years_min",
     "age_years_max",
-    "purchase_price_min",
-    "purchase_price_max",
-    "estimated_value_min",
-    "estimated_value_max",
+    "quantity_min",
+    "quantity_max",
   ];
   const date_filters = [
     "date_added_min",
@@ -42,15 +55,17 @@ document.addEventListener("DOMContentLoaded", async () => {
     "last_updated_max",
     "date_acquired_min",
     "date_acquired_max",
+    "date_made_min",
+    "date_made_max",
   ];
   const filter = {};
 
   // Convert query params → filter object (ignoring blanks)
   params.forEach((value, key) => {
-    if (value !== "") {
+    if (value !== "" && key !== "saved" && key !== "q") {
       if (key === "working") {
         filter[key] = value === "true" ? true : value === "false" ? false : null;
-      } else if (key === "deleted") {
+      } else if (key === "deleted" || key === "wishlist") {
         filter[key] = value === "true" ? true : value === "false" ? false : null;
       } else if ( numeric_filters.includes(key) ){
         filter[key] = Number(value); // numeric filters
@@ -58,56 +73,90 @@ document.addEventListener("DOMContentLoaded", async () => {
        {
         filter[key] = value; // date filters
       } else {
+        // includes money filters, sent as text like "100 EUR"
         filter[key] = value; // string filters
       }
     }
   });
 
   currentFilter = filter;
-  console.log("Built filter:", filter);
 
   try {
-    const items = await invoke("filter_items", { filter });
+    // A saved search replaces the filter built from the query string
+    if (params.get("saved")) {
+      currentFilter = await invoke("saved_search_filter", { name: params.get("saved") });
+    }
+    console.log("Built filter:", currentFilter);
+    // A text query has no filter to export, report or bulk edit with
+    const query = params.get("q");
+    const items = query
+      ? await invoke("search_items", { query })
+      : await invoke("filter_items", { filter: currentFilter });
     const tbody = document.getElementById("results-body");
     tbody.innerHTML = "";
 
     if (items.length === 0) {
       tbody.innerHTML =
-        '<tr><td colspan="8">No items found.</td></tr>';
+        '<tr><td colspan="9">No items found.</td></tr>';
     } else {
       items.forEach((item) => {
         const tr = document.createElement("tr");
         tr.innerHTML = `
+          <td><img class="thumbnail" src="${thumbnailUrl(`item-${item.id}`)}" alt="" onerror="this.remove()"></td>
           <td><a href="item.html?id=${item.id}">${item.id}</a></td>
           <td>${item.name}</td>
           <td>${item.description || ""}</td>
           <td>${prettyCategory(item.category)}</td>
           <td>${item.creator || ""}</td>
           <td>${item.working === null ? "Unknown" : item.working ? "Yes" : "No"}</td>
+          <td>${item.condition || ""}</td>
           <td>${item.action || ""}</td>
+          <td>${item.date_made || ""}</td>
           <td>${item.age_years ?? ""}</td>
           <td>${item.date_acquired || ""}</td>
           <td>${item.purchase_price ?? ""}</td>
           <td>${item.estimated_value ?? ""}</td>
           <td>${item.provenance || ""}</td>
+          <td>${item.location || ""}</td>
+          <td>${item.dimensions || ""}</td>
+          <td>${item.weight || ""}</td>
+          <td>${item.quantity ?? 1}</td>
           <td>${item.date_added}</td>
           <td>${item.last_updated}</td>
         `;
         tbody.appendChild(tr);
       });
-      exportBtn.disabled = false; // enable export only if items exist
+      if (!query) {
+        exportBtn.disabled = false; // enable export only if items exist
+        reportBtn.disabled = false;
+        labelsBtn.disabled = false;
+        auditBtn.disabled = false;
+        bulkUpdateBtn.disabled = false;
+        bulkDeleteBtn.disabled = false;
+      }
     }
   } catch (err) {
     console.error("Error filtering items:", err);
+    if (params.get("q")) {
+      document.getElementById("results-body").innerHTML =
+        `<tr><td colspan="9">Invalid search: ${err}</td></tr>`;
+    }
     exportBtn.disabled = true;
+    reportBtn.disabled = true;
+    labelsBtn.disabled = true;
+    auditBtn.disabled = true;
   }
 });
  
 document.getElementById("export-csv").addEventListener("click", async () => {
   try {
-    // Use Rust function to export filtered items to CSV
-    const csvContent = await invoke("export_filtered_items_to_csv", { 
+    // The extension chosen in the save dialog picks CSV, XLSX, ODS or JSON
+    await invoke("export_filtered_items_to_csv", {
       filter: currentFilter,
+      options: {
+        sheet_per_category: document.getElementById("sheet-per-category").checked,
+      },
+      profile: document.getElementById("csv-profile").value || null,
     });
 
   } catch (err) {
@@ -115,3 +164,118 @@ document.getElementById("export-csv").addEventListener("click", async () => {
   }
 });
 
+document.getElementById("save-report").addEventListener("click", async () => {
+  try {
+    await invoke("save_report", {
+      filter: currentFilter,
+      grouping: document.getElementById("report-grouping").value,
+    });
+  } catch (err) {
+    console.error("Report failed:", err);
+    alert("Failed to save report: " + err);
+  }
+});
+
+labelsBtn.addEventListener("click", async () => {
+  try {
+    await invoke("save_labels", {
+      filter: currentFilter,
+      template: document.getElementById("label-template").value,
+    });
+  } catch (err) {
+    console.error("Labels failed:", err);
+    alert("Failed to save labels: " + err);
+  }
+});
+
+// Audits everything the current filter matches, then carries on in the audit page
+auditBtn.addEventListener("click", async () => {
+  const name = prompt("Audit name:", `Audit ${new Date().toISOString().slice(0, 10)}`);
+  if (!name) return;
+  try {
+    const id = await invoke("begin_audit", { name, filter: currentFilter });
+    window.location.href = `audit.html?id=${id}`;
+  } catch (err) {
+    alert("Failed to start audit: " + err);
+  }
+});
+
+// Bulk actions apply to everything the current filter matches. A dry run gives the count to confirm.
+bulkUpdateBtn.addEventListener("click", async () => {
+  const updates = {
+    [document.getElementById("bulk-field").value]: document.getElementById("bulk-value").value,
+  };
+  try {
+    const preview = await invoke("bulk_update_items", { filter: currentFilter, updates, dryRun: true });
+    if (!confirm(`Update ${preview.length} items?`)) return;
+    await invoke("bulk_update_items", { filter: currentFilter, updates, dryRun: false });
+    window.location.reload();
+  } catch (err) {
+    console.error("Bulk update failed:", err);
+    alert("Bulk update failed, nothing was changed: " + err);
+  }
+});
+
+bulkDeleteBtn.addEventListener("click", async () => {
+  try {
+    const preview = await invoke("bulk_delete_items", { filter: currentFilter, dryRun: true });
+    if (!confirm(`Delete ${preview.length} items?`)) return;
+    await invoke("bulk_delete_items", { filter: currentFilter, dryRun: false });
+    window.location.reload();
+  } catch (err) {
+    console.error("Bulk delete failed:", err);
+    alert("Bulk delete failed: " + err);
+  }
+});
+
+document.getElementById("save-search").addEventListener("click", async () => {
+  const name = prompt("Name for this search:", new URLSearchParams(window.location.search).get("saved") || "");
+  if (!name) return;
+  try {
+    await invoke("save_current_search", { search: { name, filter: currentFilter } });
+  } catch (err) {
+    console.error("Saving search failed:", err);
+    alert("Failed to save search: " + err);
+  }
+});
+
+async function loadExportProfiles() {
+  const select = document.getElementById("csv-profile");
+  select.length = 1; // keep "Default"
+  for (const profile of await invoke("list_export_profiles")) {
+    select.add(new Option(profile.name, profile.name));
+  }
+  const columns = await invoke("csv_export_columns");
+  document.getElementById("profile-column-keys").textContent =
+    "Columns: " + columns.map(([key]) => key).join(", ");
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  loadExportProfiles().catch((err) => console.error("Loading export profiles failed:", err));
+});
+
+document.getElementById("csv-profile-form").addEventListener("submit", async (e) => {
+  e.preventDefault();
+  const delimiter = document.getElementById("profile-delimiter").value;
+  const profile = {
+    name: document.getElementById("profile-name").value.trim(),
+    options: {
+      columns: document.getElementById("profile-columns").value
+        .split(",")
+        .map((c) => c.trim())
+        .filter((c) => c),
+      headers: document.getElementById("profile-headers").value,
+      delimiter: delimiter === "\\t" ? "\t" : delimiter,
+      quoting: document.getElementById("profile-quoting").value,
+      date_format: document.getElementById("profile-date-format").value,
+      bom: document.getElementById("profile-bom").checked,
+    },
+  };
+  try {
+    await invoke("save_csv_export_profile", { profile });
+    await loadExportProfiles();
+    document.getElementById("csv-profile").value = profile.name;
+  } catch (err) {
+    alert("Failed to save profile: " + err);
+  }
+});
@@ -1,4 +1,4 @@
-const { invoke } = window.__TAURI__.core;
+const { invoke, convertFileSrc } = window.__TAURI__.core;
 
 document.addEventListener("DOMContentLoaded", async () => {
 
@@ -23,6 +23,13 @@ document.addEventListener("DOMContentLoaded", async () => {
 
     renderItem(item);
     prefillForm(item);
+    await loadPhotos(item);
+    await loadCondition(item);
+    await loadRelated(item);
+    await loadCreators(item);
+    await loadProvenance(item);
+    await loadReminders(item);
+    await loadBeneficiaries(item);
     
   } catch (err) {
     console.error("Error loading item:", err);
@@ -108,6 +115,203 @@ document.addEventListener("DOMContentLoaded", async () => {
     }
   });
 
+  document.getElementById("condition-form").addEventListener("submit", async (e) => {
+    e.preventDefault();
+    if (!currentItem) return;
+
+    const report = {
+      id: 0,
+      item_id: currentItem.id,
+      date: document.getElementById("condition-date").value,
+      grade: document.getElementById("condition-grade").value,
+      notes: document.getElementById("condition-notes").value || null,
+    };
+
+    try {
+      await invoke("record_condition", { report });
+      currentItem = await invoke("get_item", { id: currentItem.id });
+      renderItem(currentItem);
+      await loadCondition(currentItem);
+      document.getElementById("condition-notes").value = "";
+    } catch (err) {
+      console.error("Recording condition failed:", err);
+      alert("Failed to record condition: " + err);
+    }
+  });
+
+  document.getElementById("reminder-form").addEventListener("submit", async (e) => {
+    e.preventDefault();
+    if (!currentItem) return;
+
+    const reminder = {
+      id: 0,
+      item_id: currentItem.id,
+      kind: document.getElementById("reminder-kind").value,
+      due: document.getElementById("reminder-due").value,
+      recurrence: document.getElementById("reminder-recurrence").value.trim() || null,
+      note: document.getElementById("reminder-note").value.trim() || null,
+      last_done: null,
+    };
+
+    try {
+      await invoke("add_item_reminder", { reminder });
+      await loadReminders(currentItem);
+      document.getElementById("reminder-recurrence").value = "";
+      document.getElementById("reminder-note").value = "";
+    } catch (err) {
+      console.error("Adding reminder failed:", err);
+      alert("Failed to add reminder: " + err);
+    }
+  });
+
+  document.getElementById("assign-form").addEventListener("submit", async (e) => {
+    e.preventDefault();
+    if (!currentItem) return;
+
+    const priority = document.getElementById("assign-priority").value;
+    const assignment = {
+      item_id: currentItem.id,
+      beneficiary_id: Number(document.getElementById("assign-beneficiary").value),
+      priority: priority ? Number(priority) : null,
+      note: document.getElementById("assign-note").value.trim() || null,
+    };
+
+    try {
+      await invoke("assign_to_beneficiary", { assignment });
+      await loadBeneficiaries(currentItem);
+      document.getElementById("assign-priority").value = "";
+      document.getElementById("assign-note").value = "";
+    } catch (err) {
+      console.error("Assigning item failed:", err);
+      alert("Failed to assign item: " + err);
+    }
+  });
+
+  document.getElementById("provenance-form").addEventListener("submit", async (e) => {
+    e.preventDefault();
+    if (!currentItem) return;
+
+    const value = (id) => document.getElementById(id).value.trim() || null;
+    const editingId = Number(document.getElementById("provenance-id").value);
+    const event = {
+      id: editingId,
+      item_id: currentItem.id,
+      role: document.getElementById("provenance-role").value,
+      name: document.getElementById("provenance-name").value,
+      date_from: value("provenance-from"),
+      date_to: value("provenance-to"),
+      location: value("provenance-location"),
+      source: value("provenance-source"),
+    };
+
+    try {
+      if (editingId) {
+        await invoke("edit_provenance", { event });
+      } else {
+        await invoke("append_provenance", { event });
+      }
+      e.target.reset();
+      document.getElementById("provenance-id").value = "";
+      document.getElementById("provenance-submit").textContent = "Add Event";
+      await loadProvenance(currentItem);
+    } catch (err) {
+      console.error("Saving provenance failed:", err);
+      alert("Failed to save provenance event: " + err);
+    }
+  });
+
+  document.getElementById("provenance-events").addEventListener("click", async (e) => {
+    const { action, eventId, position } = e.target.dataset;
+    if (!action) return;
+
+    try {
+      if (action === "edit") {
+        const event = provenanceEvents.find((ev) => ev.id === Number(eventId));
+        document.getElementById("provenance-id").value = event.id;
+        document.getElementById("provenance-role").value = event.role;
+        document.getElementById("provenance-name").value = event.name;
+        document.getElementById("provenance-from").value = event.date_from || "";
+        document.getElementById("provenance-to").value = event.date_to || "";
+        document.getElementById("provenance-location").value = event.location || "";
+        document.getElementById("provenance-source").value = event.source || "";
+        document.getElementById("provenance-submit").textContent = "Save Event";
+        return;
+      }
+      if (action === "delete") {
+        await invoke("remove_provenance", { eventId: Number(eventId) });
+      } else {
+        await invoke("reorder_provenance", {
+          eventId: Number(eventId),
+          position: Number(position),
+        });
+      }
+      await loadProvenance(currentItem);
+    } catch (err) {
+      console.error("Updating provenance failed:", err);
+      alert("Failed to update provenance: " + err);
+    }
+  });
+
+  document.getElementById("link-form").addEventListener("submit", async (e) => {
+    e.preventDefault();
+    if (!currentItem) return;
+
+    try {
+      await invoke("link_items", {
+        itemId: currentItem.id,
+        kind: document.getElementById("link-kind").value,
+        relatedId: Number(document.getElementById("link-related-id").value),
+      });
+      document.getElementById("link-related-id").value = "";
+      await loadRelated(currentItem);
+    } catch (err) {
+      console.error("Linking failed:", err);
+      alert("Failed to link items: " + err);
+    }
+  });
+
+  document.getElementById("related-items").addEventListener("click", async (e) => {
+    const relationId = e.target.dataset.relationId;
+    if (!relationId) return;
+
+    try {
+      await invoke("unlink_items", { relationId: Number(relationId) });
+      await loadRelated(currentItem);
+    } catch (err) {
+      console.error("Unlinking failed:", err);
+      alert("Failed to unlink items: " + err);
+    }
+  });
+
+  document.getElementById("acquire-button").addEventListener("click", async () => {
+    const price = prompt("Price paid (leave blank if unknown):", currentItem.target_price ?? "");
+    if (price === null) return;
+    try {
+      currentItem = await invoke("acquire_wishlist", {
+        itemId: currentItem.id,
+        price: price.trim() || null,
+        date: null,
+      });
+      renderItem(currentItem);
+      prefillForm(currentItem);
+    } catch (err) {
+      console.error("Acquiring item failed:", err);
+      alert("Failed to mark item acquired: " + err);
+    }
+  });
+
+  // The copy opens straight away so its differences can be filled in
+  document.getElementById("clone-button").addEventListener("click", async () => {
+    const withAttachments = confirm("Also link the copy to this item's photos and files?");
+    try {
+      const id = await invoke("clone_existing_item", { itemId: currentItem.id, withAttachments });
+      window.location.href = `item.html?id=${id}`;
+    } catch (err) {
+      console.error("Copying item failed:", err);
+      alert("Failed to copy item: " + err);
+    }
+  });
+
   document.getElementById("restore-button").addEventListener("click", async () => {
     const updates = {deleted: "false"};
     try {
@@ -142,27 +346,276 @@ function renderItem(item) {
       <tr><td>Action</td><td>${item.action || ""}</td></tr>
       <tr><td>Creator</td><td>${item.creator || ""}</td></tr>
       <tr><td>Place of Origin</td><td>${item.provenance || ""}</td></tr>
+      <tr><td>Location</td><td>${item.location || ""}</td></tr>
+      <tr><td>Date Made</td><td>${item.date_made || ""}</td></tr>
       <tr><td>Age (Years)</td><td>${item.age_years ?? ""}</td></tr>
       <tr><td>Date Acquired</td><td>${item.date_acquired || ""}</td></tr>
       <tr><td>Purchase Price</td><td>${item.purchase_price ?? ""}</td></tr>
       <tr><td>Estimated Value</td><td>${item.estimated_value ?? ""}</td></tr>
+      <tr><td>Dimensions</td><td>${item.dimensions || ""}</td></tr>
+      <tr><td>Weight</td><td>${item.weight || ""}</td></tr>
+      <tr><td>Quantity</td><td>${item.quantity ?? 1}</td></tr>
+      <tr><td>Total Weight</td><td>${item.total_weight || ""}</td></tr>
+      <tr><td>Condition</td><td>${item.condition || ""}</td></tr>
+      <tr><td>Condition Notes</td><td>${item.condition_notes || ""}</td></tr>
       <tr><td>Working Condition</td><td>${
         item.working === null ? "Unknown" : item.working ? "Yes" : "No"
       }</td></tr>
       <tr><td>Date Added</td></th><td>${item.date_added}</td></tr>
       <tr><td>Last Updated</td></th><td>${item.last_updated}</td></tr>
+      <tr><td>Last Audited</td></th><td>${item.last_audited || "Never"}</td></tr>
+      ${item.wishlist ? `
+      <tr><td>Wishlist</td><td>Looking for this item</td></tr>
+      <tr><td>Target Price</td><td>${item.target_price ?? ""}</td></tr>
+      <tr><td>Wishlist Priority</td><td>${item.wishlist_priority ?? ""}</td></tr>
+      <tr><td>Wishlist Notes</td><td>${item.wishlist_notes || ""}</td></tr>` : ""}
     </table>
   `;
+  document.getElementById("acquire-button").style.display = item.wishlist ? "inline-block" : "none";
   
   // check whether item.deleted is true. If so, hide update/delete options. Show a Restore button instead.
   if (item.deleted) {
     document.getElementById("update-button").style.display = "none";
     document.getElementById("delete-button").style.display = "none";
+    document.getElementById("clone-button").style.display = "none";
     const rb = document.getElementById("restore-button");
     rb.style.display = "inline-block";
   }
 }
 
+// Medium thumbnails of the item's photos, captioned with when and how they were taken
+async function loadPhotos(item) {
+  const attachments = await invoke("item_attachments", { itemId: item.id });
+  const photos = attachments.filter((a) => /\.(jpe?g|png|webp)$/i.test(a.path));
+  const container = document.getElementById("item-photos");
+  if (photos.length === 0) {
+    container.innerHTML = "<p>No photos.</p>";
+    return;
+  }
+  container.innerHTML = "";
+  photos.forEach((photo) => {
+    const figure = document.createElement("figure");
+    const details = [photo.caption, photo.captured?.replace("T", " "), photo.camera].filter(Boolean);
+    figure.innerHTML = `
+      <img src="${convertFileSrc(`attachment-${photo.id}`, "thumb")}?size=medium" alt="">
+      <figcaption>${details.join("<br>")}</figcaption>
+    `;
+    container.appendChild(figure);
+  });
+}
+
+async function loadCreators(item) {
+  const creators = await invoke("item_creators", { itemId: item.id });
+  const container = document.getElementById("item-creators");
+  if (creators.length === 0) {
+    container.innerHTML = "<p>No creators linked.</p>";
+    return;
+  }
+
+  const rows = creators
+    .map(({ creator, role }) => `
+      <tr>
+        <td>${creator.name}</td>
+        <td>${role}</td>
+        <td>${[creator.born, creator.died].some(Boolean) ? `${creator.born || "?"} - ${creator.died || "?"}` : ""}</td>
+        <td>${creator.aliases.join(", ")}</td>
+      </tr>`)
+    .join("");
+  container.innerHTML = `
+    <table border="1">
+      <tr><th>Name</th><th>Role</th><th>Lived</th><th>Also Known As</th></tr>
+      ${rows}
+    </table>
+  `;
+}
+
+let provenanceEvents = [];
+
+async function loadProvenance(item) {
+  provenanceEvents = await invoke("item_provenance", { itemId: item.id });
+  const container = document.getElementById("provenance-events");
+  if (provenanceEvents.length === 0) {
+    container.innerHTML = "<p>No provenance recorded.</p>";
+    return;
+  }
+
+  const dates = (ev) =>
+    [ev.date_from, ev.date_to].some(Boolean) ? `${ev.date_from || "?"} - ${ev.date_to || "?"}` : "";
+  const rows = provenanceEvents
+    .map((ev, i) => `
+      <tr>
+        <td>${i + 1}</td>
+        <td>${ev.role}</td>
+        <td>${ev.name}</td>
+        <td>${dates(ev)}</td>
+        <td>${ev.location || ""}</td>
+        <td>${ev.source || ""}</td>
+        <td>
+          <button type="button" data-action="move" data-event-id="${ev.id}" data-position="${i}" ${i === 0 ? "disabled" : ""}>Up</button>
+          <button type="button" data-action="move" data-event-id="${ev.id}" data-position="${i + 2}" ${i === provenanceEvents.length - 1 ? "disabled" : ""}>Down</button>
+          <button type="button" data-action="edit" data-event-id="${ev.id}">Edit</button>
+          <button type="button" data-action="delete" data-event-id="${ev.id}">Remove</button>
+        </td>
+      </tr>`)
+    .join("");
+  container.innerHTML = `
+    <table border="1">
+      <tr><th>#</th><th>Role</th><th>Name</th><th>Dates</th><th>Location</th><th>Source</th><th></th></tr>
+      ${rows}
+    </table>
+  `;
+}
+
+async function loadRelated(item) {
+  const related = await invoke("related_items", { itemId: item.id });
+  const container = document.getElementById("related-items");
+  if (related.length === 0) {
+    container.innerHTML = "<p>No related items.</p>";
+    return;
+  }
+
+  const rows = related
+    .map((r) => `
+      <tr>
+        <td>${r.label}</td>
+        <td><a href="item.html?id=${r.item.id}">${r.item.name}</a></td>
+        <td>${r.item.estimated_value ?? ""}</td>
+        <td><button type="button" data-relation-id="${r.relation_id}">Unlink</button></td>
+      </tr>`)
+    .join("");
+  container.innerHTML = `
+    <table border="1">
+      <tr><th>Relation</th><th>Item</th><th>Estimated Value</th><th></th></tr>
+      ${rows}
+    </table>
+  `;
+
+  // Items that contain others show the value of the whole set
+  if (related.some((r) => r.label === "Has part" || r.label === "Set contains")) {
+    const stats = await invoke("set_stats", { setId: item.id });
+    const total = stats.estimated_total_home ?? "unavailable (missing exchange rate)";
+    container.innerHTML += `<p>Set of ${stats.item_count} items, estimated value ${total}</p>`;
+  }
+}
+
+async function loadCondition(item) {
+  const grades = await invoke("condition_scale", { category: item.category });
+  document.getElementById("condition-grade").innerHTML = grades
+    .map((grade) => `<option value="${grade}">${grade}</option>`)
+    .join("");
+  if (item.condition) {
+    document.getElementById("condition-grade").value = item.condition;
+  }
+  document.getElementById("condition-date").value = new Date().toISOString().slice(0, 10);
+
+  const history = await invoke("condition_history", { itemId: item.id });
+  const container = document.getElementById("condition-history");
+  if (history.length === 0) {
+    container.innerHTML = "<p>No condition reports yet.</p>";
+    return;
+  }
+  const rows = history
+    .map((report) => `
+      <tr>
+        <td>${report.date}</td>
+        <td>${report.grade}</td>
+        <td>${report.notes || ""}</td>
+        <td>${report.photos.map((photo) => photo.path).join("<br>")}</td>
+      </tr>`)
+    .join("");
+  container.innerHTML = `
+    <table border="1">
+      <tr><th>Date</th><th>Grade</th><th>Notes</th><th>Photos</th></tr>
+      ${rows}
+    </table>
+  `;
+}
+
+async function loadReminders(item) {
+  const reminders = await invoke("item_reminders", { itemId: item.id });
+  const container = document.getElementById("reminder-list");
+  if (reminders.length === 0) {
+    container.innerHTML = "<p>No reminders.</p>";
+    return;
+  }
+  container.innerHTML = `
+    <table border="1">
+      <tr><th>Kind</th><th>Due</th><th>Repeats</th><th>Note</th><th>Last Done</th><th></th></tr>
+    </table>
+  `;
+  const table = container.querySelector("table");
+  reminders.forEach((reminder) => {
+    const open = reminder.recurrence || !reminder.last_done;
+    const tr = document.createElement("tr");
+    tr.innerHTML = `
+      <td>${reminder.kind}</td>
+      <td>${open ? reminder.due : "Done"}</td>
+      <td>${reminder.recurrence || ""}</td>
+      <td>${reminder.note || ""}</td>
+      <td>${reminder.last_done || ""}</td>
+      <td></td>
+    `;
+    if (open) {
+      const done = document.createElement("button");
+      done.textContent = "Done";
+      done.addEventListener("click", async () => {
+        await invoke("complete_item_reminder", { id: reminder.id, date: null });
+        await loadReminders(item);
+      });
+      tr.lastElementChild.appendChild(done);
+    }
+    const remove = document.createElement("button");
+    remove.textContent = "Delete";
+    remove.addEventListener("click", async () => {
+      await invoke("remove_reminder", { id: reminder.id });
+      await loadReminders(item);
+    });
+    tr.lastElementChild.appendChild(remove);
+    table.appendChild(tr);
+  });
+}
+
+async function loadBeneficiaries(item) {
+  const lists = await invoke("list_beneficiaries");
+  document.getElementById("assign-beneficiary").innerHTML = lists
+    .map(({ beneficiary }) => `<option value="${beneficiary.id}">${beneficiary.name}</option>`)
+    .join("");
+
+  const claims = await invoke("item_beneficiaries", { itemId: item.id });
+  const container = document.getElementById("beneficiary-claims");
+  if (claims.length === 0) {
+    container.innerHTML = "<p>Not assigned to anyone.</p>";
+    return;
+  }
+  container.innerHTML = `
+    ${claims.length > 1 ? "<p><strong>Wanted by more than one person.</strong></p>" : ""}
+    <table border="1">
+      <tr><th>Beneficiary</th><th>Priority</th><th>Note</th><th></th></tr>
+    </table>
+  `;
+  const table = container.querySelector("table");
+  claims.forEach((claim) => {
+    const tr = document.createElement("tr");
+    tr.innerHTML = `
+      <td><a href="beneficiaries.html">${claim.beneficiary.name}</a></td>
+      <td>${claim.priority ?? ""}</td>
+      <td>${claim.note || ""}</td>
+      <td></td>
+    `;
+    const btn = document.createElement("button");
+    btn.textContent = "Unassign";
+    btn.addEventListener("click", async () => {
+      await invoke("unassign_from_beneficiary", {
+        itemId: item.id,
+        beneficiaryId: claim.beneficiary.id,
+      });
+      await loadBeneficiaries(item);
+    });
+    tr.lastElementChild.appendChild(btn);
+    table.appendChild(tr);
+  });
+}
+
 function prefillForm(item) {
   // Pre-fill form with current item data
   document.getElementById("update-name").value = item.name;
@@ -171,10 +624,14 @@ function prefillForm(item) {
   document.getElementById("update-action").value = item.action || "";
   document.getElementById("update-creator").value = item.creator || "";
   document.getElementById("update-provenance").value = item.provenance || "";
-  document.getElementById("update-age").value = item.age_years ?? "";
+  document.getElementById("update-location").value = item.location || "";
+  document.getElementById("update-date-made").value = item.date_made || "";
   document.getElementById("update-date-acquired").value = item.date_acquired || "";
   document.getElementById("update-purchase-price").value = item.purchase_price ?? "";
   document.getElementById("update-estimated-value").value = item.estimated_value ?? "";
+  document.getElementById("update-dimensions").value = item.dimensions || "";
+  document.getElementById("update-weight").value = item.weight || "";
+  document.getElementById("update-quantity").value = item.quantity ?? "";
   if (item.working !== null) {
     document.getElementById("update-working").value = item.working ? "true" : "false";
   }
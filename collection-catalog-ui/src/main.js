@@ -1,6 +1,46 @@
 const { invoke } = window.__TAURI__.core;
 
-document.addEventListener("DOMContentLoaded", () => {
+document.addEventListener("DOMContentLoaded", async () => {
+
+	try {
+		const searches = await invoke("list_saved_searches");
+		document.getElementById("saved-searches").innerHTML = searches
+			.map((s) => `<li> <a class="btn-nav" href="filter.html?saved=${encodeURIComponent(s.name)}">${s.name}</a></li>`)
+			.join("");
+	} catch (err) {
+		console.error("Loading saved searches failed:", err);
+	}
+
+	try {
+		const reminders = await invoke("startup_reminders");
+		if (reminders.length > 0) {
+			const today = new Date().toISOString().slice(0, 10);
+			document.getElementById("due-reminder-list").innerHTML = reminders
+				.map(({ reminder, item_name }) => {
+					const when = reminder.due < today ? `<strong>overdue since ${reminder.due}</strong>` : `due ${reminder.due}`;
+					return `<li>${reminder.kind}: <a href="item.html?id=${reminder.item_id}">${item_name}</a> ${when}${reminder.note ? ` - ${reminder.note}` : ""}</li>`;
+				})
+				.join("");
+			document.getElementById("due-reminders").style.display = "block";
+		}
+	} catch (err) {
+		console.error("Loading reminders failed:", err);
+	}
+
+	document.getElementById("export-reminders").addEventListener("click", async () => {
+		try {
+			const path = await invoke("save_reminders_ics");
+			if (path) alert(`Reminders exported to ${path}`);
+		} catch (err) {
+			alert("Failed to export reminders: " + err);
+		}
+	});
+
+	document.getElementById("search-form").addEventListener("submit", (e) => {
+		e.preventDefault();
+		const query = document.getElementById("search-query").value;
+		window.location.href = `filter.html?q=${encodeURIComponent(query)}`;
+	});
 
 	document.getElementById("filter-form").addEventListener("submit", (e) => {
 		e.preventDefault();